@@ -1,9 +1,70 @@
 fn main() {
+    config_defaults();
+    build_info();
+    dashboard();
     linker_be_nice();
     // make sure linkall.x is the last linker script (otherwise might cause problems with flip-link)
     println!("cargo:rustc-link-arg=-Tlinkall.x");
 }
 
+/// Settings the firmware can't do without are taken from the environment. A
+/// build without them still compiles, but the unit can't connect until they
+/// are set.
+fn config_defaults() {
+    let defaults = [
+        ("SSID", ""),
+        ("PASSWORD", ""),
+        ("MQTT_SERVER", "mqtt"),
+        ("MQTT_TOPIC", "esp-rf-ook2"),
+        // Empty token disables the commands that need it
+        ("COMMAND_TOKEN", ""),
+    ];
+    for (name, default) in defaults {
+        println!("cargo:rerun-if-env-changed={}", name);
+        if std::env::var_os(name).is_none() {
+            println!(
+                "cargo:warning={} is not set, defaulting to \"{}\"",
+                name, default
+            );
+            println!("cargo:rustc-env={}={}", name, default);
+        }
+    }
+}
+
+/// Embed git revision and build time, so deployed units can be audited
+fn build_info() {
+    let git_hash = std::process::Command::new("git")
+        .args(["describe", "--always", "--dirty"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .and_then(|output| String::from_utf8(output.stdout).ok())
+        .map(|hash| hash.trim().to_string())
+        .unwrap_or_else(|| "unknown".to_string());
+    let build_timestamp = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|time| time.as_secs())
+        .unwrap_or(0);
+    println!("cargo:rustc-env=GIT_HASH={}", git_hash);
+    println!("cargo:rustc-env=BUILD_TIMESTAMP={}", build_timestamp);
+    println!("cargo:rerun-if-changed=.git/HEAD");
+    println!("cargo:rerun-if-changed=.git/index");
+    println!("cargo:rerun-if-changed=src");
+}
+
+/// Gzip the dashboard page, it's served as is with Content-Encoding: gzip
+fn dashboard() {
+    use std::io::Write;
+
+    let page = std::fs::read("web/dashboard.html").expect("Failed to read dashboard page");
+    let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::best());
+    encoder.write_all(&page).unwrap();
+    let gzipped = encoder.finish().unwrap();
+    let out = std::path::PathBuf::from(std::env::var_os("OUT_DIR").unwrap());
+    std::fs::write(out.join("dashboard.html.gz"), gzipped).expect("Failed to write dashboard page");
+    println!("cargo:rerun-if-changed=web/dashboard.html");
+}
+
 fn linker_be_nice() {
     let args: Vec<String> = std::env::args().collect();
     if args.len() > 1 {
@@ -49,8 +110,14 @@ fn linker_be_nice() {
         std::process::exit(0);
     }
 
+    // Xtensa links through gcc, RISC-V uses rust-lld directly
+    let prefix = match std::env::var("CARGO_CFG_TARGET_ARCH").as_deref() {
+        Ok("xtensa") => "-Wl,",
+        _ => "",
+    };
     println!(
-        "cargo:rustc-link-arg=-Wl,--error-handling-script={}",
+        "cargo:rustc-link-arg={}--error-handling-script={}",
+        prefix,
         std::env::current_exe().unwrap().display()
     );
 }
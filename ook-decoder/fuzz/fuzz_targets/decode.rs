@@ -0,0 +1,17 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use ook_decoder::decode_raw;
+
+// Every 2 bytes of input are one edge: MSB is the level, the rest is the
+// duration in uS
+fuzz_target!(|data: &[u8]| {
+    let edges: Vec<(bool, u16)> = data
+        .chunks_exact(2)
+        .map(|chunk| {
+            let raw = u16::from_le_bytes([chunk[0], chunk[1]]);
+            (raw & 0x8000 != 0, raw & 0x7fff)
+        })
+        .collect();
+    let _ = decode_raw(&edges);
+});
@@ -0,0 +1,210 @@
+/// Reverse bit order in a byte
+#[cfg_attr(feature = "esp-hal", esp_hal::ram)]
+pub fn reverse8(byte: u8) -> u8 {
+    byte.reverse_bits()
+}
+
+/// Swap high and low nibbles of a byte
+#[cfg_attr(feature = "esp-hal", esp_hal::ram)]
+pub fn nibble_swap(byte: u8) -> u8 {
+    byte.rotate_left(4)
+}
+
+/// 1 if byte has odd number of bits set, 0 otherwise
+#[cfg_attr(feature = "esp-hal", esp_hal::ram)]
+pub fn parity8(byte: u8) -> u8 {
+    (byte.count_ones() & 1) as u8
+}
+
+/// Bit at `idx` of MSB first bit buffer
+#[cfg_attr(feature = "esp-hal", esp_hal::ram)]
+pub fn bit(buf: &[u8], idx: usize) -> bool {
+    (buf[idx / 8] >> (7 - idx % 8)) & 1 != 0
+}
+
+/// XOR of all bytes
+#[cfg_attr(feature = "esp-hal", esp_hal::ram)]
+pub fn xor_bytes(data: &[u8]) -> u8 {
+    data.iter().fold(0, |acc, byte| acc ^ byte)
+}
+
+/// CRC-4 over whole bytes, MSB first, no final XOR
+#[cfg_attr(feature = "esp-hal", esp_hal::ram)]
+pub fn crc4(data: &[u8], poly: u8, init: u8) -> u8 {
+    // Work in the upper nibble, lower bits are unused
+    let poly = poly << 4;
+    let mut crc = init << 4;
+    for byte in data {
+        crc ^= byte;
+        for _ in 0..8 {
+            crc = if crc & 0x80 != 0 {
+                (crc << 1) ^ poly
+            } else {
+                crc << 1
+            };
+        }
+    }
+    crc >> 4
+}
+
+/// CRC-8, MSB first, no final XOR
+#[cfg_attr(feature = "esp-hal", esp_hal::ram)]
+pub fn crc8(data: &[u8], poly: u8, init: u8) -> u8 {
+    let mut crc = init;
+    for byte in data {
+        crc ^= byte;
+        for _ in 0..8 {
+            crc = if crc & 0x80 != 0 {
+                (crc << 1) ^ poly
+            } else {
+                crc << 1
+            };
+        }
+    }
+    crc
+}
+
+/// CRC-8, LSB first (reflected), `poly` is given in normal MSB first form
+#[cfg_attr(feature = "esp-hal", esp_hal::ram)]
+pub fn crc8le(data: &[u8], poly: u8, init: u8) -> u8 {
+    let poly = reverse8(poly);
+    let mut crc = reverse8(init);
+    for byte in data {
+        crc ^= byte;
+        for _ in 0..8 {
+            crc = if crc & 0x01 != 0 {
+                (crc >> 1) ^ poly
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    crc
+}
+
+/// CRC-16, MSB first, no final XOR
+#[cfg_attr(feature = "esp-hal", esp_hal::ram)]
+pub fn crc16(data: &[u8], poly: u16, init: u16) -> u16 {
+    let mut crc = init;
+    for byte in data {
+        crc ^= (*byte as u16) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 {
+                (crc << 1) ^ poly
+            } else {
+                crc << 1
+            };
+        }
+    }
+    crc
+}
+
+/// CRC-32, LSB first (reflected), `poly` is given in normal MSB first form.
+/// The usual CRC-32 (zlib, Ethernet) is `!crc32le(data, 0x04c11db7, !0)`.
+#[cfg_attr(feature = "esp-hal", esp_hal::ram)]
+pub fn crc32le(data: &[u8], poly: u32, init: u32) -> u32 {
+    let poly = poly.reverse_bits();
+    let mut crc = init.reverse_bits();
+    for byte in data {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ poly
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    crc
+}
+
+/// 8-bit LFSR based digest (a.k.a. "Galois keyed hash"): for every set bit
+/// of the message, MSB first, the current key is XORed into the result, then
+/// the key is shifted right with `generator` applied on carry.
+#[cfg_attr(feature = "esp-hal", esp_hal::ram)]
+pub fn lfsr_digest8(data: &[u8], generator: u8, key: u8) -> u8 {
+    let mut key = key;
+    let mut sum = 0;
+    for byte in data {
+        for i in (0..8).rev() {
+            if (byte >> i) & 1 != 0 {
+                sum ^= key;
+            }
+            key = if key & 1 != 0 {
+                (key >> 1) ^ generator
+            } else {
+                key >> 1
+            };
+        }
+    }
+    sum
+}
+
+/// 16-bit variant of [`lfsr_digest8`]
+#[cfg_attr(feature = "esp-hal", esp_hal::ram)]
+pub fn lfsr_digest16(data: &[u8], generator: u16, key: u16) -> u16 {
+    let mut key = key;
+    let mut sum = 0;
+    for byte in data {
+        for i in (0..8).rev() {
+            if (byte >> i) & 1 != 0 {
+                sum ^= key;
+            }
+            key = if key & 1 != 0 {
+                (key >> 1) ^ generator
+            } else {
+                key >> 1
+            };
+        }
+    }
+    sum
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Standard check input of the CRC catalogue
+    const CHECK: &[u8] = b"123456789";
+
+    #[test]
+    fn bytes() {
+        assert_eq!(reverse8(0x01), 0x80);
+        assert_eq!(reverse8(0xa0), 0x05);
+        assert_eq!(nibble_swap(0x12), 0x21);
+        assert_eq!(parity8(0x07), 1);
+        assert_eq!(parity8(0x03), 0);
+        assert_eq!(xor_bytes(&[0x12, 0x34, 0x56]), 0x70);
+        assert!(bit(&[0x80, 0x01], 0));
+        assert!(!bit(&[0x80, 0x01], 1));
+        assert!(bit(&[0x80, 0x01], 15));
+    }
+
+    #[test]
+    fn crc_check_values() {
+        // CRC-4/INTERLAKEN, final XOR 0xf
+        assert_eq!(crc4(CHECK, 0x3, 0xf) ^ 0xf, 0xb);
+        // CRC-8/SMBUS
+        assert_eq!(crc8(CHECK, 0x07, 0x00), 0xf4);
+        // CRC-8/MAXIM-DOW (Dallas 1-Wire)
+        assert_eq!(crc8le(CHECK, 0x31, 0x00), 0xa1);
+        // CRC-16/XMODEM and CRC-16/IBM-3740 (CCITT-FALSE)
+        assert_eq!(crc16(CHECK, 0x1021, 0x0000), 0x31c3);
+        assert_eq!(crc16(CHECK, 0x1021, 0xffff), 0x29b1);
+        // CRC-32/ISO-HDLC
+        assert_eq!(!crc32le(CHECK, 0x04c11db7, !0), 0xcbf43926);
+    }
+
+    #[test]
+    fn lfsr_digest() {
+        // First message bit takes the key as is, every following bit takes
+        // the key shifted once more: 0x01 -> 0x98 -> 0x4c
+        assert_eq!(lfsr_digest8(&[0x80], 0x98, 0x01), 0x01);
+        assert_eq!(lfsr_digest8(&[0x40], 0x98, 0x01), 0x98);
+        assert_eq!(lfsr_digest8(&[0x60], 0x98, 0x01), 0x98 ^ 0x4c);
+
+        assert_eq!(lfsr_digest16(&[0x80], 0x8810, 0x0001), 0x0001);
+        assert_eq!(lfsr_digest16(&[0x40], 0x8810, 0x0001), 0x8810);
+        assert_eq!(lfsr_digest16(&[0x60], 0x8810, 0x0001), 0x8810 ^ 0x4408);
+    }
+}
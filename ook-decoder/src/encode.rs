@@ -0,0 +1,44 @@
+use crate::{MAX_HIGH, MAX_LOW, MIN_HIGH, MIN_LOW, PAYLOAD_LEN_BITS, PULSE_MAX, PULSE_MIN};
+
+// Frames synthesized from readings, as (level, duration in us) edges the way
+// they're captured, for transmitting test signals. Timings are the middle of
+// the ranges the decoders accept.
+
+/// Edges of a Nexus-TH frame: preamble pulse and pause, a pulse and a pause
+/// per bit and the closing pulse
+pub const NEXUS_EDGES: usize = 2 * PAYLOAD_LEN_BITS + 3;
+
+const PULSE: u16 = (PULSE_MIN + PULSE_MAX) / 2;
+const ONE: u16 = (MIN_HIGH + MAX_HIGH) / 2;
+const ZERO: u16 = (MIN_LOW + MAX_LOW) / 2;
+/// Longer than any bit, but short of ending the capture
+const PREAMBLE: u16 = 2700;
+
+/// Nexus-TH frame of a reading, `channel` is 1 to 4. None if a value doesn't
+/// fit into its field.
+pub fn nexus(
+    id: u8,
+    channel: u8,
+    battery_ok: bool,
+    temp_decicelsius: i16,
+    humidity: u8,
+) -> Option<[(bool, u16); NEXUS_EDGES]> {
+    if !(1..=4).contains(&channel) || !(-2048..2048).contains(&temp_decicelsius) || humidity > 100 {
+        return None;
+    }
+    // ID, battery, a fixed 0, channel, temperature, fixed 1111 and humidity
+    let payload = (id as u64) << 28
+        | (battery_ok as u64) << 27
+        | ((channel - 1) as u64) << 24
+        | (temp_decicelsius as u16 as u64 & 0xfff) << 12
+        | 0xf << 8
+        | humidity as u64;
+
+    let mut edges = [(true, PULSE); NEXUS_EDGES];
+    edges[1] = (false, PREAMBLE);
+    for bit in 0..PAYLOAD_LEN_BITS {
+        let one = payload >> (PAYLOAD_LEN_BITS - 1 - bit) & 1 == 1;
+        edges[3 + 2 * bit] = (false, if one { ONE } else { ZERO });
+    }
+    Some(edges)
+}
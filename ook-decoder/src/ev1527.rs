@@ -0,0 +1,72 @@
+use crate::Symbol;
+use crate::timing::Timing;
+
+use super::sync::locate_frame;
+use super::{DecodeError, Profile, SensorData, SensorReading};
+
+// EV1527 based PIR motion sensors use pulse width modulation with base period
+// T of ~300-450 uS. 0 is T carrier followed by 3T pause, 1 is 3T carrier
+// followed by T pause. Frame is 24 bits followed by T sync pulse and 31T
+// pause, which ends the capture:
+//
+// AAAAAAAAAAAAAAAAAAAA DDDD, where:
+//
+// * A - address, unique per device
+// * D - key code, PIRs usually send a fixed one
+//
+// There is no checksum, so the frame is only accepted if every symbol has the
+// expected 1:3 ratio.
+
+const PERIOD_MIN: u16 = 900; // us
+const PERIOD_MAX: u16 = 2200; // us
+
+pub(crate) static PERIOD: Timing = Timing::new("ev1527_period", PERIOD_MIN, PERIOD_MAX);
+
+const PAYLOAD_LEN_BITS: usize = 24;
+
+// Every bit is a short segment of at most 3/8 of the period and a long one
+// with the rest
+#[cfg_attr(feature = "esp-hal", esp_hal::ram)]
+pub(super) fn profile() -> Option<Profile> {
+    let period = PERIOD.range();
+    Some(Profile::new(
+        &[
+            period.start / 8..period.end / 8 * 3,
+            period.start / 8 * 5..period.end - period.end / 8,
+        ],
+        2 * PAYLOAD_LEN_BITS,
+    ))
+}
+
+#[cfg_attr(feature = "esp-hal", esp_hal::ram)]
+pub(super) fn decode(pulses: &[Symbol]) -> Result<SensorData, DecodeError> {
+    // Frame is number of bits + sync pulse, sync pause is longer than the idle
+    // threshold so there is nothing to search for in front of it
+    let frame = locate_frame(pulses, PAYLOAD_LEN_BITS + 1, 0..0)?;
+
+    let mut code: u32 = 0;
+    for entry in &frame[..PAYLOAD_LEN_BITS] {
+        if !entry.level1 {
+            return Err(DecodeError::UnpackFailed);
+        }
+        let (high, low) = (entry.length1, entry.length2);
+        let period = high + low;
+        if !PERIOD.range().contains(&period) {
+            return Err(DecodeError::SampleOutOfRange(period));
+        }
+        // Short part should be a quarter of the period, allow some slack
+        let short = high.min(low);
+        if !(period / 8..period * 3 / 8).contains(&short) {
+            return Err(DecodeError::PulseOutOfRange(short));
+        }
+        code = (code << 1) | (high > low) as u32;
+    }
+
+    Ok(SensorData::new(
+        "EV1527-PIR",
+        SensorReading::Motion((code & 0x0f) as u8),
+        true,
+        0,
+        code >> 4,
+    ))
+}
@@ -0,0 +1,126 @@
+use crate::Symbol;
+use crate::timing::Timing;
+
+use super::bits::crc8;
+use super::{Climate, DecodeError, Profile, Rain, SensorData, SensorReading, Wind, edges};
+
+// Fine Offset WH1080 / WS-3080 weather station uses pulse width modulation:
+// short (~550 uS) carrier pulse is 1, long (~1500 uS) is 0, pause between
+// pulses is ~1000 uS. Frame is 8 bits of preamble (all ones) followed by
+// 10 bytes:
+//
+// AAAABBBB BBBBCCCC CCCCCCCC DDDDDDDD EEEEEEEE FFFFFFFF GGGGGGGG GGGGGGGG HHHHIIII JJJJJJJJ
+//
+// * A - message type, 0xa for weather data
+// * B - station ID
+// * C - temperature * 10 in C, bit 3 is sign, lowest 10 bits are magnitude
+// * D - humidity
+// * E - average wind speed, 0.34 m/s per unit
+// * F - gust wind speed, 0.34 m/s per unit
+// * G - rain counter, lower 12 bits, 0.3 mm per unit
+// * H - bit 0 is low battery
+// * I - wind direction, 22.5 degrees per unit
+// * J - CRC-8, poly 0x31, init 0 over first 9 bytes
+
+const SHORT_MIN: u16 = 350; // us
+const SHORT_MAX: u16 = 800; // us
+const LONG_MIN: u16 = 1200; // us
+const LONG_MAX: u16 = 1800; // us
+
+pub(crate) static SHORT: Timing = Timing::new("fineoffset_short", SHORT_MIN, SHORT_MAX);
+pub(crate) static LONG: Timing = Timing::new("fineoffset_long", LONG_MIN, LONG_MAX);
+
+const PAYLOAD_LEN_BYTES: usize = 10;
+const PAYLOAD_LEN_BITS: usize = PAYLOAD_LEN_BYTES * 8;
+const MAX_BITS: usize = PAYLOAD_LEN_BITS + 8;
+
+const MSG_TYPE_WEATHER: u8 = 0xa;
+const CRC_POLY: u8 = 0x31;
+
+// Every payload bit is a carrier pulse
+#[cfg_attr(feature = "esp-hal", esp_hal::ram)]
+pub(super) fn profile() -> Option<Profile> {
+    Some(Profile::new(
+        &[SHORT.range(), LONG.range()],
+        PAYLOAD_LEN_BITS,
+    ))
+}
+
+#[cfg_attr(feature = "esp-hal", esp_hal::ram)]
+pub(super) fn decode(pulses: &[Symbol]) -> Result<SensorData, DecodeError> {
+    // Capture should be number of bits + terminator, allow some of the
+    // preamble to be lost
+    let len = pulses.len();
+    if !(PAYLOAD_LEN_BITS + 1..=MAX_BITS + 1).contains(&len) {
+        return Err(DecodeError::WrongPayloadLen(len));
+    }
+
+    let mut bits = [false; MAX_BITS];
+    let mut count = 0;
+    for (level, length) in edges(pulses) {
+        if !level {
+            continue;
+        }
+        if count == MAX_BITS {
+            return Err(DecodeError::WrongPayloadLen(len));
+        }
+        bits[count] = if SHORT.range().contains(&length) {
+            true
+        } else if LONG.range().contains(&length) {
+            false
+        } else {
+            return Err(DecodeError::PulseOutOfRange(length));
+        };
+        count += 1;
+    }
+
+    if count < PAYLOAD_LEN_BITS {
+        return Err(DecodeError::WrongPayloadLen(len));
+    }
+
+    // Payload is aligned to the end of the frame
+    let mut data = [0u8; PAYLOAD_LEN_BYTES];
+    for (idx, bit) in bits[count - PAYLOAD_LEN_BITS..count].iter().enumerate() {
+        if *bit {
+            data[idx / 8] |= 1 << (7 - idx % 8);
+        }
+    }
+
+    let crc = crc8(&data[..9], CRC_POLY, 0);
+    if crc != data[9] {
+        return Err(DecodeError::ChecksumMismatch(crc, data[9]));
+    }
+
+    if data[0] >> 4 != MSG_TYPE_WEATHER {
+        return Err(DecodeError::UnpackFailed);
+    }
+
+    let id = (data[0] << 4) | (data[1] >> 4);
+
+    let mut temp_10x = (((data[1] & 0x03) as i16) << 8) | data[2] as i16;
+    if data[1] & 0x08 != 0 {
+        temp_10x = -temp_10x;
+    }
+    let humidity = data[3];
+
+    let wind = Wind {
+        avg_speed: data[4] as u16 * 34 / 10,
+        gust_speed: data[5] as u16 * 34 / 10,
+        direction: (data[8] & 0x0f) as u16 * 225 / 10,
+    };
+
+    let rain = Rain {
+        total: ((((data[6] & 0x0f) as u32) << 8) | data[7] as u32) * 3,
+        rate: None,
+    };
+
+    let battery_ok = data[8] & 0x10 == 0;
+
+    Ok(SensorData::new(
+        "WH1080",
+        SensorReading::Weather(Climate::new(temp_10x, Some(humidity)), wind, rain),
+        battery_ok,
+        0,
+        id.into(),
+    ))
+}
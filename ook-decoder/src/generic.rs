@@ -0,0 +1,299 @@
+use core::cell::RefCell;
+use core::ops::Range;
+
+use critical_section::Mutex;
+
+use crate::Symbol;
+use crate::bits::{bit, crc8, xor_bytes};
+use crate::learn::Modulation;
+use crate::timing::Timing;
+
+use super::sync::locate_frame;
+use super::{Climate, DecodeError, Profile, SensorData, SensorReading, TempUnit};
+
+// Simple PPM and PWM protocols can be defined at runtime, without
+// recompiling. A descriptor gives the segment lengths of a frame, its length,
+// where the fields are and how it's verified:
+//
+// * PPM - every bit is a carrier pulse within `pulse`, followed by a pause
+//   within `zero` or `one`
+// * PWM - every bit is a carrier pulse within `zero` or `one`, the pause
+//   after it isn't checked
+//
+// Frame is `bits` bits followed by a terminating carrier pulse, it starts
+// after a pause within `gap` if there is one. Fields are MSB first.
+
+/// Number of generic protocols that can be defined at once
+pub const GENERIC_SLOTS: usize = 4;
+/// Longest frame of a generic protocol
+pub const MAX_BITS: u8 = 64;
+
+/// Bits of a frame a value is read from
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Field {
+    pub offset: u8,
+    pub width: u8,
+}
+
+impl Field {
+    fn fits(&self, bits: u8, max_width: u8) -> bool {
+        (1..=max_width).contains(&self.width)
+            && self.offset as u16 + self.width as u16 <= bits as u16
+    }
+
+    #[cfg_attr(feature = "esp-hal", esp_hal::ram)]
+    fn read(&self, frame: &[u8]) -> u32 {
+        (self.offset..self.offset + self.width).fold(0, |value, idx| {
+            (value << 1) | bit(frame, idx as usize) as u32
+        })
+    }
+}
+
+/// Checksum over all whole bytes of the frame but the last one, which holds it
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Checksum {
+    /// Sum of bytes, modulo 256
+    Sum8,
+    Xor8,
+    /// CRC-8, MSB first, no final XOR
+    Crc8 {
+        poly: u8,
+        init: u8,
+    },
+}
+
+impl Checksum {
+    pub fn key(self) -> &'static str {
+        match self {
+            Checksum::Sum8 => "sum8",
+            Checksum::Xor8 => "xor8",
+            Checksum::Crc8 { .. } => "crc8",
+        }
+    }
+
+    #[cfg_attr(feature = "esp-hal", esp_hal::ram)]
+    fn compute(self, data: &[u8]) -> u8 {
+        match self {
+            Checksum::Sum8 => data.iter().fold(0, |acc: u8, byte| acc.wrapping_add(*byte)),
+            Checksum::Xor8 => xor_bytes(data),
+            Checksum::Crc8 { poly, init } => crc8(data, poly, init),
+        }
+    }
+}
+
+/// Protocol defined at runtime. Frames with a `temperature` field are climate
+/// readings, the rest are events carrying `code`, like a button or PIR.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Descriptor {
+    pub modulation: Modulation,
+    pub bits: u8,
+    /// Carrier pulse of every bit, only checked for PPM
+    pub pulse: Range<u16>,
+    pub zero: Range<u16>,
+    pub one: Range<u16>,
+    /// Pause in front of the frame
+    pub gap: Option<Range<u16>>,
+    pub id: Option<Field>,
+    /// 0-based in the frame, readings carry it 1-based
+    pub channel: Option<Field>,
+    /// Set while the battery is good
+    pub battery: Option<Field>,
+    /// Two's complement, in 0.1 of `unit`
+    pub temperature: Option<Field>,
+    pub unit: TempUnit,
+    /// In %, frames with more than 100 are rejected
+    pub humidity: Option<Field>,
+    pub code: Option<Field>,
+    pub checksum: Option<Checksum>,
+}
+
+impl Descriptor {
+    /// Frame fits the decoder, fields fit the frame and their readings
+    pub fn is_valid(&self) -> bool {
+        let ranges = [&self.pulse, &self.zero, &self.one];
+        let fields = [
+            (self.id, 32),
+            (self.channel, 8),
+            (self.battery, 1),
+            (self.temperature, 16),
+            (self.humidity, 8),
+            (self.code, 8),
+        ];
+        // Checksum is the last byte
+        let checksum = self.checksum.is_none() || (self.bits >= 16 && self.bits.is_multiple_of(8));
+        (1..=MAX_BITS).contains(&self.bits)
+            && ranges.iter().all(|range| !range.is_empty())
+            && self.gap.as_ref().is_none_or(|gap| !gap.is_empty())
+            // A bit must be unambiguous
+            && (self.zero.end <= self.one.start || self.one.end <= self.zero.start)
+            && fields.iter().all(|(field, max_width)| {
+                field.is_none_or(|field| field.fits(self.bits, *max_width))
+            })
+            && checksum
+    }
+
+    #[cfg_attr(feature = "esp-hal", esp_hal::ram)]
+    fn decode(&self, pulses: &[Symbol], model: &'static str) -> Result<SensorData, DecodeError> {
+        let bits = self.bits as usize;
+        let frame = locate_frame(pulses, bits + 1, self.gap.clone().unwrap_or(0..0))?;
+
+        let mut decoded = [0u8; MAX_BITS as usize / 8];
+        for (idx, entry) in frame[..bits].iter().enumerate() {
+            let (high, low) = if entry.level1 {
+                (entry.length1, entry.length2)
+            } else {
+                (entry.length2, entry.length1)
+            };
+            let sample = match self.modulation {
+                Modulation::Ppm if !self.pulse.contains(&high) => {
+                    return Err(DecodeError::PulseOutOfRange(high));
+                }
+                Modulation::Ppm => low,
+                Modulation::Pwm => high,
+            };
+            if self.one.contains(&sample) {
+                decoded[idx / 8] |= 1 << (7 - idx % 8);
+            } else if !self.zero.contains(&sample) {
+                return Err(DecodeError::SampleOutOfRange(sample));
+            }
+        }
+
+        if let Some(checksum) = self.checksum {
+            let len = bits / 8;
+            let expected = checksum.compute(&decoded[..len - 1]);
+            if expected != decoded[len - 1] {
+                return Err(DecodeError::ChecksumMismatch(expected, decoded[len - 1]));
+            }
+        }
+
+        let read = |field: Option<Field>| field.map(|field| field.read(&decoded));
+        let reading = match read(self.temperature) {
+            Some(raw) => {
+                let width = self.temperature.map_or(16, |field| field.width) as u32;
+                // Sign extend
+                let temp_10x = ((raw << (32 - width)) as i32 >> (32 - width)) as i16;
+                let humidity = read(self.humidity).map(|humidity| humidity as u8);
+                SensorReading::Climate(match self.unit {
+                    TempUnit::Celsius => Climate::new(temp_10x, humidity),
+                    TempUnit::Fahrenheit => Climate::from_fahrenheit(temp_10x, humidity),
+                })
+            }
+            None => SensorReading::Motion(read(self.code).unwrap_or(0) as u8),
+        };
+        Ok(SensorData::new(
+            model,
+            reading,
+            read(self.battery).is_none_or(|battery| battery != 0),
+            read(self.channel).map_or(0, |channel| channel as u8 + 1),
+            read(self.id).unwrap_or(0),
+        ))
+    }
+}
+
+/// Keys of the generic protocols in PROTOCOLS, in slot order
+pub const KEYS: [&str; GENERIC_SLOTS] = ["generic1", "generic2", "generic3", "generic4"];
+
+/// Where a generic protocol is defined, PROTOCOLS has an entry for each
+pub(crate) struct Slot {
+    model: &'static str,
+    /// Ranges of the descriptor, to score the timing fit
+    pub(crate) pulse: Timing,
+    pub(crate) zero: Timing,
+    pub(crate) one: Timing,
+}
+
+impl Slot {
+    const fn new(model: &'static str, timings: [&'static str; 3]) -> Self {
+        // Empty until a descriptor is loaded
+        Slot {
+            model,
+            pulse: Timing::new(timings[0], 0, 0),
+            zero: Timing::new(timings[1], 0, 0),
+            one: Timing::new(timings[2], 0, 0),
+        }
+    }
+}
+
+pub(crate) static SLOTS: [Slot; GENERIC_SLOTS] = [
+    Slot::new(
+        "Generic-1",
+        ["generic1_pulse", "generic1_zero", "generic1_one"],
+    ),
+    Slot::new(
+        "Generic-2",
+        ["generic2_pulse", "generic2_zero", "generic2_one"],
+    ),
+    Slot::new(
+        "Generic-3",
+        ["generic3_pulse", "generic3_zero", "generic3_one"],
+    ),
+    Slot::new(
+        "Generic-4",
+        ["generic4_pulse", "generic4_zero", "generic4_one"],
+    ),
+];
+
+static DESCRIPTORS: Mutex<RefCell<[Option<Descriptor>; GENERIC_SLOTS]>> =
+    Mutex::new(RefCell::new([None, None, None, None]));
+
+/// Index of the slot with protocol `key`
+pub fn find_slot(key: &str) -> Option<usize> {
+    KEYS.iter().position(|&other| other == key)
+}
+
+pub fn descriptor(slot: usize) -> Option<Descriptor> {
+    critical_section::with(|cs| DESCRIPTORS.borrow_ref(cs)[slot].clone())
+}
+
+/// Define the protocol of `slot`, or clear it with None. Returns false if
+/// the descriptor is invalid, leaving the slot as it was.
+pub fn load(slot: usize, descriptor: Option<Descriptor>) -> bool {
+    if let Some(descriptor) = &descriptor {
+        if !descriptor.is_valid() {
+            return false;
+        }
+        let slot = &SLOTS[slot];
+        // PWM has no fixed pulse, both bit pulses fit it
+        let pulse = match descriptor.modulation {
+            Modulation::Ppm => descriptor.pulse.clone(),
+            Modulation::Pwm => {
+                descriptor.zero.start.min(descriptor.one.start)
+                    ..descriptor.zero.end.max(descriptor.one.end)
+            }
+        };
+        slot.pulse.set(pulse);
+        slot.zero.set(descriptor.zero.clone());
+        slot.one.set(descriptor.one.clone());
+    }
+    critical_section::with(|cs| DESCRIPTORS.borrow_ref_mut(cs)[slot] = descriptor);
+    true
+}
+
+/// PPM bits are a pulse and a pause within the descriptor ranges, PWM ones
+/// only have the pulse checked
+#[cfg_attr(feature = "esp-hal", esp_hal::ram)]
+pub(crate) fn profile<const SLOT: usize>() -> Option<Profile> {
+    let (modulation, bits) = critical_section::with(|cs| {
+        let descriptors = DESCRIPTORS.borrow_ref(cs);
+        let descriptor = descriptors[SLOT].as_ref()?;
+        Some((descriptor.modulation, descriptor.bits as usize))
+    })?;
+    let slot = &SLOTS[SLOT];
+    let count = match modulation {
+        Modulation::Ppm => 2 * bits,
+        Modulation::Pwm => bits,
+    };
+    Some(Profile::new(
+        &[slot.pulse.range(), slot.zero.range(), slot.one.range()],
+        count,
+    ))
+}
+
+#[cfg_attr(feature = "esp-hal", esp_hal::ram)]
+pub(crate) fn decode<const SLOT: usize>(pulses: &[Symbol]) -> Result<SensorData, DecodeError> {
+    let descriptor = critical_section::with(|cs| DESCRIPTORS.borrow_ref(cs)[SLOT].clone());
+    match descriptor {
+        Some(descriptor) => descriptor.decode(pulses, SLOTS[SLOT].model),
+        None => Err(DecodeError::WrongPayloadLen(pulses.len())),
+    }
+}
@@ -0,0 +1,86 @@
+use crate::Symbol;
+use crate::timing::Timing;
+
+use super::sync::locate_frame;
+use super::{DecodeError, Leak, Profile, SensorData, SensorReading};
+
+// Govee H5054 water leak detectors use pulse width modulation with bit period
+// of ~1400 uS. 1 is ~440 uS carrier followed by ~940 uS pause, 0 is the other
+// way around. Frame is 48 bits followed by a lone pulse and a long pause,
+// which ends the capture:
+//
+// IIIIIIII IIIIIIII EEEEEEEE EEEEEEEE XXXXXXXX XXXXXXXX, where:
+//
+// * I - device ID
+// * E - event code, sent twice:
+//   * 0xfa - water detected
+//   * 0xaf - button pressed
+// * X - differ between units and events, not understood
+//
+// There is no checksum. The frame is only accepted if both copies of the
+// event code match a known one and every symbol has the expected 1:2 ratio.
+// Battery reports use other codes and are dropped.
+
+const PERIOD_MIN: u16 = 1100; // us
+const PERIOD_MAX: u16 = 1800; // us
+
+pub(crate) static PERIOD: Timing = Timing::new("govee_period", PERIOD_MIN, PERIOD_MAX);
+
+const PAYLOAD_LEN_BITS: usize = 48;
+
+const EVENT_WATER: u8 = 0xfa;
+const EVENT_BUTTON: u8 = 0xaf;
+
+// Every bit is a short segment of about a third of the period and a long one
+// with the rest
+#[cfg_attr(feature = "esp-hal", esp_hal::ram)]
+pub(super) fn profile() -> Option<Profile> {
+    let period = PERIOD.range();
+    Some(Profile::new(
+        &[
+            period.start / 5..period.end / 5 * 2,
+            period.start / 5 * 3..period.end - period.end / 5,
+        ],
+        2 * PAYLOAD_LEN_BITS,
+    ))
+}
+
+#[cfg_attr(feature = "esp-hal", esp_hal::ram)]
+pub(super) fn decode(pulses: &[Symbol]) -> Result<SensorData, DecodeError> {
+    let frame = locate_frame(pulses, PAYLOAD_LEN_BITS + 1, 0..0)?;
+
+    let mut bytes = [0u8; PAYLOAD_LEN_BITS / 8];
+    for (idx, entry) in frame[..PAYLOAD_LEN_BITS].iter().enumerate() {
+        if !entry.level1 {
+            return Err(DecodeError::UnpackFailed);
+        }
+        let (high, low) = (entry.length1, entry.length2);
+        let period = high + low;
+        if !PERIOD.range().contains(&period) {
+            return Err(DecodeError::SampleOutOfRange(period));
+        }
+        // Short part should be a third of the period, allow some slack
+        let short = high.min(low);
+        if !(period / 5..period * 2 / 5).contains(&short) {
+            return Err(DecodeError::PulseOutOfRange(short));
+        }
+        bytes[idx / 8] = (bytes[idx / 8] << 1) | (high < low) as u8;
+    }
+
+    if bytes[2] != bytes[3] {
+        return Err(DecodeError::FixedBitsMismatch(bytes[3]));
+    }
+    let water = match bytes[2] {
+        EVENT_WATER => true,
+        EVENT_BUTTON => false,
+        event => return Err(DecodeError::FixedBitsMismatch(event)),
+    };
+
+    Ok(SensorData::new(
+        "Govee-Water",
+        SensorReading::Leak(Leak { water }),
+        true,
+        0,
+        u16::from_be_bytes([bytes[0], bytes[1]]) as u32,
+    ))
+}
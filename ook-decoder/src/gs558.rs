@@ -0,0 +1,90 @@
+use core::ops::Range;
+
+use crate::Symbol;
+use crate::timing::Timing;
+
+use super::sync::locate_frame;
+use super::{DecodeError, Hazard, Profile, SensorData, SensorReading};
+
+// Globaltronics GS 558 interlinked smoke alarms (also sold as Fartools
+// FA-SD-2 and under other brands) transmit while they sound. They use pulse
+// width modulation with bit period of ~1730 uS. 0 is ~440 uS carrier followed
+// by ~1300 uS pause, 1 is ~1200 uS carrier followed by ~530 uS pause. A frame
+// is a wake-up pulse and ~11.8 mS pause, 24 bits, then two short stop pulses
+// within a single bit period:
+//
+// IIIIIIII IIIIIIII IIIIIIII, where:
+//
+// * I - code, set at the factory. Alarms are interlinked by pairing them to
+//   the same code, so the whole group shares it.
+//
+// There is no checksum, so the frame is only accepted if every symbol has the
+// expected 1:3 ratio and it ends with the stop pulses.
+
+const PERIOD_MIN: u16 = 1400; // us
+const PERIOD_MAX: u16 = 2100; // us
+const STOP_MIN: u16 = 300; // us
+const STOP_MAX: u16 = 600; // us
+const WAKE_UP_GAP: Range<u16> = 10000..14000; // us
+
+pub(crate) static PERIOD: Timing = Timing::new("gs558_period", PERIOD_MIN, PERIOD_MAX);
+
+const PAYLOAD_LEN_BITS: usize = 24;
+
+// Every bit is a short segment of at most 3/8 of the period and a long one
+// with the rest
+#[cfg_attr(feature = "esp-hal", esp_hal::ram)]
+pub(super) fn profile() -> Option<Profile> {
+    let period = PERIOD.range();
+    Some(Profile::new(
+        &[
+            period.start / 8..period.end / 8 * 3,
+            period.start / 8 * 5..period.end - period.end / 8,
+        ],
+        2 * PAYLOAD_LEN_BITS,
+    ))
+}
+
+#[cfg_attr(feature = "esp-hal", esp_hal::ram)]
+pub(super) fn decode(pulses: &[Symbol]) -> Result<SensorData, DecodeError> {
+    // Frame is number of bits + both stop pulses, the last one ending the
+    // capture
+    let frame = locate_frame(pulses, PAYLOAD_LEN_BITS + 2, WAKE_UP_GAP)?;
+
+    let stop = STOP_MIN..STOP_MAX;
+    let (first, last) = (frame[PAYLOAD_LEN_BITS], frame[PAYLOAD_LEN_BITS + 1]);
+    for length in [first.length1, first.length2, last.length1] {
+        if !stop.contains(&length) {
+            return Err(DecodeError::PulseOutOfRange(length));
+        }
+    }
+    if !first.level1 || !last.level1 {
+        return Err(DecodeError::UnpackFailed);
+    }
+
+    let mut code: u32 = 0;
+    for entry in &frame[..PAYLOAD_LEN_BITS] {
+        if !entry.level1 {
+            return Err(DecodeError::UnpackFailed);
+        }
+        let (high, low) = (entry.length1, entry.length2);
+        let period = high + low;
+        if !PERIOD.range().contains(&period) {
+            return Err(DecodeError::SampleOutOfRange(period));
+        }
+        // Short part should be a quarter of the period, allow some slack
+        let short = high.min(low);
+        if !(period / 8..period * 3 / 8).contains(&short) {
+            return Err(DecodeError::PulseOutOfRange(short));
+        }
+        code = (code << 1) | (high > low) as u32;
+    }
+
+    Ok(SensorData::new(
+        "GS558-Smoke",
+        SensorReading::Hazard(Hazard::Smoke),
+        true,
+        0,
+        code,
+    ))
+}
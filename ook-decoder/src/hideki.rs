@@ -0,0 +1,157 @@
+use crate::Symbol;
+use crate::timing::Timing;
+
+use super::bits::{bit, crc8, parity8, reverse8, xor_bytes};
+use super::{Climate, DecodeError, Profile, SensorData, SensorReading, edges};
+
+// Hideki (TFA Dostmann 30.3180 and friends) uses differential Manchester
+// coding: there is a level change at every bit boundary, and an extra one in
+// the middle of the bit for 1. So 1 is two short (~520 uS) segments and 0 is
+// one long (~1040 uS) segment, regardless of carrier level.
+//
+// Data is sent inverted as 8-bit units, LSB first, each followed by an even
+// parity bit. Once unstuffed and unscrambled, the packet is:
+//
+// 0x9f CCCCRRRR LLLLLLLx XXXTTTTT ... XOR CRC, where:
+//
+// * C - channel (>= 5 is shifted by one)
+// * R - rolling code, used as ID
+// * L - length
+// * T - sensor type
+// * XOR - XOR of all bytes past the header
+// * CRC - CRC-8, poly 0x07 over all bytes past the header
+//
+// For thermo/hygro sensors the payload is:
+//
+// * byte 4 - BCD temperature, tens and ones of 0.1C
+// * byte 5 - S B x x HHHH: S - sign (1 is positive), B - battery OK,
+//   H - BCD hundreds of 0.1C
+// * byte 6 - BCD humidity
+
+const HALF_MIN: u16 = 300; // us
+const HALF_MAX: u16 = 780; // us
+const FULL_MIN: u16 = 780; // us
+const FULL_MAX: u16 = 1300; // us
+
+pub(crate) static HALF: Timing = Timing::new("hideki_half", HALF_MIN, HALF_MAX);
+pub(crate) static FULL: Timing = Timing::new("hideki_full", FULL_MIN, FULL_MAX);
+
+const MAX_BYTES: usize = 13;
+const MAX_BITS: usize = MAX_BYTES * 9;
+
+const HEADER: u8 = 0x9f;
+const CRC_POLY: u8 = 0x07;
+
+const TYPE_TS04: u8 = 0x1e;
+const TYPE_TEMP: u8 = 0x1f;
+
+#[cfg_attr(feature = "esp-hal", esp_hal::ram)]
+fn bcd(byte: u8) -> u16 {
+    ((byte >> 4) as u16) * 10 + (byte & 0x0f) as u16
+}
+
+// Every bit is one full or two half segments, and the shortest packet needs
+// all but 4 bits of its 8 units
+#[cfg_attr(feature = "esp-hal", esp_hal::ram)]
+pub(super) fn profile() -> Option<Profile> {
+    Some(Profile::new(&[HALF.range(), FULL.range()], 8 * 9 - 4))
+}
+
+#[cfg_attr(feature = "esp-hal", esp_hal::ram)]
+pub(super) fn decode(pulses: &[Symbol]) -> Result<SensorData, DecodeError> {
+    // Shortest packet is 8 units of 9 bits, every bit takes at most 2 segments
+    // and every symbol holds 2 segments.
+    let len = pulses.len();
+    if len < 8 * 9 / 2 {
+        return Err(DecodeError::WrongPayloadLen(len));
+    }
+
+    let mut raw = [0u8; MAX_BITS.div_ceil(8) + 1];
+    let mut bits = 0;
+    let mut half = false;
+    for (_, length) in edges(pulses) {
+        if bits == MAX_BITS {
+            break;
+        }
+        if HALF.range().contains(&length) {
+            if half {
+                raw[bits / 8] |= 1 << (7 - bits % 8);
+                bits += 1;
+            }
+            half = !half;
+        } else if FULL.range().contains(&length) && !half {
+            bits += 1;
+        } else if bits < 8 * 9 {
+            return Err(DecodeError::SampleOutOfRange(length));
+        } else {
+            break;
+        }
+    }
+
+    // Allow up to 4 missing bits at the end
+    let units = match (bits + 4) / 9 {
+        n @ (8..=10) => n,
+        n @ (13..=14) => n.min(MAX_BYTES),
+        _ => return Err(DecodeError::WrongPayloadLen(len)),
+    };
+
+    let mut packet = [0u8; MAX_BYTES];
+    for (idx, byte) in packet.iter_mut().enumerate().take(units) {
+        let offset = idx * 9;
+        let mut value = 0;
+        for n in 0..8 {
+            value = (value << 1) | !bit(&raw, offset + n) as u8;
+        }
+        // Last segment merges with the idle period, so the final parity bit
+        // may be missing
+        let parity = !bit(&raw, offset + 8) as u8;
+        if offset + 8 < bits && parity8(value) != parity {
+            return Err(DecodeError::ChecksumMismatch(value, parity));
+        }
+        *byte = reverse8(value);
+    }
+    let packet = &packet[..units];
+
+    if packet[0] != HEADER {
+        return Err(DecodeError::UnpackFailed);
+    }
+
+    let xor = xor_bytes(&packet[1..units - 2]);
+    if xor != packet[units - 2] {
+        return Err(DecodeError::ChecksumMismatch(xor, packet[units - 2]));
+    }
+    let crc = crc8(&packet[1..units - 1], CRC_POLY, 0);
+    if crc != packet[units - 1] {
+        return Err(DecodeError::ChecksumMismatch(crc, packet[units - 1]));
+    }
+
+    let sensor_type = packet[3] & 0x1f;
+    if sensor_type != TYPE_TS04 && sensor_type != TYPE_TEMP {
+        return Err(DecodeError::UnpackFailed);
+    }
+
+    let mut channel = (packet[1] >> 5) & 0x07;
+    if channel >= 5 {
+        channel -= 1;
+    }
+    let id = packet[1] & 0x0f;
+
+    let mut temp_10x = (((packet[5] & 0x0f) as u16) * 100 + bcd(packet[4])) as i16;
+    if packet[5] & 0x80 == 0 {
+        temp_10x = -temp_10x;
+    }
+    let battery_ok = packet[5] & 0x40 != 0;
+    let humidity = if sensor_type == TYPE_TS04 {
+        Some(bcd(packet[6]) as u8)
+    } else {
+        None
+    };
+
+    Ok(SensorData::new(
+        "Hideki",
+        SensorReading::Climate(Climate::new(temp_10x, humidity)),
+        battery_ok,
+        channel,
+        id.into(),
+    ))
+}
@@ -0,0 +1,140 @@
+use crate::Symbol;
+use crate::timing::Timing;
+
+use super::bits::crc16;
+use super::{
+    Contact, DecodeError, Profile, SensorData, SensorReading, manchester_bits, manchester_halves,
+};
+
+// Honeywell 5800-style door/window contacts (and many OOK clones) use
+// Manchester coding with ~150 uS half-bit period. Frame is 64 bits:
+//
+// PPPPPPPP PPPPPPPP CCCCIIII IIIIIIII IIIIIIII EEEEEEEE SSSSSSSS SSSSSSSS, where:
+//
+// * P - preamble and sync, 0xfffe
+// * C - brand/loop channel
+// * I - device serial number
+// * E - event flags:
+//   * 0x80 - contact open
+//   * 0x40 - tamper
+//   * 0x20 - reed switch open
+//   * 0x08 - low battery
+//   * 0x04 - heartbeat
+// * S - CRC-16 over preamble and data, poly 0x8050 for 2GIG channels
+//   (0x2, 0x4, 0xa), 0x8005 for everything else
+//
+// Depending on receiver the frame may arrive inverted, so the sync is searched
+// for with both polarities.
+
+const HALF_MIN: u16 = 80; // us
+const HALF_MAX: u16 = 230; // us
+const FULL_MIN: u16 = 230; // us
+const FULL_MAX: u16 = 400; // us
+
+pub(crate) static HALF: Timing = Timing::new("honeywell_half", HALF_MIN, HALF_MAX);
+pub(crate) static FULL: Timing = Timing::new("honeywell_full", FULL_MIN, FULL_MAX);
+
+const MAX_HALF_BITS: usize = 160;
+const MAX_BITS: usize = MAX_HALF_BITS / 2;
+const MIN_PREAMBLE_BITS: usize = 12;
+const DATA_LEN_BITS: usize = 48;
+
+const PREAMBLE: [u8; 2] = [0xff, 0xfe];
+const CRC_POLY: u16 = 0x8005;
+const CRC_POLY_2GIG: u16 = 0x8050;
+
+const EVENT_OPEN: u8 = 0x80;
+const EVENT_TAMPER: u8 = 0x40;
+const EVENT_REED: u8 = 0x20;
+const EVENT_BATTERY_LOW: u8 = 0x08;
+const EVENT_HEARTBEAT: u8 = 0x04;
+
+#[cfg_attr(feature = "esp-hal", esp_hal::ram)]
+fn data_from_halves(halves: &[bool], phase: usize) -> Option<[u8; DATA_LEN_BITS / 8]> {
+    let mut bits = [false; MAX_BITS];
+    let count = manchester_bits(halves, phase, &mut bits);
+    let bits = &bits[..count];
+
+    // Look for run of identical preamble bits followed by the sync bit
+    let mut run = 1;
+    let mut start = None;
+    for idx in 1..bits.len() {
+        if bits[idx] == bits[idx - 1] {
+            run += 1;
+            continue;
+        }
+        if run >= MIN_PREAMBLE_BITS {
+            start = Some((idx + 1, !bits[idx - 1]));
+            break;
+        }
+        run = 1;
+    }
+    let (start, inverted) = start?;
+    if bits.len() < start + DATA_LEN_BITS {
+        return None;
+    }
+
+    let mut data = [0u8; DATA_LEN_BITS / 8];
+    for (idx, bit) in bits[start..start + DATA_LEN_BITS].iter().enumerate() {
+        if bit ^ inverted {
+            data[idx / 8] |= 1 << (7 - idx % 8);
+        }
+    }
+    Some(data)
+}
+
+// Manchester bits take at least one segment each
+#[cfg_attr(feature = "esp-hal", esp_hal::ram)]
+pub(super) fn profile() -> Option<Profile> {
+    Some(Profile::new(
+        &[HALF.range(), FULL.range()],
+        MIN_PREAMBLE_BITS + DATA_LEN_BITS,
+    ))
+}
+
+#[cfg_attr(feature = "esp-hal", esp_hal::ram)]
+pub(super) fn decode(pulses: &[Symbol]) -> Result<SensorData, DecodeError> {
+    // Preamble and data take at least this many symbols
+    if pulses.len() < 32 {
+        return Err(DecodeError::WrongPayloadLen(pulses.len()));
+    }
+
+    let mut halves = [false; MAX_HALF_BITS];
+    let count = manchester_halves(pulses, HALF.range(), FULL.range(), &mut halves);
+
+    let data = data_from_halves(&halves[..count], 0)
+        .or_else(|| data_from_halves(&halves[..count], 1))
+        .ok_or(DecodeError::UnpackFailed)?;
+
+    let channel = data[0] >> 4;
+    let poly = match channel {
+        0x2 | 0x4 | 0xa => CRC_POLY_2GIG,
+        _ => CRC_POLY,
+    };
+    let crc = crc16(
+        &[PREAMBLE[0], PREAMBLE[1], data[0], data[1], data[2], data[3]],
+        poly,
+        0,
+    );
+    let expected = ((data[4] as u16) << 8) | data[5] as u16;
+    if crc != expected {
+        return Err(DecodeError::ChecksumMismatch(crc as u8, expected as u8));
+    }
+
+    let id = (((data[0] & 0x0f) as u32) << 16) | ((data[1] as u32) << 8) | data[2] as u32;
+    let event = data[3];
+
+    let contact = Contact {
+        open: event & (EVENT_OPEN | EVENT_REED) != 0,
+        tamper: event & EVENT_TAMPER != 0,
+        heartbeat: event & EVENT_HEARTBEAT != 0,
+    };
+
+    Ok(SensorData::new(
+        "Honeywell-Contact",
+        SensorReading::Contact(contact),
+        event & EVENT_BATTERY_LOW == 0,
+        0,
+        id,
+    ))
+}
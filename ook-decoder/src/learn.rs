@@ -0,0 +1,244 @@
+use core::cmp::Reverse;
+use core::ops::Range;
+
+use crate::{Symbol, edges};
+
+// Lengths within this share of a cluster's mean are taken for the same symbol
+const TOLERANCE_PCT: u32 = 20;
+// Clusters kept per level, lengths fitting none of them once full are dropped
+const MAX_CLUSTERS: usize = 8;
+// Distinct frame lengths kept
+const MAX_FRAMES: usize = 8;
+// Shorter runs of bits are noise or a preamble
+const MIN_BITS: u16 = 8;
+
+/// How bits are encoded in the segments of a frame
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Modulation {
+    /// Pulse-position: fixed carrier pulse, the pause after it is the bit
+    Ppm,
+    /// Pulse-width: carrier pulse is the bit, the pause after it fills the
+    /// period
+    Pwm,
+}
+
+impl Modulation {
+    pub fn key(self) -> &'static str {
+        match self {
+            Modulation::Ppm => "ppm",
+            Modulation::Pwm => "pwm",
+        }
+    }
+}
+
+/// Segment lengths of a protocol inferred from captures of an unknown sensor
+#[derive(Clone, Debug, PartialEq)]
+pub struct Suggestion {
+    pub modulation: Modulation,
+    /// Carrier pulse every PPM bit starts with, None for PWM
+    pub pulse: Option<Range<u16>>,
+    /// Pause (PPM) or carrier pulse (PWM) of 0 and 1 bits, the shorter one is
+    /// taken for 0
+    pub zero: Range<u16>,
+    pub one: Range<u16>,
+    /// Pause in front of frames, if transmissions repeat them
+    pub gap: Option<Range<u16>>,
+    /// Bits in a frame
+    pub bits: u16,
+    /// Frames of that length the suggestion is based on
+    pub frames: u16,
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+struct Cluster {
+    sum: u32,
+    count: u16,
+}
+
+impl Cluster {
+    fn mean(&self) -> u16 {
+        (self.sum / self.count.max(1) as u32) as u16
+    }
+
+    fn fits(&self, length: u16) -> bool {
+        let mean = self.mean() as u32;
+        (length as u32).abs_diff(mean) <= mean * TOLERANCE_PCT / 100
+    }
+
+    fn range(&self) -> Range<u16> {
+        let mean = self.mean() as u32;
+        let slack = mean * TOLERANCE_PCT / 100;
+        (mean - slack) as u16..(mean + slack + 1).min(u16::MAX as u32) as u16
+    }
+}
+
+/// Segment lengths of one level grouped by similarity, most frequent first
+#[derive(Debug, Default)]
+struct Clusters {
+    list: [Cluster; MAX_CLUSTERS],
+    len: usize,
+}
+
+impl Clusters {
+    fn add(&mut self, length: u16) {
+        let list = &mut self.list[..self.len];
+        if let Some(cluster) = list.iter_mut().find(|cluster| cluster.fits(length)) {
+            // Plenty to learn from, keep the mean from overflowing
+            if cluster.count < u16::MAX {
+                cluster.sum += length as u32;
+                cluster.count += 1;
+            }
+        } else if self.len < MAX_CLUSTERS {
+            self.list[self.len] = Cluster {
+                sum: length as u32,
+                count: 1,
+            };
+            self.len += 1;
+        }
+        self.list[..self.len].sort_unstable_by_key(|cluster| Reverse(cluster.count));
+    }
+
+    fn get(&self, idx: usize) -> Option<&Cluster> {
+        self.list[..self.len].get(idx)
+    }
+}
+
+/// Segments a bit is made of
+struct Roles {
+    modulation: Modulation,
+    /// Carrier pulses and pauses that can make up a bit
+    highs: [Range<u16>; 2],
+    lows: [Range<u16>; 2],
+}
+
+impl Roles {
+    fn is_bit(&self, high: u16, low: u16) -> bool {
+        self.highs.iter().any(|range| range.contains(&high))
+            && self.lows.iter().any(|range| range.contains(&low))
+    }
+}
+
+/// Guided learning of an unknown protocol. Fed with captures of the same
+/// sensor, it clusters the pulse and pause lengths and counts the bits of
+/// frames, then suggests the timings and length of a PPM or PWM protocol.
+/// Manchester coded protocols aren't recognized.
+#[derive(Debug, Default)]
+pub struct Learner {
+    highs: Clusters,
+    lows: Clusters,
+    /// Frame lengths in bits, with the number of frames seen
+    frames: [(u16, u16); MAX_FRAMES],
+    bursts: u16,
+}
+
+impl Learner {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of captures fed so far
+    pub fn bursts(&self) -> u16 {
+        self.bursts
+    }
+
+    /// Learn from a capture, including the terminator
+    pub fn feed(&mut self, pulses: &[Symbol]) {
+        self.bursts = self.bursts.saturating_add(1);
+        for (level, length) in edges(pulses) {
+            if level {
+                self.highs.add(length);
+            } else {
+                self.lows.add(length);
+            }
+        }
+        // Clusters already hold every kind of segment of this capture
+        let Some(roles) = self.roles() else {
+            return;
+        };
+        let mut bits = 0;
+        let mut high = None;
+        for (level, length) in edges(pulses) {
+            match (level, high.take()) {
+                (true, _) => high = Some(length),
+                (false, Some(high)) if roles.is_bit(high, length) => bits += 1,
+                _ => self.frame(core::mem::take(&mut bits)),
+            }
+        }
+        // Trailing pulse has no pause, it's a terminator
+        self.frame(bits);
+    }
+
+    fn frame(&mut self, bits: u16) {
+        if bits < MIN_BITS {
+            return;
+        }
+        let frames = &mut self.frames;
+        if let Some(entry) = frames.iter_mut().find(|(len, _)| *len == bits) {
+            entry.1 = entry.1.saturating_add(1);
+        } else if let Some(entry) = frames.iter_mut().find(|(_, count)| *count == 0) {
+            *entry = (bits, 1);
+        }
+    }
+
+    fn roles(&self) -> Option<Roles> {
+        let (zero, one) = shorter_first(self.lows.get(0)?, self.lows.get(1)?);
+        let first = self.highs.get(0)?;
+        // Second pulse length is a protocol symbol only if it's about as
+        // frequent as the first, otherwise it's noise or a sync pulse
+        match self.highs.get(1) {
+            Some(second) if second.count >= first.count / 4 => Some(Roles {
+                modulation: Modulation::Pwm,
+                highs: [first.range(), second.range()],
+                lows: [zero.range(), one.range()],
+            }),
+            _ => Some(Roles {
+                modulation: Modulation::Ppm,
+                highs: [first.range(), first.range()],
+                lows: [zero.range(), one.range()],
+            }),
+        }
+    }
+
+    /// Protocol that fits the captures best, None until a frame of at least
+    /// MIN_BITS bits is seen
+    pub fn suggest(&self) -> Option<Suggestion> {
+        let roles = self.roles()?;
+        // Most frequent length, ties go to the longer frame
+        let (bits, frames) = self
+            .frames
+            .iter()
+            .copied()
+            .filter(|(_, count)| *count > 0)
+            .max_by_key(|&(len, count)| (count, len))?;
+        // Pause longer than any bit, the most frequent one if there are several
+        let longest = roles.lows[1].end;
+        let gap = self.lows.list[..self.lows.len]
+            .iter()
+            .find(|cluster| cluster.range().start >= longest)
+            .map(Cluster::range);
+        let (zero, one, pulse) = match roles.modulation {
+            Modulation::Ppm => {
+                let [zero, one] = roles.lows;
+                let [pulse, _] = roles.highs;
+                (zero, one, Some(pulse))
+            }
+            Modulation::Pwm => {
+                let (zero, one) = shorter_first(self.highs.get(0)?, self.highs.get(1)?);
+                (zero.range(), one.range(), None)
+            }
+        };
+        Some(Suggestion {
+            modulation: roles.modulation,
+            pulse,
+            zero,
+            one,
+            gap,
+            bits,
+            frames,
+        })
+    }
+}
+
+fn shorter_first<'a>(a: &'a Cluster, b: &'a Cluster) -> (&'a Cluster, &'a Cluster) {
+    if a.mean() <= b.mean() { (a, b) } else { (b, a) }
+}
@@ -0,0 +1,1283 @@
+#![no_std]
+
+use core::fmt;
+use core::ops::Range;
+use core::sync::atomic::{AtomicU32, Ordering};
+
+use timing::Timing;
+
+pub mod bits;
+pub mod encode;
+#[cfg(feature = "proto-ev1527")]
+mod ev1527;
+#[cfg(feature = "proto-fineoffset")]
+mod fineoffset;
+#[cfg(feature = "proto-generic")]
+pub mod generic;
+#[cfg(feature = "proto-govee")]
+mod govee;
+#[cfg(feature = "proto-gs558")]
+mod gs558;
+#[cfg(feature = "proto-hideki")]
+mod hideki;
+#[cfg(feature = "proto-honeywell")]
+mod honeywell;
+pub mod learn;
+#[cfg(feature = "proto-nexus")]
+mod nexus;
+#[cfg(feature = "proto-oregon")]
+mod oregon;
+#[cfg(feature = "proto-rubicson")]
+mod rubicson;
+pub mod sync;
+pub mod timing;
+
+#[cfg(not(any(
+    feature = "proto-hideki",
+    feature = "proto-fineoffset",
+    feature = "proto-oregon",
+    feature = "proto-honeywell",
+    feature = "proto-ev1527",
+    feature = "proto-govee",
+    feature = "proto-gs558",
+    feature = "proto-rubicson",
+    feature = "proto-nexus",
+    feature = "proto-generic",
+)))]
+compile_error!("At least one protocol feature (proto-*) must be enabled");
+
+pub const PAYLOAD_LEN_BITS: usize = 36;
+// Payload is 36 bits, 36 / 5 = 4.5 bytes, round up to 5 bytes
+pub const PAYLOAD_LEN_BYTES: usize = 5;
+
+/// Longest capture the decoders handle, in symbols
+pub const MAX_SYMBOLS: usize = 128;
+
+pub const PULSE_MIN: u16 = 300; // us
+pub const PULSE_MAX: u16 = 650; // us
+
+pub const MIN_HIGH: u16 = 1650;
+pub const MAX_HIGH: u16 = 2150;
+pub const MIN_LOW: u16 = 800;
+pub const MAX_LOW: u16 = 1100;
+
+#[cfg(any(feature = "proto-rubicson", feature = "proto-nexus"))]
+pub(crate) static PPM_PULSE: Timing = Timing::new("ppm_pulse", PULSE_MIN, PULSE_MAX);
+#[cfg(any(feature = "proto-rubicson", feature = "proto-nexus"))]
+pub(crate) static PPM_ONE: Timing = Timing::new("ppm_one", MIN_HIGH, MAX_HIGH);
+#[cfg(any(feature = "proto-rubicson", feature = "proto-nexus"))]
+pub(crate) static PPM_ZERO: Timing = Timing::new("ppm_zero", MIN_LOW, MAX_LOW);
+
+/// Two consecutive segments of a capture, the way RMT peripherals store them.
+/// `true` level is carrier present, lengths are in uS, zero length terminates
+/// the capture.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Symbol {
+    pub level1: bool,
+    pub length1: u16,
+    pub level2: bool,
+    pub length2: u16,
+}
+
+impl Symbol {
+    pub const fn new(level1: bool, length1: u16, level2: bool, length2: u16) -> Self {
+        Symbol {
+            level1,
+            length1,
+            level2,
+            length2,
+        }
+    }
+
+    /// Symbol with both lengths zero, terminates the capture
+    pub const fn end_marker() -> Self {
+        Symbol::new(false, 0, false, 0)
+    }
+}
+
+#[cfg(feature = "esp-hal")]
+impl From<esp_hal::rmt::PulseCode> for Symbol {
+    #[esp_hal::ram]
+    fn from(code: esp_hal::rmt::PulseCode) -> Self {
+        use esp_hal::gpio::Level;
+
+        Symbol::new(
+            code.level1() == Level::High,
+            code.length1(),
+            code.level2() == Level::High,
+            code.length2(),
+        )
+    }
+}
+
+#[cfg(feature = "esp-hal")]
+impl From<Symbol> for esp_hal::rmt::PulseCode {
+    fn from(symbol: Symbol) -> Self {
+        use esp_hal::gpio::Level;
+
+        esp_hal::rmt::PulseCode::new_clamped(
+            Level::from(symbol.level1),
+            symbol.length1,
+            Level::from(symbol.level2),
+            symbol.length2,
+        )
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum DecodeError {
+    WrongPayloadLen(usize),
+    SampleOutOfRange(u16),
+    PulseOutOfRange(u16),
+    WrongChannel(u8),
+    /// Temperature in 0.1 C
+    TempOutOfRange(i16),
+    /// Humidity over 100 %, the frame is corrupt
+    HumidityOutOfRange(u8),
+    ChecksumMismatch(u8, u8),
+    FixedBitsMismatch(u8),
+    UnpackFailed,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Climate {
+    /// Temperature in 0.1 C
+    pub temp_decicelsius: i16,
+    /// In %, never over 100
+    pub humidity: Option<u8>,
+}
+
+impl Climate {
+    #[cfg_attr(feature = "esp-hal", esp_hal::ram)]
+    pub(crate) fn new(temp_decicelsius: i16, humidity: Option<u8>) -> Self {
+        Climate {
+            temp_decicelsius,
+            humidity,
+        }
+    }
+
+    /// Build from temperature in 0.1 F, for sensors that transmit Fahrenheit.
+    /// Rounded to the nearest 0.1 C.
+    #[cfg(feature = "proto-generic")]
+    #[cfg_attr(feature = "esp-hal", esp_hal::ram)]
+    pub(crate) fn from_fahrenheit(temp_decifahrenheit: i16, humidity: Option<u8>) -> Self {
+        let scaled = (temp_decifahrenheit as i32 - 320) * 5;
+        let temp_decicelsius = (2 * scaled + 9 * scaled.signum()) / 18;
+        Climate::new(temp_decicelsius as i16, humidity)
+    }
+
+    /// Whole degrees C, rounded toward zero
+    pub fn degrees(&self) -> i16 {
+        self.temp_decicelsius / 10
+    }
+
+    /// Temperature in C with one decimal, e.g. `-0.5`
+    pub fn celsius(&self) -> impl fmt::Display {
+        Tenths(self.temp_decicelsius)
+    }
+}
+
+/// Unit a protocol transmits temperature in. Readings are always converted
+/// to Celsius.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum TempUnit {
+    #[default]
+    Celsius,
+    Fahrenheit,
+}
+
+impl TempUnit {
+    pub fn key(self) -> &'static str {
+        match self {
+            TempUnit::Celsius => "c",
+            TempUnit::Fahrenheit => "f",
+        }
+    }
+}
+
+/// Fixed-point value in tenths, formatted with one decimal
+struct Tenths(i16);
+
+impl fmt::Display for Tenths {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // -0.5 has no whole degrees to carry the sign
+        let sign = if self.0 < 0 { "-" } else { "" };
+        let value = self.0.unsigned_abs();
+        write!(f, "{}{}.{}", sign, value / 10, value % 10)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Wind {
+    /// Average speed in 0.1 m/s
+    pub avg_speed: u16,
+    /// Gust speed in 0.1 m/s
+    pub gust_speed: u16,
+    /// Direction in degrees, 0 is North
+    pub direction: u16,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Rain {
+    /// Cumulative rainfall since sensor power up in 0.1 mm
+    pub total: u32,
+    /// Rain rate in 0.1 mm/h, if sensor reports it
+    pub rate: Option<u16>,
+}
+
+/// Door/window contact state change or heartbeat
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Contact {
+    pub open: bool,
+    pub tamper: bool,
+    pub heartbeat: bool,
+}
+
+impl Contact {
+    /// Event type for payloads
+    #[cfg_attr(feature = "esp-hal", esp_hal::ram)]
+    pub fn event(&self) -> &'static str {
+        if self.tamper {
+            "tamper"
+        } else if self.heartbeat {
+            "heartbeat"
+        } else if self.open {
+            "open"
+        } else {
+            "close"
+        }
+    }
+}
+
+/// Water leak detector event
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Leak {
+    /// Water detected, otherwise the button was pressed
+    pub water: bool,
+}
+
+impl Leak {
+    /// Event type for payloads
+    #[cfg_attr(feature = "esp-hal", esp_hal::ram)]
+    pub fn event(&self) -> &'static str {
+        if self.water { "leak" } else { "button" }
+    }
+}
+
+/// What a smoke or CO alarm is sounding for
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum Hazard {
+    Smoke,
+    CarbonMonoxide,
+}
+
+impl Hazard {
+    /// Event type for payloads
+    #[cfg_attr(feature = "esp-hal", esp_hal::ram)]
+    pub fn key(self) -> &'static str {
+        match self {
+            Hazard::Smoke => "smoke",
+            Hazard::CarbonMonoxide => "co",
+        }
+    }
+}
+
+/// Quantities carried by a single frame
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum SensorReading {
+    Climate(Climate),
+    Weather(Climate, Wind, Rain),
+    Rain(Rain),
+    Wind(Wind),
+    Contact(Contact),
+    /// Motion detected, with the key code sent by the sensor
+    Motion(u8),
+    /// Doorbell button pressed, with the key code sent. Doorbell buttons send
+    /// the same frames as PIRs, decoders can't tell them apart, so it's up to
+    /// the sensor's settings to say which are.
+    Doorbell(u8),
+    Leak(Leak),
+    /// Smoke or CO alarm sounding
+    Hazard(Hazard),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct SensorData {
+    model: &'static str,
+    pub reading: SensorReading,
+    pub battery_ok: bool,
+    /// 1-based channel, 0 if protocol has no channels
+    pub channel: u8,
+    pub id: u32,
+}
+
+impl Default for SensorData {
+    fn default() -> Self {
+        SensorData::new(
+            "Unknown",
+            SensorReading::Climate(Climate::new(100, Some(80))),
+            true,
+            0,
+            0,
+        )
+    }
+}
+
+impl SensorData {
+    #[cfg_attr(feature = "esp-hal", esp_hal::ram)]
+    pub(crate) fn new(
+        model: &'static str,
+        reading: SensorReading,
+        battery_ok: bool,
+        channel: u8,
+        id: u32,
+    ) -> Self {
+        SensorData {
+            model,
+            reading,
+            battery_ok,
+            channel,
+            id,
+        }
+    }
+
+    #[cfg_attr(feature = "esp-hal", esp_hal::ram)]
+    pub fn model(&self) -> &'static str {
+        self.model
+    }
+
+    /// Event sensors only transmit on state change, so they have no regular
+    /// transmit interval
+    #[cfg_attr(feature = "esp-hal", esp_hal::ram)]
+    pub fn is_event(&self) -> bool {
+        matches!(
+            self.reading,
+            SensorReading::Contact(_)
+                | SensorReading::Motion(_)
+                | SensorReading::Doorbell(_)
+                | SensorReading::Leak(_)
+                | SensorReading::Hazard(_)
+        )
+    }
+
+    #[cfg_attr(feature = "esp-hal", esp_hal::ram)]
+    pub fn climate(&self) -> Option<&Climate> {
+        match &self.reading {
+            SensorReading::Climate(climate) | SensorReading::Weather(climate, _, _) => {
+                Some(climate)
+            }
+            _ => None,
+        }
+    }
+
+    #[cfg_attr(feature = "esp-hal", esp_hal::ram)]
+    pub fn wind(&self) -> Option<&Wind> {
+        match &self.reading {
+            SensorReading::Wind(wind) | SensorReading::Weather(_, wind, _) => Some(wind),
+            _ => None,
+        }
+    }
+
+    #[cfg_attr(feature = "esp-hal", esp_hal::ram)]
+    pub fn rain(&self) -> Option<&Rain> {
+        match &self.reading {
+            SensorReading::Rain(rain) | SensorReading::Weather(_, _, rain) => Some(rain),
+            _ => None,
+        }
+    }
+
+    #[cfg_attr(feature = "esp-hal", esp_hal::ram)]
+    pub fn contact(&self) -> Option<&Contact> {
+        match &self.reading {
+            SensorReading::Contact(contact) => Some(contact),
+            _ => None,
+        }
+    }
+
+    #[cfg_attr(feature = "esp-hal", esp_hal::ram)]
+    pub fn motion(&self) -> Option<u8> {
+        match &self.reading {
+            SensorReading::Motion(code) => Some(*code),
+            _ => None,
+        }
+    }
+
+    #[cfg_attr(feature = "esp-hal", esp_hal::ram)]
+    pub fn doorbell(&self) -> Option<u8> {
+        match &self.reading {
+            SensorReading::Doorbell(code) => Some(*code),
+            _ => None,
+        }
+    }
+
+    #[cfg_attr(feature = "esp-hal", esp_hal::ram)]
+    pub fn leak(&self) -> Option<&Leak> {
+        match &self.reading {
+            SensorReading::Leak(leak) => Some(leak),
+            _ => None,
+        }
+    }
+
+    #[cfg_attr(feature = "esp-hal", esp_hal::ram)]
+    pub fn hazard(&self) -> Option<Hazard> {
+        match &self.reading {
+            SensorReading::Hazard(hazard) => Some(*hazard),
+            _ => None,
+        }
+    }
+
+    /// Combine with a `later` frame of the same sensor, for stations that
+    /// split a reading over consecutive frames: values of `later` win, ones
+    /// it lacks are kept. Battery is low if either frame says so. None if
+    /// the frames are from different sensors or don't go together.
+    pub fn merge(&self, later: &SensorData) -> Option<SensorData> {
+        if self.model != later.model || self.id != later.id || self.channel != later.channel {
+            return None;
+        }
+        Some(SensorData::new(
+            later.model,
+            self.reading.merge(&later.reading)?,
+            self.battery_ok && later.battery_ok,
+            later.channel,
+            later.id,
+        ))
+    }
+}
+
+impl SensorReading {
+    fn merge(&self, later: &SensorReading) -> Option<SensorReading> {
+        let fill = |earlier: &Climate, later: &Climate| {
+            Climate::new(later.temp_decicelsius, later.humidity.or(earlier.humidity))
+        };
+        Some(match (self, later) {
+            (SensorReading::Climate(earlier), SensorReading::Climate(climate)) => {
+                SensorReading::Climate(fill(earlier, climate))
+            }
+            (SensorReading::Weather(earlier, wind, rain), SensorReading::Climate(climate)) => {
+                SensorReading::Weather(fill(earlier, climate), wind.clone(), rain.clone())
+            }
+            (SensorReading::Weather(climate, _, rain), SensorReading::Wind(wind)) => {
+                SensorReading::Weather(climate.clone(), wind.clone(), rain.clone())
+            }
+            (SensorReading::Weather(climate, wind, _), SensorReading::Rain(rain)) => {
+                SensorReading::Weather(climate.clone(), wind.clone(), rain.clone())
+            }
+            (
+                SensorReading::Climate(earlier) | SensorReading::Weather(earlier, _, _),
+                SensorReading::Weather(climate, wind, rain),
+            ) => SensorReading::Weather(fill(earlier, climate), wind.clone(), rain.clone()),
+            (SensorReading::Wind(_) | SensorReading::Rain(_), SensorReading::Weather(..)) => {
+                later.clone()
+            }
+            (SensorReading::Wind(_), SensorReading::Wind(_))
+            | (SensorReading::Rain(_), SensorReading::Rain(_)) => later.clone(),
+            _ => return None,
+        })
+    }
+}
+
+impl fmt::Display for Climate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} C", self.celsius())?;
+        if let Some(humidity) = self.humidity {
+            write!(f, ", {} %", humidity)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Wind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "wind {}.{} m/s, gust {}.{} m/s, {} deg",
+            self.avg_speed / 10,
+            self.avg_speed % 10,
+            self.gust_speed / 10,
+            self.gust_speed % 10,
+            self.direction
+        )
+    }
+}
+
+impl fmt::Display for Rain {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "rain {}.{} mm", self.total / 10, self.total % 10)?;
+        if let Some(rate) = self.rate {
+            write!(f, ", {}.{} mm/h", rate / 10, rate % 10)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for SensorReading {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SensorReading::Climate(climate) => write!(f, "{}", climate),
+            SensorReading::Weather(climate, wind, rain) => {
+                write!(f, "{}, {}, {}", climate, wind, rain)
+            }
+            SensorReading::Rain(rain) => write!(f, "{}", rain),
+            SensorReading::Wind(wind) => write!(f, "{}", wind),
+            SensorReading::Contact(contact) => write!(f, "{}", contact.event()),
+            SensorReading::Motion(code) => write!(f, "motion, code {}", code),
+            SensorReading::Doorbell(code) => write!(f, "doorbell, code {}", code),
+            SensorReading::Leak(leak) => write!(f, "{}", leak.event()),
+            SensorReading::Hazard(hazard) => write!(f, "{} alarm", hazard.key()),
+        }
+    }
+}
+
+/// One line for logs, e.g. `Nexus-TH id 12 channel 1: 21.5 C, 45 %`
+impl fmt::Display for SensorData {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} id {} channel {}: {}",
+            self.model, self.id, self.channel, self.reading
+        )?;
+        if !self.battery_ok {
+            write!(f, ", battery low")?;
+        }
+        Ok(())
+    }
+}
+
+/// A protocol the receiver knows how to decode. Every enabled protocol is
+/// tried on a frame, and the ones that accept it are ranked by [`Candidate`]
+/// confidence, ties going to the one that comes first in [`PROTOCOLS`].
+pub struct Protocol {
+    pub name: &'static str,
+    /// Short name used to enable or disable the protocol
+    pub key: &'static str,
+    decode: fn(&[Symbol]) -> Result<SensorData, DecodeError>,
+    /// Frames are verified by a checksum or CRC
+    checksum: bool,
+    /// Segment lengths the protocol is made of, to score the timing fit
+    timings: &'static [&'static Timing],
+    /// Coarse shape of its frames, None while it can't decode anything
+    profile: fn() -> Option<Profile>,
+}
+
+/// What frames of a protocol look like at a glance: the range their segment
+/// lengths fall in and how many such segments a frame has at least. Bursts
+/// that can't match are never handed to the decoder, which saves time and
+/// keeps decoders with loose checks from claiming frames of other protocols.
+pub(crate) struct Profile {
+    segments: Range<u16>,
+    min_count: usize,
+}
+
+impl Profile {
+    /// Segments within any of `ranges`
+    #[cfg_attr(feature = "esp-hal", esp_hal::ram)]
+    pub(crate) fn new(ranges: &[Range<u16>], min_count: usize) -> Self {
+        let segments = ranges
+            .iter()
+            .cloned()
+            .reduce(|a, b| a.start.min(b.start)..a.end.max(b.end))
+            .unwrap_or(0..0);
+        Profile {
+            segments,
+            min_count,
+        }
+    }
+}
+
+// Only protocols enabled by their `proto-*` feature are compiled in
+const PROTOCOL_COUNT: usize = cfg!(feature = "proto-hideki") as usize
+    + cfg!(feature = "proto-fineoffset") as usize
+    + cfg!(feature = "proto-oregon") as usize
+    + cfg!(feature = "proto-honeywell") as usize
+    + cfg!(feature = "proto-ev1527") as usize
+    + cfg!(feature = "proto-govee") as usize
+    + cfg!(feature = "proto-gs558") as usize
+    + cfg!(feature = "proto-rubicson") as usize
+    + cfg!(feature = "proto-nexus") as usize
+    + GENERIC_COUNT;
+
+#[cfg(feature = "proto-generic")]
+const GENERIC_COUNT: usize = generic::GENERIC_SLOTS;
+#[cfg(not(feature = "proto-generic"))]
+const GENERIC_COUNT: usize = 0;
+
+pub static PROTOCOLS: [Protocol; PROTOCOL_COUNT] = [
+    #[cfg(feature = "proto-hideki")]
+    Protocol {
+        name: "Hideki",
+        key: "hideki",
+        decode: hideki::decode,
+        checksum: true,
+        timings: &[&hideki::HALF, &hideki::FULL],
+        profile: hideki::profile,
+    },
+    #[cfg(feature = "proto-fineoffset")]
+    Protocol {
+        name: "Fine Offset WH1080",
+        key: "fineoffset",
+        decode: fineoffset::decode,
+        checksum: true,
+        timings: &[&fineoffset::SHORT, &fineoffset::LONG],
+        profile: fineoffset::profile,
+    },
+    #[cfg(feature = "proto-oregon")]
+    Protocol {
+        name: "Oregon Scientific v3",
+        key: "oregon",
+        decode: oregon::decode,
+        checksum: true,
+        timings: &[&oregon::HALF, &oregon::FULL],
+        profile: oregon::profile,
+    },
+    #[cfg(feature = "proto-honeywell")]
+    Protocol {
+        name: "Honeywell contact",
+        key: "honeywell",
+        decode: honeywell::decode,
+        checksum: true,
+        timings: &[&honeywell::HALF, &honeywell::FULL],
+        profile: honeywell::profile,
+    },
+    #[cfg(feature = "proto-ev1527")]
+    Protocol {
+        name: "EV1527 PIR",
+        key: "ev1527",
+        decode: ev1527::decode,
+        checksum: false,
+        timings: &[&ev1527::PERIOD],
+        profile: ev1527::profile,
+    },
+    #[cfg(feature = "proto-govee")]
+    Protocol {
+        name: "Govee water leak",
+        key: "govee",
+        decode: govee::decode,
+        checksum: false,
+        timings: &[&govee::PERIOD],
+        profile: govee::profile,
+    },
+    #[cfg(feature = "proto-gs558")]
+    Protocol {
+        name: "GS 558 smoke alarm",
+        key: "gs558",
+        decode: gs558::decode,
+        checksum: false,
+        timings: &[&gs558::PERIOD],
+        profile: gs558::profile,
+    },
+    #[cfg(feature = "proto-rubicson")]
+    Protocol {
+        name: "Rubicson",
+        key: "rubicson",
+        decode: rubicson::decode,
+        checksum: true,
+        timings: &[&PPM_PULSE, &PPM_ONE, &PPM_ZERO],
+        profile: ppm_profile,
+    },
+    #[cfg(feature = "proto-nexus")]
+    Protocol {
+        name: "Nexus-TH",
+        key: "nexus",
+        decode: nexus::decode,
+        checksum: false,
+        timings: &[&PPM_PULSE, &PPM_ONE, &PPM_ZERO],
+        profile: ppm_profile,
+    },
+    // Defined at runtime, they only decode once a descriptor is loaded.
+    // Checksum is optional, so they don't get credit for it.
+    #[cfg(feature = "proto-generic")]
+    Protocol {
+        name: "Generic 1",
+        key: generic::KEYS[0],
+        decode: generic::decode::<0>,
+        checksum: false,
+        timings: &[
+            &generic::SLOTS[0].pulse,
+            &generic::SLOTS[0].zero,
+            &generic::SLOTS[0].one,
+        ],
+        profile: generic::profile::<0>,
+    },
+    #[cfg(feature = "proto-generic")]
+    Protocol {
+        name: "Generic 2",
+        key: generic::KEYS[1],
+        decode: generic::decode::<1>,
+        checksum: false,
+        timings: &[
+            &generic::SLOTS[1].pulse,
+            &generic::SLOTS[1].zero,
+            &generic::SLOTS[1].one,
+        ],
+        profile: generic::profile::<1>,
+    },
+    #[cfg(feature = "proto-generic")]
+    Protocol {
+        name: "Generic 3",
+        key: generic::KEYS[2],
+        decode: generic::decode::<2>,
+        checksum: false,
+        timings: &[
+            &generic::SLOTS[2].pulse,
+            &generic::SLOTS[2].zero,
+            &generic::SLOTS[2].one,
+        ],
+        profile: generic::profile::<2>,
+    },
+    #[cfg(feature = "proto-generic")]
+    Protocol {
+        name: "Generic 4",
+        key: generic::KEYS[3],
+        decode: generic::decode::<3>,
+        checksum: false,
+        timings: &[
+            &generic::SLOTS[3].pulse,
+            &generic::SLOTS[3].zero,
+            &generic::SLOTS[3].one,
+        ],
+        profile: generic::profile::<3>,
+    },
+];
+
+/// All protocols enabled, bit N stands for `PROTOCOLS[N]`
+pub const ALL_PROTOCOLS: u32 = (1 << PROTOCOL_COUNT) - 1;
+
+/// Overlapping pulse timings of different protocols can cause misattribution,
+/// so decoding can be restricted to protocols of the sensors actually owned.
+static ENABLED: AtomicU32 = AtomicU32::new(ALL_PROTOCOLS);
+
+#[cfg_attr(feature = "esp-hal", esp_hal::ram)]
+pub fn enabled_protocols() -> u32 {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+pub fn set_enabled_protocols(mask: u32) {
+    ENABLED.store(mask & ALL_PROTOCOLS, Ordering::Relaxed);
+}
+
+/// Readings outside this range, in 0.1 C, are taken for corrupt frames
+pub const DEFAULT_TEMP_RANGE: Range<i16> = -600..600;
+
+// Bounds of the valid range, start in the high half
+static TEMP_RANGE: AtomicU32 = AtomicU32::new(pack_range(DEFAULT_TEMP_RANGE));
+
+const fn pack_range(range: Range<i16>) -> u32 {
+    (range.start as u16 as u32) << 16 | range.end as u16 as u32
+}
+
+/// Valid temperatures, in 0.1 C
+#[cfg_attr(feature = "esp-hal", esp_hal::ram)]
+pub fn temp_range() -> Range<i16> {
+    let packed = TEMP_RANGE.load(Ordering::Relaxed);
+    (packed >> 16) as i16..packed as i16
+}
+
+/// Set the valid temperatures, in 0.1 C, e.g. to make room for a freezer
+/// or sauna sensor
+pub fn set_temp_range(range: Range<i16>) {
+    TEMP_RANGE.store(pack_range(range), Ordering::Relaxed);
+}
+
+/// Index of protocol with `key` in [`PROTOCOLS`]
+pub fn find_protocol(key: &str) -> Option<usize> {
+    PROTOCOLS.iter().position(|proto| proto.key == key)
+}
+
+/// A parse of a frame by one protocol
+#[derive(Clone, Debug)]
+pub struct Candidate {
+    pub data: SensorData,
+    /// How likely the frame is of this protocol, 0 to 100. Half of it is
+    /// given for a verified checksum, the other half is the share of segments
+    /// that fit the protocol timings.
+    pub confidence: u8,
+}
+
+impl Candidate {
+    #[cfg_attr(feature = "esp-hal", esp_hal::ram)]
+    fn new(proto: &Protocol, pulses: &[Symbol], data: SensorData) -> Self {
+        let (total, fit) = edges(pulses).fold((0u32, 0u32), |(total, fit), (_, length)| {
+            let fits = proto
+                .timings
+                .iter()
+                .any(|timing| timing.range().contains(&length));
+            (total + 1, fit + fits as u32)
+        });
+        let checksum = if proto.checksum { 50 } else { 0 };
+        let timing = (fit * 50).checked_div(total).unwrap_or(0);
+        Candidate {
+            data,
+            confidence: checksum + timing as u8,
+        }
+    }
+}
+
+/// Parses of a frame by every protocol that accepted it, best first
+#[derive(Clone, Debug, Default)]
+pub struct Candidates {
+    list: [Option<Candidate>; PROTOCOL_COUNT],
+}
+
+impl Candidates {
+    /// Insert keeping the order, after candidates with the same confidence
+    #[cfg_attr(feature = "esp-hal", esp_hal::ram)]
+    fn insert(&mut self, candidate: Candidate) {
+        let pos = self
+            .iter()
+            .position(|other| other.confidence < candidate.confidence)
+            .unwrap_or(self.len());
+        if pos < PROTOCOL_COUNT {
+            self.list[pos..].rotate_right(1);
+            self.list[pos] = Some(candidate);
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Candidate> {
+        self.list.iter().map_while(Option::as_ref)
+    }
+
+    pub fn len(&self) -> usize {
+        self.iter().count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.list[0].is_none()
+    }
+
+    pub fn best(&self) -> Option<&Candidate> {
+        self.list[0].as_ref()
+    }
+
+    /// Best candidate, unless the runner-up is just as likely
+    pub fn unambiguous(&self) -> Option<&Candidate> {
+        let mut iter = self.iter();
+        let best = iter.next()?;
+        match iter.next() {
+            Some(other) if other.confidence == best.confidence => None,
+            _ => Some(best),
+        }
+    }
+
+    #[cfg_attr(feature = "esp-hal", esp_hal::ram)]
+    fn retain(&mut self, f: impl Fn(&SensorData) -> bool) {
+        let mut kept = 0;
+        for idx in 0..PROTOCOL_COUNT {
+            if let Some(candidate) = self.list[idx].take()
+                && f(&candidate.data)
+            {
+                self.list[kept] = Some(candidate);
+                kept += 1;
+            }
+        }
+    }
+
+    #[cfg_attr(feature = "esp-hal", esp_hal::ram)]
+    fn into_best(self) -> Option<SensorData> {
+        let [best, ..] = self.list;
+        best.map(|candidate| candidate.data)
+    }
+}
+
+/// Sign extend 12-bit two's complement value
+#[cfg(any(feature = "proto-rubicson", feature = "proto-nexus"))]
+#[cfg_attr(feature = "esp-hal", esp_hal::ram)]
+pub(crate) fn sign_extend_12bit(raw: u16) -> i16 {
+    ((raw << 4) as i16) >> 4
+}
+
+/// Flatten symbols into (level, duration) pairs, `true` is carrier
+/// present. Stops at the end marker.
+pub(crate) fn edges(pulses: &[Symbol]) -> impl Iterator<Item = (bool, u16)> + '_ {
+    pulses
+        .iter()
+        .flat_map(|entry| [(entry.level1, entry.length1), (entry.level2, entry.length2)])
+        .take_while(|&(_, length)| length != 0)
+}
+
+/// Expand Manchester coded segments into half-bit periods. Segment within
+/// `half` range is one half-bit, within `full` range is two. Stops at the first
+/// segment that fits neither, once at least one half-bit is found. Returns the
+/// number of half-bits written.
+#[cfg(any(feature = "proto-oregon", feature = "proto-honeywell"))]
+#[cfg_attr(feature = "esp-hal", esp_hal::ram)]
+pub(crate) fn manchester_halves(
+    pulses: &[Symbol],
+    half: Range<u16>,
+    full: Range<u16>,
+    halves: &mut [bool],
+) -> usize {
+    let mut count = 0;
+    for (level, length) in edges(pulses) {
+        let n = if half.contains(&length) {
+            1
+        } else if full.contains(&length) {
+            2
+        } else if count == 0 {
+            continue;
+        } else {
+            break;
+        };
+        for _ in 0..n {
+            if count < halves.len() {
+                halves[count] = level;
+                count += 1;
+            }
+        }
+    }
+    // Trailing half of the last bit merges with the idle period
+    if count % 2 == 1 && count < halves.len() {
+        halves[count] = !halves[count - 1];
+        count += 1;
+    }
+    count
+}
+
+/// Pair up half-bits starting at `phase` into bits, level of the first half
+/// is the bit value. Stops at the first pair without a mid-bit transition.
+/// Returns the number of bits written.
+#[cfg(any(feature = "proto-oregon", feature = "proto-honeywell"))]
+#[cfg_attr(feature = "esp-hal", esp_hal::ram)]
+pub(crate) fn manchester_bits(halves: &[bool], phase: usize, bits: &mut [bool]) -> usize {
+    let mut count = 0;
+    for pair in halves[phase.min(halves.len())..].chunks_exact(2) {
+        if pair[0] == pair[1] || count == bits.len() {
+            break;
+        }
+        bits[count] = pair[0];
+        count += 1;
+    }
+    count
+}
+
+/// Every bit of a PPM frame is a carrier pulse and a pause
+#[cfg(any(feature = "proto-rubicson", feature = "proto-nexus"))]
+#[cfg_attr(feature = "esp-hal", esp_hal::ram)]
+fn ppm_profile() -> Option<Profile> {
+    Some(Profile::new(
+        &[PPM_PULSE.range(), PPM_ONE.range(), PPM_ZERO.range()],
+        2 * PAYLOAD_LEN_BITS,
+    ))
+}
+
+/// Slice pulse-position modulated frame into bits. Carrier pulses must be
+/// within PPM_PULSE range, the following pause encodes the bit value.
+#[cfg(any(feature = "proto-rubicson", feature = "proto-nexus"))]
+#[cfg_attr(feature = "esp-hal", esp_hal::ram)]
+pub(crate) fn ppm_bits(pulses: &[Symbol]) -> Result<[u8; PAYLOAD_LEN_BYTES], DecodeError> {
+    // Frame is number of bits + terminator, it follows the preamble pause
+    // which is longer than any bit
+    let (pulse, one, zero) = (PPM_PULSE.range(), PPM_ONE.range(), PPM_ZERO.range());
+    let frame = sync::locate_frame(pulses, PAYLOAD_LEN_BITS + 1, one.end..u16::MAX)?;
+
+    for entry in frame {
+        if entry.level1 && !pulse.contains(&entry.length1) {
+            return Err(DecodeError::PulseOutOfRange(entry.length1));
+        }
+        if entry.level2 && !pulse.contains(&entry.length2) {
+            return Err(DecodeError::PulseOutOfRange(entry.length2));
+        }
+    }
+
+    let mut samples: [u16; PAYLOAD_LEN_BITS] = [0; PAYLOAD_LEN_BITS];
+    for (idx, entry) in frame.iter().take(PAYLOAD_LEN_BITS).enumerate() {
+        samples[idx] = if !entry.level1 {
+            entry.length1
+        } else {
+            entry.length2
+        };
+    }
+
+    let mut decoded: [u8; PAYLOAD_LEN_BYTES] = [0; PAYLOAD_LEN_BYTES];
+    for (idx, value) in samples.iter().enumerate() {
+        if one.contains(value) {
+            decoded[idx / 8] |= 1 << (7 - idx % 8);
+        } else if zero.contains(value) {
+            decoded[idx / 8] &= !(1 << (7 - idx % 8));
+        } else {
+            return Err(DecodeError::SampleOutOfRange(*value));
+        }
+    }
+
+    Ok(decoded)
+}
+
+/// Pack (level, duration) edges into symbols the way RMT RX channel does:
+/// two edges per symbol, the last one terminated with a zero length. Returns
+/// number of symbols including the terminator, or None if they don't fit.
+#[cfg_attr(feature = "esp-hal", esp_hal::ram)]
+pub fn pulses_from_edges(edges: &[(bool, u16)], pulses: &mut [Symbol]) -> Option<usize> {
+    let mut count = 0;
+    for pair in edges.chunks(2) {
+        let (level1, length1) = pair[0];
+        let (level2, length2) = pair.get(1).copied().unwrap_or((!level1, 0));
+        *pulses.get_mut(count)? = Symbol::new(level1, length1, level2, length2);
+        count += 1;
+    }
+
+    if edges.len().is_multiple_of(2) {
+        *pulses.get_mut(count)? = Symbol::end_marker();
+        count += 1;
+    }
+
+    Some(count)
+}
+
+/// Rescale lengths counted in ticks of `tick_num / tick_den` uS to uS, the unit
+/// every decoder timing is in. Lengths are rounded to the nearest uS, but
+/// never down to zero, which would end the capture early.
+#[cfg_attr(feature = "esp-hal", esp_hal::ram)]
+pub fn normalize(pulses: &mut [Symbol], tick_num: u32, tick_den: u32) {
+    if tick_num == tick_den {
+        return;
+    }
+    let scale = |length: u16| match length {
+        0 => 0,
+        ticks => {
+            let us = (ticks as u64 * tick_num as u64 + tick_den as u64 / 2) / tick_den as u64;
+            us.clamp(1, u16::MAX as u64) as u16
+        }
+    };
+    for symbol in pulses {
+        symbol.length1 = scale(symbol.length1);
+        symbol.length2 = scale(symbol.length2);
+    }
+}
+
+/// Merge segments shorter than `min_us` into the ones around them, the way
+/// superregenerative receivers chop up pulses and pauses on weak signals. Each
+/// glitch and the segment after it are added to the segment before, which
+/// they interrupted. Returns the new length of `pulses`, which is terminated
+/// the same way [`pulses_from_edges`] terminates a capture. Lengths are in uS.
+#[cfg_attr(feature = "esp-hal", esp_hal::ram)]
+pub fn merge_glitches(pulses: &mut [Symbol], min_us: u16) -> usize {
+    let count = pulses.len() * 2;
+    let segment = |pulses: &[Symbol], idx: usize| {
+        let symbol = pulses[idx / 2];
+        if idx.is_multiple_of(2) {
+            (symbol.level1, symbol.length1)
+        } else {
+            (symbol.level2, symbol.length2)
+        }
+    };
+    let set = |pulses: &mut [Symbol], idx: usize, (level, length): (bool, u32)| {
+        let symbol = &mut pulses[idx / 2];
+        let length = length.min(u16::MAX as u32) as u16;
+        if idx.is_multiple_of(2) {
+            (symbol.level1, symbol.length1) = (level, length);
+        } else {
+            (symbol.level2, symbol.length2) = (level, length);
+        }
+    };
+    // Segments are written behind the one being read, a merge only ever
+    // makes them fewer
+    let mut written = 0;
+    let mut pending: Option<(bool, u32)> = None;
+    let mut idx = 0;
+    while idx < count {
+        let (level, length) = segment(pulses, idx);
+        if length == 0 {
+            break;
+        }
+        match pending {
+            None => pending = Some((level, length.into())),
+            Some((last, total)) if last == level => {
+                pending = Some((last, total + length as u32));
+            }
+            Some((last, total)) => {
+                let next = (idx + 1 < count).then(|| segment(pulses, idx + 1));
+                match next {
+                    Some((next_level, next_length))
+                        if length < min_us && next_level == last && next_length != 0 =>
+                    {
+                        pending = Some((last, total + length as u32 + next_length as u32));
+                        idx += 1;
+                    }
+                    _ => {
+                        set(pulses, written, (last, total));
+                        written += 1;
+                        pending = Some((level, length.into()));
+                    }
+                }
+            }
+        }
+        idx += 1;
+    }
+    if let Some(last) = pending {
+        set(pulses, written, last);
+        written += 1;
+    }
+    if written % 2 == 1 {
+        let (level, _) = segment(pulses, written - 1);
+        set(pulses, written, (!level, 0));
+        return written / 2 + 1;
+    }
+    match pulses.get_mut(written / 2) {
+        Some(end) => {
+            *end = Symbol::end_marker();
+            written / 2 + 1
+        }
+        None => written / 2,
+    }
+}
+
+/// Decode a capture given as (level, duration in uS) edges, `true` is carrier
+/// present. Runs the same protocols as [`decode`] but accepts any channel.
+/// Meant for feeding arbitrary input from the host, e.g. fuzzing.
+#[cfg_attr(feature = "esp-hal", esp_hal::ram)]
+pub fn decode_raw(edges: &[(bool, u16)]) -> Result<SensorData, DecodeError> {
+    let mut pulses = [Symbol::default(); MAX_SYMBOLS];
+    let len =
+        pulses_from_edges(edges, &mut pulses).ok_or(DecodeError::WrongPayloadLen(edges.len()))?;
+    decode_any(&pulses[..len], || 0, |_, _| {})?
+        .into_best()
+        .ok_or(DecodeError::WrongPayloadLen(len))
+}
+
+// Segment lengths are counted in buckets this wide, the last one takes
+// everything longer
+const BUCKET_US: u16 = 128;
+const BUCKETS: usize = 32;
+
+/// Coarse timing signature of a burst, taken once and held against the
+/// [`Profile`] of every protocol
+struct Signature {
+    buckets: [u16; BUCKETS],
+}
+
+impl Signature {
+    #[cfg_attr(feature = "esp-hal", esp_hal::ram)]
+    fn new(pulses: &[Symbol]) -> Self {
+        let mut buckets = [0; BUCKETS];
+        for (_, length) in edges(pulses) {
+            buckets[bucket(length)] += 1;
+        }
+        Signature { buckets }
+    }
+
+    /// Whether a frame of `profile` can be in the burst. Buckets are only
+    /// partly covered by the segment range, so this errs on the side of yes.
+    #[cfg_attr(feature = "esp-hal", esp_hal::ram)]
+    fn fits(&self, profile: &Profile) -> bool {
+        let Profile {
+            segments,
+            min_count,
+        } = profile;
+        if segments.is_empty() {
+            return false;
+        }
+        let count: u16 = self.buckets[bucket(segments.start)..=bucket(segments.end - 1)]
+            .iter()
+            .sum();
+        count as usize >= *min_count
+    }
+}
+
+#[cfg_attr(feature = "esp-hal", esp_hal::ram)]
+fn bucket(length: u16) -> usize {
+    ((length / BUCKET_US) as usize).min(BUCKETS - 1)
+}
+
+/// Protocols a capture may be of, judging by its length and coarse timing
+/// alone, bit N stands for `PROTOCOLS[N]`. Only these are tried by
+/// [`decode`], disabled ones are left out.
+#[cfg_attr(feature = "esp-hal", esp_hal::ram)]
+pub fn plausible_protocols(pulses: &[Symbol]) -> u32 {
+    let signature = Signature::new(pulses);
+    let enabled = enabled_protocols();
+    PROTOCOLS
+        .iter()
+        .enumerate()
+        .filter(|&(idx, proto)| {
+            enabled & (1 << idx) != 0 && (proto.profile)().is_some_and(|p| signature.fits(&p))
+        })
+        .fold(0, |mask, (idx, _)| mask | 1 << idx)
+}
+
+#[cfg_attr(feature = "esp-hal", esp_hal::ram)]
+fn decode_any(
+    pulses: &[Symbol],
+    now: impl Fn() -> u64,
+    mut spent: impl FnMut(usize, u64),
+) -> Result<Candidates, DecodeError> {
+    let mut err = DecodeError::WrongPayloadLen(pulses.len());
+    let mut candidates = Candidates::default();
+
+    let plausible = plausible_protocols(pulses);
+    for (idx, proto) in PROTOCOLS.iter().enumerate() {
+        if plausible & (1 << idx) == 0 {
+            continue;
+        }
+        let start = now();
+        let result = (proto.decode)(pulses);
+        spent(idx, now().wrapping_sub(start));
+        match result {
+            Ok(data) => {
+                if let Some(climate) = data.climate()
+                    && !temp_range().contains(&climate.temp_decicelsius)
+                {
+                    err = DecodeError::TempOutOfRange(climate.temp_decicelsius);
+                    continue;
+                }
+                if let Some(humidity) = data.climate().and_then(|climate| climate.humidity)
+                    && humidity > 100
+                {
+                    err = DecodeError::HumidityOutOfRange(humidity);
+                    continue;
+                }
+                candidates.insert(Candidate::new(proto, pulses, data));
+            }
+            Err(DecodeError::WrongPayloadLen(_)) => {}
+            Err(e) => err = e,
+        }
+    }
+
+    if candidates.is_empty() {
+        return Err(err);
+    }
+
+    Ok(candidates)
+}
+
+/// Decode a capture. `pulses` must hold only the received symbols, including
+/// the terminator. Frames from channels other than `ch` are rejected.
+#[cfg_attr(feature = "esp-hal", esp_hal::ram)]
+pub fn decode(pulses: &[Symbol], ch: u8) -> Result<SensorData, DecodeError> {
+    decode_ranked(pulses, ch)?
+        .into_best()
+        .ok_or(DecodeError::WrongPayloadLen(pulses.len()))
+}
+
+/// Same as [`decode`], but returns parses of every protocol that accepted the
+/// frame, leaving it to the caller to pick one.
+#[cfg_attr(feature = "esp-hal", esp_hal::ram)]
+pub fn decode_ranked(pulses: &[Symbol], ch: u8) -> Result<Candidates, DecodeError> {
+    decode_timed(pulses, ch, || 0, |_, _| {})
+}
+
+/// Same as [`decode_ranked`], but tells `spent` how long every protocol
+/// tried took, by its index in [`PROTOCOLS`]. Time is taken with `now`, in
+/// whatever unit it counts. Meant for profiling the decoders on the target.
+#[cfg_attr(feature = "esp-hal", esp_hal::ram)]
+pub fn decode_timed(
+    pulses: &[Symbol],
+    ch: u8,
+    now: impl Fn() -> u64,
+    spent: impl FnMut(usize, u64),
+) -> Result<Candidates, DecodeError> {
+    let mut candidates = decode_any(pulses, now, spent)?;
+    let wrong = candidates.best().map(|candidate| candidate.data.channel);
+
+    candidates.retain(|data| data.channel == 0 || data.channel == ch);
+    match wrong {
+        Some(channel) if candidates.is_empty() => Err(DecodeError::WrongChannel(channel)),
+        _ => Ok(candidates),
+    }
+}
@@ -0,0 +1,62 @@
+use crate::Symbol;
+use packed_struct::prelude::*;
+
+use super::{Climate, DecodeError, SensorData, SensorReading, ppm_bits, sign_extend_12bit};
+
+const FIXED_0: u8 = 0x0;
+const FIXED_1: u8 = 0xf;
+
+impl From<NexusTHPayload> for SensorData {
+    #[cfg_attr(feature = "esp-hal", esp_hal::ram)]
+    fn from(pld: NexusTHPayload) -> Self {
+        let temp_10x = sign_extend_12bit(pld.temp_10x.into());
+
+        let humidity: u8 = pld.humidity.into();
+        let channel: u8 = pld.channel.into();
+        SensorData::new(
+            "Nexus-TH",
+            SensorReading::Climate(Climate::new(temp_10x, Some(humidity))),
+            pld.battery_ok,
+            channel + 1,
+            u8::from(pld.id).into(),
+        )
+    }
+}
+
+#[derive(PackedStruct, Debug)]
+#[packed_struct(bit_numbering = "msb0")]
+struct NexusTHPayload {
+    #[packed_field(bits = "0:7")]
+    id: Integer<u8, packed_bits::Bits<8>>,
+    #[packed_field(bits = "8:8")]
+    battery_ok: bool,
+    #[packed_field(bits = "9:9")]
+    fixed_0: Integer<u8, packed_bits::Bits<1>>,
+    #[packed_field(bits = "10:11")]
+    channel: Integer<u8, packed_bits::Bits<2>>,
+    #[packed_field(endian = "msb", bits = "12:23")]
+    temp_10x: Integer<u16, packed_bits::Bits<12>>,
+    #[packed_field(bits = "24:27")]
+    fixed_1: Integer<u8, packed_bits::Bits<4>>,
+    #[packed_field(bits = "28:35")]
+    humidity: Integer<u8, packed_bits::Bits<8>>,
+}
+
+#[cfg_attr(feature = "esp-hal", esp_hal::ram)]
+pub(super) fn decode(pulses: &[Symbol]) -> Result<SensorData, DecodeError> {
+    let decoded = ppm_bits(pulses)?;
+
+    let unpacked = NexusTHPayload::unpack(&decoded).map_err(|_| DecodeError::UnpackFailed)?;
+
+    // There is no checksum, but bit 9 is always 0 and bits 24-27 are always 1
+    let fixed_0: u8 = unpacked.fixed_0.into();
+    if fixed_0 != FIXED_0 {
+        return Err(DecodeError::FixedBitsMismatch(fixed_0));
+    }
+    let fixed_1: u8 = unpacked.fixed_1.into();
+    if fixed_1 != FIXED_1 {
+        return Err(DecodeError::FixedBitsMismatch(fixed_1));
+    }
+
+    Ok(unpacked.into())
+}
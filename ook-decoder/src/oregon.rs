@@ -0,0 +1,157 @@
+use crate::Symbol;
+use crate::timing::Timing;
+
+use super::{
+    DecodeError, Profile, Rain, SensorData, SensorReading, Wind, manchester_bits, manchester_halves,
+};
+
+// Oregon Scientific v3 protocol uses Manchester coding with ~490 uS half-bit
+// period. Frame starts with 24 bits of preamble (all ones) followed by 0xA
+// sync nibble, then the message as nibbles sent LSB first:
+//
+// IIII C RR F DDDD... SS, where:
+//
+// * I - sensor ID, 0x2914 for PCR800, 0x1984 for WGR800
+// * C - channel
+// * R - rolling code, changes on battery replacement
+// * F - flags, bit 2 is low battery
+// * D - sensor specific data, mostly BCD digits, least significant first
+// * S - sum of all preceding nibbles
+//
+// Depending on receiver the frame may arrive inverted, so the sync is searched
+// for with both polarities.
+
+const HALF_MIN: u16 = 250; // us
+const HALF_MAX: u16 = 740; // us
+const FULL_MIN: u16 = 740; // us
+const FULL_MAX: u16 = 1250; // us
+
+pub(crate) static HALF: Timing = Timing::new("oregon_half", HALF_MIN, HALF_MAX);
+pub(crate) static FULL: Timing = Timing::new("oregon_full", FULL_MIN, FULL_MAX);
+
+const MAX_HALF_BITS: usize = 256;
+const MAX_BITS: usize = MAX_HALF_BITS / 2;
+const MAX_NIBBLES: usize = 24;
+const MIN_PREAMBLE_BITS: usize = 16;
+
+const ID_PCR800: u16 = 0x2914;
+const ID_WGR800: u16 = 0x1984;
+
+#[cfg_attr(feature = "esp-hal", esp_hal::ram)]
+fn nibbles_from_halves(halves: &[bool], phase: usize) -> Option<([u8; MAX_NIBBLES], usize)> {
+    let mut bits = [false; MAX_BITS];
+    let count = manchester_bits(halves, phase, &mut bits);
+    let bits = &bits[..count];
+
+    // Look for run of identical preamble bits followed by sync nibble
+    let mut run = 1;
+    let mut start = None;
+    for idx in 1..bits.len() {
+        if bits[idx] == bits[idx - 1] {
+            run += 1;
+            continue;
+        }
+        let b = bits[idx - 1];
+        if run >= MIN_PREAMBLE_BITS && idx + 4 <= bits.len() && bits[idx..idx + 4] == [!b, b, !b, b]
+        {
+            start = Some((idx + 4, !b));
+            break;
+        }
+        run = 1;
+    }
+    let (start, inverted) = start?;
+
+    let mut nibbles = [0u8; MAX_NIBBLES];
+    let mut count = 0;
+    for chunk in bits[start..].chunks_exact(4).take(MAX_NIBBLES) {
+        nibbles[count] = chunk
+            .iter()
+            .enumerate()
+            .fold(0, |acc, (n, &bit)| acc | (((bit ^ inverted) as u8) << n));
+        count += 1;
+    }
+
+    Some((nibbles, count))
+}
+
+#[cfg_attr(feature = "esp-hal", esp_hal::ram)]
+fn checksum(nibbles: &[u8], idx: usize) -> Result<(), DecodeError> {
+    if nibbles.len() < idx + 2 {
+        return Err(DecodeError::WrongPayloadLen(nibbles.len()));
+    }
+    let sum = nibbles[..idx].iter().map(|&n| n as u16).sum::<u16>() as u8;
+    let expected = nibbles[idx] | (nibbles[idx + 1] << 4);
+    if sum != expected {
+        return Err(DecodeError::ChecksumMismatch(sum, expected));
+    }
+    Ok(())
+}
+
+#[cfg_attr(feature = "esp-hal", esp_hal::ram)]
+fn digits(nibbles: &[u8]) -> u32 {
+    nibbles.iter().rev().fold(0, |acc, &n| acc * 10 + n as u32)
+}
+
+// Manchester bits take at least one segment each, the preamble and the
+// shortest message are needed
+#[cfg_attr(feature = "esp-hal", esp_hal::ram)]
+pub(super) fn profile() -> Option<Profile> {
+    Some(Profile::new(
+        &[HALF.range(), FULL.range()],
+        MIN_PREAMBLE_BITS + 8 * 4,
+    ))
+}
+
+#[cfg_attr(feature = "esp-hal", esp_hal::ram)]
+pub(super) fn decode(pulses: &[Symbol]) -> Result<SensorData, DecodeError> {
+    // Preamble, sync and shortest message take at least this many symbols
+    if pulses.len() < 48 {
+        return Err(DecodeError::WrongPayloadLen(pulses.len()));
+    }
+
+    let mut halves = [false; MAX_HALF_BITS];
+    let count = manchester_halves(pulses, HALF.range(), FULL.range(), &mut halves);
+
+    let (nibbles, count) = nibbles_from_halves(&halves[..count], 0)
+        .or_else(|| nibbles_from_halves(&halves[..count], 1))
+        .ok_or(DecodeError::UnpackFailed)?;
+    let n = &nibbles[..count];
+    if count < 8 {
+        return Err(DecodeError::WrongPayloadLen(count));
+    }
+
+    let sensor_id = n[..4].iter().fold(0u16, |acc, &x| (acc << 4) | x as u16);
+    let id = (n[5] << 4) | n[6];
+    let battery_ok = n[7] & 0x4 == 0;
+
+    let reading = match sensor_id {
+        ID_PCR800 => {
+            checksum(n, 18)?;
+            // Rain rate is in 0.01 in/h, total in 0.001 in
+            let rate = digits(&n[8..12]);
+            let total = digits(&n[12..18]);
+            SensorReading::Rain(Rain {
+                total: total * 254 / 1000,
+                rate: Some((rate * 254 / 100) as u16),
+            })
+        }
+        ID_WGR800 => {
+            checksum(n, 17)?;
+            // Speeds are in 0.1 m/s
+            SensorReading::Wind(Wind {
+                avg_speed: digits(&n[14..17]) as u16,
+                gust_speed: digits(&n[11..14]) as u16,
+                direction: n[8] as u16 * 225 / 10,
+            })
+        }
+        _ => return Err(DecodeError::UnpackFailed),
+    };
+
+    let model = if sensor_id == ID_PCR800 {
+        "PCR800"
+    } else {
+        "WGR800"
+    };
+
+    Ok(SensorData::new(model, reading, battery_ok, 0, id.into()))
+}
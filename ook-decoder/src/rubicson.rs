@@ -0,0 +1,75 @@
+use crate::Symbol;
+use packed_struct::prelude::*;
+
+use super::bits::crc8;
+use super::{Climate, DecodeError, SensorData, SensorReading, ppm_bits, sign_extend_12bit};
+
+// Rubicson 48659 and Solight TE44 use the same timings as Nexus-TH, but
+// there is no humidity, last 8 bits are CRC-8 instead:
+//
+// AAAAAAAA BXCC DDDDDDDDDDDD 1111 EEEEEEEE, where:
+//
+// * A - ID
+// * B - 1 if battery is OK, 0 if battery low
+// * X - TX button pressed
+// * C - channel, zero based (0 for channel 1)
+// * D - temperature * 10 in C, two's complement
+// * E - CRC-8 over first 28 bits, poly 0x31, init 0x6c
+
+const CRC_POLY: u8 = 0x31;
+const CRC_INIT: u8 = 0x6c;
+
+impl From<RubicsonPayload> for SensorData {
+    #[cfg_attr(feature = "esp-hal", esp_hal::ram)]
+    fn from(pld: RubicsonPayload) -> Self {
+        let temp_10x = sign_extend_12bit(pld.temp_10x.into());
+
+        let channel: u8 = pld.channel.into();
+        SensorData::new(
+            "Rubicson",
+            SensorReading::Climate(Climate::new(temp_10x, None)),
+            pld.battery_ok,
+            channel + 1,
+            u8::from(pld.id).into(),
+        )
+    }
+}
+
+#[derive(PackedStruct, Debug)]
+#[packed_struct(bit_numbering = "msb0")]
+struct RubicsonPayload {
+    #[packed_field(bits = "0:7")]
+    id: Integer<u8, packed_bits::Bits<8>>,
+    #[packed_field(bits = "8:8")]
+    battery_ok: bool,
+    #[packed_field(bits = "9:9")]
+    _tx_button: bool,
+    #[packed_field(bits = "10:11")]
+    channel: Integer<u8, packed_bits::Bits<2>>,
+    #[packed_field(endian = "msb", bits = "12:23")]
+    temp_10x: Integer<u16, packed_bits::Bits<12>>,
+    #[packed_field(bits = "24:27")]
+    _fixed: Integer<u8, packed_bits::Bits<4>>,
+    #[packed_field(bits = "28:35")]
+    crc: Integer<u8, packed_bits::Bits<8>>,
+}
+
+#[cfg_attr(feature = "esp-hal", esp_hal::ram)]
+pub(super) fn decode(pulses: &[Symbol]) -> Result<SensorData, DecodeError> {
+    let decoded = ppm_bits(pulses)?;
+
+    let unpacked = RubicsonPayload::unpack(&decoded).map_err(|_| DecodeError::UnpackFailed)?;
+
+    // CRC covers 28 bits, pad the last nibble with zeroes
+    let crc = crc8(
+        &[decoded[0], decoded[1], decoded[2], decoded[3] & 0xf0],
+        CRC_POLY,
+        CRC_INIT,
+    );
+    let expected: u8 = unpacked.crc.into();
+    if crc != expected {
+        return Err(DecodeError::ChecksumMismatch(crc, expected));
+    }
+
+    Ok(unpacked.into())
+}
@@ -0,0 +1,46 @@
+use core::ops::Range;
+
+use crate::Symbol;
+
+use super::DecodeError;
+
+/// How many noise symbols in front of a frame are tolerated. Anything longer
+/// is most likely a different protocol.
+pub const MAX_NOISE_SYMBOLS: usize = 8;
+
+/// Index of the first symbol following a pause within `gap`. Searches from the
+/// end, so the last preamble in the capture wins.
+#[cfg_attr(feature = "esp-hal", esp_hal::ram)]
+pub fn find_sync(pulses: &[Symbol], gap: Range<u16>) -> Option<usize> {
+    pulses
+        .iter()
+        .rposition(|entry| {
+            (!entry.level1 && gap.contains(&entry.length1))
+                || (!entry.level2 && gap.contains(&entry.length2))
+        })
+        .map(|idx| idx + 1)
+}
+
+/// Locate a frame of `frame_len` symbols (including the terminator) within a
+/// capture. The frame starts right after the last pause
+/// within `gap` if there is one. Otherwise, and if there are noise pulses
+/// before the real transmission, the frame is aligned to the end of the
+/// capture, since the end-of-payload pause is what terminates it.
+#[cfg_attr(feature = "esp-hal", esp_hal::ram)]
+pub fn locate_frame(
+    pulses: &[Symbol],
+    frame_len: usize,
+    gap: Range<u16>,
+) -> Result<&[Symbol], DecodeError> {
+    let len = pulses.len();
+    if len < frame_len {
+        return Err(DecodeError::WrongPayloadLen(len));
+    }
+
+    let start = find_sync(pulses, gap).unwrap_or(0);
+    if !(frame_len..=frame_len + MAX_NOISE_SYMBOLS).contains(&(len - start)) {
+        return Err(DecodeError::WrongPayloadLen(len - start));
+    }
+
+    Ok(&pulses[len - frame_len..])
+}
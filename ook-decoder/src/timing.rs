@@ -0,0 +1,92 @@
+use core::ops::Range;
+use core::sync::atomic::{AtomicU16, Ordering};
+
+/// Range of segment lengths (in uS) a decoder accepts for one kind of symbol.
+/// Defaults are compiled in, but can be adjusted at runtime to tune decoding
+/// for sensors that drift out of spec, e.g. on weak batteries.
+pub struct Timing {
+    name: &'static str,
+    default: Range<u16>,
+    min: AtomicU16,
+    max: AtomicU16,
+}
+
+impl Timing {
+    pub(crate) const fn new(name: &'static str, min: u16, max: u16) -> Self {
+        Timing {
+            name,
+            default: min..max,
+            min: AtomicU16::new(min),
+            max: AtomicU16::new(max),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    #[cfg_attr(feature = "esp-hal", esp_hal::ram)]
+    pub fn range(&self) -> Range<u16> {
+        self.min.load(Ordering::Relaxed)..self.max.load(Ordering::Relaxed)
+    }
+
+    /// Set accepted range, returns false if it's empty
+    pub fn set(&self, range: Range<u16>) -> bool {
+        if range.is_empty() {
+            return false;
+        }
+        self.min.store(range.start, Ordering::Relaxed);
+        self.max.store(range.end, Ordering::Relaxed);
+        true
+    }
+
+    pub fn reset(&self) {
+        self.set(self.default.clone());
+    }
+}
+
+// PPM timings are shared by Rubicson and Nexus-TH
+pub const TIMING_COUNT: usize = 3 * cfg!(any(feature = "proto-rubicson", feature = "proto-nexus"))
+    as usize
+    + 2 * cfg!(feature = "proto-hideki") as usize
+    + 2 * cfg!(feature = "proto-fineoffset") as usize
+    + 2 * cfg!(feature = "proto-oregon") as usize
+    + 2 * cfg!(feature = "proto-honeywell") as usize
+    + cfg!(feature = "proto-ev1527") as usize;
+
+/// All adjustable timings of compiled in protocols, named `<protocol>_<symbol>`
+pub static TIMINGS: [&Timing; TIMING_COUNT] = [
+    #[cfg(any(feature = "proto-rubicson", feature = "proto-nexus"))]
+    &crate::PPM_PULSE,
+    #[cfg(any(feature = "proto-rubicson", feature = "proto-nexus"))]
+    &crate::PPM_ONE,
+    #[cfg(any(feature = "proto-rubicson", feature = "proto-nexus"))]
+    &crate::PPM_ZERO,
+    #[cfg(feature = "proto-hideki")]
+    &crate::hideki::HALF,
+    #[cfg(feature = "proto-hideki")]
+    &crate::hideki::FULL,
+    #[cfg(feature = "proto-fineoffset")]
+    &crate::fineoffset::SHORT,
+    #[cfg(feature = "proto-fineoffset")]
+    &crate::fineoffset::LONG,
+    #[cfg(feature = "proto-oregon")]
+    &crate::oregon::HALF,
+    #[cfg(feature = "proto-oregon")]
+    &crate::oregon::FULL,
+    #[cfg(feature = "proto-honeywell")]
+    &crate::honeywell::HALF,
+    #[cfg(feature = "proto-honeywell")]
+    &crate::honeywell::FULL,
+    #[cfg(feature = "proto-ev1527")]
+    &crate::ev1527::PERIOD,
+];
+
+pub fn find(name: &str) -> Option<&'static Timing> {
+    TIMINGS.iter().copied().find(|timing| timing.name == name)
+}
+
+/// Restore compiled in defaults
+pub fn reset() {
+    TIMINGS.iter().for_each(|timing| timing.reset());
+}
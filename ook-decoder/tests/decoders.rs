@@ -0,0 +1,567 @@
+use core::cell::Cell;
+
+use ook_decoder::encode;
+use ook_decoder::generic::{self, Checksum, Descriptor, Field};
+use ook_decoder::learn::{Learner, Modulation, Suggestion};
+use ook_decoder::{
+    Climate, Contact, DEFAULT_TEMP_RANGE, DecodeError, MAX_SYMBOLS, PROTOCOLS, SensorReading,
+    Symbol, TempUnit, Wind, decode_ranked, decode_raw, decode_timed, find_protocol,
+    plausible_protocols, pulses_from_edges,
+};
+
+mod fixtures;
+
+use fixtures::{Expect, Fixture};
+
+fn check(fixtures: &[Fixture]) {
+    for fixture in fixtures {
+        match &fixture.expect {
+            Expect::Decoded {
+                model,
+                id,
+                channel,
+                battery_ok,
+                reading,
+            } => {
+                let data = decode_raw(fixture.edges)
+                    .unwrap_or_else(|e| panic!("{}: {:?}", fixture.name, e));
+                assert_eq!(data.model(), *model, "{}", fixture.name);
+                assert_eq!(data.id, *id, "{}", fixture.name);
+                assert_eq!(data.channel, *channel, "{}", fixture.name);
+                assert_eq!(data.battery_ok, *battery_ok, "{}", fixture.name);
+                assert_eq!(data.reading, *reading, "{}", fixture.name);
+            }
+            Expect::Rejected => {
+                if let Ok(data) = decode_raw(fixture.edges) {
+                    panic!("{}: decoded {:?}", fixture.name, data);
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn nexus_th() {
+    check(fixtures::NEXUS_TH);
+}
+
+#[test]
+fn nexus_encode() {
+    for (id, channel, battery_ok, temp, humidity) in [
+        (174, 1, true, 217, 45),
+        (3, 4, false, -155, 0),
+        (255, 2, true, 599, 100),
+    ] {
+        let edges = encode::nexus(id, channel, battery_ok, temp, humidity).unwrap();
+        let data = decode_raw(&edges).unwrap();
+        assert_eq!(data.model(), "Nexus-TH");
+        assert_eq!(data.id, id as u32);
+        assert_eq!(data.channel, channel);
+        assert_eq!(data.battery_ok, battery_ok);
+        assert_eq!(
+            data.reading,
+            SensorReading::Climate(Climate {
+                temp_decicelsius: temp,
+                humidity: Some(humidity),
+            })
+        );
+    }
+    assert!(encode::nexus(1, 5, true, 0, 0).is_none());
+    assert!(encode::nexus(1, 1, true, 2048, 0).is_none());
+    assert!(encode::nexus(1, 1, true, 0, 101).is_none());
+}
+
+#[test]
+fn normalize() {
+    let edges = fixtures::NEXUS_TH[0].edges;
+    let mut expected = [Symbol::default(); MAX_SYMBOLS];
+    let len = pulses_from_edges(edges, &mut expected).unwrap();
+    let data = decode_raw(edges).unwrap();
+    // 0.5 uS ticks of an RMT clock divider of 40, and 2 uS ones of 160
+    for (num, den) in [(1, 2), (2, 1)] {
+        let ticks: Vec<_> = edges
+            .iter()
+            .map(|&(level, us)| (level, (us as u32 * den / num) as u16))
+            .collect();
+        let mut pulses = [Symbol::default(); MAX_SYMBOLS];
+        assert_eq!(pulses_from_edges(&ticks, &mut pulses), Some(len));
+        ook_decoder::normalize(&mut pulses[..len], num, den);
+        for (pulse, expected) in pulses.iter().zip(&expected).take(len) {
+            assert!(pulse.length1.abs_diff(expected.length1) <= 1);
+            assert!(pulse.length2.abs_diff(expected.length2) <= 1);
+            assert_eq!(pulse.length2 == 0, expected.length2 == 0);
+        }
+        let best = decode_ranked(&pulses[..len], 1)
+            .unwrap()
+            .best()
+            .unwrap()
+            .data
+            .clone();
+        assert_eq!(best, data);
+    }
+}
+
+#[test]
+fn merge_glitches() {
+    let edges = fixtures::NEXUS_TH[0].edges;
+    let mut expected = [Symbol::default(); MAX_SYMBOLS];
+    let len = pulses_from_edges(edges, &mut expected).unwrap();
+    // A spike splitting every pause longer than 1 ms, and a dropout in the
+    // first pulse
+    let mut chopped = Vec::new();
+    for (idx, &(level, us)) in edges.iter().enumerate() {
+        if !level && us > 1000 {
+            chopped.extend([(false, us / 2 - 10), (true, 20), (false, us - us / 2 - 10)]);
+        } else if level && idx < 2 {
+            chopped.extend([(true, us / 2 - 5), (false, 10), (true, us - us / 2 - 5)]);
+        } else {
+            chopped.push((level, us));
+        }
+    }
+    let mut pulses = [Symbol::default(); MAX_SYMBOLS];
+    let chopped_len = pulses_from_edges(&chopped, &mut pulses).unwrap();
+    assert!(chopped_len > len);
+    assert_eq!(
+        ook_decoder::merge_glitches(&mut pulses[..chopped_len], 50),
+        len
+    );
+    assert_eq!(pulses[..len], expected[..len]);
+    assert_eq!(
+        decode_raw(edges).unwrap(),
+        decode_ranked(&pulses[..len], 1)
+            .unwrap()
+            .best()
+            .unwrap()
+            .data
+    );
+
+    // Nothing shorter than the threshold, nothing changes
+    let mut pulses = expected;
+    assert_eq!(ook_decoder::merge_glitches(&mut pulses[..len], 50), len);
+    assert_eq!(pulses, expected);
+}
+
+#[test]
+fn display() {
+    let data = decode_raw(fixtures::NEXUS_TH[0].edges).unwrap();
+    assert_eq!(data.to_string(), "Nexus-TH id 174 channel 1: 21.7 C, 45 %");
+}
+
+#[test]
+fn merge() {
+    let data = decode_raw(fixtures::NEXUS_TH[0].edges).unwrap();
+    // Temperature only frame keeps the humidity of the one before
+    let mut later = data.clone();
+    later.reading = SensorReading::Climate(Climate {
+        temp_decicelsius: 220,
+        humidity: None,
+    });
+    later.battery_ok = false;
+    let merged = data.merge(&later).unwrap();
+    assert_eq!(
+        merged.reading,
+        SensorReading::Climate(Climate {
+            temp_decicelsius: 220,
+            humidity: Some(45),
+        })
+    );
+    assert!(!merged.battery_ok);
+
+    let mut other = later.clone();
+    other.id += 1;
+    assert!(data.merge(&other).is_none());
+    let mut contact = later.clone();
+    contact.reading = SensorReading::Contact(Contact {
+        open: true,
+        tamper: false,
+        heartbeat: false,
+    });
+    assert!(data.merge(&contact).is_none());
+
+    // Wind frame updates the wind of a weather station
+    let weather = decode_raw(fixtures::WH1080[0].edges).unwrap();
+    let wind = Wind {
+        avg_speed: 30,
+        gust_speed: 52,
+        direction: 90,
+    };
+    let mut later = weather.clone();
+    later.reading = SensorReading::Wind(wind.clone());
+    let SensorReading::Weather(climate, _, rain) = &weather.reading else {
+        panic!("{:?}", weather.reading);
+    };
+    assert_eq!(
+        weather.merge(&later).unwrap().reading,
+        SensorReading::Weather(climate.clone(), wind, rain.clone())
+    );
+}
+
+#[test]
+fn rubicson() {
+    check(fixtures::RUBICSON);
+}
+
+#[test]
+fn hideki() {
+    check(fixtures::HIDEKI);
+}
+
+#[test]
+fn wh1080() {
+    check(fixtures::WH1080);
+}
+
+#[test]
+fn oregon() {
+    check(fixtures::OREGON);
+}
+
+#[test]
+fn honeywell() {
+    check(fixtures::HONEYWELL);
+}
+
+#[test]
+fn ev1527() {
+    check(fixtures::EV1527);
+}
+
+#[test]
+fn govee() {
+    check(fixtures::GOVEE);
+}
+
+#[test]
+fn gs558() {
+    check(fixtures::GS558);
+}
+
+#[test]
+fn noise() {
+    check(fixtures::NOISE);
+}
+
+#[test]
+fn ranking() {
+    // Rubicson frames also pass as Nexus-TH, CRC must make Rubicson win
+    for fixture in fixtures::RUBICSON {
+        let Expect::Decoded { model, channel, .. } = &fixture.expect else {
+            continue;
+        };
+        let mut pulses = [Symbol::default(); MAX_SYMBOLS];
+        let len = pulses_from_edges(fixture.edges, &mut pulses).unwrap();
+        let candidates = decode_ranked(&pulses[..len], *channel)
+            .unwrap_or_else(|e| panic!("{}: {:?}", fixture.name, e));
+        assert!(candidates.len() > 1, "{}", fixture.name);
+        let best = candidates.unambiguous().expect(fixture.name);
+        assert_eq!(best.data.model(), *model, "{}", fixture.name);
+    }
+}
+
+#[test]
+fn classify() {
+    let plausible = |edges| {
+        let mut pulses = [Symbol::default(); MAX_SYMBOLS];
+        let len = pulses_from_edges(edges, &mut pulses).unwrap();
+        plausible_protocols(&pulses[..len])
+    };
+    // Generic slots are loaded by another test meanwhile, leave them out
+    let mut builtin = 0;
+    for (fixtures, key) in [
+        (fixtures::NEXUS_TH, "nexus"),
+        (fixtures::RUBICSON, "rubicson"),
+        (fixtures::HIDEKI, "hideki"),
+        (fixtures::WH1080, "fineoffset"),
+        (fixtures::OREGON, "oregon"),
+        (fixtures::HONEYWELL, "honeywell"),
+        (fixtures::EV1527, "ev1527"),
+    ] {
+        let bit = 1 << find_protocol(key).unwrap();
+        builtin |= bit;
+        for fixture in fixtures {
+            if matches!(fixture.expect, Expect::Decoded { .. }) {
+                assert_ne!(plausible(fixture.edges) & bit, 0, "{}", fixture.name);
+            }
+        }
+    }
+
+    // Segments of PPM frames are too long for Honeywell, too few for WH1080
+    let nexus = plausible(fixtures::NEXUS_TH[0].edges);
+    assert_eq!(nexus & 1 << find_protocol("honeywell").unwrap(), 0);
+    assert_eq!(nexus & 1 << find_protocol("fineoffset").unwrap(), 0);
+    for fixture in fixtures::NOISE.iter().filter(|f| f.edges.len() < 16) {
+        assert_eq!(plausible(fixture.edges) & builtin, 0, "{}", fixture.name);
+    }
+}
+
+#[test]
+fn timed() {
+    let fixture = &fixtures::NEXUS_TH[0];
+    let mut pulses = [Symbol::default(); MAX_SYMBOLS];
+    let len = pulses_from_edges(fixture.edges, &mut pulses).unwrap();
+    let pulses = &pulses[..len];
+
+    // Clock ticks on every look, so each decoder takes exactly one tick
+    let ticks = Cell::new(0);
+    let mut tried = 0;
+    let candidates = decode_timed(
+        pulses,
+        1,
+        || ticks.replace(ticks.get() + 1),
+        |idx, spent| {
+            assert_eq!(spent, 1, "{}", PROTOCOLS[idx].key);
+            tried |= 1 << idx;
+        },
+    )
+    .unwrap();
+    // Generic slots are loaded by another test meanwhile, leave them out
+    let generic = generic::KEYS
+        .iter()
+        .fold(0, |mask, key| mask | 1 << find_protocol(key).unwrap());
+    assert_eq!(tried & !generic, plausible_protocols(pulses) & !generic);
+    assert_ne!(tried & 1 << find_protocol("nexus").unwrap(), 0);
+    assert_eq!(
+        candidates.best().unwrap().data,
+        decode_ranked(pulses, 1).unwrap().best().unwrap().data
+    );
+}
+
+fn learn(fixtures: &[Fixture]) -> Suggestion {
+    let mut learner = Learner::new();
+    for fixture in fixtures {
+        let mut pulses = [Symbol::default(); MAX_SYMBOLS];
+        let len = pulses_from_edges(fixture.edges, &mut pulses).unwrap();
+        learner.feed(&pulses[..len]);
+    }
+    learner.suggest().expect("nothing learned")
+}
+
+#[test]
+fn learn_ppm() {
+    let suggestion = learn(fixtures::NEXUS_TH);
+    assert_eq!(suggestion.modulation, Modulation::Ppm);
+    assert_eq!(suggestion.bits, 36);
+    assert!(suggestion.pulse.unwrap().contains(&500));
+    assert!(suggestion.zero.contains(&950));
+    assert!(suggestion.one.contains(&1950));
+}
+
+#[test]
+fn learn_pwm() {
+    let suggestion = learn(fixtures::EV1527);
+    assert_eq!(suggestion.modulation, Modulation::Pwm);
+    assert_eq!(suggestion.bits, 24);
+    assert_eq!(suggestion.pulse, None);
+    assert!(suggestion.zero.contains(&350));
+    assert!(suggestion.one.contains(&1050));
+}
+
+#[test]
+fn learn_noise() {
+    let mut learner = Learner::new();
+    for fixture in fixtures::NOISE {
+        let mut pulses = [Symbol::default(); MAX_SYMBOLS];
+        if let Some(len) = pulses_from_edges(fixture.edges, &mut pulses) {
+            learner.feed(&pulses[..len]);
+        }
+    }
+    assert_eq!(learner.bursts() as usize, fixtures::NOISE.len());
+    assert_eq!(learner.suggest(), None);
+}
+
+#[test]
+fn generic() {
+    // Timings no built-in protocol uses, so frames can't be taken for theirs
+    let descriptor = Descriptor {
+        modulation: Modulation::Ppm,
+        bits: 32,
+        pulse: 200..300,
+        zero: 500..700,
+        one: 1100..1300,
+        gap: None,
+        id: Some(Field {
+            offset: 0,
+            width: 8,
+        }),
+        channel: Some(Field {
+            offset: 10,
+            width: 2,
+        }),
+        battery: Some(Field {
+            offset: 8,
+            width: 1,
+        }),
+        temperature: Some(Field {
+            offset: 12,
+            width: 12,
+        }),
+        unit: TempUnit::Celsius,
+        humidity: None,
+        code: None,
+        checksum: Some(Checksum::Sum8),
+    };
+    // ID 0x5a, battery good, channel 3, -5.2C
+    let frame = [0x5a, 0xaf, 0xcc, 0xd5];
+    let mut edges = Vec::new();
+    for idx in 0..32 {
+        let one = frame[idx / 8] & (0x80 >> (idx % 8)) != 0;
+        edges.extend([(true, 250), (false, if one { 1200 } else { 600 })]);
+    }
+    edges.push((true, 250));
+
+    assert!(decode_raw(&edges).is_err());
+    assert!(generic::load(0, Some(descriptor.clone())));
+    let data = decode_raw(&edges).unwrap();
+    assert_eq!(data.model(), "Generic-1");
+    assert_eq!(data.id, 0x5a);
+    assert_eq!(data.channel, 3);
+    assert!(data.battery_ok);
+    assert_eq!(
+        data.reading,
+        SensorReading::Climate(Climate {
+            temp_decicelsius: -52,
+            humidity: None,
+        })
+    );
+
+    // Checksum mismatch
+    let (_, length) = edges[1];
+    edges[1].1 = if length == 600 { 1200 } else { 600 };
+    assert!(decode_raw(&edges).is_err());
+
+    // Overlapping bits and fields past the frame are rejected
+    let overlapping = Descriptor {
+        one: 600..1300,
+        ..descriptor.clone()
+    };
+    assert!(!generic::load(1, Some(overlapping)));
+    let past = Descriptor {
+        humidity: Some(Field {
+            offset: 28,
+            width: 8,
+        }),
+        ..descriptor
+    };
+    assert!(!generic::load(1, Some(past)));
+    assert!(generic::load(0, None));
+}
+
+#[test]
+fn humidity_out_of_range() {
+    // Timings of neither the built-in protocols nor the generic test
+    let descriptor = Descriptor {
+        modulation: Modulation::Ppm,
+        bits: 24,
+        pulse: 200..300,
+        zero: 1400..1600,
+        one: 2400..2600,
+        gap: None,
+        id: None,
+        channel: None,
+        battery: None,
+        temperature: Some(Field {
+            offset: 0,
+            width: 8,
+        }),
+        unit: TempUnit::Celsius,
+        humidity: Some(Field {
+            offset: 8,
+            width: 8,
+        }),
+        code: None,
+        checksum: Some(Checksum::Sum8),
+    };
+    let edges = |frame: [u8; 3]| {
+        let mut edges = Vec::new();
+        for idx in 0..24 {
+            let one = frame[idx / 8] & (0x80 >> (idx % 8)) != 0;
+            edges.extend([(true, 250), (false, if one { 2500 } else { 1500 })]);
+        }
+        edges.push((true, 250));
+        edges
+    };
+    assert!(generic::load(2, Some(descriptor)));
+
+    // 5.0C, 100% is fine
+    let data = decode_raw(&edges([0x32, 100, 0x32 + 100])).unwrap();
+    assert_eq!(data.climate().unwrap().humidity, Some(100));
+
+    // 150% is a corrupt frame, not clamped to 100%
+    let result = decode_raw(&edges([0x32, 150, 0x32 + 150]));
+    assert!(matches!(result, Err(DecodeError::HumidityOutOfRange(150))));
+    assert!(generic::load(2, None));
+}
+
+#[test]
+fn temperature_range() {
+    // Timings of neither the built-in protocols nor the other generic tests
+    let descriptor = Descriptor {
+        modulation: Modulation::Ppm,
+        bits: 24,
+        pulse: 200..300,
+        zero: 3000..3200,
+        one: 3600..3800,
+        gap: None,
+        id: None,
+        channel: None,
+        battery: None,
+        temperature: Some(Field {
+            offset: 0,
+            width: 12,
+        }),
+        unit: TempUnit::Celsius,
+        humidity: None,
+        code: None,
+        checksum: Some(Checksum::Sum8),
+    };
+    let decode = |raw: u16| {
+        let frame = [(raw >> 4) as u8, (raw << 4) as u8];
+        let frame = [frame[0], frame[1], frame[0].wrapping_add(frame[1])];
+        let mut edges = Vec::new();
+        for idx in 0..24 {
+            let one = frame[idx / 8] & (0x80 >> (idx % 8)) != 0;
+            edges.extend([(true, 250), (false, if one { 3700 } else { 3100 })]);
+        }
+        edges.push((true, 250));
+        decode_raw(&edges)
+    };
+    assert!(generic::load(3, Some(descriptor.clone())));
+    assert_eq!(DEFAULT_TEMP_RANGE, -600..600);
+
+    // Two's complement, 12 bits
+    for (raw, temp) in [
+        (0x000, 0),
+        (0xfff, -1),
+        (0xf06, -250),
+        (0xda8, -600),
+        (0x257, 599),
+    ] {
+        let data = decode(raw).unwrap_or_else(|e| panic!("{:#x}: {:?}", raw, e));
+        assert_eq!(data.climate().unwrap().temp_decicelsius, temp, "{:#x}", raw);
+    }
+    let data = decode(0xfff).unwrap();
+    assert_eq!(data.climate().unwrap().celsius().to_string(), "-0.1");
+    assert_eq!(data.climate().unwrap().degrees(), 0);
+
+    for (raw, temp) in [(0x258, 600), (0xda7, -601), (0x800, -2048), (0x7ff, 2047)] {
+        let result = decode(raw);
+        assert!(
+            matches!(result, Err(DecodeError::TempOutOfRange(t)) if t == temp),
+            "{:#x}: {:?}",
+            raw,
+            result
+        );
+    }
+
+    // Sent in 0.1 F, converted to the nearest 0.1 C
+    let fahrenheit = Descriptor {
+        unit: TempUnit::Fahrenheit,
+        ..descriptor
+    };
+    assert!(generic::load(3, Some(fahrenheit)));
+    for (raw, temp) in [(0x140, 0), (0x000, -178), (0x3da, 370), (0xe70, -400)] {
+        let data = decode(raw).unwrap_or_else(|e| panic!("{:#x}: {:?}", raw, e));
+        assert_eq!(data.climate().unwrap().temp_decicelsius, temp, "{:#x}", raw);
+    }
+    assert!(generic::load(3, None));
+}
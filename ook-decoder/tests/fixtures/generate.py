@@ -0,0 +1,449 @@
+#!/usr/bin/env python3
+"""Generate the synthetic decoder fixtures in mod.rs.
+
+Every frame is encoded here from the protocol's frame layout, with timing
+jitter from a seeded RNG, so the output is reproducible. Regenerate with
+
+    python3 tests/fixtures/generate.py > tests/fixtures/mod.rs
+    cargo +stable fmt
+
+from the ook-decoder directory. New groups are appended at the end and
+reseed the RNG, so adding one doesn't change the edges of the others.
+"""
+
+import random
+import sys
+
+
+def j(v, d=None):
+    """Jitter a duration in us by up to d, 5% by default"""
+    d = d if d is not None else max(10, v // 20)
+    return v + random.randint(-d, d)
+
+
+def crc8(data, poly, init):
+    crc = init
+    for b in data:
+        crc ^= b
+        for _ in range(8):
+            crc = ((crc << 1) ^ poly) & 0xff if crc & 0x80 else (crc << 1) & 0xff
+    return crc
+
+
+def crc16(data, poly, init):
+    crc = init
+    for b in data:
+        crc ^= b << 8
+        for _ in range(8):
+            crc = ((crc << 1) ^ poly) & 0xffff if crc & 0x8000 else (crc << 1) & 0xffff
+    return crc
+
+
+def bitstr(v, n):
+    return [int(c) for c in format(v, f"0{n}b")]
+
+
+def runs(halves, half_us):
+    """Merge equal consecutive half bits into pulses, drop leading and
+    trailing idle"""
+    segs = []
+    for h in halves:
+        if segs and segs[-1][0] == h:
+            segs[-1][1] += 1
+        else:
+            segs.append([h, 1])
+    while segs and not segs[0][0]:
+        segs.pop(0)
+    while segs and not segs[-1][0]:
+        segs.pop()
+    return [(bool(l), j(n * half_us)) for l, n in segs]
+
+
+# Nexus and Rubicson, pulse position: 500 us pulse, 1 is a 1930 us gap,
+# 0 a 960 us one, preceded by a 2700 us sync gap
+
+
+def ppm(bits):
+    e = [(True, j(500, 40)), (False, j(2700, 60))]
+    for b in bits:
+        e += [(True, j(500, 40)), (False, j(1930 if b else 960, 50))]
+    e.append((True, j(500, 40)))
+    return e
+
+
+def nexus(id_, batt, ch, temp, hum, fixed=0xf, x=0):
+    return ppm(
+        bitstr(id_, 8) + [batt, x] + bitstr(ch, 2) + bitstr(temp & 0xfff, 12) + bitstr(fixed, 4) + bitstr(hum, 8)
+    )
+
+
+def rubicson(id_, batt, ch, temp, flip=None):
+    b = bitstr(id_, 8) + [batt, 0] + bitstr(ch, 2) + bitstr(temp & 0xfff, 12) + [1, 1, 1, 1]
+    by = [int("".join(map(str, b[i:i + 8])), 2) for i in range(0, 24, 8)]
+    by.append(int("".join(map(str, b[24:28])), 2) << 4)
+    b += bitstr(crc8(by, 0x31, 0x6c), 8)
+    if flip is not None:
+        b[flip] ^= 1
+    return ppm(b)
+
+
+# Hideki, differential Manchester: bytes LSB first and inverted, each
+# followed by an even parity bit. 1 toggles twice in 1040 us, 0 once.
+
+
+def hideki(packet, flip=None):
+    raw = []
+    for byte in packet:
+        raw += [1 - ((byte >> n) & 1) for n in range(8)]
+        raw.append(1 - (bin(byte).count("1") & 1))
+    if flip is not None:
+        raw[flip] ^= 1
+    durations = []
+    for b in raw:
+        if b:
+            durations += [j(520), j(520)]
+        else:
+            durations.append(j(1040))
+    # The final parity bit merges into the idle gap when it ends low
+    edges = [(idx % 2 == 0, d) for idx, d in enumerate(durations)]
+    while edges and not edges[-1][0]:
+        edges.pop()
+    return edges
+
+
+def hideki_th(ch, id_, temp, batt, hum, flip=None):
+    t = abs(temp)
+    b4 = ((t // 10 % 10) << 4) | (t % 10)
+    b5 = (0x80 if temp >= 0 else 0) | (0x40 if batt else 0) | (t // 100)
+    body = [(ch << 5) | id_, 0x0c, 0x1e, b4, b5, ((hum // 10) << 4) | (hum % 10), 0x00]
+    x = 0
+    for b in body:
+        x ^= b
+    body.append(x)
+    body.append(crc8(body, 0x07, 0))
+    return hideki([0x9f] + body, flip)
+
+
+# WH1080, pulse width: 550 us pulse is 1, 1500 us is 0, 1000 us gaps
+
+
+def wh1080(data9, flip=None):
+    data = data9 + [crc8(data9, 0x31, 0)]
+    bits = [1] * 8
+    for b in data:
+        bits += bitstr(b, 8)
+    if flip is not None:
+        bits[8 + flip] ^= 1
+    e = []
+    for b in bits:
+        e += [(True, j(550 if b else 1500, 40)), (False, j(1000, 40))]
+    e.pop()
+    return e
+
+
+# Manchester, the first half is the bit value
+
+
+def manchester(bits, half_us):
+    halves = []
+    for b in bits:
+        halves += [b, 1 - b]
+    return runs(halves, half_us)
+
+
+def oregon(nibbles, chk_idx, invert=False, flip=None):
+    """Oregon Scientific v3, nibbles LSB first with the checksum inserted at
+    chk_idx, 24 bit preamble and 0101 sync"""
+    n = list(nibbles[:chk_idx])
+    s = sum(n) & 0xff
+    n += [s & 0xf, s >> 4] + list(nibbles[chk_idx:])
+    bits = [1] * 24 + [0, 1, 0, 1]
+    for x in n:
+        bits += [(x >> i) & 1 for i in range(4)]
+    if flip is not None:
+        bits[28 + flip] ^= 1
+    if invert:
+        bits = [1 - b for b in bits]
+    return manchester(bits, 490)
+
+
+def honeywell(ch, id_, event, flip=None):
+    d = [(ch << 4) | (id_ >> 16), (id_ >> 8) & 0xff, id_ & 0xff, event]
+    poly = 0x8050 if ch in (2, 4, 0xa) else 0x8005
+    crc = crc16([0xff, 0xfe] + d, poly, 0)
+    d += [crc >> 8, crc & 0xff]
+    bits = bitstr(0xfffe, 16)
+    for b in d:
+        bits += bitstr(b, 8)
+    if flip is not None:
+        bits[16 + flip] ^= 1
+    return manchester(bits, 150)
+
+
+# EV1527, 24 bits of 1:3 or 3:1 pulse/gap, bit `bad` is sent 2:2
+
+
+def ev1527(code, period=1400, bad=None):
+    e = []
+    t = period // 4
+    for i, b in enumerate(bitstr(code, 24)):
+        if bad == i:
+            e += [(True, j(2 * t)), (False, j(2 * t))]
+        elif b:
+            e += [(True, j(3 * t)), (False, j(t))]
+        else:
+            e += [(True, j(t)), (False, j(3 * t))]
+    e.append((True, j(t)))
+    return e
+
+
+def noise(n):
+    e, level = [], True
+    for _ in range(n):
+        e.append((level, random.randint(100, 2900)))
+        level = not level
+    if not e[-1][0]:
+        e.pop()
+    return e
+
+
+def truncate(e, frac=0.25):
+    """Drop the last `frac` of the edges, still ending with a pulse"""
+    k = int(len(e) * (1 - frac))
+    if not e[k - 1][0]:
+        k -= 1
+    return e[:k]
+
+
+def prefix_noise(e, n=3):
+    pre = []
+    for _ in range(n):
+        pre += [(True, random.randint(120, 290)), (False, random.randint(300, 700))]
+    return pre + e
+
+
+# Govee water leak detector, 48 bits: 1 is a 440 us pulse and a 940 us gap,
+# 0 the other way around, followed by a 440 us stop pulse
+
+
+def govee(data):
+    e = []
+    for b in data:
+        for i in range(8):
+            s = 440 + random.randint(-25, 25)
+            l = 940 + random.randint(-40, 40)
+            if (b >> (7 - i)) & 1:
+                e += [(True, s), (False, l)]
+            else:
+                e += [(True, l), (False, s)]
+    e.append((True, 440 + random.randint(-25, 25)))
+    return e
+
+
+# GS 558 smoke alarm, 24 bits: 1 is a 1200 us pulse and a 530 us gap, 0 a
+# 440 us pulse and a 1300 us gap. A wake-up pulse with an 11.8 ms gap goes
+# ahead and two short stop pulses follow.
+
+
+def gs558(code, wake=True, stop=True):
+    e = []
+    if wake:
+        e += [(True, 436 + random.randint(-20, 20)), (False, 11764 + random.randint(-100, 100))]
+    for i in range(24):
+        if (code >> (23 - i)) & 1:
+            e += [(True, 1202 + random.randint(-40, 40)), (False, 526 + random.randint(-25, 25))]
+        else:
+            e += [(True, 436 + random.randint(-25, 25)), (False, 1299 + random.randint(-40, 40))]
+    if stop:
+        e += [
+            (True, 434 + random.randint(-20, 20)),
+            (False, 434 + random.randint(-20, 20)),
+            (True, 434 + random.randint(-20, 20)),
+        ]
+    else:
+        e.pop()
+    return e
+
+
+# Rust output, left for rustfmt to lay out
+
+
+def fixture(name, e, expect):
+    edges = " ".join(f"({str(l).lower()}, {d})," for l, d in e)
+    return f'Fixture {{ name: "{name}", edges: &[{edges}], expect: {expect} }},'
+
+
+def climate(temp, hum):
+    h = "None" if hum is None else f"Some({hum})"
+    return f"Climate {{ temp_decicelsius: {temp}, humidity: {h} }}"
+
+
+def decoded(model, id_, ch, batt, reading):
+    return (
+        f'Expect::Decoded {{ model: "{model}", id: {id_}, channel: {ch}, '
+        f"battery_ok: {str(bool(batt)).lower()}, reading: {reading} }}"
+    )
+
+
+R = "Expect::Rejected"
+groups = {}
+
+random.seed(1374)
+
+good = nexus(0xae, 1, 0, 217, 45)
+groups["NEXUS_TH"] = [
+    fixture("channel 1, 21.7C, 45%", good, decoded("Nexus-TH", 174, 1, 1, f"SensorReading::Climate({climate(217, 45)})")),
+    fixture(
+        "channel 3, low battery, -5.2C, 88%",
+        nexus(0x31, 0, 2, -52, 88),
+        decoded("Nexus-TH", 49, 3, 0, f"SensorReading::Climate({climate(-52, 88)})"),
+    ),
+    fixture(
+        "noise in front of the frame",
+        prefix_noise(nexus(0xae, 1, 0, 218, 45)[2:]),
+        decoded("Nexus-TH", 174, 1, 1, f"SensorReading::Climate({climate(218, 45)})"),
+    ),
+    fixture("truncated", truncate(good), R),
+    fixture("fixed bits mismatch", nexus(0xae, 1, 0, 217, 45, fixed=0xb), R),
+]
+
+rgood = rubicson(0x9d, 1, 0, 154)
+groups["RUBICSON"] = [
+    fixture("channel 1, 15.4C", rgood, decoded("Rubicson", 157, 1, 1, f"SensorReading::Climate({climate(154, None)})")),
+    fixture(
+        "channel 2, -12.0C",
+        rubicson(0x42, 1, 1, -120),
+        decoded("Rubicson", 66, 2, 1, f"SensorReading::Climate({climate(-120, None)})"),
+    ),
+    fixture("truncated", truncate(rgood), R),
+    fixture("CRC mismatch", rubicson(0x9d, 1, 0, 154, flip=25), R),
+]
+
+hgood = hideki_th(1, 0x5, 234, 1, 56)
+groups["HIDEKI"] = [
+    fixture("channel 1, 23.4C, 56%", hgood, decoded("Hideki", 5, 1, 1, f"SensorReading::Climate({climate(234, 56)})")),
+    fixture(
+        "channel 1, -3.7C, 91%",
+        hideki_th(1, 0xc, -37, 1, 91),
+        decoded("Hideki", 12, 1, 1, f"SensorReading::Climate({climate(-37, 91)})"),
+    ),
+    fixture("truncated", truncate(hgood), R),
+    fixture("parity error", hideki_th(1, 0x5, 234, 1, 56, flip=40), R),
+]
+
+wdata = [0xa5, 0xc0, 0xb7, 0x43, 0x05, 0x09, 0x01, 0x23, 0x06]
+wgood = wh1080(wdata)
+wind = "Wind { avg_speed: 17, gust_speed: 30, direction: 135 }"
+rain = "Rain { total: 873, rate: None }"
+weather = f"SensorReading::Weather({climate(183, 67)}, {wind}, {rain})"
+groups["WH1080"] = [
+    fixture("18.3C, 67%, wind and rain", wgood, decoded("WH1080", 92, 0, 1, weather)),
+    fixture("preamble partially lost", wgood[6:], decoded("WH1080", 92, 0, 1, weather)),
+    fixture("truncated", truncate(wgood), R),
+    fixture("CRC mismatch", wh1080(wdata, flip=30), R),
+]
+
+wgr = [1, 9, 8, 4, 0, 0xb, 0x3, 0, 12, 0, 0, 5, 8, 0, 2, 4, 0, 0, 0]
+pcr = [2, 9, 1, 4, 0, 0x6, 0x1, 0, 2, 1, 0, 0, 4, 3, 2, 1, 0, 0, 0, 0]
+ogood = oregon(wgr, 17)
+owind = "SensorReading::Wind(Wind { avg_speed: 42, gust_speed: 85, direction: 270 })"
+orain = "SensorReading::Rain(Rain { total: 313, rate: Some(30) })"
+groups["OREGON"] = [
+    fixture("WGR800 wind", ogood, decoded("WGR800", 0xb3, 0, 1, owind)),
+    fixture("PCR800 rain", oregon(pcr, 18), decoded("PCR800", 0x61, 0, 1, orain)),
+    fixture("PCR800 rain, inverted", oregon(pcr, 18, invert=True), decoded("PCR800", 0x61, 0, 1, orain)),
+    fixture("truncated", truncate(ogood, 0.4), R),
+    fixture("checksum mismatch", oregon(wgr, 17, flip=33), R),
+]
+
+contact = "SensorReading::Contact(Contact {{ open: {}, tamper: false, heartbeat: {} }})"
+cgood = honeywell(0x8, 0x2d5a1, 0x80)
+groups["HONEYWELL"] = [
+    fixture("open", cgood, decoded("Honeywell-Contact", 0x2d5a1, 0, 1, contact.format("true", "false"))),
+    fixture(
+        "2GIG heartbeat, low battery",
+        honeywell(0xa, 0x01f00, 0x0c),
+        decoded("Honeywell-Contact", 0x01f00, 0, 0, contact.format("false", "true")),
+    ),
+    fixture("truncated", truncate(cgood), R),
+    fixture("CRC mismatch", honeywell(0x8, 0x2d5a1, 0x80, flip=12), R),
+]
+
+egood = ev1527((0x5a3c1 << 4) | 0x8)
+groups["EV1527"] = [
+    fixture("motion", egood, decoded("EV1527-PIR", 0x5a3c1, 0, 1, "SensorReading::Motion(8)")),
+    fixture(
+        "motion, slow oscillator",
+        ev1527((0x0b2e7 << 4) | 0x3, period=1900),
+        decoded("EV1527-PIR", 0x0b2e7, 0, 1, "SensorReading::Motion(3)"),
+    ),
+    fixture("truncated", truncate(egood), R),
+    fixture("wrong symbol ratio", ev1527((0x5a3c1 << 4) | 0x8, bad=7), R),
+]
+
+groups["NOISE"] = [
+    fixture("short burst", noise(9), R),
+    fixture("long burst", noise(75), R),
+    fixture("full buffer", noise(250), R),
+]
+
+random.seed(1479)
+
+leak = "SensorReading::Leak(Leak {{ water: {} }})"
+groups["GOVEE"] = [
+    fixture("leak", govee([0x1d, 0x2c, 0xfa, 0xfa, 0x3b, 0x91]), decoded("Govee-Water", 0x1d2c, 0, 1, leak.format("true"))),
+    fixture("button", govee([0x1d, 0x2c, 0xaf, 0xaf, 0x6e, 0x04]), decoded("Govee-Water", 0x1d2c, 0, 1, leak.format("false"))),
+    fixture("event copies differ", govee([0x1d, 0x2c, 0xfa, 0xfb, 0x3b, 0x91]), R),
+    fixture("unknown event", govee([0x1d, 0x2c, 0x55, 0x55, 0x3b, 0x91]), R),
+    fixture("truncated", govee([0x1d, 0x2c, 0xfa, 0xfa, 0x3b, 0x91])[12:], R),
+]
+
+random.seed(1480)
+
+smoke = "SensorReading::Hazard(Hazard::Smoke)"
+groups["GS558"] = [
+    fixture("smoke", gs558(0x5a3c81), decoded("GS558-Smoke", 0x5a3c81, 0, 1, smoke)),
+    fixture("smoke, no wake-up pulse", gs558(0x0e17b2, wake=False), decoded("GS558-Smoke", 0x0e17b2, 0, 1, smoke)),
+    fixture("no stop pulses", gs558(0x5a3c81, stop=False), R),
+    fixture("truncated", gs558(0x5a3c81)[:-12], R),
+]
+
+HEADER = """\
+// Synthetic pulse trains for the decoder tests, generated by generate.py. Do
+// not edit, change the script and regenerate instead. Every frame is encoded
+// by the script from the protocol's frame layout with seeded timing jitter,
+// none of them was recorded from a real sensor, so they only hold the
+// decoders to the script's reading of each protocol.
+//
+// Every capture is a list of (level, duration in uS) edges as the receiver
+// would see them, `true` is carrier present. Captures start with the first
+// carrier pulse and end with the last one, the idle pause that terminates
+// them is not recorded.
+//
+// Every protocol has good frames along with truncated and corrupted ones,
+// which must be rejected.
+
+use ook_decoder::{Climate, Contact, Hazard, Leak, Rain, SensorReading, Wind};
+
+pub struct Fixture {
+    pub name: &'static str,
+    pub edges: &'static [(bool, u16)],
+    pub expect: Expect,
+}
+
+pub enum Expect {
+    Decoded {
+        model: &'static str,
+        id: u32,
+        channel: u8,
+        battery_ok: bool,
+        reading: SensorReading,
+    },
+    Rejected,
+}
+"""
+
+out = [HEADER]
+for name, fixtures in groups.items():
+    out.append(f"pub static {name}: &[Fixture] = &[\n" + "\n".join(fixtures) + "\n];\n")
+sys.stdout.write("\n".join(out))
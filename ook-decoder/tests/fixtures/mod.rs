@@ -0,0 +1,4795 @@
+// Synthetic pulse trains for the decoder tests, generated by generate.py. Do
+// not edit, change the script and regenerate instead. Every frame is encoded
+// by the script from the protocol's frame layout with seeded timing jitter,
+// none of them was recorded from a real sensor, so they only hold the
+// decoders to the script's reading of each protocol.
+//
+// Every capture is a list of (level, duration in uS) edges as the receiver
+// would see them, `true` is carrier present. Captures start with the first
+// carrier pulse and end with the last one, the idle pause that terminates
+// them is not recorded.
+//
+// Every protocol has good frames along with truncated and corrupted ones,
+// which must be rejected.
+
+use ook_decoder::{Climate, Contact, Hazard, Leak, Rain, SensorReading, Wind};
+
+pub struct Fixture {
+    pub name: &'static str,
+    pub edges: &'static [(bool, u16)],
+    pub expect: Expect,
+}
+
+pub enum Expect {
+    Decoded {
+        model: &'static str,
+        id: u32,
+        channel: u8,
+        battery_ok: bool,
+        reading: SensorReading,
+    },
+    Rejected,
+}
+
+pub static NEXUS_TH: &[Fixture] = &[
+    Fixture {
+        name: "channel 1, 21.7C, 45%",
+        edges: &[
+            (true, 461),
+            (false, 2721),
+            (true, 515),
+            (false, 1955),
+            (true, 527),
+            (false, 916),
+            (true, 495),
+            (false, 1960),
+            (true, 486),
+            (false, 942),
+            (true, 473),
+            (false, 1959),
+            (true, 518),
+            (false, 1939),
+            (true, 514),
+            (false, 1898),
+            (true, 461),
+            (false, 916),
+            (true, 501),
+            (false, 1890),
+            (true, 518),
+            (false, 989),
+            (true, 498),
+            (false, 956),
+            (true, 503),
+            (false, 923),
+            (true, 522),
+            (false, 941),
+            (true, 484),
+            (false, 1003),
+            (true, 477),
+            (false, 938),
+            (true, 472),
+            (false, 927),
+            (true, 536),
+            (false, 1898),
+            (true, 461),
+            (false, 1947),
+            (true, 530),
+            (false, 982),
+            (true, 466),
+            (false, 1950),
+            (true, 508),
+            (false, 1891),
+            (true, 522),
+            (false, 977),
+            (true, 511),
+            (false, 929),
+            (true, 523),
+            (false, 1976),
+            (true, 479),
+            (false, 1941),
+            (true, 500),
+            (false, 1980),
+            (true, 498),
+            (false, 1930),
+            (true, 535),
+            (false, 1907),
+            (true, 486),
+            (false, 974),
+            (true, 516),
+            (false, 943),
+            (true, 460),
+            (false, 1894),
+            (true, 512),
+            (false, 989),
+            (true, 522),
+            (false, 1960),
+            (true, 537),
+            (false, 1965),
+            (true, 518),
+            (false, 935),
+            (true, 533),
+            (false, 1921),
+            (true, 503),
+        ],
+        expect: Expect::Decoded {
+            model: "Nexus-TH",
+            id: 174,
+            channel: 1,
+            battery_ok: true,
+            reading: SensorReading::Climate(Climate {
+                temp_decicelsius: 217,
+                humidity: Some(45),
+            }),
+        },
+    },
+    Fixture {
+        name: "channel 3, low battery, -5.2C, 88%",
+        edges: &[
+            (true, 524),
+            (false, 2674),
+            (true, 535),
+            (false, 970),
+            (true, 464),
+            (false, 962),
+            (true, 527),
+            (false, 1939),
+            (true, 497),
+            (false, 1966),
+            (true, 473),
+            (false, 963),
+            (true, 519),
+            (false, 998),
+            (true, 467),
+            (false, 921),
+            (true, 509),
+            (false, 1957),
+            (true, 470),
+            (false, 911),
+            (true, 463),
+            (false, 1000),
+            (true, 506),
+            (false, 1897),
+            (true, 482),
+            (false, 990),
+            (true, 488),
+            (false, 1950),
+            (true, 463),
+            (false, 1972),
+            (true, 493),
+            (false, 1898),
+            (true, 516),
+            (false, 1936),
+            (true, 519),
+            (false, 1953),
+            (true, 471),
+            (false, 1977),
+            (true, 533),
+            (false, 989),
+            (true, 507),
+            (false, 917),
+            (true, 504),
+            (false, 1909),
+            (true, 537),
+            (false, 1928),
+            (true, 532),
+            (false, 950),
+            (true, 467),
+            (false, 1006),
+            (true, 466),
+            (false, 1967),
+            (true, 481),
+            (false, 1924),
+            (true, 500),
+            (false, 1936),
+            (true, 494),
+            (false, 1928),
+            (true, 482),
+            (false, 969),
+            (true, 519),
+            (false, 1937),
+            (true, 526),
+            (false, 985),
+            (true, 517),
+            (false, 1932),
+            (true, 511),
+            (false, 1935),
+            (true, 484),
+            (false, 979),
+            (true, 486),
+            (false, 971),
+            (true, 462),
+            (false, 979),
+            (true, 461),
+        ],
+        expect: Expect::Decoded {
+            model: "Nexus-TH",
+            id: 49,
+            channel: 3,
+            battery_ok: false,
+            reading: SensorReading::Climate(Climate {
+                temp_decicelsius: -52,
+                humidity: Some(88),
+            }),
+        },
+    },
+    Fixture {
+        name: "noise in front of the frame",
+        edges: &[
+            (true, 241),
+            (false, 532),
+            (true, 247),
+            (false, 560),
+            (true, 155),
+            (false, 694),
+            (true, 523),
+            (false, 1957),
+            (true, 495),
+            (false, 1006),
+            (true, 467),
+            (false, 1940),
+            (true, 472),
+            (false, 937),
+            (true, 473),
+            (false, 1927),
+            (true, 526),
+            (false, 1926),
+            (true, 496),
+            (false, 1886),
+            (true, 488),
+            (false, 926),
+            (true, 518),
+            (false, 1968),
+            (true, 537),
+            (false, 963),
+            (true, 466),
+            (false, 919),
+            (true, 491),
+            (false, 947),
+            (true, 494),
+            (false, 939),
+            (true, 486),
+            (false, 969),
+            (true, 477),
+            (false, 975),
+            (true, 534),
+            (false, 946),
+            (true, 519),
+            (false, 1979),
+            (true, 536),
+            (false, 1908),
+            (true, 466),
+            (false, 1006),
+            (true, 508),
+            (false, 1892),
+            (true, 470),
+            (false, 1908),
+            (true, 466),
+            (false, 910),
+            (true, 516),
+            (false, 1892),
+            (true, 530),
+            (false, 934),
+            (true, 520),
+            (false, 1913),
+            (true, 466),
+            (false, 1925),
+            (true, 493),
+            (false, 1902),
+            (true, 534),
+            (false, 1951),
+            (true, 508),
+            (false, 940),
+            (true, 476),
+            (false, 1006),
+            (true, 517),
+            (false, 1974),
+            (true, 513),
+            (false, 1000),
+            (true, 534),
+            (false, 1930),
+            (true, 501),
+            (false, 1965),
+            (true, 485),
+            (false, 926),
+            (true, 535),
+            (false, 1882),
+            (true, 493),
+        ],
+        expect: Expect::Decoded {
+            model: "Nexus-TH",
+            id: 174,
+            channel: 1,
+            battery_ok: true,
+            reading: SensorReading::Climate(Climate {
+                temp_decicelsius: 218,
+                humidity: Some(45),
+            }),
+        },
+    },
+    Fixture {
+        name: "truncated",
+        edges: &[
+            (true, 461),
+            (false, 2721),
+            (true, 515),
+            (false, 1955),
+            (true, 527),
+            (false, 916),
+            (true, 495),
+            (false, 1960),
+            (true, 486),
+            (false, 942),
+            (true, 473),
+            (false, 1959),
+            (true, 518),
+            (false, 1939),
+            (true, 514),
+            (false, 1898),
+            (true, 461),
+            (false, 916),
+            (true, 501),
+            (false, 1890),
+            (true, 518),
+            (false, 989),
+            (true, 498),
+            (false, 956),
+            (true, 503),
+            (false, 923),
+            (true, 522),
+            (false, 941),
+            (true, 484),
+            (false, 1003),
+            (true, 477),
+            (false, 938),
+            (true, 472),
+            (false, 927),
+            (true, 536),
+            (false, 1898),
+            (true, 461),
+            (false, 1947),
+            (true, 530),
+            (false, 982),
+            (true, 466),
+            (false, 1950),
+            (true, 508),
+            (false, 1891),
+            (true, 522),
+            (false, 977),
+            (true, 511),
+            (false, 929),
+            (true, 523),
+            (false, 1976),
+            (true, 479),
+            (false, 1941),
+            (true, 500),
+            (false, 1980),
+            (true, 498),
+        ],
+        expect: Expect::Rejected,
+    },
+    Fixture {
+        name: "fixed bits mismatch",
+        edges: &[
+            (true, 504),
+            (false, 2708),
+            (true, 487),
+            (false, 1888),
+            (true, 486),
+            (false, 1001),
+            (true, 528),
+            (false, 1885),
+            (true, 514),
+            (false, 949),
+            (true, 519),
+            (false, 1933),
+            (true, 517),
+            (false, 1883),
+            (true, 468),
+            (false, 1913),
+            (true, 489),
+            (false, 997),
+            (true, 526),
+            (false, 1974),
+            (true, 490),
+            (false, 948),
+            (true, 509),
+            (false, 982),
+            (true, 536),
+            (false, 925),
+            (true, 523),
+            (false, 1004),
+            (true, 497),
+            (false, 962),
+            (true, 464),
+            (false, 1010),
+            (true, 526),
+            (false, 1005),
+            (true, 462),
+            (false, 1945),
+            (true, 483),
+            (false, 1925),
+            (true, 465),
+            (false, 965),
+            (true, 511),
+            (false, 1895),
+            (true, 491),
+            (false, 1895),
+            (true, 506),
+            (false, 981),
+            (true, 506),
+            (false, 1003),
+            (true, 520),
+            (false, 1964),
+            (true, 463),
+            (false, 1954),
+            (true, 524),
+            (false, 1010),
+            (true, 477),
+            (false, 1979),
+            (true, 535),
+            (false, 1905),
+            (true, 476),
+            (false, 919),
+            (true, 531),
+            (false, 969),
+            (true, 509),
+            (false, 1938),
+            (true, 463),
+            (false, 945),
+            (true, 518),
+            (false, 1914),
+            (true, 460),
+            (false, 1939),
+            (true, 537),
+            (false, 996),
+            (true, 538),
+            (false, 1945),
+            (true, 507),
+        ],
+        expect: Expect::Rejected,
+    },
+];
+
+pub static RUBICSON: &[Fixture] = &[
+    Fixture {
+        name: "channel 1, 15.4C",
+        edges: &[
+            (true, 528),
+            (false, 2690),
+            (true, 513),
+            (false, 1896),
+            (true, 533),
+            (false, 970),
+            (true, 482),
+            (false, 998),
+            (true, 513),
+            (false, 1947),
+            (true, 487),
+            (false, 1926),
+            (true, 515),
+            (false, 1978),
+            (true, 510),
+            (false, 998),
+            (true, 533),
+            (false, 1880),
+            (true, 499),
+            (false, 1905),
+            (true, 486),
+            (false, 955),
+            (true, 512),
+            (false, 994),
+            (true, 490),
+            (false, 939),
+            (true, 500),
+            (false, 910),
+            (true, 476),
+            (false, 982),
+            (true, 462),
+            (false, 976),
+            (true, 512),
+            (false, 969),
+            (true, 482),
+            (false, 1885),
+            (true, 521),
+            (false, 916),
+            (true, 520),
+            (false, 981),
+            (true, 463),
+            (false, 1927),
+            (true, 508),
+            (false, 1962),
+            (true, 460),
+            (false, 930),
+            (true, 468),
+            (false, 1929),
+            (true, 539),
+            (false, 1004),
+            (true, 460),
+            (false, 1979),
+            (true, 509),
+            (false, 1972),
+            (true, 503),
+            (false, 1892),
+            (true, 478),
+            (false, 1959),
+            (true, 516),
+            (false, 923),
+            (true, 537),
+            (false, 967),
+            (true, 504),
+            (false, 1967),
+            (true, 527),
+            (false, 1893),
+            (true, 526),
+            (false, 987),
+            (true, 488),
+            (false, 1977),
+            (true, 484),
+            (false, 1893),
+            (true, 471),
+            (false, 952),
+            (true, 520),
+        ],
+        expect: Expect::Decoded {
+            model: "Rubicson",
+            id: 157,
+            channel: 1,
+            battery_ok: true,
+            reading: SensorReading::Climate(Climate {
+                temp_decicelsius: 154,
+                humidity: None,
+            }),
+        },
+    },
+    Fixture {
+        name: "channel 2, -12.0C",
+        edges: &[
+            (true, 509),
+            (false, 2660),
+            (true, 532),
+            (false, 955),
+            (true, 539),
+            (false, 1923),
+            (true, 476),
+            (false, 965),
+            (true, 482),
+            (false, 956),
+            (true, 474),
+            (false, 948),
+            (true, 485),
+            (false, 982),
+            (true, 534),
+            (false, 1882),
+            (true, 487),
+            (false, 938),
+            (true, 510),
+            (false, 1948),
+            (true, 528),
+            (false, 917),
+            (true, 509),
+            (false, 934),
+            (true, 500),
+            (false, 1942),
+            (true, 506),
+            (false, 1946),
+            (true, 488),
+            (false, 1922),
+            (true, 464),
+            (false, 1881),
+            (true, 514),
+            (false, 1960),
+            (true, 532),
+            (false, 1920),
+            (true, 522),
+            (false, 923),
+            (true, 491),
+            (false, 974),
+            (true, 498),
+            (false, 984),
+            (true, 497),
+            (false, 1896),
+            (true, 526),
+            (false, 969),
+            (true, 481),
+            (false, 946),
+            (true, 461),
+            (false, 957),
+            (true, 467),
+            (false, 1971),
+            (true, 525),
+            (false, 1915),
+            (true, 484),
+            (false, 1882),
+            (true, 503),
+            (false, 1884),
+            (true, 497),
+            (false, 910),
+            (true, 492),
+            (false, 996),
+            (true, 465),
+            (false, 1950),
+            (true, 511),
+            (false, 1892),
+            (true, 494),
+            (false, 929),
+            (true, 464),
+            (false, 961),
+            (true, 533),
+            (false, 1933),
+            (true, 525),
+            (false, 1901),
+            (true, 509),
+        ],
+        expect: Expect::Decoded {
+            model: "Rubicson",
+            id: 66,
+            channel: 2,
+            battery_ok: true,
+            reading: SensorReading::Climate(Climate {
+                temp_decicelsius: -120,
+                humidity: None,
+            }),
+        },
+    },
+    Fixture {
+        name: "truncated",
+        edges: &[
+            (true, 528),
+            (false, 2690),
+            (true, 513),
+            (false, 1896),
+            (true, 533),
+            (false, 970),
+            (true, 482),
+            (false, 998),
+            (true, 513),
+            (false, 1947),
+            (true, 487),
+            (false, 1926),
+            (true, 515),
+            (false, 1978),
+            (true, 510),
+            (false, 998),
+            (true, 533),
+            (false, 1880),
+            (true, 499),
+            (false, 1905),
+            (true, 486),
+            (false, 955),
+            (true, 512),
+            (false, 994),
+            (true, 490),
+            (false, 939),
+            (true, 500),
+            (false, 910),
+            (true, 476),
+            (false, 982),
+            (true, 462),
+            (false, 976),
+            (true, 512),
+            (false, 969),
+            (true, 482),
+            (false, 1885),
+            (true, 521),
+            (false, 916),
+            (true, 520),
+            (false, 981),
+            (true, 463),
+            (false, 1927),
+            (true, 508),
+            (false, 1962),
+            (true, 460),
+            (false, 930),
+            (true, 468),
+            (false, 1929),
+            (true, 539),
+            (false, 1004),
+            (true, 460),
+            (false, 1979),
+            (true, 509),
+            (false, 1972),
+            (true, 503),
+        ],
+        expect: Expect::Rejected,
+    },
+    Fixture {
+        name: "CRC mismatch",
+        edges: &[
+            (true, 495),
+            (false, 2703),
+            (true, 512),
+            (false, 1919),
+            (true, 511),
+            (false, 938),
+            (true, 480),
+            (false, 916),
+            (true, 539),
+            (false, 1934),
+            (true, 511),
+            (false, 1892),
+            (true, 482),
+            (false, 1936),
+            (true, 538),
+            (false, 968),
+            (true, 467),
+            (false, 1935),
+            (true, 488),
+            (false, 1926),
+            (true, 501),
+            (false, 930),
+            (true, 512),
+            (false, 925),
+            (true, 531),
+            (false, 968),
+            (true, 528),
+            (false, 982),
+            (true, 467),
+            (false, 955),
+            (true, 519),
+            (false, 922),
+            (true, 491),
+            (false, 978),
+            (true, 535),
+            (false, 1954),
+            (true, 490),
+            (false, 953),
+            (true, 520),
+            (false, 912),
+            (true, 486),
+            (false, 1890),
+            (true, 467),
+            (false, 1972),
+            (true, 540),
+            (false, 997),
+            (true, 507),
+            (false, 1897),
+            (true, 530),
+            (false, 918),
+            (true, 471),
+            (false, 1949),
+            (true, 468),
+            (false, 928),
+            (true, 526),
+            (false, 1911),
+            (true, 476),
+            (false, 1951),
+            (true, 493),
+            (false, 942),
+            (true, 492),
+            (false, 958),
+            (true, 465),
+            (false, 1921),
+            (true, 496),
+            (false, 1967),
+            (true, 500),
+            (false, 995),
+            (true, 520),
+            (false, 1961),
+            (true, 534),
+            (false, 1978),
+            (true, 529),
+            (false, 964),
+            (true, 479),
+        ],
+        expect: Expect::Rejected,
+    },
+];
+
+pub static HIDEKI: &[Fixture] = &[
+    Fixture {
+        name: "channel 1, 23.4C, 56%",
+        edges: &[
+            (true, 1003),
+            (false, 994),
+            (true, 1020),
+            (false, 1036),
+            (true, 1043),
+            (false, 513),
+            (true, 513),
+            (false, 537),
+            (true, 530),
+            (false, 1055),
+            (true, 519),
+            (false, 515),
+            (true, 999),
+            (false, 505),
+            (true, 501),
+            (false, 1083),
+            (true, 542),
+            (false, 518),
+            (true, 515),
+            (false, 501),
+            (true, 1038),
+            (false, 542),
+            (true, 546),
+            (false, 514),
+            (true, 506),
+            (false, 1075),
+            (true, 513),
+            (false, 519),
+            (true, 529),
+            (false, 540),
+            (true, 1087),
+            (false, 1012),
+            (true, 498),
+            (false, 535),
+            (true, 526),
+            (false, 546),
+            (true, 512),
+            (false, 512),
+            (true, 494),
+            (false, 537),
+            (true, 517),
+            (false, 531),
+            (true, 535),
+            (false, 534),
+            (true, 1069),
+            (false, 1012),
+            (true, 1034),
+            (false, 1047),
+            (true, 518),
+            (false, 531),
+            (true, 514),
+            (false, 520),
+            (true, 530),
+            (false, 538),
+            (true, 537),
+            (false, 504),
+            (true, 503),
+            (false, 497),
+            (true, 495),
+            (false, 503),
+            (true, 1078),
+            (false, 498),
+            (true, 521),
+            (false, 1004),
+            (true, 1047),
+            (false, 517),
+            (true, 494),
+            (false, 537),
+            (true, 501),
+            (false, 1015),
+            (true, 543),
+            (false, 519),
+            (true, 1027),
+            (false, 505),
+            (true, 532),
+            (false, 544),
+            (true, 523),
+            (false, 512),
+            (true, 505),
+            (false, 502),
+            (true, 501),
+            (false, 1023),
+            (true, 1043),
+            (false, 1050),
+            (true, 545),
+            (false, 504),
+            (true, 1023),
+            (false, 988),
+            (true, 513),
+            (false, 537),
+            (true, 1068),
+            (false, 500),
+            (true, 524),
+            (false, 1038),
+            (true, 503),
+            (false, 529),
+            (true, 530),
+            (false, 535),
+            (true, 523),
+            (false, 527),
+            (true, 538),
+            (false, 539),
+            (true, 507),
+            (false, 514),
+            (true, 541),
+            (false, 503),
+            (true, 527),
+            (false, 530),
+            (true, 526),
+            (false, 525),
+            (true, 504),
+            (false, 545),
+            (true, 497),
+            (false, 531),
+            (true, 528),
+            (false, 527),
+            (true, 1012),
+            (false, 1018),
+            (true, 993),
+            (false, 519),
+            (true, 521),
+            (false, 1057),
+            (true, 533),
+            (false, 538),
+            (true, 516),
+            (false, 517),
+            (true, 1067),
+            (false, 1052),
+            (true, 1070),
+            (false, 1001),
+            (true, 503),
+            (false, 496),
+            (true, 546),
+            (false, 539),
+            (true, 499),
+            (false, 538),
+            (true, 1069),
+            (false, 1044),
+            (true, 502),
+            (false, 528),
+            (true, 504),
+        ],
+        expect: Expect::Decoded {
+            model: "Hideki",
+            id: 5,
+            channel: 1,
+            battery_ok: true,
+            reading: SensorReading::Climate(Climate {
+                temp_decicelsius: 234,
+                humidity: Some(56),
+            }),
+        },
+    },
+    Fixture {
+        name: "channel 1, -3.7C, 91%",
+        edges: &[
+            (true, 1014),
+            (false, 1038),
+            (true, 1061),
+            (false, 1036),
+            (true, 1058),
+            (false, 518),
+            (true, 526),
+            (false, 507),
+            (true, 511),
+            (false, 1005),
+            (true, 508),
+            (false, 508),
+            (true, 501),
+            (false, 523),
+            (true, 517),
+            (false, 535),
+            (true, 1076),
+            (false, 1015),
+            (true, 515),
+            (false, 520),
+            (true, 1026),
+            (false, 507),
+            (true, 522),
+            (false, 521),
+            (true, 509),
+            (false, 1035),
+            (true, 514),
+            (false, 531),
+            (true, 546),
+            (false, 546),
+            (true, 1069),
+            (false, 1066),
+            (true, 519),
+            (false, 518),
+            (true, 494),
+            (false, 510),
+            (true, 530),
+            (false, 522),
+            (true, 509),
+            (false, 514),
+            (true, 523),
+            (false, 507),
+            (true, 500),
+            (false, 542),
+            (true, 1089),
+            (false, 1050),
+            (true, 1082),
+            (false, 1071),
+            (true, 528),
+            (false, 501),
+            (true, 507),
+            (false, 507),
+            (true, 543),
+            (false, 496),
+            (true, 540),
+            (false, 517),
+            (true, 1031),
+            (false, 1034),
+            (true, 1060),
+            (false, 524),
+            (true, 520),
+            (false, 1030),
+            (true, 1023),
+            (false, 523),
+            (true, 521),
+            (false, 543),
+            (true, 519),
+            (false, 1037),
+            (true, 509),
+            (false, 516),
+            (true, 543),
+            (false, 503),
+            (true, 520),
+            (false, 524),
+            (true, 505),
+            (false, 531),
+            (true, 497),
+            (false, 506),
+            (true, 519),
+            (false, 533),
+            (true, 1051),
+            (false, 500),
+            (true, 496),
+            (false, 1004),
+            (true, 1052),
+            (false, 497),
+            (true, 495),
+            (false, 535),
+            (true, 498),
+            (false, 515),
+            (true, 533),
+            (false, 1077),
+            (true, 534),
+            (false, 521),
+            (true, 541),
+            (false, 496),
+            (true, 1086),
+            (false, 1035),
+            (true, 498),
+            (false, 506),
+            (true, 509),
+            (false, 511),
+            (true, 515),
+            (false, 516),
+            (true, 537),
+            (false, 518),
+            (true, 504),
+            (false, 537),
+            (true, 495),
+            (false, 518),
+            (true, 543),
+            (false, 499),
+            (true, 545),
+            (false, 512),
+            (true, 527),
+            (false, 529),
+            (true, 507),
+            (false, 535),
+            (true, 521),
+            (false, 545),
+            (true, 543),
+            (false, 516),
+            (true, 1087),
+            (false, 995),
+            (true, 512),
+            (false, 501),
+            (true, 1001),
+            (false, 999),
+            (true, 517),
+            (false, 534),
+            (true, 524),
+            (false, 508),
+            (true, 1063),
+            (false, 533),
+            (true, 497),
+            (false, 534),
+            (true, 537),
+            (false, 541),
+            (true, 531),
+            (false, 1054),
+            (true, 532),
+            (false, 511),
+            (true, 521),
+            (false, 522),
+            (true, 502),
+        ],
+        expect: Expect::Decoded {
+            model: "Hideki",
+            id: 12,
+            channel: 1,
+            battery_ok: true,
+            reading: SensorReading::Climate(Climate {
+                temp_decicelsius: -37,
+                humidity: Some(91),
+            }),
+        },
+    },
+    Fixture {
+        name: "truncated",
+        edges: &[
+            (true, 1003),
+            (false, 994),
+            (true, 1020),
+            (false, 1036),
+            (true, 1043),
+            (false, 513),
+            (true, 513),
+            (false, 537),
+            (true, 530),
+            (false, 1055),
+            (true, 519),
+            (false, 515),
+            (true, 999),
+            (false, 505),
+            (true, 501),
+            (false, 1083),
+            (true, 542),
+            (false, 518),
+            (true, 515),
+            (false, 501),
+            (true, 1038),
+            (false, 542),
+            (true, 546),
+            (false, 514),
+            (true, 506),
+            (false, 1075),
+            (true, 513),
+            (false, 519),
+            (true, 529),
+            (false, 540),
+            (true, 1087),
+            (false, 1012),
+            (true, 498),
+            (false, 535),
+            (true, 526),
+            (false, 546),
+            (true, 512),
+            (false, 512),
+            (true, 494),
+            (false, 537),
+            (true, 517),
+            (false, 531),
+            (true, 535),
+            (false, 534),
+            (true, 1069),
+            (false, 1012),
+            (true, 1034),
+            (false, 1047),
+            (true, 518),
+            (false, 531),
+            (true, 514),
+            (false, 520),
+            (true, 530),
+            (false, 538),
+            (true, 537),
+            (false, 504),
+            (true, 503),
+            (false, 497),
+            (true, 495),
+            (false, 503),
+            (true, 1078),
+            (false, 498),
+            (true, 521),
+            (false, 1004),
+            (true, 1047),
+            (false, 517),
+            (true, 494),
+            (false, 537),
+            (true, 501),
+            (false, 1015),
+            (true, 543),
+            (false, 519),
+            (true, 1027),
+            (false, 505),
+            (true, 532),
+            (false, 544),
+            (true, 523),
+            (false, 512),
+            (true, 505),
+            (false, 502),
+            (true, 501),
+            (false, 1023),
+            (true, 1043),
+            (false, 1050),
+            (true, 545),
+            (false, 504),
+            (true, 1023),
+            (false, 988),
+            (true, 513),
+            (false, 537),
+            (true, 1068),
+            (false, 500),
+            (true, 524),
+            (false, 1038),
+            (true, 503),
+            (false, 529),
+            (true, 530),
+            (false, 535),
+            (true, 523),
+            (false, 527),
+            (true, 538),
+            (false, 539),
+            (true, 507),
+            (false, 514),
+            (true, 541),
+        ],
+        expect: Expect::Rejected,
+    },
+    Fixture {
+        name: "parity error",
+        edges: &[
+            (true, 1090),
+            (false, 1015),
+            (true, 1003),
+            (false, 1036),
+            (true, 1063),
+            (false, 525),
+            (true, 520),
+            (false, 545),
+            (true, 506),
+            (false, 1092),
+            (true, 514),
+            (false, 510),
+            (true, 1072),
+            (false, 540),
+            (true, 528),
+            (false, 1069),
+            (true, 535),
+            (false, 535),
+            (true, 531),
+            (false, 539),
+            (true, 1053),
+            (false, 508),
+            (true, 531),
+            (false, 524),
+            (true, 546),
+            (false, 1056),
+            (true, 524),
+            (false, 515),
+            (true, 522),
+            (false, 505),
+            (true, 1088),
+            (false, 1051),
+            (true, 541),
+            (false, 542),
+            (true, 525),
+            (false, 529),
+            (true, 496),
+            (false, 524),
+            (true, 539),
+            (false, 528),
+            (true, 498),
+            (false, 543),
+            (true, 532),
+            (false, 511),
+            (true, 996),
+            (false, 1053),
+            (true, 1021),
+            (false, 1034),
+            (true, 510),
+            (false, 519),
+            (true, 498),
+            (false, 515),
+            (true, 494),
+            (false, 534),
+            (true, 518),
+            (false, 544),
+            (true, 527),
+            (false, 519),
+            (true, 528),
+            (false, 532),
+            (true, 1044),
+            (false, 502),
+            (true, 516),
+            (false, 506),
+            (true, 528),
+            (false, 1045),
+            (true, 508),
+            (false, 499),
+            (true, 505),
+            (false, 500),
+            (true, 1010),
+            (false, 519),
+            (true, 529),
+            (false, 1065),
+            (true, 500),
+            (false, 523),
+            (true, 539),
+            (false, 543),
+            (true, 533),
+            (false, 514),
+            (true, 497),
+            (false, 518),
+            (true, 1083),
+            (false, 995),
+            (true, 994),
+            (false, 527),
+            (true, 528),
+            (false, 1035),
+            (true, 1011),
+            (false, 541),
+            (true, 497),
+            (false, 1022),
+            (true, 543),
+            (false, 503),
+            (true, 1045),
+            (false, 536),
+            (true, 537),
+            (false, 501),
+            (true, 544),
+            (false, 495),
+            (true, 501),
+            (false, 539),
+            (true, 520),
+            (false, 504),
+            (true, 533),
+            (false, 496),
+            (true, 519),
+            (false, 538),
+            (true, 504),
+            (false, 529),
+            (true, 516),
+            (false, 519),
+            (true, 540),
+            (false, 545),
+            (true, 504),
+            (false, 525),
+            (true, 508),
+            (false, 1064),
+            (true, 1019),
+            (false, 1058),
+            (true, 499),
+            (false, 520),
+            (true, 1063),
+            (false, 513),
+            (true, 546),
+            (false, 522),
+            (true, 513),
+            (false, 1007),
+            (true, 1049),
+            (false, 1088),
+            (true, 1048),
+            (false, 522),
+            (true, 543),
+            (false, 517),
+            (true, 538),
+            (false, 500),
+            (true, 506),
+            (false, 1079),
+            (true, 1063),
+            (false, 504),
+            (true, 536),
+            (false, 532),
+            (true, 520),
+        ],
+        expect: Expect::Rejected,
+    },
+];
+
+pub static WH1080: &[Fixture] = &[
+    Fixture {
+        name: "18.3C, 67%, wind and rain",
+        edges: &[
+            (true, 515),
+            (false, 996),
+            (true, 568),
+            (false, 1014),
+            (true, 584),
+            (false, 972),
+            (true, 536),
+            (false, 972),
+            (true, 517),
+            (false, 1014),
+            (true, 529),
+            (false, 999),
+            (true, 516),
+            (false, 985),
+            (true, 542),
+            (false, 998),
+            (true, 516),
+            (false, 1002),
+            (true, 1477),
+            (false, 1034),
+            (true, 564),
+            (false, 964),
+            (true, 1524),
+            (false, 973),
+            (true, 1540),
+            (false, 994),
+            (true, 543),
+            (false, 982),
+            (true, 1531),
+            (false, 978),
+            (true, 575),
+            (false, 1026),
+            (true, 549),
+            (false, 981),
+            (true, 528),
+            (false, 1039),
+            (true, 1516),
+            (false, 991),
+            (true, 1529),
+            (false, 1036),
+            (true, 1504),
+            (false, 1024),
+            (true, 1497),
+            (false, 1005),
+            (true, 1485),
+            (false, 1020),
+            (true, 1473),
+            (false, 1023),
+            (true, 531),
+            (false, 986),
+            (true, 1499),
+            (false, 1039),
+            (true, 557),
+            (false, 975),
+            (true, 523),
+            (false, 1003),
+            (true, 1503),
+            (false, 980),
+            (true, 555),
+            (false, 962),
+            (true, 518),
+            (false, 977),
+            (true, 572),
+            (false, 997),
+            (true, 1525),
+            (false, 1037),
+            (true, 529),
+            (false, 977),
+            (true, 1506),
+            (false, 964),
+            (true, 1506),
+            (false, 1038),
+            (true, 1508),
+            (false, 1021),
+            (true, 1464),
+            (false, 969),
+            (true, 574),
+            (false, 978),
+            (true, 563),
+            (false, 964),
+            (true, 1511),
+            (false, 984),
+            (true, 1518),
+            (false, 996),
+            (true, 1471),
+            (false, 969),
+            (true, 1516),
+            (false, 982),
+            (true, 1466),
+            (false, 996),
+            (true, 545),
+            (false, 986),
+            (true, 1465),
+            (false, 1001),
+            (true, 515),
+            (false, 961),
+            (true, 1504),
+            (false, 977),
+            (true, 1508),
+            (false, 1018),
+            (true, 1483),
+            (false, 962),
+            (true, 1477),
+            (false, 964),
+            (true, 527),
+            (false, 971),
+            (true, 1503),
+            (false, 975),
+            (true, 1485),
+            (false, 1027),
+            (true, 581),
+            (false, 1009),
+            (true, 1518),
+            (false, 1015),
+            (true, 1497),
+            (false, 978),
+            (true, 1504),
+            (false, 1007),
+            (true, 1510),
+            (false, 977),
+            (true, 1511),
+            (false, 1034),
+            (true, 1481),
+            (false, 967),
+            (true, 1516),
+            (false, 981),
+            (true, 566),
+            (false, 982),
+            (true, 1500),
+            (false, 1026),
+            (true, 1482),
+            (false, 1018),
+            (true, 571),
+            (false, 991),
+            (true, 1481),
+            (false, 1001),
+            (true, 1526),
+            (false, 963),
+            (true, 1524),
+            (false, 1006),
+            (true, 558),
+            (false, 1010),
+            (true, 519),
+            (false, 1022),
+            (true, 1469),
+            (false, 1003),
+            (true, 1524),
+            (false, 1005),
+            (true, 1504),
+            (false, 962),
+            (true, 1480),
+            (false, 960),
+            (true, 1468),
+            (false, 973),
+            (true, 582),
+            (false, 1013),
+            (true, 548),
+            (false, 1004),
+            (true, 1512),
+            (false, 998),
+            (true, 560),
+            (false, 990),
+            (true, 531),
+            (false, 990),
+            (true, 512),
+            (false, 1022),
+            (true, 561),
+            (false, 1004),
+            (true, 1487),
+            (false, 1030),
+            (true, 581),
+            (false, 1029),
+            (true, 1527),
+            (false, 975),
+            (true, 1500),
+        ],
+        expect: Expect::Decoded {
+            model: "WH1080",
+            id: 92,
+            channel: 0,
+            battery_ok: true,
+            reading: SensorReading::Weather(
+                Climate {
+                    temp_decicelsius: 183,
+                    humidity: Some(67),
+                },
+                Wind {
+                    avg_speed: 17,
+                    gust_speed: 30,
+                    direction: 135,
+                },
+                Rain {
+                    total: 873,
+                    rate: None,
+                },
+            ),
+        },
+    },
+    Fixture {
+        name: "preamble partially lost",
+        edges: &[
+            (true, 536),
+            (false, 972),
+            (true, 517),
+            (false, 1014),
+            (true, 529),
+            (false, 999),
+            (true, 516),
+            (false, 985),
+            (true, 542),
+            (false, 998),
+            (true, 516),
+            (false, 1002),
+            (true, 1477),
+            (false, 1034),
+            (true, 564),
+            (false, 964),
+            (true, 1524),
+            (false, 973),
+            (true, 1540),
+            (false, 994),
+            (true, 543),
+            (false, 982),
+            (true, 1531),
+            (false, 978),
+            (true, 575),
+            (false, 1026),
+            (true, 549),
+            (false, 981),
+            (true, 528),
+            (false, 1039),
+            (true, 1516),
+            (false, 991),
+            (true, 1529),
+            (false, 1036),
+            (true, 1504),
+            (false, 1024),
+            (true, 1497),
+            (false, 1005),
+            (true, 1485),
+            (false, 1020),
+            (true, 1473),
+            (false, 1023),
+            (true, 531),
+            (false, 986),
+            (true, 1499),
+            (false, 1039),
+            (true, 557),
+            (false, 975),
+            (true, 523),
+            (false, 1003),
+            (true, 1503),
+            (false, 980),
+            (true, 555),
+            (false, 962),
+            (true, 518),
+            (false, 977),
+            (true, 572),
+            (false, 997),
+            (true, 1525),
+            (false, 1037),
+            (true, 529),
+            (false, 977),
+            (true, 1506),
+            (false, 964),
+            (true, 1506),
+            (false, 1038),
+            (true, 1508),
+            (false, 1021),
+            (true, 1464),
+            (false, 969),
+            (true, 574),
+            (false, 978),
+            (true, 563),
+            (false, 964),
+            (true, 1511),
+            (false, 984),
+            (true, 1518),
+            (false, 996),
+            (true, 1471),
+            (false, 969),
+            (true, 1516),
+            (false, 982),
+            (true, 1466),
+            (false, 996),
+            (true, 545),
+            (false, 986),
+            (true, 1465),
+            (false, 1001),
+            (true, 515),
+            (false, 961),
+            (true, 1504),
+            (false, 977),
+            (true, 1508),
+            (false, 1018),
+            (true, 1483),
+            (false, 962),
+            (true, 1477),
+            (false, 964),
+            (true, 527),
+            (false, 971),
+            (true, 1503),
+            (false, 975),
+            (true, 1485),
+            (false, 1027),
+            (true, 581),
+            (false, 1009),
+            (true, 1518),
+            (false, 1015),
+            (true, 1497),
+            (false, 978),
+            (true, 1504),
+            (false, 1007),
+            (true, 1510),
+            (false, 977),
+            (true, 1511),
+            (false, 1034),
+            (true, 1481),
+            (false, 967),
+            (true, 1516),
+            (false, 981),
+            (true, 566),
+            (false, 982),
+            (true, 1500),
+            (false, 1026),
+            (true, 1482),
+            (false, 1018),
+            (true, 571),
+            (false, 991),
+            (true, 1481),
+            (false, 1001),
+            (true, 1526),
+            (false, 963),
+            (true, 1524),
+            (false, 1006),
+            (true, 558),
+            (false, 1010),
+            (true, 519),
+            (false, 1022),
+            (true, 1469),
+            (false, 1003),
+            (true, 1524),
+            (false, 1005),
+            (true, 1504),
+            (false, 962),
+            (true, 1480),
+            (false, 960),
+            (true, 1468),
+            (false, 973),
+            (true, 582),
+            (false, 1013),
+            (true, 548),
+            (false, 1004),
+            (true, 1512),
+            (false, 998),
+            (true, 560),
+            (false, 990),
+            (true, 531),
+            (false, 990),
+            (true, 512),
+            (false, 1022),
+            (true, 561),
+            (false, 1004),
+            (true, 1487),
+            (false, 1030),
+            (true, 581),
+            (false, 1029),
+            (true, 1527),
+            (false, 975),
+            (true, 1500),
+        ],
+        expect: Expect::Decoded {
+            model: "WH1080",
+            id: 92,
+            channel: 0,
+            battery_ok: true,
+            reading: SensorReading::Weather(
+                Climate {
+                    temp_decicelsius: 183,
+                    humidity: Some(67),
+                },
+                Wind {
+                    avg_speed: 17,
+                    gust_speed: 30,
+                    direction: 135,
+                },
+                Rain {
+                    total: 873,
+                    rate: None,
+                },
+            ),
+        },
+    },
+    Fixture {
+        name: "truncated",
+        edges: &[
+            (true, 515),
+            (false, 996),
+            (true, 568),
+            (false, 1014),
+            (true, 584),
+            (false, 972),
+            (true, 536),
+            (false, 972),
+            (true, 517),
+            (false, 1014),
+            (true, 529),
+            (false, 999),
+            (true, 516),
+            (false, 985),
+            (true, 542),
+            (false, 998),
+            (true, 516),
+            (false, 1002),
+            (true, 1477),
+            (false, 1034),
+            (true, 564),
+            (false, 964),
+            (true, 1524),
+            (false, 973),
+            (true, 1540),
+            (false, 994),
+            (true, 543),
+            (false, 982),
+            (true, 1531),
+            (false, 978),
+            (true, 575),
+            (false, 1026),
+            (true, 549),
+            (false, 981),
+            (true, 528),
+            (false, 1039),
+            (true, 1516),
+            (false, 991),
+            (true, 1529),
+            (false, 1036),
+            (true, 1504),
+            (false, 1024),
+            (true, 1497),
+            (false, 1005),
+            (true, 1485),
+            (false, 1020),
+            (true, 1473),
+            (false, 1023),
+            (true, 531),
+            (false, 986),
+            (true, 1499),
+            (false, 1039),
+            (true, 557),
+            (false, 975),
+            (true, 523),
+            (false, 1003),
+            (true, 1503),
+            (false, 980),
+            (true, 555),
+            (false, 962),
+            (true, 518),
+            (false, 977),
+            (true, 572),
+            (false, 997),
+            (true, 1525),
+            (false, 1037),
+            (true, 529),
+            (false, 977),
+            (true, 1506),
+            (false, 964),
+            (true, 1506),
+            (false, 1038),
+            (true, 1508),
+            (false, 1021),
+            (true, 1464),
+            (false, 969),
+            (true, 574),
+            (false, 978),
+            (true, 563),
+            (false, 964),
+            (true, 1511),
+            (false, 984),
+            (true, 1518),
+            (false, 996),
+            (true, 1471),
+            (false, 969),
+            (true, 1516),
+            (false, 982),
+            (true, 1466),
+            (false, 996),
+            (true, 545),
+            (false, 986),
+            (true, 1465),
+            (false, 1001),
+            (true, 515),
+            (false, 961),
+            (true, 1504),
+            (false, 977),
+            (true, 1508),
+            (false, 1018),
+            (true, 1483),
+            (false, 962),
+            (true, 1477),
+            (false, 964),
+            (true, 527),
+            (false, 971),
+            (true, 1503),
+            (false, 975),
+            (true, 1485),
+            (false, 1027),
+            (true, 581),
+            (false, 1009),
+            (true, 1518),
+            (false, 1015),
+            (true, 1497),
+            (false, 978),
+            (true, 1504),
+            (false, 1007),
+            (true, 1510),
+            (false, 977),
+            (true, 1511),
+            (false, 1034),
+            (true, 1481),
+            (false, 967),
+            (true, 1516),
+            (false, 981),
+            (true, 566),
+            (false, 982),
+            (true, 1500),
+            (false, 1026),
+            (true, 1482),
+        ],
+        expect: Expect::Rejected,
+    },
+    Fixture {
+        name: "CRC mismatch",
+        edges: &[
+            (true, 566),
+            (false, 992),
+            (true, 512),
+            (false, 974),
+            (true, 519),
+            (false, 1018),
+            (true, 545),
+            (false, 1021),
+            (true, 549),
+            (false, 968),
+            (true, 561),
+            (false, 990),
+            (true, 553),
+            (false, 996),
+            (true, 586),
+            (false, 967),
+            (true, 542),
+            (false, 972),
+            (true, 1504),
+            (false, 1034),
+            (true, 511),
+            (false, 970),
+            (true, 1471),
+            (false, 1013),
+            (true, 1519),
+            (false, 1034),
+            (true, 528),
+            (false, 978),
+            (true, 1538),
+            (false, 968),
+            (true, 533),
+            (false, 963),
+            (true, 562),
+            (false, 995),
+            (true, 583),
+            (false, 1034),
+            (true, 1508),
+            (false, 1010),
+            (true, 1491),
+            (false, 1019),
+            (true, 1479),
+            (false, 1039),
+            (true, 1470),
+            (false, 995),
+            (true, 1483),
+            (false, 1039),
+            (true, 1473),
+            (false, 991),
+            (true, 586),
+            (false, 1040),
+            (true, 1490),
+            (false, 978),
+            (true, 549),
+            (false, 1013),
+            (true, 562),
+            (false, 1035),
+            (true, 1463),
+            (false, 982),
+            (true, 562),
+            (false, 970),
+            (true, 570),
+            (false, 1037),
+            (true, 585),
+            (false, 961),
+            (true, 1473),
+            (false, 988),
+            (true, 538),
+            (false, 1008),
+            (true, 1522),
+            (false, 1024),
+            (true, 1475),
+            (false, 964),
+            (true, 1540),
+            (false, 998),
+            (true, 1487),
+            (false, 1032),
+            (true, 1516),
+            (false, 1020),
+            (true, 522),
+            (false, 1038),
+            (true, 1500),
+            (false, 998),
+            (true, 1462),
+            (false, 987),
+            (true, 1530),
+            (false, 1025),
+            (true, 1480),
+            (false, 969),
+            (true, 1519),
+            (false, 1002),
+            (true, 587),
+            (false, 987),
+            (true, 1535),
+            (false, 985),
+            (true, 556),
+            (false, 989),
+            (true, 1500),
+            (false, 996),
+            (true, 1508),
+            (false, 995),
+            (true, 1538),
+            (false, 998),
+            (true, 1522),
+            (false, 990),
+            (true, 551),
+            (false, 981),
+            (true, 1500),
+            (false, 1019),
+            (true, 1509),
+            (false, 976),
+            (true, 586),
+            (false, 1018),
+            (true, 1480),
+            (false, 984),
+            (true, 1484),
+            (false, 1032),
+            (true, 1496),
+            (false, 1015),
+            (true, 1476),
+            (false, 1006),
+            (true, 1511),
+            (false, 1010),
+            (true, 1472),
+            (false, 980),
+            (true, 1468),
+            (false, 980),
+            (true, 520),
+            (false, 1000),
+            (true, 1473),
+            (false, 965),
+            (true, 1524),
+            (false, 1022),
+            (true, 588),
+            (false, 975),
+            (true, 1499),
+            (false, 1007),
+            (true, 1521),
+            (false, 1033),
+            (true, 1512),
+            (false, 1036),
+            (true, 515),
+            (false, 1040),
+            (true, 542),
+            (false, 1017),
+            (true, 1466),
+            (false, 980),
+            (true, 1538),
+            (false, 1001),
+            (true, 1503),
+            (false, 1032),
+            (true, 1497),
+            (false, 986),
+            (true, 1498),
+            (false, 1020),
+            (true, 585),
+            (false, 1004),
+            (true, 537),
+            (false, 972),
+            (true, 1473),
+            (false, 1017),
+            (true, 546),
+            (false, 1024),
+            (true, 512),
+            (false, 1014),
+            (true, 575),
+            (false, 999),
+            (true, 569),
+            (false, 976),
+            (true, 1506),
+            (false, 1022),
+            (true, 537),
+            (false, 1000),
+            (true, 1505),
+            (false, 984),
+            (true, 1535),
+        ],
+        expect: Expect::Rejected,
+    },
+];
+
+pub static OREGON: &[Fixture] = &[
+    Fixture {
+        name: "WGR800 wind",
+        edges: &[
+            (true, 513),
+            (false, 472),
+            (true, 502),
+            (false, 487),
+            (true, 499),
+            (false, 471),
+            (true, 476),
+            (false, 475),
+            (true, 476),
+            (false, 488),
+            (true, 514),
+            (false, 482),
+            (true, 509),
+            (false, 472),
+            (true, 470),
+            (false, 501),
+            (true, 500),
+            (false, 476),
+            (true, 510),
+            (false, 476),
+            (true, 471),
+            (false, 501),
+            (true, 480),
+            (false, 483),
+            (true, 503),
+            (false, 470),
+            (true, 467),
+            (false, 467),
+            (true, 495),
+            (false, 492),
+            (true, 468),
+            (false, 501),
+            (true, 484),
+            (false, 508),
+            (true, 482),
+            (false, 482),
+            (true, 499),
+            (false, 466),
+            (true, 496),
+            (false, 505),
+            (true, 502),
+            (false, 485),
+            (true, 510),
+            (false, 471),
+            (true, 486),
+            (false, 502),
+            (true, 476),
+            (false, 986),
+            (true, 1024),
+            (false, 936),
+            (true, 1018),
+            (false, 477),
+            (true, 487),
+            (false, 931),
+            (true, 499),
+            (false, 492),
+            (true, 499),
+            (false, 492),
+            (true, 972),
+            (false, 988),
+            (true, 469),
+            (false, 470),
+            (true, 972),
+            (false, 1015),
+            (true, 511),
+            (false, 509),
+            (true, 503),
+            (false, 472),
+            (true, 1028),
+            (false, 980),
+            (true, 479),
+            (false, 484),
+            (true, 951),
+            (false, 936),
+            (true, 468),
+            (false, 476),
+            (true, 512),
+            (false, 468),
+            (true, 483),
+            (false, 466),
+            (true, 471),
+            (false, 471),
+            (true, 932),
+            (false, 505),
+            (true, 498),
+            (false, 950),
+            (true, 1027),
+            (false, 508),
+            (true, 486),
+            (false, 514),
+            (true, 492),
+            (false, 993),
+            (true, 492),
+            (false, 493),
+            (true, 491),
+            (false, 494),
+            (true, 470),
+            (false, 476),
+            (true, 486),
+            (false, 491),
+            (true, 473),
+            (false, 477),
+            (true, 476),
+            (false, 504),
+            (true, 512),
+            (false, 470),
+            (true, 933),
+            (false, 499),
+            (true, 509),
+            (false, 1014),
+            (true, 509),
+            (false, 466),
+            (true, 485),
+            (false, 482),
+            (true, 467),
+            (false, 476),
+            (true, 507),
+            (false, 501),
+            (true, 478),
+            (false, 482),
+            (true, 512),
+            (false, 506),
+            (true, 466),
+            (false, 503),
+            (true, 1023),
+            (false, 989),
+            (true, 1029),
+            (false, 1007),
+            (true, 511),
+            (false, 481),
+            (true, 489),
+            (false, 512),
+            (true, 487),
+            (false, 499),
+            (true, 958),
+            (false, 1020),
+            (true, 473),
+            (false, 509),
+            (true, 487),
+            (false, 507),
+            (true, 502),
+            (false, 471),
+            (true, 507),
+            (false, 496),
+            (true, 934),
+            (false, 1000),
+            (true, 490),
+            (false, 484),
+            (true, 493),
+            (false, 509),
+            (true, 503),
+            (false, 500),
+            (true, 975),
+            (false, 1012),
+            (true, 500),
+            (false, 468),
+            (true, 494),
+            (false, 500),
+            (true, 480),
+            (false, 504),
+            (true, 497),
+            (false, 490),
+            (true, 1016),
+            (false, 467),
+            (true, 499),
+            (false, 934),
+            (true, 493),
+            (false, 509),
+            (true, 506),
+            (false, 510),
+            (true, 506),
+            (false, 484),
+            (true, 967),
+            (false, 1006),
+            (true, 503),
+            (false, 472),
+            (true, 481),
+            (false, 509),
+            (true, 497),
+            (false, 486),
+            (true, 474),
+            (false, 484),
+            (true, 484),
+            (false, 482),
+            (true, 497),
+            (false, 509),
+            (true, 477),
+            (false, 488),
+            (true, 511),
+            (false, 487),
+            (true, 504),
+        ],
+        expect: Expect::Decoded {
+            model: "WGR800",
+            id: 179,
+            channel: 0,
+            battery_ok: true,
+            reading: SensorReading::Wind(Wind {
+                avg_speed: 42,
+                gust_speed: 85,
+                direction: 270,
+            }),
+        },
+    },
+    Fixture {
+        name: "PCR800 rain",
+        edges: &[
+            (true, 468),
+            (false, 499),
+            (true, 500),
+            (false, 486),
+            (true, 512),
+            (false, 492),
+            (true, 490),
+            (false, 504),
+            (true, 490),
+            (false, 498),
+            (true, 490),
+            (false, 487),
+            (true, 491),
+            (false, 509),
+            (true, 486),
+            (false, 505),
+            (true, 472),
+            (false, 501),
+            (true, 488),
+            (false, 508),
+            (true, 479),
+            (false, 514),
+            (true, 491),
+            (false, 466),
+            (true, 512),
+            (false, 509),
+            (true, 507),
+            (false, 511),
+            (true, 486),
+            (false, 506),
+            (true, 504),
+            (false, 506),
+            (true, 486),
+            (false, 514),
+            (true, 484),
+            (false, 508),
+            (true, 498),
+            (false, 512),
+            (true, 492),
+            (false, 480),
+            (true, 490),
+            (false, 480),
+            (true, 499),
+            (false, 510),
+            (true, 502),
+            (false, 476),
+            (true, 482),
+            (false, 969),
+            (true, 973),
+            (false, 939),
+            (true, 956),
+            (false, 1015),
+            (true, 1026),
+            (false, 939),
+            (true, 486),
+            (false, 513),
+            (true, 1020),
+            (false, 962),
+            (true, 498),
+            (false, 513),
+            (true, 1010),
+            (false, 503),
+            (true, 513),
+            (false, 988),
+            (true, 481),
+            (false, 514),
+            (true, 499),
+            (false, 471),
+            (true, 506),
+            (false, 504),
+            (true, 497),
+            (false, 496),
+            (true, 985),
+            (false, 1016),
+            (true, 474),
+            (false, 478),
+            (true, 474),
+            (false, 495),
+            (true, 466),
+            (false, 496),
+            (true, 474),
+            (false, 471),
+            (true, 512),
+            (false, 513),
+            (true, 949),
+            (false, 466),
+            (true, 490),
+            (false, 953),
+            (true, 954),
+            (false, 1029),
+            (true, 474),
+            (false, 503),
+            (true, 509),
+            (false, 514),
+            (true, 512),
+            (false, 498),
+            (true, 474),
+            (false, 473),
+            (true, 499),
+            (false, 471),
+            (true, 504),
+            (false, 481),
+            (true, 479),
+            (false, 473),
+            (true, 969),
+            (false, 975),
+            (true, 507),
+            (false, 502),
+            (true, 999),
+            (false, 1000),
+            (true, 504),
+            (false, 496),
+            (true, 478),
+            (false, 504),
+            (true, 508),
+            (false, 475),
+            (true, 467),
+            (false, 506),
+            (true, 498),
+            (false, 508),
+            (true, 506),
+            (false, 491),
+            (true, 491),
+            (false, 503),
+            (true, 476),
+            (false, 513),
+            (true, 490),
+            (false, 505),
+            (true, 506),
+            (false, 512),
+            (true, 475),
+            (false, 484),
+            (true, 475),
+            (false, 478),
+            (true, 992),
+            (false, 1024),
+            (true, 996),
+            (false, 490),
+            (true, 496),
+            (false, 935),
+            (true, 499),
+            (false, 484),
+            (true, 493),
+            (false, 478),
+            (true, 939),
+            (false, 988),
+            (true, 466),
+            (false, 488),
+            (true, 987),
+            (false, 1015),
+            (true, 476),
+            (false, 480),
+            (true, 514),
+            (false, 510),
+            (true, 514),
+            (false, 503),
+            (true, 509),
+            (false, 494),
+            (true, 484),
+            (false, 492),
+            (true, 507),
+            (false, 466),
+            (true, 494),
+            (false, 471),
+            (true, 482),
+            (false, 487),
+            (true, 501),
+            (false, 478),
+            (true, 479),
+            (false, 469),
+            (true, 501),
+            (false, 487),
+            (true, 466),
+            (false, 510),
+            (true, 963),
+            (false, 958),
+            (true, 491),
+            (false, 506),
+            (true, 972),
+            (false, 970),
+            (true, 498),
+            (false, 508),
+            (true, 472),
+            (false, 475),
+            (true, 474),
+            (false, 505),
+            (true, 513),
+            (false, 481),
+            (true, 483),
+            (false, 500),
+            (true, 509),
+            (false, 481),
+            (true, 490),
+            (false, 493),
+            (true, 511),
+            (false, 470),
+            (true, 482),
+            (false, 499),
+            (true, 466),
+        ],
+        expect: Expect::Decoded {
+            model: "PCR800",
+            id: 97,
+            channel: 0,
+            battery_ok: true,
+            reading: SensorReading::Rain(Rain {
+                total: 313,
+                rate: Some(30),
+            }),
+        },
+    },
+    Fixture {
+        name: "PCR800 rain, inverted",
+        edges: &[
+            (true, 499),
+            (false, 514),
+            (true, 472),
+            (false, 492),
+            (true, 484),
+            (false, 490),
+            (true, 512),
+            (false, 501),
+            (true, 514),
+            (false, 508),
+            (true, 506),
+            (false, 470),
+            (true, 482),
+            (false, 485),
+            (true, 470),
+            (false, 467),
+            (true, 488),
+            (false, 487),
+            (true, 511),
+            (false, 467),
+            (true, 502),
+            (false, 472),
+            (true, 506),
+            (false, 466),
+            (true, 475),
+            (false, 467),
+            (true, 514),
+            (false, 480),
+            (true, 504),
+            (false, 468),
+            (true, 498),
+            (false, 486),
+            (true, 483),
+            (false, 503),
+            (true, 505),
+            (false, 506),
+            (true, 503),
+            (false, 492),
+            (true, 469),
+            (false, 496),
+            (true, 508),
+            (false, 505),
+            (true, 494),
+            (false, 468),
+            (true, 501),
+            (false, 471),
+            (true, 1025),
+            (false, 945),
+            (true, 950),
+            (false, 1007),
+            (true, 978),
+            (false, 990),
+            (true, 1017),
+            (false, 506),
+            (true, 497),
+            (false, 1004),
+            (true, 943),
+            (false, 480),
+            (true, 508),
+            (false, 982),
+            (true, 487),
+            (false, 488),
+            (true, 1020),
+            (false, 501),
+            (true, 509),
+            (false, 471),
+            (true, 472),
+            (false, 488),
+            (true, 471),
+            (false, 494),
+            (true, 493),
+            (false, 948),
+            (true, 973),
+            (false, 471),
+            (true, 469),
+            (false, 486),
+            (true, 466),
+            (false, 483),
+            (true, 489),
+            (false, 489),
+            (true, 471),
+            (false, 472),
+            (true, 511),
+            (false, 1018),
+            (true, 507),
+            (false, 468),
+            (true, 1029),
+            (false, 938),
+            (true, 949),
+            (false, 480),
+            (true, 485),
+            (false, 496),
+            (true, 503),
+            (false, 489),
+            (true, 510),
+            (false, 476),
+            (true, 472),
+            (false, 473),
+            (true, 480),
+            (false, 470),
+            (true, 466),
+            (false, 494),
+            (true, 512),
+            (false, 999),
+            (true, 998),
+            (false, 471),
+            (true, 511),
+            (false, 1027),
+            (true, 965),
+            (false, 513),
+            (true, 495),
+            (false, 496),
+            (true, 473),
+            (false, 470),
+            (true, 476),
+            (false, 498),
+            (true, 494),
+            (false, 506),
+            (true, 487),
+            (false, 503),
+            (true, 512),
+            (false, 496),
+            (true, 486),
+            (false, 468),
+            (true, 509),
+            (false, 477),
+            (true, 467),
+            (false, 489),
+            (true, 480),
+            (false, 486),
+            (true, 504),
+            (false, 513),
+            (true, 508),
+            (false, 946),
+            (true, 1003),
+            (false, 976),
+            (true, 484),
+            (false, 474),
+            (true, 999),
+            (false, 503),
+            (true, 490),
+            (false, 500),
+            (true, 487),
+            (false, 1028),
+            (true, 980),
+            (false, 508),
+            (true, 487),
+            (false, 995),
+            (true, 988),
+            (false, 480),
+            (true, 479),
+            (false, 505),
+            (true, 481),
+            (false, 486),
+            (true, 512),
+            (false, 471),
+            (true, 485),
+            (false, 468),
+            (true, 472),
+            (false, 502),
+            (true, 491),
+            (false, 475),
+            (true, 473),
+            (false, 479),
+            (true, 512),
+            (false, 481),
+            (true, 473),
+            (false, 476),
+            (true, 494),
+            (false, 488),
+            (true, 481),
+            (false, 497),
+            (true, 476),
+            (false, 1024),
+            (true, 1011),
+            (false, 494),
+            (true, 500),
+            (false, 1023),
+            (true, 964),
+            (false, 500),
+            (true, 485),
+            (false, 484),
+            (true, 466),
+            (false, 472),
+            (true, 474),
+            (false, 496),
+            (true, 501),
+            (false, 485),
+            (true, 481),
+            (false, 479),
+            (true, 507),
+            (false, 513),
+            (true, 505),
+            (false, 474),
+            (true, 507),
+            (false, 489),
+            (true, 477),
+        ],
+        expect: Expect::Decoded {
+            model: "PCR800",
+            id: 97,
+            channel: 0,
+            battery_ok: true,
+            reading: SensorReading::Rain(Rain {
+                total: 313,
+                rate: Some(30),
+            }),
+        },
+    },
+    Fixture {
+        name: "truncated",
+        edges: &[
+            (true, 513),
+            (false, 472),
+            (true, 502),
+            (false, 487),
+            (true, 499),
+            (false, 471),
+            (true, 476),
+            (false, 475),
+            (true, 476),
+            (false, 488),
+            (true, 514),
+            (false, 482),
+            (true, 509),
+            (false, 472),
+            (true, 470),
+            (false, 501),
+            (true, 500),
+            (false, 476),
+            (true, 510),
+            (false, 476),
+            (true, 471),
+            (false, 501),
+            (true, 480),
+            (false, 483),
+            (true, 503),
+            (false, 470),
+            (true, 467),
+            (false, 467),
+            (true, 495),
+            (false, 492),
+            (true, 468),
+            (false, 501),
+            (true, 484),
+            (false, 508),
+            (true, 482),
+            (false, 482),
+            (true, 499),
+            (false, 466),
+            (true, 496),
+            (false, 505),
+            (true, 502),
+            (false, 485),
+            (true, 510),
+            (false, 471),
+            (true, 486),
+            (false, 502),
+            (true, 476),
+            (false, 986),
+            (true, 1024),
+            (false, 936),
+            (true, 1018),
+            (false, 477),
+            (true, 487),
+            (false, 931),
+            (true, 499),
+            (false, 492),
+            (true, 499),
+            (false, 492),
+            (true, 972),
+            (false, 988),
+            (true, 469),
+            (false, 470),
+            (true, 972),
+            (false, 1015),
+            (true, 511),
+            (false, 509),
+            (true, 503),
+            (false, 472),
+            (true, 1028),
+            (false, 980),
+            (true, 479),
+            (false, 484),
+            (true, 951),
+            (false, 936),
+            (true, 468),
+            (false, 476),
+            (true, 512),
+            (false, 468),
+            (true, 483),
+            (false, 466),
+            (true, 471),
+            (false, 471),
+            (true, 932),
+            (false, 505),
+            (true, 498),
+            (false, 950),
+            (true, 1027),
+            (false, 508),
+            (true, 486),
+            (false, 514),
+            (true, 492),
+            (false, 993),
+            (true, 492),
+            (false, 493),
+            (true, 491),
+            (false, 494),
+            (true, 470),
+            (false, 476),
+            (true, 486),
+            (false, 491),
+            (true, 473),
+            (false, 477),
+            (true, 476),
+            (false, 504),
+            (true, 512),
+            (false, 470),
+            (true, 933),
+            (false, 499),
+            (true, 509),
+            (false, 1014),
+            (true, 509),
+            (false, 466),
+            (true, 485),
+        ],
+        expect: Expect::Rejected,
+    },
+    Fixture {
+        name: "checksum mismatch",
+        edges: &[
+            (true, 511),
+            (false, 479),
+            (true, 489),
+            (false, 497),
+            (true, 509),
+            (false, 504),
+            (true, 471),
+            (false, 466),
+            (true, 495),
+            (false, 501),
+            (true, 492),
+            (false, 467),
+            (true, 498),
+            (false, 502),
+            (true, 468),
+            (false, 473),
+            (true, 472),
+            (false, 504),
+            (true, 474),
+            (false, 481),
+            (true, 505),
+            (false, 475),
+            (true, 491),
+            (false, 477),
+            (true, 466),
+            (false, 488),
+            (true, 472),
+            (false, 487),
+            (true, 479),
+            (false, 497),
+            (true, 510),
+            (false, 505),
+            (true, 503),
+            (false, 487),
+            (true, 490),
+            (false, 466),
+            (true, 499),
+            (false, 502),
+            (true, 467),
+            (false, 477),
+            (true, 491),
+            (false, 477),
+            (true, 508),
+            (false, 509),
+            (true, 495),
+            (false, 483),
+            (true, 477),
+            (false, 937),
+            (true, 945),
+            (false, 932),
+            (true, 1019),
+            (false, 474),
+            (true, 466),
+            (false, 934),
+            (true, 504),
+            (false, 493),
+            (true, 501),
+            (false, 487),
+            (true, 970),
+            (false, 935),
+            (true, 512),
+            (false, 499),
+            (true, 987),
+            (false, 985),
+            (true, 490),
+            (false, 512),
+            (true, 510),
+            (false, 476),
+            (true, 982),
+            (false, 1001),
+            (true, 509),
+            (false, 496),
+            (true, 1028),
+            (false, 940),
+            (true, 481),
+            (false, 501),
+            (true, 470),
+            (false, 488),
+            (true, 505),
+            (false, 489),
+            (true, 507),
+            (false, 495),
+            (true, 1023),
+            (false, 470),
+            (true, 466),
+            (false, 994),
+            (true, 980),
+            (false, 503),
+            (true, 490),
+            (false, 508),
+            (true, 501),
+            (false, 945),
+            (true, 470),
+            (false, 510),
+            (true, 502),
+            (false, 499),
+            (true, 467),
+            (false, 490),
+            (true, 480),
+            (false, 476),
+            (true, 478),
+            (false, 468),
+            (true, 503),
+            (false, 478),
+            (true, 1018),
+            (false, 481),
+            (true, 491),
+            (false, 488),
+            (true, 487),
+            (false, 1016),
+            (true, 471),
+            (false, 476),
+            (true, 510),
+            (false, 502),
+            (true, 503),
+            (false, 494),
+            (true, 499),
+            (false, 490),
+            (true, 499),
+            (false, 504),
+            (true, 481),
+            (false, 500),
+            (true, 508),
+            (false, 472),
+            (true, 1014),
+            (false, 958),
+            (true, 1001),
+            (false, 999),
+            (true, 470),
+            (false, 480),
+            (true, 510),
+            (false, 498),
+            (true, 476),
+            (false, 473),
+            (true, 1026),
+            (false, 946),
+            (true, 488),
+            (false, 474),
+            (true, 472),
+            (false, 514),
+            (true, 485),
+            (false, 469),
+            (true, 505),
+            (false, 466),
+            (true, 1027),
+            (false, 1029),
+            (true, 493),
+            (false, 502),
+            (true, 482),
+            (false, 482),
+            (true, 510),
+            (false, 468),
+            (true, 961),
+            (false, 954),
+            (true, 500),
+            (false, 511),
+            (true, 491),
+            (false, 513),
+            (true, 513),
+            (false, 504),
+            (true, 507),
+            (false, 479),
+            (true, 963),
+            (false, 473),
+            (true, 483),
+            (false, 935),
+            (true, 495),
+            (false, 473),
+            (true, 491),
+            (false, 472),
+            (true, 503),
+            (false, 472),
+            (true, 960),
+            (false, 936),
+            (true, 495),
+            (false, 480),
+            (true, 470),
+            (false, 497),
+            (true, 486),
+            (false, 513),
+            (true, 501),
+            (false, 501),
+            (true, 481),
+            (false, 495),
+            (true, 504),
+            (false, 491),
+            (true, 512),
+            (false, 497),
+            (true, 474),
+            (false, 505),
+            (true, 482),
+        ],
+        expect: Expect::Rejected,
+    },
+];
+
+pub static HONEYWELL: &[Fixture] = &[
+    Fixture {
+        name: "open",
+        edges: &[
+            (true, 149),
+            (false, 157),
+            (true, 153),
+            (false, 158),
+            (true, 149),
+            (false, 153),
+            (true, 141),
+            (false, 146),
+            (true, 141),
+            (false, 140),
+            (true, 149),
+            (false, 158),
+            (true, 150),
+            (false, 160),
+            (true, 153),
+            (false, 159),
+            (true, 148),
+            (false, 147),
+            (true, 160),
+            (false, 157),
+            (true, 158),
+            (false, 152),
+            (true, 153),
+            (false, 154),
+            (true, 155),
+            (false, 156),
+            (true, 160),
+            (false, 157),
+            (true, 153),
+            (false, 299),
+            (true, 291),
+            (false, 299),
+            (true, 146),
+            (false, 142),
+            (true, 140),
+            (false, 160),
+            (true, 155),
+            (false, 159),
+            (true, 152),
+            (false, 140),
+            (true, 299),
+            (false, 292),
+            (true, 286),
+            (false, 142),
+            (true, 148),
+            (false, 307),
+            (true, 311),
+            (false, 296),
+            (true, 299),
+            (false, 291),
+            (true, 291),
+            (false, 143),
+            (true, 160),
+            (false, 300),
+            (true, 294),
+            (false, 289),
+            (true, 159),
+            (false, 146),
+            (true, 142),
+            (false, 141),
+            (true, 141),
+            (false, 143),
+            (true, 296),
+            (false, 146),
+            (true, 160),
+            (false, 285),
+            (true, 155),
+            (false, 160),
+            (true, 152),
+            (false, 140),
+            (true, 155),
+            (false, 151),
+            (true, 146),
+            (false, 147),
+            (true, 158),
+            (false, 140),
+            (true, 160),
+            (false, 149),
+            (true, 285),
+            (false, 153),
+            (true, 156),
+            (false, 149),
+            (true, 149),
+            (false, 293),
+            (true, 146),
+            (false, 157),
+            (true, 141),
+            (false, 142),
+            (true, 314),
+            (false, 151),
+            (true, 144),
+            (false, 148),
+            (true, 155),
+            (false, 306),
+            (true, 151),
+            (false, 160),
+            (true, 315),
+            (false, 290),
+            (true, 158),
+            (false, 148),
+            (true, 302),
+            (false, 298),
+            (true, 140),
+        ],
+        expect: Expect::Decoded {
+            model: "Honeywell-Contact",
+            id: 185761,
+            channel: 0,
+            battery_ok: true,
+            reading: SensorReading::Contact(Contact {
+                open: true,
+                tamper: false,
+                heartbeat: false,
+            }),
+        },
+    },
+    Fixture {
+        name: "2GIG heartbeat, low battery",
+        edges: &[
+            (true, 146),
+            (false, 153),
+            (true, 160),
+            (false, 145),
+            (true, 148),
+            (false, 152),
+            (true, 145),
+            (false, 158),
+            (true, 153),
+            (false, 151),
+            (true, 144),
+            (false, 141),
+            (true, 157),
+            (false, 153),
+            (true, 145),
+            (false, 141),
+            (true, 153),
+            (false, 146),
+            (true, 143),
+            (false, 143),
+            (true, 153),
+            (false, 152),
+            (true, 141),
+            (false, 142),
+            (true, 159),
+            (false, 142),
+            (true, 140),
+            (false, 156),
+            (true, 142),
+            (false, 305),
+            (true, 295),
+            (false, 292),
+            (true, 300),
+            (false, 294),
+            (true, 145),
+            (false, 159),
+            (true, 155),
+            (false, 157),
+            (true, 147),
+            (false, 156),
+            (true, 156),
+            (false, 155),
+            (true, 149),
+            (false, 158),
+            (true, 140),
+            (false, 147),
+            (true, 140),
+            (false, 151),
+            (true, 301),
+            (false, 151),
+            (true, 142),
+            (false, 157),
+            (true, 145),
+            (false, 144),
+            (true, 156),
+            (false, 143),
+            (true, 145),
+            (false, 293),
+            (true, 143),
+            (false, 149),
+            (true, 143),
+            (false, 147),
+            (true, 147),
+            (false, 150),
+            (true, 148),
+            (false, 145),
+            (true, 152),
+            (false, 155),
+            (true, 158),
+            (false, 143),
+            (true, 149),
+            (false, 155),
+            (true, 140),
+            (false, 160),
+            (true, 152),
+            (false, 159),
+            (true, 160),
+            (false, 160),
+            (true, 155),
+            (false, 148),
+            (true, 296),
+            (false, 153),
+            (true, 153),
+            (false, 313),
+            (true, 158),
+            (false, 148),
+            (true, 140),
+            (false, 153),
+            (true, 154),
+            (false, 148),
+            (true, 157),
+            (false, 159),
+            (true, 295),
+            (false, 151),
+            (true, 159),
+            (false, 146),
+            (true, 160),
+            (false, 307),
+            (true, 312),
+            (false, 153),
+            (true, 150),
+            (false, 292),
+            (true, 156),
+            (false, 154),
+            (true, 297),
+            (false, 298),
+            (true, 146),
+            (false, 152),
+            (true, 144),
+            (false, 154),
+            (true, 156),
+            (false, 157),
+            (true, 146),
+        ],
+        expect: Expect::Decoded {
+            model: "Honeywell-Contact",
+            id: 7936,
+            channel: 0,
+            battery_ok: false,
+            reading: SensorReading::Contact(Contact {
+                open: false,
+                tamper: false,
+                heartbeat: true,
+            }),
+        },
+    },
+    Fixture {
+        name: "truncated",
+        edges: &[
+            (true, 149),
+            (false, 157),
+            (true, 153),
+            (false, 158),
+            (true, 149),
+            (false, 153),
+            (true, 141),
+            (false, 146),
+            (true, 141),
+            (false, 140),
+            (true, 149),
+            (false, 158),
+            (true, 150),
+            (false, 160),
+            (true, 153),
+            (false, 159),
+            (true, 148),
+            (false, 147),
+            (true, 160),
+            (false, 157),
+            (true, 158),
+            (false, 152),
+            (true, 153),
+            (false, 154),
+            (true, 155),
+            (false, 156),
+            (true, 160),
+            (false, 157),
+            (true, 153),
+            (false, 299),
+            (true, 291),
+            (false, 299),
+            (true, 146),
+            (false, 142),
+            (true, 140),
+            (false, 160),
+            (true, 155),
+            (false, 159),
+            (true, 152),
+            (false, 140),
+            (true, 299),
+            (false, 292),
+            (true, 286),
+            (false, 142),
+            (true, 148),
+            (false, 307),
+            (true, 311),
+            (false, 296),
+            (true, 299),
+            (false, 291),
+            (true, 291),
+            (false, 143),
+            (true, 160),
+            (false, 300),
+            (true, 294),
+            (false, 289),
+            (true, 159),
+            (false, 146),
+            (true, 142),
+            (false, 141),
+            (true, 141),
+            (false, 143),
+            (true, 296),
+            (false, 146),
+            (true, 160),
+            (false, 285),
+            (true, 155),
+            (false, 160),
+            (true, 152),
+            (false, 140),
+            (true, 155),
+            (false, 151),
+            (true, 146),
+            (false, 147),
+            (true, 158),
+            (false, 140),
+            (true, 160),
+        ],
+        expect: Expect::Rejected,
+    },
+    Fixture {
+        name: "CRC mismatch",
+        edges: &[
+            (true, 144),
+            (false, 144),
+            (true, 160),
+            (false, 155),
+            (true, 142),
+            (false, 145),
+            (true, 148),
+            (false, 156),
+            (true, 143),
+            (false, 141),
+            (true, 142),
+            (false, 152),
+            (true, 157),
+            (false, 145),
+            (true, 153),
+            (false, 147),
+            (true, 152),
+            (false, 155),
+            (true, 147),
+            (false, 144),
+            (true, 141),
+            (false, 145),
+            (true, 154),
+            (false, 146),
+            (true, 141),
+            (false, 150),
+            (true, 152),
+            (false, 147),
+            (true, 150),
+            (false, 306),
+            (true, 291),
+            (false, 315),
+            (true, 155),
+            (false, 146),
+            (true, 151),
+            (false, 151),
+            (true, 140),
+            (false, 145),
+            (true, 152),
+            (false, 149),
+            (true, 293),
+            (false, 310),
+            (true, 308),
+            (false, 152),
+            (true, 160),
+            (false, 299),
+            (true, 293),
+            (false, 140),
+            (true, 155),
+            (false, 146),
+            (true, 154),
+            (false, 309),
+            (true, 302),
+            (false, 151),
+            (true, 147),
+            (false, 296),
+            (true, 303),
+            (false, 291),
+            (true, 159),
+            (false, 153),
+            (true, 145),
+            (false, 160),
+            (true, 150),
+            (false, 156),
+            (true, 292),
+            (false, 159),
+            (true, 141),
+            (false, 299),
+            (true, 148),
+            (false, 155),
+            (true, 143),
+            (false, 152),
+            (true, 157),
+            (false, 143),
+            (true, 144),
+            (false, 156),
+            (true, 145),
+            (false, 146),
+            (true, 149),
+            (false, 153),
+            (true, 314),
+            (false, 142),
+            (true, 150),
+            (false, 153),
+            (true, 150),
+            (false, 290),
+            (true, 159),
+            (false, 153),
+            (true, 143),
+            (false, 158),
+            (true, 292),
+            (false, 157),
+            (true, 159),
+            (false, 144),
+            (true, 141),
+            (false, 307),
+            (true, 141),
+            (false, 152),
+            (true, 285),
+            (false, 311),
+            (true, 151),
+            (false, 146),
+            (true, 288),
+            (false, 299),
+            (true, 140),
+        ],
+        expect: Expect::Rejected,
+    },
+];
+
+pub static EV1527: &[Fixture] = &[
+    Fixture {
+        name: "motion",
+        edges: &[
+            (true, 363),
+            (false, 1052),
+            (true, 1087),
+            (false, 356),
+            (true, 346),
+            (false, 1060),
+            (true, 1018),
+            (false, 341),
+            (true, 1066),
+            (false, 360),
+            (true, 358),
+            (false, 1026),
+            (true, 1080),
+            (false, 341),
+            (true, 338),
+            (false, 1015),
+            (true, 365),
+            (false, 1051),
+            (true, 338),
+            (false, 1047),
+            (true, 1019),
+            (false, 343),
+            (true, 1051),
+            (false, 356),
+            (true, 1040),
+            (false, 345),
+            (true, 1021),
+            (false, 352),
+            (true, 346),
+            (false, 1012),
+            (true, 336),
+            (false, 1078),
+            (true, 344),
+            (false, 1056),
+            (true, 339),
+            (false, 1027),
+            (true, 334),
+            (false, 1088),
+            (true, 1006),
+            (false, 359),
+            (true, 1051),
+            (false, 339),
+            (true, 340),
+            (false, 1065),
+            (true, 345),
+            (false, 1045),
+            (true, 367),
+            (false, 1075),
+            (true, 366),
+        ],
+        expect: Expect::Decoded {
+            model: "EV1527-PIR",
+            id: 369601,
+            channel: 0,
+            battery_ok: true,
+            reading: SensorReading::Motion(8),
+        },
+    },
+    Fixture {
+        name: "motion, slow oscillator",
+        edges: &[
+            (true, 496),
+            (false, 1471),
+            (true, 481),
+            (false, 1451),
+            (true, 488),
+            (false, 1410),
+            (true, 470),
+            (false, 1407),
+            (true, 1408),
+            (false, 469),
+            (true, 474),
+            (false, 1451),
+            (true, 1422),
+            (false, 475),
+            (true, 1447),
+            (false, 460),
+            (true, 474),
+            (false, 1372),
+            (true, 459),
+            (false, 1470),
+            (true, 1421),
+            (false, 460),
+            (true, 455),
+            (false, 1378),
+            (true, 1410),
+            (false, 478),
+            (true, 1388),
+            (false, 469),
+            (true, 1429),
+            (false, 495),
+            (true, 488),
+            (false, 1486),
+            (true, 493),
+            (false, 1443),
+            (true, 1401),
+            (false, 490),
+            (true, 1400),
+            (false, 473),
+            (true, 1372),
+            (false, 470),
+            (true, 463),
+            (false, 1472),
+            (true, 483),
+            (false, 1477),
+            (true, 1407),
+            (false, 455),
+            (true, 1437),
+            (false, 473),
+            (true, 476),
+        ],
+        expect: Expect::Decoded {
+            model: "EV1527-PIR",
+            id: 45799,
+            channel: 0,
+            battery_ok: true,
+            reading: SensorReading::Motion(3),
+        },
+    },
+    Fixture {
+        name: "truncated",
+        edges: &[
+            (true, 363),
+            (false, 1052),
+            (true, 1087),
+            (false, 356),
+            (true, 346),
+            (false, 1060),
+            (true, 1018),
+            (false, 341),
+            (true, 1066),
+            (false, 360),
+            (true, 358),
+            (false, 1026),
+            (true, 1080),
+            (false, 341),
+            (true, 338),
+            (false, 1015),
+            (true, 365),
+            (false, 1051),
+            (true, 338),
+            (false, 1047),
+            (true, 1019),
+            (false, 343),
+            (true, 1051),
+            (false, 356),
+            (true, 1040),
+            (false, 345),
+            (true, 1021),
+            (false, 352),
+            (true, 346),
+            (false, 1012),
+            (true, 336),
+            (false, 1078),
+            (true, 344),
+            (false, 1056),
+            (true, 339),
+        ],
+        expect: Expect::Rejected,
+    },
+    Fixture {
+        name: "wrong symbol ratio",
+        edges: &[
+            (true, 334),
+            (false, 1063),
+            (true, 1033),
+            (false, 365),
+            (true, 333),
+            (false, 1073),
+            (true, 1101),
+            (false, 342),
+            (true, 1047),
+            (false, 361),
+            (true, 353),
+            (false, 1079),
+            (true, 1065),
+            (false, 346),
+            (true, 702),
+            (false, 694),
+            (true, 349),
+            (false, 1047),
+            (true, 346),
+            (false, 1034),
+            (true, 1089),
+            (false, 357),
+            (true, 1004),
+            (false, 340),
+            (true, 1037),
+            (false, 336),
+            (true, 1041),
+            (false, 348),
+            (true, 363),
+            (false, 1072),
+            (true, 335),
+            (false, 1014),
+            (true, 367),
+            (false, 1036),
+            (true, 356),
+            (false, 1042),
+            (true, 359),
+            (false, 1063),
+            (true, 1056),
+            (false, 357),
+            (true, 1016),
+            (false, 361),
+            (true, 360),
+            (false, 1021),
+            (true, 364),
+            (false, 1066),
+            (true, 334),
+            (false, 1061),
+            (true, 351),
+        ],
+        expect: Expect::Rejected,
+    },
+];
+
+pub static NOISE: &[Fixture] = &[
+    Fixture {
+        name: "short burst",
+        edges: &[
+            (true, 391),
+            (false, 2831),
+            (true, 101),
+            (false, 2601),
+            (true, 1108),
+            (false, 488),
+            (true, 1519),
+            (false, 1272),
+            (true, 2092),
+        ],
+        expect: Expect::Rejected,
+    },
+    Fixture {
+        name: "long burst",
+        edges: &[
+            (true, 1528),
+            (false, 965),
+            (true, 2328),
+            (false, 2264),
+            (true, 503),
+            (false, 2225),
+            (true, 1645),
+            (false, 820),
+            (true, 2399),
+            (false, 552),
+            (true, 1490),
+            (false, 1895),
+            (true, 544),
+            (false, 876),
+            (true, 2214),
+            (false, 1463),
+            (true, 2375),
+            (false, 144),
+            (true, 134),
+            (false, 1243),
+            (true, 1014),
+            (false, 2696),
+            (true, 268),
+            (false, 691),
+            (true, 2091),
+            (false, 1470),
+            (true, 621),
+            (false, 656),
+            (true, 2293),
+            (false, 2082),
+            (true, 2289),
+            (false, 376),
+            (true, 1845),
+            (false, 2796),
+            (true, 595),
+            (false, 2034),
+            (true, 200),
+            (false, 1787),
+            (true, 252),
+            (false, 2026),
+            (true, 229),
+            (false, 1676),
+            (true, 1891),
+            (false, 2259),
+            (true, 1393),
+            (false, 1026),
+            (true, 551),
+            (false, 1881),
+            (true, 449),
+            (false, 2668),
+            (true, 2824),
+            (false, 2761),
+            (true, 1442),
+            (false, 1000),
+            (true, 1831),
+            (false, 2342),
+            (true, 1090),
+            (false, 1019),
+            (true, 967),
+            (false, 2302),
+            (true, 1122),
+            (false, 1529),
+            (true, 884),
+            (false, 836),
+            (true, 2126),
+            (false, 2085),
+            (true, 912),
+            (false, 2515),
+            (true, 1779),
+            (false, 209),
+            (true, 2383),
+            (false, 2442),
+            (true, 1307),
+            (false, 2028),
+            (true, 1788),
+        ],
+        expect: Expect::Rejected,
+    },
+    Fixture {
+        name: "full buffer",
+        edges: &[
+            (true, 852),
+            (false, 1681),
+            (true, 2301),
+            (false, 334),
+            (true, 1376),
+            (false, 1297),
+            (true, 988),
+            (false, 2339),
+            (true, 2834),
+            (false, 2811),
+            (true, 1107),
+            (false, 2772),
+            (true, 522),
+            (false, 750),
+            (true, 1066),
+            (false, 482),
+            (true, 1930),
+            (false, 2526),
+            (true, 1103),
+            (false, 441),
+            (true, 1660),
+            (false, 1819),
+            (true, 1330),
+            (false, 2077),
+            (true, 2539),
+            (false, 2299),
+            (true, 2662),
+            (false, 1954),
+            (true, 2289),
+            (false, 1525),
+            (true, 850),
+            (false, 1583),
+            (true, 566),
+            (false, 583),
+            (true, 1814),
+            (false, 660),
+            (true, 1162),
+            (false, 2530),
+            (true, 2539),
+            (false, 1205),
+            (true, 1889),
+            (false, 1797),
+            (true, 948),
+            (false, 825),
+            (true, 2504),
+            (false, 1145),
+            (true, 419),
+            (false, 1401),
+            (true, 783),
+            (false, 489),
+            (true, 225),
+            (false, 2186),
+            (true, 375),
+            (false, 2752),
+            (true, 2673),
+            (false, 1921),
+            (true, 1396),
+            (false, 287),
+            (true, 269),
+            (false, 1561),
+            (true, 772),
+            (false, 1980),
+            (true, 2661),
+            (false, 804),
+            (true, 2103),
+            (false, 2677),
+            (true, 923),
+            (false, 2787),
+            (true, 1669),
+            (false, 345),
+            (true, 613),
+            (false, 1900),
+            (true, 516),
+            (false, 2694),
+            (true, 290),
+            (false, 1738),
+            (true, 1481),
+            (false, 936),
+            (true, 1263),
+            (false, 698),
+            (true, 1650),
+            (false, 2142),
+            (true, 1371),
+            (false, 1368),
+            (true, 2183),
+            (false, 329),
+            (true, 729),
+            (false, 2871),
+            (true, 1445),
+            (false, 1599),
+            (true, 2526),
+            (false, 1791),
+            (true, 1349),
+            (false, 1758),
+            (true, 1952),
+            (false, 670),
+            (true, 2350),
+            (false, 2105),
+            (true, 454),
+            (false, 2875),
+            (true, 698),
+            (false, 1703),
+            (true, 2523),
+            (false, 2737),
+            (true, 2068),
+            (false, 2465),
+            (true, 2049),
+            (false, 2671),
+            (true, 1359),
+            (false, 2351),
+            (true, 605),
+            (false, 943),
+            (true, 759),
+            (false, 2893),
+            (true, 1169),
+            (false, 288),
+            (true, 2166),
+            (false, 819),
+            (true, 557),
+            (false, 2799),
+            (true, 909),
+            (false, 2202),
+            (true, 552),
+            (false, 1930),
+            (true, 388),
+            (false, 795),
+            (true, 2334),
+            (false, 566),
+            (true, 936),
+            (false, 2179),
+            (true, 1917),
+            (false, 949),
+            (true, 2202),
+            (false, 867),
+            (true, 2747),
+            (false, 2082),
+            (true, 2347),
+            (false, 131),
+            (true, 883),
+            (false, 202),
+            (true, 693),
+            (false, 2481),
+            (true, 103),
+            (false, 580),
+            (true, 1402),
+            (false, 647),
+            (true, 1034),
+            (false, 2800),
+            (true, 2882),
+            (false, 520),
+            (true, 161),
+            (false, 414),
+            (true, 2893),
+            (false, 1660),
+            (true, 173),
+            (false, 1804),
+            (true, 390),
+            (false, 1982),
+            (true, 1988),
+            (false, 371),
+            (true, 1231),
+            (false, 1295),
+            (true, 1558),
+            (false, 1879),
+            (true, 2392),
+            (false, 1774),
+            (true, 1291),
+            (false, 643),
+            (true, 1935),
+            (false, 551),
+            (true, 499),
+            (false, 1876),
+            (true, 1910),
+            (false, 2406),
+            (true, 2386),
+            (false, 2724),
+            (true, 783),
+            (false, 428),
+            (true, 2586),
+            (false, 2405),
+            (true, 2480),
+            (false, 249),
+            (true, 2829),
+            (false, 1261),
+            (true, 2020),
+            (false, 2746),
+            (true, 2431),
+            (false, 2323),
+            (true, 236),
+            (false, 1445),
+            (true, 229),
+            (false, 1415),
+            (true, 401),
+            (false, 1164),
+            (true, 1188),
+            (false, 2570),
+            (true, 2577),
+            (false, 2017),
+            (true, 2434),
+            (false, 2006),
+            (true, 624),
+            (false, 1906),
+            (true, 1109),
+            (false, 1485),
+            (true, 668),
+            (false, 1462),
+            (true, 2450),
+            (false, 2265),
+            (true, 108),
+            (false, 2421),
+            (true, 2808),
+            (false, 1596),
+            (true, 679),
+            (false, 1191),
+            (true, 1131),
+            (false, 2392),
+            (true, 619),
+            (false, 2207),
+            (true, 2876),
+            (false, 189),
+            (true, 1356),
+            (false, 153),
+            (true, 1389),
+            (false, 734),
+            (true, 1165),
+            (false, 2427),
+            (true, 1803),
+            (false, 2277),
+            (true, 1234),
+            (false, 2282),
+            (true, 822),
+            (false, 1856),
+            (true, 2881),
+            (false, 712),
+            (true, 1600),
+            (false, 378),
+            (true, 1461),
+            (false, 2038),
+            (true, 1913),
+            (false, 2617),
+            (true, 466),
+            (false, 566),
+            (true, 2725),
+            (false, 911),
+            (true, 2581),
+            (false, 953),
+            (true, 2231),
+            (false, 2624),
+            (true, 577),
+        ],
+        expect: Expect::Rejected,
+    },
+];
+
+pub static GOVEE: &[Fixture] = &[
+    Fixture {
+        name: "leak",
+        edges: &[
+            (true, 937),
+            (false, 418),
+            (true, 933),
+            (false, 453),
+            (true, 956),
+            (false, 417),
+            (true, 437),
+            (false, 954),
+            (true, 426),
+            (false, 924),
+            (true, 415),
+            (false, 914),
+            (true, 962),
+            (false, 444),
+            (true, 444),
+            (false, 963),
+            (true, 945),
+            (false, 459),
+            (true, 929),
+            (false, 424),
+            (true, 417),
+            (false, 919),
+            (true, 937),
+            (false, 434),
+            (true, 428),
+            (false, 970),
+            (true, 435),
+            (false, 919),
+            (true, 979),
+            (false, 429),
+            (true, 964),
+            (false, 427),
+            (true, 418),
+            (false, 915),
+            (true, 457),
+            (false, 914),
+            (true, 447),
+            (false, 969),
+            (true, 416),
+            (false, 927),
+            (true, 443),
+            (false, 938),
+            (true, 932),
+            (false, 455),
+            (true, 458),
+            (false, 938),
+            (true, 946),
+            (false, 415),
+            (true, 465),
+            (false, 979),
+            (true, 427),
+            (false, 936),
+            (true, 454),
+            (false, 918),
+            (true, 449),
+            (false, 970),
+            (true, 447),
+            (false, 932),
+            (true, 935),
+            (false, 416),
+            (true, 428),
+            (false, 939),
+            (true, 928),
+            (false, 429),
+            (true, 902),
+            (false, 425),
+            (true, 921),
+            (false, 457),
+            (true, 433),
+            (false, 956),
+            (true, 427),
+            (false, 957),
+            (true, 435),
+            (false, 970),
+            (true, 980),
+            (false, 415),
+            (true, 441),
+            (false, 956),
+            (true, 456),
+            (false, 919),
+            (true, 435),
+            (false, 908),
+            (true, 952),
+            (false, 459),
+            (true, 913),
+            (false, 447),
+            (true, 452),
+            (false, 921),
+            (true, 976),
+            (false, 451),
+            (true, 913),
+            (false, 448),
+            (true, 946),
+            (false, 427),
+            (true, 458),
+            (false, 913),
+            (true, 440),
+        ],
+        expect: Expect::Decoded {
+            model: "Govee-Water",
+            id: 7468,
+            channel: 0,
+            battery_ok: true,
+            reading: SensorReading::Leak(Leak { water: true }),
+        },
+    },
+    Fixture {
+        name: "button",
+        edges: &[
+            (true, 931),
+            (false, 442),
+            (true, 974),
+            (false, 461),
+            (true, 909),
+            (false, 418),
+            (true, 429),
+            (false, 914),
+            (true, 429),
+            (false, 980),
+            (true, 452),
+            (false, 914),
+            (true, 940),
+            (false, 457),
+            (true, 436),
+            (false, 934),
+            (true, 958),
+            (false, 438),
+            (true, 961),
+            (false, 462),
+            (true, 428),
+            (false, 913),
+            (true, 980),
+            (false, 460),
+            (true, 455),
+            (false, 939),
+            (true, 423),
+            (false, 925),
+            (true, 924),
+            (false, 422),
+            (true, 976),
+            (false, 427),
+            (true, 422),
+            (false, 965),
+            (true, 949),
+            (false, 447),
+            (true, 437),
+            (false, 931),
+            (true, 976),
+            (false, 425),
+            (true, 435),
+            (false, 934),
+            (true, 452),
+            (false, 937),
+            (true, 459),
+            (false, 919),
+            (true, 438),
+            (false, 933),
+            (true, 458),
+            (false, 975),
+            (true, 925),
+            (false, 438),
+            (true, 458),
+            (false, 942),
+            (true, 953),
+            (false, 426),
+            (true, 437),
+            (false, 934),
+            (true, 455),
+            (false, 955),
+            (true, 455),
+            (false, 968),
+            (true, 427),
+            (false, 951),
+            (true, 936),
+            (false, 420),
+            (true, 448),
+            (false, 946),
+            (true, 443),
+            (false, 953),
+            (true, 946),
+            (false, 434),
+            (true, 420),
+            (false, 946),
+            (true, 444),
+            (false, 945),
+            (true, 438),
+            (false, 927),
+            (true, 914),
+            (false, 448),
+            (true, 931),
+            (false, 433),
+            (true, 940),
+            (false, 423),
+            (true, 955),
+            (false, 442),
+            (true, 952),
+            (false, 438),
+            (true, 940),
+            (false, 462),
+            (true, 430),
+            (false, 942),
+            (true, 937),
+            (false, 450),
+            (true, 936),
+            (false, 447),
+            (true, 446),
+        ],
+        expect: Expect::Decoded {
+            model: "Govee-Water",
+            id: 7468,
+            channel: 0,
+            battery_ok: true,
+            reading: SensorReading::Leak(Leak { water: false }),
+        },
+    },
+    Fixture {
+        name: "event copies differ",
+        edges: &[
+            (true, 909),
+            (false, 463),
+            (true, 934),
+            (false, 449),
+            (true, 904),
+            (false, 426),
+            (true, 424),
+            (false, 902),
+            (true, 418),
+            (false, 976),
+            (true, 445),
+            (false, 929),
+            (true, 912),
+            (false, 415),
+            (true, 454),
+            (false, 957),
+            (true, 912),
+            (false, 420),
+            (true, 950),
+            (false, 448),
+            (true, 449),
+            (false, 953),
+            (true, 960),
+            (false, 465),
+            (true, 453),
+            (false, 928),
+            (true, 457),
+            (false, 955),
+            (true, 919),
+            (false, 454),
+            (true, 937),
+            (false, 449),
+            (true, 453),
+            (false, 967),
+            (true, 441),
+            (false, 958),
+            (true, 434),
+            (false, 939),
+            (true, 446),
+            (false, 977),
+            (true, 420),
+            (false, 972),
+            (true, 930),
+            (false, 437),
+            (true, 429),
+            (false, 959),
+            (true, 928),
+            (false, 421),
+            (true, 442),
+            (false, 959),
+            (true, 431),
+            (false, 966),
+            (true, 425),
+            (false, 964),
+            (true, 449),
+            (false, 952),
+            (true, 431),
+            (false, 904),
+            (true, 927),
+            (false, 464),
+            (true, 425),
+            (false, 938),
+            (true, 451),
+            (false, 950),
+            (true, 955),
+            (false, 415),
+            (true, 939),
+            (false, 434),
+            (true, 462),
+            (false, 913),
+            (true, 425),
+            (false, 901),
+            (true, 438),
+            (false, 941),
+            (true, 978),
+            (false, 440),
+            (true, 459),
+            (false, 973),
+            (true, 447),
+            (false, 951),
+            (true, 421),
+            (false, 958),
+            (true, 922),
+            (false, 424),
+            (true, 936),
+            (false, 419),
+            (true, 461),
+            (false, 977),
+            (true, 963),
+            (false, 425),
+            (true, 954),
+            (false, 447),
+            (true, 938),
+            (false, 418),
+            (true, 419),
+            (false, 926),
+            (true, 456),
+        ],
+        expect: Expect::Rejected,
+    },
+    Fixture {
+        name: "unknown event",
+        edges: &[
+            (true, 977),
+            (false, 456),
+            (true, 948),
+            (false, 415),
+            (true, 965),
+            (false, 447),
+            (true, 437),
+            (false, 946),
+            (true, 454),
+            (false, 969),
+            (true, 451),
+            (false, 910),
+            (true, 958),
+            (false, 465),
+            (true, 422),
+            (false, 975),
+            (true, 914),
+            (false, 456),
+            (true, 948),
+            (false, 427),
+            (true, 446),
+            (false, 944),
+            (true, 931),
+            (false, 431),
+            (true, 423),
+            (false, 916),
+            (true, 417),
+            (false, 967),
+            (true, 973),
+            (false, 445),
+            (true, 956),
+            (false, 453),
+            (true, 971),
+            (false, 457),
+            (true, 443),
+            (false, 971),
+            (true, 974),
+            (false, 465),
+            (true, 439),
+            (false, 932),
+            (true, 955),
+            (false, 455),
+            (true, 426),
+            (false, 934),
+            (true, 908),
+            (false, 455),
+            (true, 457),
+            (false, 931),
+            (true, 948),
+            (false, 420),
+            (true, 462),
+            (false, 954),
+            (true, 920),
+            (false, 451),
+            (true, 415),
+            (false, 931),
+            (true, 940),
+            (false, 440),
+            (true, 422),
+            (false, 976),
+            (true, 910),
+            (false, 415),
+            (true, 444),
+            (false, 919),
+            (true, 963),
+            (false, 464),
+            (true, 960),
+            (false, 423),
+            (true, 455),
+            (false, 935),
+            (true, 417),
+            (false, 912),
+            (true, 435),
+            (false, 907),
+            (true, 976),
+            (false, 454),
+            (true, 456),
+            (false, 916),
+            (true, 450),
+            (false, 900),
+            (true, 447),
+            (false, 959),
+            (true, 974),
+            (false, 428),
+            (true, 952),
+            (false, 452),
+            (true, 459),
+            (false, 914),
+            (true, 900),
+            (false, 423),
+            (true, 926),
+            (false, 450),
+            (true, 971),
+            (false, 460),
+            (true, 439),
+            (false, 931),
+            (true, 459),
+        ],
+        expect: Expect::Rejected,
+    },
+    Fixture {
+        name: "truncated",
+        edges: &[
+            (true, 940),
+            (false, 461),
+            (true, 416),
+            (false, 977),
+            (true, 960),
+            (false, 448),
+            (true, 939),
+            (false, 417),
+            (true, 462),
+            (false, 909),
+            (true, 947),
+            (false, 422),
+            (true, 425),
+            (false, 904),
+            (true, 463),
+            (false, 974),
+            (true, 942),
+            (false, 441),
+            (true, 910),
+            (false, 465),
+            (true, 438),
+            (false, 948),
+            (true, 439),
+            (false, 902),
+            (true, 435),
+            (false, 938),
+            (true, 416),
+            (false, 914),
+            (true, 448),
+            (false, 957),
+            (true, 953),
+            (false, 426),
+            (true, 461),
+            (false, 923),
+            (true, 922),
+            (false, 440),
+            (true, 464),
+            (false, 958),
+            (true, 439),
+            (false, 936),
+            (true, 451),
+            (false, 931),
+            (true, 430),
+            (false, 961),
+            (true, 431),
+            (false, 911),
+            (true, 915),
+            (false, 432),
+            (true, 463),
+            (false, 947),
+            (true, 929),
+            (false, 424),
+            (true, 900),
+            (false, 441),
+            (true, 969),
+            (false, 447),
+            (true, 447),
+            (false, 900),
+            (true, 423),
+            (false, 977),
+            (true, 460),
+            (false, 907),
+            (true, 970),
+            (false, 420),
+            (true, 431),
+            (false, 973),
+            (true, 426),
+            (false, 960),
+            (true, 430),
+            (false, 908),
+            (true, 903),
+            (false, 452),
+            (true, 937),
+            (false, 416),
+            (true, 417),
+            (false, 907),
+            (true, 944),
+            (false, 429),
+            (true, 938),
+            (false, 456),
+            (true, 971),
+            (false, 443),
+            (true, 418),
+            (false, 955),
+            (true, 427),
+        ],
+        expect: Expect::Rejected,
+    },
+];
+
+pub static GS558: &[Fixture] = &[
+    Fixture {
+        name: "smoke",
+        edges: &[
+            (true, 422),
+            (false, 11729),
+            (true, 443),
+            (false, 1311),
+            (true, 1215),
+            (false, 515),
+            (true, 453),
+            (false, 1261),
+            (true, 1217),
+            (false, 529),
+            (true, 1200),
+            (false, 534),
+            (true, 425),
+            (false, 1291),
+            (true, 1201),
+            (false, 501),
+            (true, 460),
+            (false, 1262),
+            (true, 419),
+            (false, 1318),
+            (true, 424),
+            (false, 1264),
+            (true, 1183),
+            (false, 550),
+            (true, 1177),
+            (false, 541),
+            (true, 1195),
+            (false, 511),
+            (true, 1207),
+            (false, 512),
+            (true, 447),
+            (false, 1296),
+            (true, 458),
+            (false, 1293),
+            (true, 1209),
+            (false, 547),
+            (true, 437),
+            (false, 1282),
+            (true, 456),
+            (false, 1325),
+            (true, 422),
+            (false, 1336),
+            (true, 441),
+            (false, 1338),
+            (true, 438),
+            (false, 1270),
+            (true, 429),
+            (false, 1299),
+            (true, 1165),
+            (false, 519),
+            (true, 432),
+            (false, 450),
+            (true, 436),
+        ],
+        expect: Expect::Decoded {
+            model: "GS558-Smoke",
+            id: 5913729,
+            channel: 0,
+            battery_ok: true,
+            reading: SensorReading::Hazard(Hazard::Smoke),
+        },
+    },
+    Fixture {
+        name: "smoke, no wake-up pulse",
+        edges: &[
+            (true, 435),
+            (false, 1264),
+            (true, 447),
+            (false, 1264),
+            (true, 437),
+            (false, 1268),
+            (true, 444),
+            (false, 1289),
+            (true, 1219),
+            (false, 548),
+            (true, 1170),
+            (false, 534),
+            (true, 1190),
+            (false, 505),
+            (true, 437),
+            (false, 1280),
+            (true, 428),
+            (false, 1329),
+            (true, 456),
+            (false, 1262),
+            (true, 459),
+            (false, 1329),
+            (true, 1170),
+            (false, 527),
+            (true, 420),
+            (false, 1270),
+            (true, 1236),
+            (false, 538),
+            (true, 1212),
+            (false, 548),
+            (true, 1203),
+            (false, 539),
+            (true, 1207),
+            (false, 543),
+            (true, 437),
+            (false, 1316),
+            (true, 1203),
+            (false, 538),
+            (true, 1196),
+            (false, 545),
+            (true, 420),
+            (false, 1279),
+            (true, 422),
+            (false, 1291),
+            (true, 1176),
+            (false, 530),
+            (true, 456),
+            (false, 1275),
+            (true, 440),
+            (false, 416),
+            (true, 429),
+        ],
+        expect: Expect::Decoded {
+            model: "GS558-Smoke",
+            id: 923570,
+            channel: 0,
+            battery_ok: true,
+            reading: SensorReading::Hazard(Hazard::Smoke),
+        },
+    },
+    Fixture {
+        name: "no stop pulses",
+        edges: &[
+            (true, 443),
+            (false, 11769),
+            (true, 443),
+            (false, 1316),
+            (true, 1189),
+            (false, 537),
+            (true, 460),
+            (false, 1297),
+            (true, 1184),
+            (false, 501),
+            (true, 1233),
+            (false, 521),
+            (true, 414),
+            (false, 1292),
+            (true, 1166),
+            (false, 516),
+            (true, 461),
+            (false, 1294),
+            (true, 444),
+            (false, 1290),
+            (true, 455),
+            (false, 1269),
+            (true, 1240),
+            (false, 509),
+            (true, 1190),
+            (false, 516),
+            (true, 1164),
+            (false, 545),
+            (true, 1212),
+            (false, 534),
+            (true, 412),
+            (false, 1318),
+            (true, 434),
+            (false, 1310),
+            (true, 1180),
+            (false, 511),
+            (true, 416),
+            (false, 1263),
+            (true, 439),
+            (false, 1326),
+            (true, 461),
+            (false, 1335),
+            (true, 440),
+            (false, 1318),
+            (true, 432),
+            (false, 1328),
+            (true, 433),
+            (false, 1292),
+            (true, 1186),
+        ],
+        expect: Expect::Rejected,
+    },
+    Fixture {
+        name: "truncated",
+        edges: &[
+            (true, 436),
+            (false, 11776),
+            (true, 434),
+            (false, 1296),
+            (true, 1189),
+            (false, 504),
+            (true, 412),
+            (false, 1328),
+            (true, 1208),
+            (false, 522),
+            (true, 1210),
+            (false, 538),
+            (true, 432),
+            (false, 1322),
+            (true, 1189),
+            (false, 533),
+            (true, 419),
+            (false, 1316),
+            (true, 435),
+            (false, 1324),
+            (true, 437),
+            (false, 1306),
+            (true, 1231),
+            (false, 505),
+            (true, 1168),
+            (false, 508),
+            (true, 1208),
+            (false, 536),
+            (true, 1229),
+            (false, 539),
+            (true, 416),
+            (false, 1328),
+            (true, 457),
+            (false, 1323),
+            (true, 1207),
+            (false, 541),
+            (true, 458),
+            (false, 1312),
+            (true, 424),
+            (false, 1313),
+            (true, 434),
+        ],
+        expect: Expect::Rejected,
+    },
+];
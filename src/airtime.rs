@@ -0,0 +1,118 @@
+use core::cell::RefCell;
+
+use embassy_sync::blocking_mutex::CriticalSectionMutex;
+
+use crate::decoder::Symbol;
+use crate::time;
+
+// How busy the band is, so interference from car fobs or a neighbour's
+// weather station shows up in health reports before it shows up as missed
+// readings. The receiver notes every burst as it comes in, whether it decodes
+// or not, and each report takes what's been counted since the last one.
+
+const MINUTE_US: u64 = 60_000_000;
+
+/// Band use over a report interval
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Traffic {
+    /// Bursts received, empty ones included
+    pub bursts: u32,
+    /// Share of the time a burst was being received, in tenths of %
+    pub busy_permille: u32,
+    /// Share of the time carrier was present, in tenths of %
+    pub carrier_permille: u32,
+    /// Average over the interval
+    pub bursts_per_min: u32,
+    /// Most bursts received within a minute of the interval
+    pub peak_bursts_per_min: u32,
+}
+
+struct Counts {
+    /// Monotonic time counting started at
+    since: u64,
+    bursts: u32,
+    busy_us: u64,
+    carrier_us: u64,
+    /// Monotonic time the current minute started at, and its bursts
+    minute: u64,
+    minute_bursts: u32,
+    peak_bursts: u32,
+}
+
+impl Counts {
+    const fn new(now: u64) -> Self {
+        Counts {
+            since: now,
+            bursts: 0,
+            busy_us: 0,
+            carrier_us: 0,
+            minute: now,
+            minute_bursts: 0,
+            peak_bursts: 0,
+        }
+    }
+
+    fn roll_minute(&mut self, now: u64) {
+        if now.saturating_sub(self.minute) >= MINUTE_US {
+            self.peak_bursts = self.peak_bursts.max(self.minute_bursts);
+            self.minute = now;
+            self.minute_bursts = 0;
+        }
+    }
+}
+
+static COUNTS: CriticalSectionMutex<RefCell<Counts>> =
+    CriticalSectionMutex::new(RefCell::new(Counts::new(0)));
+
+/// Start counting from now, once the receiver is up
+pub fn start() {
+    let now = time::monotonic();
+    COUNTS.lock(|counts| counts.replace(Counts::new(now)));
+}
+
+/// Count a burst received just now, `pulses` in uS up to the end marker
+pub fn record(pulses: &[Symbol]) {
+    let (mut busy_us, mut carrier_us) = (0, 0);
+    for (level, length) in pulses
+        .iter()
+        .flat_map(|symbol| {
+            [
+                (symbol.level1, symbol.length1),
+                (symbol.level2, symbol.length2),
+            ]
+        })
+        .take_while(|&(_, length)| length != 0)
+    {
+        busy_us += length as u64;
+        if level {
+            carrier_us += length as u64;
+        }
+    }
+    let now = time::monotonic();
+    COUNTS.lock(|counts| {
+        let mut counts = counts.borrow_mut();
+        counts.roll_minute(now);
+        counts.bursts = counts.bursts.wrapping_add(1);
+        counts.minute_bursts = counts.minute_bursts.wrapping_add(1);
+        counts.busy_us += busy_us;
+        counts.carrier_us += carrier_us;
+    });
+}
+
+/// Band use since the last call, or since start, and start counting over
+pub fn take() -> Traffic {
+    let now = time::monotonic();
+    let counts = COUNTS.lock(|counts| counts.replace(Counts::new(now)));
+    let elapsed = now.saturating_sub(counts.since).max(1);
+    let permille = |us: u64| (us * 1000 / elapsed).min(1000) as u32;
+    // Minute in progress is cut short, but what it has counts towards the peak
+    let peak = counts.peak_bursts.max(counts.minute_bursts);
+    Traffic {
+        bursts: counts.bursts,
+        busy_permille: permille(counts.busy_us),
+        carrier_permille: permille(counts.carrier_us),
+        // Not extrapolated from intervals shorter than a minute
+        bursts_per_min: (counts.bursts as u64 * MINUTE_US / elapsed.max(MINUTE_US)) as u32,
+        peak_bursts_per_min: peak,
+    }
+}
@@ -0,0 +1,148 @@
+use core::cell::RefCell;
+
+use embassy_sync::blocking_mutex::CriticalSectionMutex;
+
+use crate::decoder::ALL_PROTOCOLS;
+use crate::time;
+
+// Decode time and latency measurements of the `bench` feature, to check the
+// receive path keeps up with bursts as protocols are added. Percentiles are
+// taken over the latest SAMPLES of every measurement.
+
+const SAMPLES: usize = 64;
+/// Protocols in PROTOCOLS
+pub const PROTOCOL_COUNT: usize = ALL_PROTOCOLS.count_ones() as usize;
+
+/// Latest samples of a measurement, in us
+#[derive(Clone, Copy)]
+struct Samples {
+    values: [u32; SAMPLES],
+    next: usize,
+    /// Samples taken since boot
+    count: u32,
+}
+
+impl Samples {
+    const fn new() -> Self {
+        Samples {
+            values: [0; SAMPLES],
+            next: 0,
+            count: 0,
+        }
+    }
+
+    fn record(&mut self, value: u32) {
+        self.values[self.next] = value;
+        self.next = (self.next + 1) % SAMPLES;
+        self.count = self.count.saturating_add(1);
+    }
+
+    fn percentiles(mut self) -> Option<Percentiles> {
+        let len = (self.count as usize).min(SAMPLES);
+        let sorted = &mut self.values[..len];
+        sorted.sort_unstable();
+        let at = |percent: usize| sorted[(len - 1) * percent / 100];
+        (len > 0).then(|| Percentiles {
+            count: self.count,
+            p50: at(50),
+            p90: at(90),
+            p99: at(99),
+            max: sorted[len - 1],
+        })
+    }
+}
+
+/// Distribution of the latest samples of a measurement, in us
+#[derive(Clone, Copy, Debug)]
+pub struct Percentiles {
+    /// Samples taken since boot
+    pub count: u32,
+    pub p50: u32,
+    pub p90: u32,
+    pub p99: u32,
+    pub max: u32,
+}
+
+struct Bench {
+    /// Time spent in the decoder, by index in PROTOCOLS
+    decode: [Samples; PROTOCOL_COUNT],
+    /// RMT completion to the end of decoding, queueing included
+    decoded: Samples,
+    /// RMT completion to MQTT publish of events
+    event: Samples,
+    /// RMT completion of the last repeat to MQTT publish of periodic
+    /// readings, which are held until their repeats are over
+    periodic: Samples,
+}
+
+static BENCH: CriticalSectionMutex<RefCell<Bench>> =
+    CriticalSectionMutex::new(RefCell::new(Bench {
+        decode: [Samples::new(); PROTOCOL_COUNT],
+        decoded: Samples::new(),
+        event: Samples::new(),
+        periodic: Samples::new(),
+    }));
+
+fn since(received: u64) -> u32 {
+    time::monotonic()
+        .saturating_sub(received)
+        .try_into()
+        .unwrap_or(u32::MAX)
+}
+
+/// Protocol at `idx` in PROTOCOLS took `us` to try a burst
+pub fn decode_time(idx: usize, us: u64) {
+    let us = us.try_into().unwrap_or(u32::MAX);
+    BENCH.lock(|bench| {
+        if let Some(samples) = bench.borrow_mut().decode.get_mut(idx) {
+            samples.record(us);
+        }
+    });
+}
+
+/// Burst received at `received`, in monotonic us, is decoded
+pub fn decoded(received: u64) {
+    let us = since(received);
+    BENCH.lock(|bench| bench.borrow_mut().decoded.record(us));
+}
+
+/// Reading whose last frame was received at `received`, in monotonic us, is
+/// published
+pub fn published(received: u64, event: bool) {
+    let us = since(received);
+    BENCH.lock(|bench| {
+        let mut bench = bench.borrow_mut();
+        if event {
+            bench.event.record(us);
+        } else {
+            bench.periodic.record(us);
+        }
+    });
+}
+
+/// Decode time of protocol at `idx` in PROTOCOLS, None if it hasn't been
+/// tried yet
+pub fn decode(idx: usize) -> Option<Percentiles> {
+    BENCH
+        .lock(|bench| bench.borrow().decode.get(idx).copied())?
+        .percentiles()
+}
+
+/// Latencies from RMT completion, None where nothing was measured yet
+pub struct Latency {
+    pub decoded: Option<Percentiles>,
+    pub event: Option<Percentiles>,
+    pub periodic: Option<Percentiles>,
+}
+
+pub fn latency() -> Latency {
+    let (decoded, event, periodic) = BENCH.lock(|bench| {
+        let bench = bench.borrow();
+        (bench.decoded, bench.event, bench.periodic)
+    });
+    Latency {
+        decoded: decoded.percentiles(),
+        event: event.percentiles(),
+        periodic: periodic.percentiles(),
+    }
+}
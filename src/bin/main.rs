@@ -7,138 +7,2600 @@
 )]
 
 use embassy_executor::Spawner;
-use embassy_time::{Duration, Timer};
+use embassy_time::{Duration, Instant, Timer, with_timeout};
 use esp_alloc as _;
 use esp_backtrace as _;
+#[cfg(not(feature = "replay"))]
+use esp_hal::Blocking;
 use esp_hal::clock::CpuClock;
+#[cfg(not(feature = "replay"))]
+use esp_hal::gpio::interconnect::InputSignal;
+use esp_hal::gpio::{AnyPin, Level, Output, OutputConfig};
+use esp_hal::interrupt::Priority;
+use esp_hal::interrupt::software::SoftwareInterruptControl;
 use esp_hal::ram;
-use esp_hal::rmt::{PulseCode, Rmt, RxChannelConfig, RxChannelCreator};
+use esp_hal::rmt::Error as RmtError;
+#[cfg(not(feature = "replay"))]
+use esp_hal::rmt::{Rmt, RxChannelConfig, RxChannelCreator, TxChannelConfig, TxChannelCreator};
 use esp_hal::rng::Rng;
-use esp_hal::rtc_cntl::Rtc;
+use esp_hal::rtc_cntl::{Rtc, SocResetReason};
+#[cfg(any(feature = "esp32", feature = "esp32s3"))]
+use esp_hal::system::Stack;
+#[cfg(not(feature = "replay"))]
 use esp_hal::time::Rate;
-use esp_hal::timer::timg::{MwdtStage, TimerGroup};
+use esp_hal::timer::timg::TimerGroup;
+#[cfg(feature = "provisioning")]
+use esp_hal::{hmac::Hmac, usb_serial_jtag::UsbSerialJtag};
 use esp_radio::Controller;
+#[cfg(any(feature = "esp32", feature = "esp32s3"))]
+use esp_rtos::embassy::Executor;
+use esp_rtos::embassy::InterruptExecutor;
 
 use embassy_sync::channel::Channel;
+use embassy_sync::signal::Signal;
 use embassy_sync::{
-    blocking_mutex::raw::{CriticalSectionRawMutex, NoopRawMutex},
+    blocking_mutex::{
+        CriticalSectionMutex,
+        raw::{CriticalSectionRawMutex, NoopRawMutex},
+    },
     mutex::Mutex,
 };
 use log::{info, warn};
 
-use esp_rf_ook2::decoder::{DecodeError, SensorData, decode};
-use esp_rf_ook2::mqtt::Mqtt;
-use esp_rf_ook2::ntpc::Ntpc;
-use esp_rf_ook2::wifi::Wifi;
+#[cfg(feature = "bench")]
+use esp_rf_ook2::MQTT_BENCH_TOPIC;
+#[cfg(feature = "postmortem")]
+use esp_rf_ook2::MQTT_PANICS_TOPIC;
+#[cfg(feature = "trace")]
+use esp_rf_ook2::MQTT_TRACE_TOPIC;
+use esp_rf_ook2::airtime;
+#[cfg(feature = "bench")]
+use esp_rf_ook2::bench::{self, Percentiles};
+use esp_rf_ook2::bursts;
+use esp_rf_ook2::clock::{self, Clock, RtcClock};
+use esp_rf_ook2::collision::Collisions;
+use esp_rf_ook2::config::{self, Config, SensorConfig};
+#[cfg(feature = "provisioning")]
+use esp_rf_ook2::credentials;
+use esp_rf_ook2::credentials::Credentials;
+use esp_rf_ook2::decoder::{
+    self, Candidates, DecodeError, MAX_SYMBOLS, PROTOCOLS, SensorData, Symbol,
+    encode::{self, NEXUS_EDGES},
+    learn::{Learner, Suggestion},
+    pulses_from_edges, timing,
+};
+use esp_rf_ook2::dedup::{Cluster, Cooldown, Finished, ReplayFilter, Suppressor};
+use esp_rf_ook2::derived::{self, Metric};
+use esp_rf_ook2::ds3231::Ds3231;
+use esp_rf_ook2::entropy;
+use esp_rf_ook2::errors::{self, Code, Pending, Report};
+use esp_rf_ook2::esphome::{self, MAX_ENTITIES};
+use esp_rf_ook2::events::{self, Event, Reading};
+use esp_rf_ook2::health::{self, Health, Monitor};
+#[cfg(feature = "history")]
+use esp_rf_ook2::history;
+use esp_rf_ook2::http;
+use esp_rf_ook2::hysteresis::Hysteresis;
+use esp_rf_ook2::latest::{self, DIAGNOSTICS_LEN, REGISTRY};
+use esp_rf_ook2::logger;
+use esp_rf_ook2::mqtt::{COMMAND_PAYLOAD_LEN, Class, Command, Mqtt};
+use esp_rf_ook2::ntpc::{self, Ntpc, Schedule};
+#[cfg(feature = "ota")]
+use esp_rf_ook2::ota;
+use esp_rf_ook2::output::{Outputs, Publisher};
+use esp_rf_ook2::pool::{self, Burst, POOL_LEN};
+#[cfg(feature = "postmortem")]
+use esp_rf_ook2::postmortem::{self, Kind};
+#[cfg(feature = "provisioning")]
+use esp_rf_ook2::provision;
+use esp_rf_ook2::pubstats::{self, BACKENDS, Counts};
+use esp_rf_ook2::queue::FairQueue;
+use esp_rf_ook2::rain;
+use esp_rf_ook2::ratelimit::TokenBucket;
+use esp_rf_ook2::registry::Sensor;
+#[cfg(feature = "replay")]
+use esp_rf_ook2::replay::{DUMPS, Replay};
+use esp_rf_ook2::rx;
+use esp_rf_ook2::schedule::{Daily, Interval};
+use esp_rf_ook2::selftest::{self, Check, Connectivity, SelfTest};
+use esp_rf_ook2::snapshot::{
+    self, COOLDOWN_LEN, REGISTRY_LEN, REPLAY_LEN, SUMMARY_LEN, SUPPRESSOR_LEN, Snapshot,
+};
+use esp_rf_ook2::station::{self, Station};
+use esp_rf_ook2::summary::{SensorStats, Summary};
+use esp_rf_ook2::time;
+#[cfg(feature = "trace")]
+use esp_rf_ook2::trace::{self, Phase};
+use esp_rf_ook2::trend;
+use esp_rf_ook2::watchdog::Watchdog;
+use esp_rf_ook2::wifi;
+use esp_rf_ook2::wifi::{Link, Wifi};
+use esp_rf_ook2::wind;
+use esp_rf_ook2::zone::{self, ZoneClimate};
+use esp_rf_ook2::{
+    BOOT_JITTER_SECS, CLUSTER_WINDOW_MS, COMMAND_POLL_SECS, COMMAND_TOKEN, DECODE_ERROR_RATE,
+    DOORBELL_COOLDOWN_SECS, EVENT_REPLAY_MS, LEARN_BURSTS, MAX_CAPTURES, MAX_LEARN_BURSTS,
+    MOTION_COOLDOWN_SECS, MQTT_ALARMS_TOPIC, MQTT_BURST_TOPIC, MQTT_CAPTURE_TOPIC,
+    MQTT_CAPTURES_TOPIC, MQTT_CLEAR_ALARM_TOPIC, MQTT_COMMAND_TOPICS, MQTT_CONFIG_TOPIC,
+    MQTT_DEBUG_TOPIC, MQTT_DIAGNOSE_TOPIC, MQTT_DOORBELL_TOPIC, MQTT_ERRORS_TOPIC,
+    MQTT_FACTORY_RESET_TOPIC, MQTT_GET_BURST_TOPIC, MQTT_GET_READINGS_TOPIC, MQTT_HEALTH_TOPIC,
+    MQTT_LEARN_TOPIC, MQTT_LEARNED_TOPIC, MQTT_NETWORK_TOPIC, MQTT_PROTOCOLS_TOPIC,
+    MQTT_PUBLISHES_TOPIC, MQTT_READINGS_TOPIC, MQTT_REBOOT_TOPIC, MQTT_RECEIVER_TOPIC,
+    MQTT_SENSOR_CONFIG_TOPICS, MQTT_SET_CONFIG_TOPIC, MQTT_SET_DEBUG_TOPIC,
+    MQTT_SET_PROTOCOLS_TOPIC, MQTT_SET_TIME_TOPIC, MQTT_SET_TIMING_TOPIC, MQTT_STATIONS_TOPIC,
+    MQTT_SUMMARY_TOPIC, MQTT_TIMING_TOPIC, MQTT_TRANSMIT_TOPIC, MQTT_ZONES_TOPIC, NTP_ENABLED,
+    RECEIVER_SILENT_SECS, STATION_RECORDS, SUMMARY_HOUR, SUMMARY_MINUTE, SUPPRESS_WINDOW_MS,
+    UTC_OFFSET_MINUTES, ZONE_AVERAGES,
+};
+use esp_rf_ook2::{
+    BUILD_TIMESTAMP, DS3231, ESPHOME_SCHEMA, GIT_HASH, HTTP_PORT, PAYLOAD_FORMAT, PUBLISH_BURST,
+    PUBLISH_HEARTBEAT_SECS, PUBLISH_HUMIDITY_DELTA, PUBLISH_RATE_PER_MIN, PUBLISH_TEMP_DELTA,
+    PUBLISH_TIMEOUT_SECS, STATUS_LED, TIME_EPOCH, TIME_RFC3339, TX_PIN, VERSION,
+};
+#[cfg(feature = "history")]
+use esp_rf_ook2::{MQTT_GET_HISTORY_TOPIC, MQTT_HISTORY_TOPIC};
+#[cfg(feature = "ota")]
+use esp_rf_ook2::{MQTT_OTA_TOPIC, MQTT_SET_OTA_TOPIC};
+#[cfg(not(feature = "replay"))]
+use esp_rf_ook2::{RMT_CLK_DIVIDER, RMT_SOURCE_MHZ, RX_INVERT};
+
+use embassy_futures::join::join;
+use embassy_futures::select::{Either, select};
+use embassy_net::Stack;
+
+use static_cell::StaticCell;
+
+use core::cell::RefCell;
+use core::fmt::{self, Write};
+use core::ops::Range;
+use core::sync::atomic::{AtomicBool, AtomicU8, AtomicU32, Ordering};
+use heapless::{String, Vec};
+
+// When you are okay with using a nightly compiler it's better to use https://docs.rs/static_cell/2.1.0/static_cell/macro.make_static.html
+macro_rules! mk_static {
+    ($t:ty,$val:expr) => {{
+        static STATIC_CELL: static_cell::StaticCell<$t> = static_cell::StaticCell::new();
+        #[deny(unused_attributes)]
+        let x = STATIC_CELL.uninit().write($val);
+        x
+    }};
+}
+
+// This creates a default app-descriptor required by the esp-idf bootloader.
+// For more information see: <https://docs.espressif.com/projects/esp-idf/en/stable/esp32/api-reference/system/app_image_format.html#application-description>
+esp_bootloader_esp_idf::esp_app_desc!();
+
+static SHARED_STACK: StaticCell<Mutex<NoopRawMutex, Stack<'static>>> = StaticCell::new();
+
+// Bursts waiting to be decoded. Decoding is quick, but the main loop waits for
+// room in the publish queue every now and then.
+static BURSTS: Channel<CriticalSectionRawMutex, Burst, POOL_LEN> = Channel::new();
+// Receiver counters since boot: bursts received while every pooled buffer was
+// in flight, bursts that overflowed RMT memory, other RMT errors and
+// receptions that ended without a single pulse
+static DROPPED_BURSTS: AtomicU32 = AtomicU32::new(0);
+static RX_OVERFLOWS: AtomicU32 = AtomicU32::new(0);
+static RX_ERRORS: AtomicU32 = AtomicU32::new(0);
+static EMPTY_BURSTS: AtomicU32 = AtomicU32::new(0);
+/// Burst and what the decoder made of it
+struct Decoded {
+    pulses: Burst,
+    result: Result<Candidates, DecodeError>,
+}
+static DECODED: Channel<CriticalSectionRawMutex, Decoded, 2> = Channel::new();
+// Receiver and decoder run on the APP CPU, they need little stack
+#[cfg(any(feature = "esp32", feature = "esp32s3"))]
+const APP_CPU_STACK_LEN: usize = 8 * 1024;
+
+// Number of bursts to publish verbatim, requested via MQTT command
+static CAPTURES: AtomicU8 = AtomicU8::new(0);
+// Raw bursts with the time they were received at (in us). Kept out of Event
+// so that readings don't take as much space as a full burst.
+static CAPTURE_CHANNEL: Channel<CriticalSectionRawMutex, (Vec<Symbol, MAX_SYMBOLS>, i64), 1> =
+    Channel::new();
+// Every edge takes 4 hex digits
+const CAPTURE_PAYLOAD_LEN: usize = MAX_SYMBOLS * 2 * 4 + 64;
+// Capture plus where the burst stands among the ones kept
+const BURST_PAYLOAD_LEN: usize = CAPTURE_PAYLOAD_LEN + 64;
+
+// Publish decode context of every burst, toggled via MQTT command
+static DEBUG: AtomicBool = AtomicBool::new(false);
+/// Everything the decoder had and made of a burst
+struct DecodeContext {
+    pulses: Vec<Symbol, MAX_SYMBOLS>,
+    result: Result<Candidates, DecodeError>,
+    /// Time the burst was received at, in us
+    timestamp: i64,
+}
+// Bursts arriving while the previous one is still being published are dropped
+static DEBUG_CHANNEL: Channel<CriticalSectionRawMutex, DecodeContext, 1> = Channel::new();
+// Edges plus a few candidates
+const DEBUG_PAYLOAD_LEN: usize = CAPTURE_PAYLOAD_LEN + PAYLOAD_LEN;
+
+// Number of bursts left to learn an unknown protocol from, requested via MQTT
+// command
+static LEARN: AtomicU8 = AtomicU8::new(0);
+// Protocol suggested once learning is over, with the number of bursts it saw
+static LEARN_CHANNEL: Channel<CriticalSectionRawMutex, (Option<Suggestion>, u16), 1> =
+    Channel::new();
+
+// Time set via MQTT command, in us since Unix epoch
+static SET_TIME: Signal<CriticalSectionRawMutex, u64> = Signal::new();
+
+// Frame to transmit, a test frame requested via MQTT command or a doorbell
+// press for its chime, with the pause after it and the end marker
+const TX_LEN: usize = MAX_SYMBOLS + 1;
+static TRANSMIT: Signal<CriticalSectionRawMutex, [Symbol; TX_LEN]> = Signal::new();
+// Test frames are repeated like sensors do, the pause sets them apart
+const TX_REPEATS: usize = 6;
+const TX_GAP_US: u16 = 10_000;
+// Sensor ID test frames carry unless the command sets one
+const TX_ID: u8 = 0x5a;
+
+static SUMMARY: CriticalSectionMutex<RefCell<Summary<SUMMARY_LEN>>> =
+    CriticalSectionMutex::new(RefCell::new(Summary::new()));
+
+// Messages are formatted into fixed buffers, so the heap is only used by Wi-Fi
+const TOPIC_LEN: usize = 64;
+const PAYLOAD_LEN: usize = 512;
+// Percentiles of every protocol and latency, at most ~110 characters each
+#[cfg(feature = "bench")]
+const BENCH_PAYLOAD_LEN: usize = (bench::PROTOCOL_COUNT + 3) * 112 + 64;
+// Counts of every backend, at most ~90 characters each, overall and for every
+// sensor, which takes another ~90 to name
+const PUBLISHES_PAYLOAD_LEN: usize = (REGISTRY_LEN + 1) * (BACKENDS.len() + 1) * 96 + 64;
+const CONFIG_LEN: usize = COMMAND_PAYLOAD_LEN;
+// Readings waiting for the broker, at most one periodic reading per sensor
+const QUEUE_LEN: usize = 16;
+// Errors waiting for the broker, one per error code
+const ERRORS_LEN: usize = 8;
+// Sensors whose readings can be held for merging at once
+const CLUSTER_LEN: usize = 8;
+// Restart commands flush the publish queue first, for at most this long
+const RESTART_TIMEOUT_SECS: u64 = 10;
+
+static HYSTERESIS: Hysteresis = Hysteresis {
+    temperature: PUBLISH_TEMP_DELTA,
+    humidity: PUBLISH_HUMIDITY_DELTA,
+    heartbeat: Duration::from_secs(PUBLISH_HEARTBEAT_SECS),
+};
+
+fn get_timestamp() -> u64 {
+    esp_hal::time::Instant::now()
+        .duration_since_epoch()
+        .as_millis()
+}
+
+fn write_reading(out: &mut impl Write, data: &SensorData) -> fmt::Result {
+    if let Some(climate) = data.climate() {
+        write!(out, ", \"temperature_C\" : {}", climate.celsius())?;
+        if let Some(humidity) = climate.humidity {
+            write!(out, ", \"humidity\" : {}", humidity)?;
+        }
+    }
+    if let Some(wind) = data.wind() {
+        write!(
+            out,
+            ", \"wind_avg_m_s\" : {}.{}, \"wind_max_m_s\" : {}.{}, \"wind_dir_deg\" : {}",
+            wind.avg_speed / 10,
+            wind.avg_speed % 10,
+            wind.gust_speed / 10,
+            wind.gust_speed % 10,
+            wind.direction
+        )?;
+    }
+    if let Some(contact) = data.contact() {
+        write!(
+            out,
+            ", \"event\" : \"{}\", \"contact_open\" : {}, \"tamper\" : {}",
+            contact.event(),
+            contact.open,
+            contact.tamper
+        )?;
+    }
+    if let Some(code) = data.motion() {
+        write!(out, ", \"event\" : \"motion\", \"code\" : {}", code)?;
+    }
+    if let Some(code) = data.doorbell() {
+        write!(out, ", \"event\" : \"doorbell\", \"code\" : {}", code)?;
+    }
+    if let Some(leak) = data.leak() {
+        write!(
+            out,
+            ", \"event\" : \"{}\", \"water\" : {}",
+            leak.event(),
+            leak.water
+        )?;
+    }
+    if let Some(hazard) = data.hazard() {
+        write!(out, ", \"event\" : \"{}\"", hazard.key())?;
+    }
+    if let Some(rain) = data.rain() {
+        write!(
+            out,
+            ", \"rain_mm\" : {}.{}",
+            rain.total / 10,
+            rain.total % 10
+        )?;
+        if let Some(rate) = rain.rate {
+            write!(out, ", \"rain_rate_mm_h\" : {}.{}", rate / 10, rate % 10)?;
+        }
+    }
+    if let Some(totals) = rain::totals(data) {
+        let total = totals.total;
+        write!(out, ", \"rain_total_mm\" : {}.{}", total / 10, total % 10)?;
+        if let Some(today) = totals.today {
+            write!(out, ", \"rain_today_mm\" : {}.{}", today / 10, today % 10)?;
+        }
+    }
+    for (metric, value) in derived::compute(data) {
+        let name = match metric {
+            Metric::WindChill => "wind_chill_C",
+            Metric::AbsoluteHumidity => "absolute_humidity_g_m3",
+        };
+        write!(out, ", \"{}\" : ", name)?;
+        write_tenths(out, value)?;
+    }
+    Ok(())
+}
+
+/// `"time"` field of payloads, followed by the epoch and RFC 3339 ones if
+/// they're enabled. `timestamp` is in us since Unix epoch.
+fn write_time(out: &mut impl Write, timestamp: i64) -> fmt::Result {
+    let time = jiff::Timestamp::from_microsecond(timestamp).unwrap_or_default();
+    write!(
+        out,
+        "\"time\" : \"{}\"",
+        time.strftime("%Y-%m-%d %H:%M:%S UTC")
+    )?;
+    if TIME_EPOCH {
+        write!(out, ", \"epoch\" : {}", time.as_second())?;
+    }
+    if TIME_RFC3339 {
+        write!(
+            out,
+            ", \"time_rfc3339\" : \"{}\"",
+            time.strftime("%Y-%m-%dT%H:%M:%SZ")
+        )?;
+    }
+    Ok(())
+}
+
+fn write_payload(
+    out: &mut impl Write,
+    data: &SensorData,
+    timestamp: i64,
+    expire_after: Option<u64>,
+    repeats: Option<u8>,
+    count: Option<u32>,
+) -> fmt::Result {
+    write_header(out, data, timestamp)?;
+    write_reading(out, data)?;
+    if let Some(expire_after) = expire_after {
+        write!(out, ", \"expire_after\" : {}", expire_after)?;
+    }
+    if let Some(repeats) = repeats {
+        write!(out, ", \"repeats\" : {}", repeats)?;
+    }
+    if let Some(count) = count {
+        write!(out, ", \"count\" : {}", count)?;
+    }
+    write!(out, ", \"uptime\" : {} }}", get_timestamp())
+}
+
+/// Opening of a sensor's payload: time, the sensor and its alias and zone
+fn write_header(out: &mut impl Write, data: &SensorData, timestamp: i64) -> fmt::Result {
+    write!(out, "{{")?;
+    write_time(out, timestamp)?;
+    write!(
+        out,
+        ", \"time_valid\" : {}, \"model\" : \"{}\", \"id\" : {}, \"channel\" : {}, \"battery_ok\" : {}",
+        ntpc::time_valid(),
+        data.model(),
+        data.id,
+        data.channel,
+        data.battery_ok,
+    )?;
+    if let Some(settings) = config::sensor(data) {
+        if !settings.alias.is_empty() {
+            write!(out, ", \"alias\" : \"{}\"", settings.alias)?;
+        }
+        if !settings.zone.is_empty() {
+            write!(out, ", \"zone\" : \"{}\"", settings.zone)?;
+        }
+    }
+    Ok(())
+}
+
+/// Reading as a SenML pack (RFC 8428), a record per value named relative to
+/// `<model>/<id>/`. The base time is left out until the clock is set, SenML
+/// takes that as now.
+fn write_senml(out: &mut impl Write, data: &SensorData, timestamp: i64) -> fmt::Result {
+    write!(out, "[{{\"bn\" : \"{}/{}/\"", data.model(), data.id)?;
+    if ntpc::time_valid() {
+        write!(out, ", \"bt\" : {}", timestamp / 1_000_000)?;
+    }
+    write!(
+        out,
+        ", \"n\" : \"battery_ok\", \"vb\" : {} }}",
+        data.battery_ok
+    )?;
+    if let Some(climate) = data.climate() {
+        write_record(out, "temperature", "Cel", climate.celsius())?;
+        if let Some(humidity) = climate.humidity {
+            write_record(out, "humidity", "%RH", humidity)?;
+        }
+    }
+    if let Some(wind) = data.wind() {
+        let (avg, max) = (wind.avg_speed, wind.gust_speed);
+        write_record(
+            out,
+            "wind_avg",
+            "m/s",
+            format_args!("{}.{}", avg / 10, avg % 10),
+        )?;
+        write_record(
+            out,
+            "wind_max",
+            "m/s",
+            format_args!("{}.{}", max / 10, max % 10),
+        )?;
+        write_record(out, "wind_dir", "deg", wind.direction)?;
+    }
+    if let Some(contact) = data.contact() {
+        write!(
+            out,
+            ", {{\"n\" : \"contact_open\", \"vb\" : {} }}, {{\"n\" : \"tamper\", \"vb\" : {} }}",
+            contact.open, contact.tamper
+        )?;
+    }
+    if let Some(code) = data.motion() {
+        write_record(out, "motion", "", code)?;
+    }
+    if let Some(code) = data.doorbell() {
+        write_record(out, "doorbell", "", code)?;
+    }
+    if let Some(leak) = data.leak() {
+        write!(out, ", {{\"n\" : \"water\", \"vb\" : {} }}", leak.water)?;
+    }
+    if let Some(hazard) = data.hazard() {
+        write!(out, ", {{\"n\" : \"{}\", \"vb\" : true }}", hazard.key())?;
+    }
+    if let Some(rain) = data.rain() {
+        let total = rain.total;
+        write_record(
+            out,
+            "rain",
+            "mm",
+            format_args!("{}.{}", total / 10, total % 10),
+        )?;
+        if let Some(rate) = rain.rate {
+            write_record(
+                out,
+                "rain_rate",
+                "mm/h",
+                format_args!("{}.{}", rate / 10, rate % 10),
+            )?;
+        }
+    }
+    if let Some(totals) = rain::totals(data) {
+        let total = totals.total;
+        write_record(
+            out,
+            "rain_total",
+            "mm",
+            format_args!("{}.{}", total / 10, total % 10),
+        )?;
+        if let Some(today) = totals.today {
+            write_record(
+                out,
+                "rain_today",
+                "mm",
+                format_args!("{}.{}", today / 10, today % 10),
+            )?;
+        }
+    }
+    for (metric, value) in derived::compute(data) {
+        let (name, unit) = match metric {
+            Metric::WindChill => ("wind_chill", "Cel"),
+            Metric::AbsoluteHumidity => ("absolute_humidity", "g/m3"),
+        };
+        let mut tenths: String<12> = String::new();
+        write_tenths(&mut tenths, value)?;
+        write_record(out, name, unit, tenths)?;
+    }
+    write!(out, " ]")
+}
+
+/// Numeric SenML record, without a unit if `unit` is empty
+fn write_record(
+    out: &mut impl Write,
+    name: &str,
+    unit: &str,
+    value: impl fmt::Display,
+) -> fmt::Result {
+    write!(out, ", {{\"n\" : \"{}\"", name)?;
+    if !unit.is_empty() {
+        write!(out, ", \"u\" : \"{}\"", unit)?;
+    }
+    write!(out, ", \"v\" : {} }}", value)
+}
+
+/// Payload of a sensor reading in the configured format
+fn write_sensor_payload(
+    out: &mut impl Write,
+    data: &SensorData,
+    timestamp: i64,
+    expire_after: Option<u64>,
+    repeats: Option<u8>,
+    count: Option<u32>,
+) -> fmt::Result {
+    if PAYLOAD_FORMAT.senml() {
+        write_senml(out, data, timestamp)
+    } else {
+        write_payload(out, data, timestamp, expire_after, repeats, count)
+    }
+}
+
+/// Edges of the burst as hex, every edge is 16-bit little endian with level in
+/// MSB and duration in uS in the rest. Same as fuzzer input, so captures can be
+/// turned into corpus entries with `xxd -r -p`.
+fn write_capture(out: &mut impl Write, pulses: &[Symbol], timestamp: i64) -> fmt::Result {
+    write!(out, "{{")?;
+    write_time(out, timestamp)?;
+    write!(out, ", \"edges\" : \"")?;
+    write_edges(out, pulses)?;
+    write!(out, "\" }}")
+}
+
+fn write_edges(out: &mut impl Write, pulses: &[Symbol]) -> fmt::Result {
+    let edges = pulses
+        .iter()
+        .flat_map(|entry| [(entry.level1, entry.length1), (entry.level2, entry.length2)])
+        .take_while(|(_, length)| *length != 0);
+    for (level, length) in edges {
+        let raw = (length & 0x7fff) | if level { 0x8000 } else { 0 };
+        let [lo, hi] = raw.to_le_bytes();
+        write!(out, "{:02x}{:02x}", lo, hi)?;
+    }
+    Ok(())
+}
+
+/// Edges of the burst as in a capture, every protocol that accepted it with
+/// its confidence, the one picked and why decoding failed, if it did
+fn write_debug(out: &mut impl Write, context: &DecodeContext) -> fmt::Result {
+    write!(out, "{{")?;
+    write_time(out, context.timestamp)?;
+    write!(out, ", \"edges\" : \"")?;
+    write_edges(out, &context.pulses)?;
+    write!(out, "\", \"candidates\" : [")?;
+    let candidates = context.result.as_ref().ok();
+    for (idx, candidate) in candidates.iter().flat_map(|c| c.iter()).enumerate() {
+        let data = &candidate.data;
+        write!(
+            out,
+            "{}{{\"model\" : \"{}\", \"id\" : {}, \"channel\" : {}, \"battery_ok\" : {}, \"confidence\" : {}, \"reading\" : \"{:?}\"}}",
+            if idx > 0 { ", " } else { " " },
+            data.model(),
+            data.id,
+            data.channel,
+            data.battery_ok,
+            candidate.confidence,
+            data.reading
+        )?;
+    }
+    write!(out, " ], \"picked\" : ")?;
+    match candidates.and_then(Candidates::unambiguous) {
+        Some(candidate) => write!(out, "\"{}\"", candidate.data.model())?,
+        None => write!(out, "null")?,
+    }
+    write!(out, ", \"error\" : ")?;
+    match &context.result {
+        Err(e) => write!(out, "\"{:?}\" }}", e),
+        Ok(_) => write!(out, "null }}"),
+    }
+}
+
+async fn publish_capture(outputs: &mut Outputs, pulses: &[Symbol], timestamp: i64) {
+    let mut payload: String<CAPTURE_PAYLOAD_LEN> = String::new();
+    if write_capture(&mut payload, pulses, timestamp).is_err() {
+        warn!("Capture doesn't fit into buffer");
+        return;
+    }
+    if let Err(e) = outputs
+        .publish_diagnostics(MQTT_CAPTURES_TOPIC, payload.as_str(), Class::Captures)
+        .await
+    {
+        warn!("Failed to publish MQTT message: {:?}", e);
+    }
+}
+
+/// Kept burst as a capture, with its number since boot and the model the
+/// decoder picked
+fn write_burst(out: &mut impl Write, burst: &bursts::Burst) -> fmt::Result {
+    write!(out, "{{")?;
+    write_time(out, burst.timestamp)?;
+    write!(out, ", \"seq\" : {}, \"picked\" : ", burst.seq)?;
+    match burst.picked {
+        Some(model) => write!(out, "\"{}\"", model)?,
+        None => write!(out, "null")?,
+    }
+    write!(out, ", \"edges\" : \"")?;
+    write_edges(out, &burst.pulses)?;
+    write!(out, "\" }}")
+}
+
+async fn publish_burst(outputs: &mut Outputs, query: bursts::Query) {
+    let Some(burst) = bursts::find(query) else {
+        warn!("No such burst, {} kept", bursts::len());
+        return;
+    };
+    let mut payload: String<BURST_PAYLOAD_LEN> = String::new();
+    if write_burst(&mut payload, &burst).is_err() {
+        warn!("Burst doesn't fit into buffer");
+        return;
+    }
+    if let Err(e) = outputs
+        .publish_diagnostics(MQTT_BURST_TOPIC, payload.as_str(), Class::Captures)
+        .await
+    {
+        warn!("Failed to publish MQTT message: {:?}", e);
+    }
+}
+
+async fn publish_debug(outputs: &mut Outputs, context: &DecodeContext) {
+    let mut payload: String<DEBUG_PAYLOAD_LEN> = String::new();
+    if write_debug(&mut payload, context).is_err() {
+        warn!("Decode context doesn't fit into buffer");
+        return;
+    }
+    if let Err(e) = outputs
+        .publish_diagnostics(MQTT_DEBUG_TOPIC, payload.as_str(), Class::Captures)
+        .await
+    {
+        warn!("Failed to publish MQTT message: {:?}", e);
+    }
+}
+
+fn write_range(out: &mut impl Write, name: &str, range: &Range<u16>) -> fmt::Result {
+    write!(out, ", \"{}\" : [{}, {}]", name, range.start, range.end)
+}
+
+/// Protocol learned from `bursts` captures of an unknown sensor, as a generic
+/// protocol descriptor without fields. Ranges are in uS, max is exclusive.
+fn write_learned(
+    out: &mut impl Write,
+    suggestion: Option<&Suggestion>,
+    bursts: u16,
+) -> fmt::Result {
+    write!(out, "{{\"bursts\" : {}", bursts)?;
+    let Some(suggestion) = suggestion else {
+        return write!(out, ", \"frames\" : 0, \"protocol\" : null }}");
+    };
+    write!(
+        out,
+        ", \"frames\" : {}, \"protocol\" : {{\"modulation\" : \"{}\", \"bits\" : {}",
+        suggestion.frames,
+        suggestion.modulation.key(),
+        suggestion.bits
+    )?;
+    if let Some(pulse) = &suggestion.pulse {
+        write_range(out, "pulse", pulse)?;
+    }
+    write_range(out, "zero", &suggestion.zero)?;
+    write_range(out, "one", &suggestion.one)?;
+    if let Some(gap) = &suggestion.gap {
+        write_range(out, "gap", gap)?;
+    }
+    write!(out, "}} }}")
+}
+
+async fn publish_learned(outputs: &mut Outputs, suggestion: Option<&Suggestion>, bursts: u16) {
+    let mut payload: String<PAYLOAD_LEN> = String::new();
+    if write_learned(&mut payload, suggestion, bursts).is_err() {
+        warn!("Learned protocol doesn't fit into buffer");
+        return;
+    }
+    if let Err(e) = outputs
+        .publish_diagnostics(MQTT_LEARNED_TOPIC, payload.as_str(), Class::Diagnostics)
+        .await
+    {
+        warn!("Failed to publish MQTT message: {:?}", e);
+    }
+}
+
+/// Republish last readings of all known sensors, so a new consumer doesn't
+/// have to wait for every sensor to transmit
+async fn publish_readings(outputs: &mut Outputs) {
+    for idx in 0.. {
+        let Some(sensor) = REGISTRY.lock(|registry| registry.borrow().iter().nth(idx).cloned())
+        else {
+            break;
+        };
+        let mut payload: String<PAYLOAD_LEN> = String::new();
+        if write_sensor_payload(
+            &mut payload,
+            &sensor.reading,
+            sensor.timestamp as i64,
+            sensor.expire_after(),
+            None,
+            None,
+        )
+        .is_err()
+        {
+            warn!("MQTT message for {} doesn't fit into buffer", sensor.model);
+            continue;
+        }
+        if let Err(e) = outputs
+            .publish_reading(MQTT_READINGS_TOPIC, payload.as_str())
+            .await
+        {
+            warn!("Failed to publish MQTT message: {:?}", e);
+            break;
+        }
+    }
+}
+
+/// Republish the last `count` logged readings, oldest first, so consumers can
+/// fill the gaps left by network outages
+#[cfg(feature = "history")]
+async fn publish_history(outputs: &mut Outputs, count: usize) {
+    let mut skip = history::len().saturating_sub(count);
+    let mut cursor = history::Cursor::default();
+    let mut buf = [0; history::RECORD_LEN];
+    while let Some(len) = history::next(&mut cursor, &mut buf) {
+        if skip > 0 {
+            skip -= 1;
+            continue;
+        }
+        let Ok(payload) = core::str::from_utf8(&buf[..len]) else {
+            continue;
+        };
+        if let Err(e) = outputs.publish_reading(MQTT_HISTORY_TOPIC, payload).await {
+            warn!("Failed to publish MQTT message: {:?}", e);
+            break;
+        }
+    }
+}
+
+/// Publish the latched alarm state of `sensor` retained, true if it went
+/// through
+async fn publish_alarm(outputs: &mut Outputs, sensor: &Sensor) -> bool {
+    let mut topic: String<TOPIC_LEN> = String::new();
+    if write!(
+        topic,
+        "{}/{}/{}",
+        MQTT_ALARMS_TOPIC, sensor.model, sensor.id
+    )
+    .is_err()
+    {
+        return true;
+    }
+    let state = sensor.alarm.unwrap_or("off");
+    info!("{} {}: alarm {}", sensor.model, sensor.id, state);
+    match outputs
+        .publish_diagnostics(topic.as_str(), state, Class::Alarms)
+        .await
+    {
+        Ok(_) => true,
+        Err(e) => {
+            warn!("Failed to publish MQTT message: {:?}", e);
+            false
+        }
+    }
+}
+
+/// Upload the report of the panic the unit last reset after and erase it
+/// once it's through, true if there's nothing left to upload
+#[cfg(feature = "postmortem")]
+async fn publish_panic(outputs: &mut Outputs) -> bool {
+    let Some((Kind::Panic, report)) = postmortem::read() else {
+        return true;
+    };
+    match outputs
+        .publish_diagnostics(MQTT_PANICS_TOPIC, report.as_str(), Class::Diagnostics)
+        .await
+    {
+        Ok(_) => {
+            info!("Panic report uploaded");
+            postmortem::clear();
+            true
+        }
+        Err(e) => {
+            warn!("Failed to publish MQTT message: {:?}", e);
+            false
+        }
+    }
+}
+
+/// Republish the alarms still latched, true if they all went through
+async fn publish_alarms(outputs: &mut Outputs) -> bool {
+    for idx in 0.. {
+        let Some(sensor) = REGISTRY.lock(|registry| {
+            registry
+                .borrow()
+                .iter()
+                .filter(|sensor| sensor.alarm.is_some())
+                .nth(idx)
+                .cloned()
+        }) else {
+            break;
+        };
+        if !publish_alarm(outputs, &sensor).await {
+            return false;
+        }
+    }
+    true
+}
+
+/// Clear the latched alarm of the sensor the payload names as `<model>/<id>`
+async fn clear_alarm(outputs: &mut Outputs, payload: &[u8]) {
+    let sensor = core::str::from_utf8(payload)
+        .ok()
+        .and_then(|payload| payload.trim().rsplit_once('/'))
+        .and_then(|(model, id)| Some((model, id.parse().ok()?)));
+    let Some((model, id)) = sensor else {
+        warn!("Invalid clear_alarm command, expected <model>/<id>");
+        return;
+    };
+    match REGISTRY.lock(|registry| registry.borrow_mut().clear_alarm(model, id)) {
+        Some(sensor) => {
+            publish_alarm(outputs, &sensor).await;
+        }
+        None => warn!("No alarm latched for {} {}", model, id),
+    }
+}
+
+/// Clear retained availability of all known sensors, so the broker doesn't
+/// keep stale state for sensors this unit may never hear again
+async fn clear_availability(mqtt: &mut Mqtt) {
+    for idx in 0.. {
+        let Some(sensor) = REGISTRY.lock(|registry| registry.borrow().iter().nth(idx).cloned())
+        else {
+            break;
+        };
+        let mut topic: String<TOPIC_LEN> = String::new();
+        if write!(topic, "sensors/{}/{}/availability", sensor.model, sensor.id).is_err() {
+            continue;
+        }
+        if let Err(e) = mqtt.clear(topic.as_str()).await {
+            warn!("Failed to publish MQTT message: {:?}", e);
+        }
+    }
+}
+
+/// Apply `<name> <min> <max>` or `reset`, anything else only reports
+fn set_timing(payload: &[u8]) {
+    let Ok(payload) = core::str::from_utf8(payload) else {
+        return;
+    };
+    let mut args = payload.split_whitespace();
+    match (args.next(), args.next(), args.next()) {
+        (Some("reset"), None, None) => timing::reset(),
+        (Some(name), Some(min), Some(max)) => {
+            let Some(timing) = timing::find(name) else {
+                warn!("Unknown timing {}", name);
+                return;
+            };
+            match (min.parse(), max.parse()) {
+                (Ok(min), Ok(max)) if timing.set(min..max) => {
+                    info!("{} set to {}..{}", name, min, max)
+                }
+                _ => warn!("Invalid range for {}: {} {}", name, min, max),
+            }
+        }
+        _ => {}
+    }
+}
+
+fn write_timings(out: &mut impl Write) -> fmt::Result {
+    write!(out, "{{")?;
+    for (idx, timing) in timing::TIMINGS.iter().enumerate() {
+        let range = timing.range();
+        if idx > 0 {
+            write!(out, ", ")?;
+        }
+        write!(
+            out,
+            "\"{}\" : [{}, {}]",
+            timing.name(),
+            range.start,
+            range.end
+        )?;
+    }
+    write!(out, " }}")
+}
+
+/// Network diagnostics with the active broker and uptime
+fn write_connectivity(
+    out: &mut impl Write,
+    connectivity: &Connectivity,
+    broker: &str,
+) -> fmt::Result {
+    connectivity.write(out)?;
+    write!(
+        out,
+        ", \"broker\" : \"{}\", \"uptime\" : {} }}",
+        broker,
+        get_timestamp()
+    )
+}
+
+/// Run network diagnostics and publish the result
+async fn publish_connectivity(outputs: &mut Outputs) {
+    let connectivity = outputs.mqtt.diagnose().await;
+    info!("Network: {:?}", connectivity);
+    let mut payload: String<PAYLOAD_LEN> = String::new();
+    if write_connectivity(&mut payload, &connectivity, outputs.mqtt.server()).is_err() {
+        warn!("Network diagnostics don't fit into buffer");
+        return;
+    }
+    if let Err(e) = outputs
+        .publish_diagnostics(MQTT_NETWORK_TOPIC, payload.as_str(), Class::Diagnostics)
+        .await
+    {
+        warn!("Failed to publish MQTT message: {:?}", e);
+    }
+}
+
+#[cfg(feature = "ota")]
+async fn publish_update(outputs: &mut Outputs, progress: Result<ota::Progress, ota::UpdateError>) {
+    let mut payload: String<PAYLOAD_LEN> = String::new();
+    let _ = match progress {
+        Ok(ota::Progress::Received { chunk, chunks }) => write!(
+            payload,
+            "{{\"state\":\"receiving\",\"chunk\":{},\"chunks\":{}}}",
+            chunk, chunks
+        ),
+        Ok(ota::Progress::Complete) => write!(payload, "{{\"state\":\"complete\"}}"),
+        Err(e) => {
+            warn!("Rejected firmware chunk: {:?}", e);
+            write!(
+                payload,
+                "{{\"state\":\"error\",\"reason\":\"{}\"}}",
+                e.key()
+            )
+        }
+    };
+    if let Err(e) = outputs
+        .publish_diagnostics(MQTT_OTA_TOPIC, payload.as_str(), Class::Diagnostics)
+        .await
+    {
+        warn!("Failed to publish MQTT message: {:?}", e);
+    }
+}
+
+/// Report active timings, retained so they can be checked at any time
+async fn publish_timings(outputs: &mut Outputs) {
+    let mut payload: String<PAYLOAD_LEN> = String::new();
+    if write_timings(&mut payload).is_err() {
+        warn!("Timings don't fit into buffer");
+        return;
+    }
+    if let Err(e) = outputs
+        .publish_diagnostics(MQTT_TIMING_TOPIC, payload.as_str(), Class::Diagnostics)
+        .await
+    {
+        warn!("Failed to publish MQTT message: {:?}", e);
+    }
+}
+
+/// Apply `<protocol> on|off` or `all`, anything else only reports
+fn set_protocols(payload: &[u8]) {
+    let Ok(payload) = core::str::from_utf8(payload) else {
+        return;
+    };
+    let mut args = payload.split_whitespace();
+    let (key, state) = match (args.next(), args.next(), args.next()) {
+        (Some("all"), None, None) => {
+            decoder::set_enabled_protocols(decoder::ALL_PROTOCOLS);
+            return;
+        }
+        (Some(key), Some(state), None) => (key, state),
+        _ => return,
+    };
+    let Some(idx) = decoder::find_protocol(key) else {
+        warn!("Unknown protocol {}", key);
+        return;
+    };
+    let enabled = decoder::enabled_protocols();
+    match state {
+        "on" => decoder::set_enabled_protocols(enabled | 1 << idx),
+        "off" => decoder::set_enabled_protocols(enabled & !(1 << idx)),
+        _ => {
+            warn!("Invalid state for {}: {}", key, state);
+            return;
+        }
+    }
+    info!("{} turned {}", key, state);
+}
+
+fn write_protocols(out: &mut impl Write) -> fmt::Result {
+    let enabled = decoder::enabled_protocols();
+    write!(out, "{{")?;
+    for (idx, proto) in PROTOCOLS.iter().enumerate() {
+        if idx > 0 {
+            write!(out, ", ")?;
+        }
+        write!(out, "\"{}\" : {}", proto.key, enabled & (1 << idx) != 0)?;
+    }
+    write!(out, " }}")
+}
+
+/// Report enabled protocols, retained so they can be checked at any time
+async fn publish_protocols(outputs: &mut Outputs) {
+    let mut payload: String<PAYLOAD_LEN> = String::new();
+    if write_protocols(&mut payload).is_err() {
+        warn!("Protocols don't fit into buffer");
+        return;
+    }
+    if let Err(e) = outputs
+        .publish_diagnostics(MQTT_PROTOCOLS_TOPIC, payload.as_str(), Class::Diagnostics)
+        .await
+    {
+        warn!("Failed to publish MQTT message: {:?}", e);
+    }
+}
+
+/// Apply a config blob as exported, anything but a JSON object only reports
+fn import_config(payload: &[u8]) {
+    let Ok(payload) = core::str::from_utf8(payload) else {
+        return;
+    };
+    if !payload.trim_start().starts_with('{') {
+        return;
+    }
+    match Config::current().merge(payload) {
+        Ok(config) => {
+            config.apply();
+            info!("Config imported");
+        }
+        Err(e) => warn!("Invalid config, nothing changed: {:?}", e),
+    }
+}
+
+/// Report runtime config, retained so it can be imported on other units
+async fn publish_config(outputs: &mut Outputs) {
+    let mut payload: String<CONFIG_LEN> = String::new();
+    if Config::current().write(&mut payload).is_err() {
+        warn!("Config doesn't fit into buffer");
+        return;
+    }
+    if let Err(e) = outputs
+        .publish_diagnostics(MQTT_CONFIG_TOPIC, payload.as_str(), Class::Diagnostics)
+        .await
+    {
+        warn!("Failed to publish MQTT message: {:?}", e);
+    }
+}
+
+/// Frames matching several protocols equally well are dropped rather than
+/// attributed to either of them
+fn pick(candidates: &Candidates) -> Option<SensorData> {
+    if candidates.len() > 1 {
+        for candidate in candidates.iter() {
+            info!(
+                "{} candidate, confidence {}",
+                candidate.data.model(),
+                candidate.confidence
+            );
+        }
+    }
+    match candidates.unambiguous() {
+        Some(candidate) => Some(candidate.data.clone()),
+        None => {
+            warn!("Ambiguous frame, dropping");
+            None
+        }
+    }
+}
+
+fn confirmed(command: &Command) -> bool {
+    if !COMMAND_TOKEN.is_empty() && command.payload == COMMAND_TOKEN.as_bytes() {
+        return true;
+    }
+    warn!("Ignoring {}: wrong confirmation token", command.topic);
+    false
+}
+
+/// Restart requested via MQTT command
+#[derive(Clone, Copy, Debug)]
+enum Restart {
+    Reboot,
+    /// Clear retained availability of the known sensors first, and erase
+    /// everything kept across resets
+    FactoryReset,
+    /// Boot firmware received over MQTT
+    #[cfg(feature = "ota")]
+    Update,
+}
+
+impl Restart {
+    fn key(self) -> &'static str {
+        match self {
+            Restart::Reboot => "reboot",
+            Restart::FactoryReset => "factory_reset",
+            #[cfg(feature = "ota")]
+            Restart::Update => "update",
+        }
+    }
+}
+
+/// Load what's retained on the broker for the unit: runtime config first, then
+/// sensor settings. True once both are in.
+async fn load_configs(mqtt: &mut Mqtt) -> bool {
+    load_config(mqtt).await && load_sensor_configs(mqtt).await
+}
+
+/// Import the runtime config retained on MQTT_CONFIG_TOPIC, the unit's own
+/// export, so it outlives flashing firmware that starts from the defaults.
+/// True once the broker could be asked.
+async fn load_config(mqtt: &mut Mqtt) -> bool {
+    let result = mqtt
+        .retained(MQTT_CONFIG_TOPIC, |_, payload| import_config(payload))
+        .await;
+    if let Err(e) = &result {
+        warn!("Failed to load config: {:?}", e);
+    }
+    result.is_ok()
+}
+
+/// Load sensor settings from the retained messages under
+/// MQTT_SENSOR_CONFIG_TOPICS, `<model>/<id>` ending their topics. True once
+/// the broker could be asked.
+async fn load_sensor_configs(mqtt: &mut Mqtt) -> bool {
+    let prefix = MQTT_SENSOR_CONFIG_TOPICS.trim_end_matches("+/+");
+    let result = mqtt
+        .retained(MQTT_SENSOR_CONFIG_TOPICS, |topic, payload| {
+            let Some((model, id)) = topic
+                .strip_prefix(prefix)
+                .and_then(|sensor| sensor.split_once('/'))
+            else {
+                return;
+            };
+            let Ok(id) = id.parse() else {
+                warn!("Invalid sensor id in {}", topic);
+                return;
+            };
+            let parsed = core::str::from_utf8(payload)
+                .map_err(|_| config::Error::Syntax)
+                .and_then(SensorConfig::parse);
+            match parsed {
+                Ok(settings) => {
+                    info!("Settings of {} {}: {:?}", model, id, settings);
+                    if !config::set_sensor(model, id, settings) {
+                        warn!("No room for settings of {} {}", model, id);
+                    }
+                }
+                Err(e) => warn!("Invalid settings of {} {}: {:?}", model, id, e),
+            }
+        })
+        .await;
+    if let Err(e) = &result {
+        warn!("Failed to load sensor settings: {:?}", e);
+    }
+    result.is_ok()
+}
+
+/// Handle the next pending command, if any. Restarts are left to the caller,
+/// which has the publish queue to flush.
+async fn handle_command(outputs: &mut Outputs) -> Option<Restart> {
+    let command = match outputs.mqtt.take_command(MQTT_COMMAND_TOPICS).await {
+        Ok(Some(command)) => command,
+        Ok(None) => return None,
+        Err(e) => {
+            warn!("Failed to fetch MQTT commands: {:?}", e);
+            return None;
+        }
+    };
+    info!("Got command {}", command.topic);
+    match command.topic.as_str() {
+        MQTT_GET_READINGS_TOPIC => publish_readings(outputs).await,
+        #[cfg(feature = "history")]
+        MQTT_GET_HISTORY_TOPIC => match core::str::from_utf8(&command.payload).map(str::trim) {
+            Ok("clear") => {
+                info!("Clearing reading log");
+                history::clear().await;
+            }
+            Ok(count) => publish_history(outputs, count.parse().unwrap_or(usize::MAX)).await,
+            Err(_) => warn!("Invalid history command"),
+        },
+        MQTT_CAPTURE_TOPIC => {
+            let count = core::str::from_utf8(&command.payload)
+                .ok()
+                .and_then(|payload| payload.trim().parse::<u8>().ok())
+                .unwrap_or(1)
+                .min(MAX_CAPTURES);
+            info!("Capturing next {} bursts", count);
+            CAPTURES.store(count, Ordering::Relaxed);
+        }
+        MQTT_GET_BURST_TOPIC => match core::str::from_utf8(&command.payload)
+            .ok()
+            .and_then(bursts::Query::parse)
+        {
+            Some(query) => publish_burst(outputs, query).await,
+            None => {
+                warn!("Invalid burst command, expected an index or @<seconds since Unix epoch>")
+            }
+        },
+        MQTT_LEARN_TOPIC => match core::str::from_utf8(&command.payload).map(str::trim) {
+            Ok("stop") => LEARN.store(0, Ordering::Relaxed),
+            Ok(count) => {
+                let count = count
+                    .parse::<u8>()
+                    .unwrap_or(LEARN_BURSTS)
+                    .min(MAX_LEARN_BURSTS);
+                info!("Learning protocol from next {} bursts", count);
+                LEARN.store(count, Ordering::Relaxed);
+            }
+            Err(_) => warn!("Invalid learn command"),
+        },
+        MQTT_SET_DEBUG_TOPIC => match core::str::from_utf8(&command.payload).map(str::trim) {
+            Ok("on" | "1") => {
+                info!("Publishing decode context of every burst");
+                DEBUG.store(true, Ordering::Relaxed);
+            }
+            Ok("off" | "0") => DEBUG.store(false, Ordering::Relaxed),
+            _ => warn!("Invalid debug command, expected on or off"),
+        },
+        MQTT_SET_TIME_TOPIC => {
+            match core::str::from_utf8(&command.payload)
+                .map(|payload| payload.trim().parse::<u64>())
+            {
+                // Anything jiff can't represent would panic when formatted
+                Ok(Ok(secs)) => match i64::try_from(secs)
+                    .ok()
+                    .and_then(|secs| jiff::Timestamp::from_second(secs).ok())
+                {
+                    Some(time) => SET_TIME.signal(time.as_microsecond() as u64),
+                    None => warn!("Time {} is out of range", secs),
+                },
+                _ => warn!("Invalid time, expected seconds since Unix epoch"),
+            }
+        }
+        MQTT_DIAGNOSE_TOPIC => publish_connectivity(outputs).await,
+        MQTT_TRANSMIT_TOPIC => transmit(&command.payload),
+        MQTT_CLEAR_ALARM_TOPIC => clear_alarm(outputs, &command.payload).await,
+        #[cfg(feature = "ota")]
+        MQTT_SET_OTA_TOPIC => {
+            let progress = ota::receive(&command.payload);
+            publish_update(outputs, progress).await;
+            if let Ok(ota::Progress::Complete) = progress {
+                return Some(Restart::Update);
+            }
+        }
+        MQTT_SET_TIMING_TOPIC => {
+            set_timing(&command.payload);
+            publish_timings(outputs).await;
+        }
+        MQTT_SET_PROTOCOLS_TOPIC => {
+            set_protocols(&command.payload);
+            publish_protocols(outputs).await;
+        }
+        MQTT_SET_CONFIG_TOPIC => {
+            import_config(&command.payload);
+            publish_config(outputs).await;
+            publish_timings(outputs).await;
+            publish_protocols(outputs).await;
+        }
+        MQTT_REBOOT_TOPIC if confirmed(&command) => return Some(Restart::Reboot),
+        MQTT_FACTORY_RESET_TOPIC if confirmed(&command) => return Some(Restart::FactoryReset),
+        MQTT_REBOOT_TOPIC | MQTT_FACTORY_RESET_TOPIC => {}
+        topic => warn!("Unknown command {}", topic),
+    }
+    None
+}
+
+/// Temperature in C with up to one decimal, in 0.1 C
+fn parse_tenths(value: &str) -> Option<i16> {
+    let (negative, value) = match value.strip_prefix('-') {
+        Some(value) => (true, value),
+        None => (false, value),
+    };
+    let (whole, tenth) = value.split_once('.').unwrap_or((value, "0"));
+    if tenth.len() != 1 {
+        return None;
+    }
+    let tenths = whole.parse::<i16>().ok()?.checked_mul(10)? + tenth.parse::<i16>().ok()?;
+    Some(if negative { -tenths } else { tenths })
+}
+
+/// Queue a Nexus-TH frame for the transmitter, the payload is
+/// `<temperature>,<humidity>[,<id>[,<channel>]]`
+fn transmit(payload: &[u8]) {
+    if TX_PIN.is_none() || cfg!(feature = "replay") {
+        warn!("No transmitter, TX_PIN isn't set");
+        return;
+    }
+    let mut fields = core::str::from_utf8(payload)
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim);
+    let temp = fields.next().and_then(parse_tenths);
+    let humidity = fields.next().and_then(|humidity| humidity.parse().ok());
+    let id = fields.next().map_or(Some(TX_ID), |id| id.parse().ok());
+    let channel = fields
+        .next()
+        .map_or(Some(1), |channel| channel.parse().ok());
+    let edges = match (temp, humidity, id, channel) {
+        (Some(temp), Some(humidity), Some(id), Some(channel)) => {
+            encode::nexus(id, channel, true, temp, humidity)
+        }
+        _ => None,
+    };
+    let Some(edges) = edges else {
+        warn!("Invalid transmit command, expected <temperature>,<humidity>[,<id>[,<channel>]]");
+        return;
+    };
+    let mut padded = [(false, TX_GAP_US); NEXUS_EDGES + 1];
+    padded[..NEXUS_EDGES].copy_from_slice(&edges);
+    let mut frame = [Symbol::default(); TX_LEN];
+    pulses_from_edges(&padded, &mut frame);
+    info!("Transmitting Nexus-TH frame {} times", TX_REPEATS);
+    TRANSMIT.signal(frame);
+}
+
+/// Queue the burst a doorbell press of `data` was received in for the
+/// transmitter, so that the doorbell's own chime rings too
+fn chime(data: &SensorData, pulses: &[Symbol]) {
+    if TX_PIN.is_none() || cfg!(feature = "replay") {
+        warn!("No transmitter for the chime, TX_PIN isn't set");
+        return;
+    }
+    let mut frame = [Symbol::end_marker(); TX_LEN];
+    let received = pulses.iter().take_while(|pulse| pulse.length1 != 0);
+    for (symbol, pulse) in frame.iter_mut().zip(received) {
+        *symbol = *pulse;
+        // Capture ends with a zero length, repeats are set apart by a pause
+        if symbol.length2 == 0 {
+            *symbol = Symbol::new(symbol.level1, symbol.length1, false, TX_GAP_US);
+        }
+    }
+    info!("Ringing the chime of {} {}", data.model(), data.id);
+    TRANSMIT.signal(frame);
+}
+
+/// Publish what's still queued and tell the broker the unit is going away,
+/// then reset. Gives up on the broker after RESTART_TIMEOUT_SECS.
+async fn restart<const N: usize, const E: usize>(
+    outputs: &mut Outputs,
+    queue: &mut FairQueue<N>,
+    pending_errors: &mut Pending<E>,
+    restart: Restart,
+) -> ! {
+    info!("Restarting ({})...", restart.key());
+    let flush = async {
+        while let Some(reading) = queue.pop() {
+            let mut topic: String<TOPIC_LEN> = String::new();
+            let mut payload: String<PAYLOAD_LEN> = String::new();
+            if write!(
+                topic,
+                "sensors/{}/{}",
+                reading.data.model(),
+                reading.data.id
+            )
+            .is_err()
+                || write_sensor_payload(
+                    &mut payload,
+                    &reading.data,
+                    reading.timestamp,
+                    reading.expire_after,
+                    reading.repeats,
+                    reading.count,
+                )
+                .is_err()
+            {
+                continue;
+            }
+            #[cfg(feature = "history")]
+            history::append(payload.as_str());
+            if let Err(e) = outputs
+                .publish_sensor(&reading.data, topic.as_str(), payload.as_str())
+                .await
+            {
+                warn!("Failed to publish MQTT message: {:?}", e);
+            }
+        }
+        publish_errors(outputs, pending_errors).await;
+        if let Restart::FactoryReset = restart {
+            clear_availability(outputs.mqtt).await;
+        }
+        let mut payload: String<PAYLOAD_LEN> = String::new();
+        if write!(
+            payload,
+            "{{\"state\" : \"offline\", \"reason\" : \"{}\", \"uptime\" : {} }}",
+            restart.key(),
+            get_timestamp()
+        )
+        .is_ok()
+            && let Err(e) = outputs.announce(payload.as_str()).await
+        {
+            warn!("Failed to publish MQTT message: {:?}", e);
+        }
+    };
+    if with_timeout(Duration::from_secs(RESTART_TIMEOUT_SECS), flush)
+        .await
+        .is_err()
+    {
+        warn!("Broker is too slow, restarting anyway");
+    }
+    if let Restart::FactoryReset = restart {
+        wipe().await;
+    }
+    esp_hal::system::software_reset();
+}
+
+/// Erase provisioned credentials, the reading log, rain totals, postmortem
+/// reports and the state kept in RTC memory, so the unit boots as if new
+async fn wipe() {
+    #[cfg(feature = "provisioning")]
+    if let Err(e) = credentials::clear() {
+        warn!("Failed to clear credentials: {:?}", e);
+    }
+    #[cfg(feature = "history")]
+    history::clear().await;
+    rain::clear();
+    #[cfg(feature = "postmortem")]
+    postmortem::clear();
+    // Last, nothing may save it again before the reset
+    snapshot::clear();
+}
+
+fn write_status(
+    out: &mut impl Write,
+    boot_time: i64,
+    broker: &str,
+    link: &Link,
+    self_test: &SelfTest,
+) -> fmt::Result {
+    let built = BUILD_TIMESTAMP
+        .parse()
+        .ok()
+        .and_then(|secs| jiff::Timestamp::from_second(secs).ok())
+        .unwrap_or_default();
+    write!(
+        out,
+        "{{\"state\" : \"online\", \"version\" : \"{}\", \"git\" : \"{}\", \"built\" : \"{}\", \"boot\" : \"{}\", \"broker\" : \"{}\"",
+        VERSION,
+        GIT_HASH,
+        built.strftime("%Y-%m-%d %H:%M:%S UTC"),
+        jiff::Timestamp::from_microsecond(boot_time)
+            .unwrap_or_default()
+            .strftime("%Y-%m-%d %H:%M:%S UTC"),
+        broker,
+    )?;
+    write!(
+        out,
+        ", \"payload_format\" : \"{}\", \"network\" : ",
+        PAYLOAD_FORMAT.key()
+    )?;
+    link.write(out)?;
+    write!(out, ", \"self_test\" : ")?;
+    self_test.write(out)?;
+    match esp_hal::system::reset_reason() {
+        Some(reason) => write!(out, ", \"reset\" : \"{:?}\" }}", reason),
+        None => write!(out, ", \"reset\" : \"unknown\" }}"),
+    }
+}
+
+fn write_health(out: &mut impl Write, health: &Health) -> fmt::Result {
+    write!(out, "{{")?;
+    if let Some(supply) = &health.supply {
+        write!(
+            out,
+            "\"supply_mV\" : {}, \"supply_min_mV\" : {}, \"supply_low\" : {}, ",
+            supply.avg_mv,
+            supply.min_mv,
+            supply.low()
+        )?;
+    }
+    if let Some(die_temp) = health.die_temp {
+        write!(out, "\"die_temp_C\" : ")?;
+        write_tenths(out, die_temp as i32)?;
+        write!(out, ", ")?;
+    }
+    let traffic = &health.traffic;
+    write!(
+        out,
+        "\"rf\" : {{\"bursts\" : {}, \"bursts_per_min\" : {}, \"peak_bursts_per_min\" : {}, \"busy_pct\" : ",
+        traffic.bursts, traffic.bursts_per_min, traffic.peak_bursts_per_min
+    )?;
+    write_tenths(out, traffic.busy_permille as i32)?;
+    write!(out, ", \"carrier_pct\" : ")?;
+    write_tenths(out, traffic.carrier_permille as i32)?;
+    write!(out, " }}, ")?;
+    write!(
+        out,
+        "\"dropped_bursts\" : {}, \"rx_overflows\" : {}, \"rx_errors\" : {}, \"empty_bursts\" : {}, \"log_dropped\" : {}, \"uptime\" : {} }}",
+        DROPPED_BURSTS.load(Ordering::Relaxed),
+        RX_OVERFLOWS.load(Ordering::Relaxed),
+        RX_ERRORS.load(Ordering::Relaxed),
+        EMPTY_BURSTS.load(Ordering::Relaxed),
+        logger::dropped(),
+        get_timestamp()
+    )
+}
+
+#[cfg(feature = "bench")]
+fn write_percentiles(out: &mut impl Write, name: &str, p: &Percentiles) -> fmt::Result {
+    write!(
+        out,
+        "\"{}\" : {{\"count\" : {}, \"p50\" : {}, \"p90\" : {}, \"p99\" : {}, \"max\" : {} }}",
+        name, p.count, p.p50, p.p90, p.p99, p.max
+    )
+}
+
+/// Decode times per protocol and latencies, in us, leaving out what wasn't
+/// measured yet
+#[cfg(feature = "bench")]
+fn write_bench(out: &mut impl Write) -> fmt::Result {
+    write!(out, "{{\"decode_us\" : {{")?;
+    let mut first = true;
+    for (idx, proto) in PROTOCOLS.iter().enumerate() {
+        let Some(p) = bench::decode(idx) else {
+            continue;
+        };
+        write!(out, "{}", if first { " " } else { ", " })?;
+        write_percentiles(out, proto.key, &p)?;
+        first = false;
+    }
+    write!(out, " }}, \"latency_us\" : {{")?;
+    let latency = bench::latency();
+    let mut first = true;
+    for (name, p) in [
+        ("decoded", latency.decoded),
+        ("event", latency.event),
+        ("periodic", latency.periodic),
+    ] {
+        let Some(p) = p else {
+            continue;
+        };
+        write!(out, "{}", if first { " " } else { ", " })?;
+        write_percentiles(out, name, &p)?;
+        first = false;
+    }
+    write!(out, " }} }}")
+}
 
-use embassy_futures::select::{Either, select};
-use embassy_net::Stack;
+#[cfg(feature = "bench")]
+async fn publish_bench(outputs: &mut Outputs) {
+    let mut payload: String<BENCH_PAYLOAD_LEN> = String::new();
+    if write_bench(&mut payload).is_err() {
+        warn!("Benchmark doesn't fit into buffer");
+        return;
+    }
+    if let Err(e) = outputs
+        .publish_diagnostics(MQTT_BENCH_TOPIC, payload.as_str(), Class::Diagnostics)
+        .await
+    {
+        warn!("Failed to publish MQTT message: {:?}", e);
+    }
+}
 
-use static_cell::StaticCell;
+/// Span timings of every phase, in us, leaving out phases not entered yet
+#[cfg(feature = "trace")]
+fn write_trace(out: &mut impl Write) -> fmt::Result {
+    let now = time::monotonic();
+    write!(out, "{{\"uptime\" : {}", get_timestamp())?;
+    for phase in Phase::ALL {
+        let timing = trace::timing(phase);
+        let Some(mean_us) = timing.mean_us() else {
+            continue;
+        };
+        write!(
+            out,
+            ", \"{}\" : {{\"count\" : {}, \"total_us\" : {}, \"mean_us\" : {}, \"max_us\" : {}",
+            phase.key(),
+            timing.count,
+            timing.total_us,
+            mean_us,
+            timing.max_us
+        )?;
+        if let Some(open_us) = timing.open_us(now) {
+            write!(out, ", \"open_us\" : {}", open_us)?;
+        }
+        write!(out, " }}")?;
+    }
+    write!(out, " }}")
+}
 
-use alloc::format;
+#[cfg(feature = "trace")]
+async fn publish_trace(outputs: &mut Outputs) {
+    let mut payload: String<PAYLOAD_LEN> = String::new();
+    if write_trace(&mut payload).is_err() {
+        warn!("Trace doesn't fit into buffer");
+        return;
+    }
+    if let Err(e) = outputs
+        .publish_diagnostics(MQTT_TRACE_TOPIC, payload.as_str(), Class::Diagnostics)
+        .await
+    {
+        warn!("Failed to publish MQTT message: {:?}", e);
+    }
+}
 
-extern crate alloc;
+fn write_counts(out: &mut impl Write, backends: &[Counts]) -> fmt::Result {
+    for (backend, counts) in BACKENDS.iter().zip(backends) {
+        let Some(pct) = counts.success_pct() else {
+            continue;
+        };
+        write!(
+            out,
+            ", \"{}\" : {{\"attempted\" : {}, \"failed\" : {}, \"success_pct\" : {} }}",
+            backend.key(),
+            counts.attempted,
+            counts.failed(),
+            pct
+        )?;
+    }
+    Ok(())
+}
 
-// When you are okay with using a nightly compiler it's better to use https://docs.rs/static_cell/2.1.0/static_cell/macro.make_static.html
-macro_rules! mk_static {
-    ($t:ty,$val:expr) => {{
-        static STATIC_CELL: static_cell::StaticCell<$t> = static_cell::StaticCell::new();
-        #[deny(unused_attributes)]
-        let x = STATIC_CELL.uninit().write($val);
-        x
-    }};
+/// Publish attempts by backend, overall and per sensor, leaving out backends
+/// that weren't tried
+fn write_publishes(out: &mut impl Write) -> fmt::Result {
+    write!(out, "{{\"uptime\" : {}", get_timestamp())?;
+    write_counts(out, &pubstats::total())?;
+    write!(out, ", \"sensors\" : [")?;
+    for (idx, sensor) in pubstats::sensors().iter().enumerate() {
+        write!(
+            out,
+            "{}{{\"model\" : \"{}\", \"id\" : {}, \"channel\" : {}",
+            if idx == 0 { "" } else { ", " },
+            sensor.model,
+            sensor.id,
+            sensor.channel
+        )?;
+        write_counts(out, &sensor.backends)?;
+        write!(out, " }}")?;
+    }
+    write!(out, "] }}")
 }
 
-// This creates a default app-descriptor required by the esp-idf bootloader.
-// For more information see: <https://docs.espressif.com/projects/esp-idf/en/stable/esp32/api-reference/system/app_image_format.html#application-description>
-esp_bootloader_esp_idf::esp_app_desc!();
+/// Publish the average climate of the zone of the sensor `data` is from, if
+/// it's in one
+async fn publish_zone(outputs: &mut Outputs, data: &SensorData, timestamp: i64) {
+    let zone = config::zone(data);
+    if zone.is_empty() {
+        return;
+    }
+    let Some(climate) = zone::climate(&zone) else {
+        return;
+    };
+    let mut topic: String<TOPIC_LEN> = String::new();
+    let mut payload: String<PAYLOAD_LEN> = String::new();
+    if write!(topic, "{}/{}", MQTT_ZONES_TOPIC, zone).is_err()
+        || write_zone(&mut payload, &zone, &climate, timestamp).is_err()
+    {
+        warn!("Zone {} doesn't fit into buffer", zone);
+        return;
+    }
+    if let Err(e) = outputs
+        .publish_reading(topic.as_str(), payload.as_str())
+        .await
+    {
+        warn!("Failed to publish MQTT message: {:?}", e);
+    }
+}
 
-static SHARED_STACK: StaticCell<Mutex<NoopRawMutex, Stack<'static>>> = StaticCell::new();
+fn write_zone(
+    out: &mut impl Write,
+    zone: &str,
+    climate: &ZoneClimate,
+    timestamp: i64,
+) -> fmt::Result {
+    write!(out, "{{")?;
+    write_time(out, timestamp)?;
+    write!(
+        out,
+        ", \"zone\" : \"{}\", \"sensors\" : {}, \"temperature_C\" : ",
+        zone, climate.sensors
+    )?;
+    write_tenths(out, climate.temp_decicelsius.into())?;
+    if let Some(humidity) = climate.humidity {
+        write!(out, ", \"humidity\" : {}", humidity)?;
+    }
+    write!(out, ", \"uptime\" : {} }}", get_timestamp())
+}
 
-static MQTT_CHANNEL: Channel<CriticalSectionRawMutex, (SensorData, i64), 2> = Channel::new();
+/// Publish the current conditions of the station `data` came from, if it is
+/// one, to MQTT_STATIONS_TOPIC
+async fn publish_station(outputs: &mut Outputs, data: &SensorData, timestamp: i64) {
+    let Some(station) = station::update(data) else {
+        return;
+    };
+    let mut topic: String<TOPIC_LEN> = String::new();
+    let mut payload: String<PAYLOAD_LEN> = String::new();
+    if write!(
+        topic,
+        "{}/{}/{}",
+        MQTT_STATIONS_TOPIC,
+        data.model(),
+        data.id
+    )
+    .is_err()
+        || write_station(&mut payload, &station, timestamp).is_err()
+    {
+        warn!("Conditions of {} don't fit into buffer", data.model());
+        return;
+    }
+    if let Err(e) = outputs
+        .publish_reading(topic.as_str(), payload.as_str())
+        .await
+    {
+        warn!("Failed to publish MQTT message: {:?}", e);
+    }
+}
 
-fn get_timestamp() -> u64 {
-    esp_hal::time::Instant::now()
-        .duration_since_epoch()
-        .as_millis()
+/// Same as a reading of the station, with every quantity it has sent
+fn write_station(out: &mut impl Write, station: &Station, timestamp: i64) -> fmt::Result {
+    write_header(out, &station.data, timestamp)?;
+    for reading in station.readings(time::monotonic()) {
+        write_reading(out, &reading)?;
+    }
+    write!(out, ", \"uptime\" : {} }}", get_timestamp())
+}
+
+/// Publish statistics, along with every health report
+async fn publish_publishes(outputs: &mut Outputs) {
+    let mut payload: String<PUBLISHES_PAYLOAD_LEN> = String::new();
+    if write_publishes(&mut payload).is_err() {
+        warn!("Publish statistics don't fit into buffer");
+        return;
+    }
+    if let Err(e) = outputs
+        .publish_diagnostics(MQTT_PUBLISHES_TOPIC, payload.as_str(), Class::Diagnostics)
+        .await
+    {
+        warn!("Failed to publish MQTT message: {:?}", e);
+    }
+}
+
+/// Temperature or other value * 10 as decimal
+fn write_tenths(out: &mut impl Write, value: i32) -> fmt::Result {
+    let sign = if value < 0 { "-" } else { "" };
+    let value = value.unsigned_abs();
+    write!(out, "{}{}.{}", sign, value / 10, value % 10)
+}
+
+fn write_sensor_summary(out: &mut impl Write, stats: &SensorStats, timestamp: i64) -> fmt::Result {
+    write!(out, "{{")?;
+    write_time(out, timestamp)?;
+    write!(
+        out,
+        ", \"model\" : \"{}\", \"id\" : {}, \"channel\" : {}, \"readings\" : {}",
+        stats.model, stats.id, stats.channel, stats.readings
+    )?;
+    if let Some(avg) = stats.temperature.avg() {
+        write!(out, ", \"temperature_C\" : {{\"min\" : ")?;
+        write_tenths(out, stats.temperature.min)?;
+        write!(out, ", \"max\" : ")?;
+        write_tenths(out, stats.temperature.max)?;
+        write!(out, ", \"avg\" : ")?;
+        write_tenths(out, avg)?;
+        write!(out, " }}")?;
+    }
+    if let Some(avg) = stats.humidity.avg() {
+        write!(
+            out,
+            ", \"humidity\" : {{\"min\" : {}, \"max\" : {}, \"avg\" : {} }}",
+            stats.humidity.min, stats.humidity.max, avg
+        )?;
+    }
+    write!(out, " }}")
+}
+
+fn write_summary(
+    out: &mut impl Write,
+    summary: &Summary<SUMMARY_LEN>,
+    timestamp: i64,
+) -> fmt::Result {
+    write!(out, "{{")?;
+    write_time(out, timestamp)?;
+    write!(
+        out,
+        ", \"uptime\" : {}, \"sensors\" : {}, \"decode_errors\" : {}, \"publish_failures\" : {}, \"rate_limited\" : {} }}",
+        get_timestamp(),
+        summary.sensors.len(),
+        summary.decode_errors,
+        summary.publish_failures,
+        summary.rate_limited
+    )
+}
+
+/// Publish statistics of the past day, one message per sensor and an overall
+/// one, then start over
+async fn publish_summary(outputs: &mut Outputs, timestamp: i64) {
+    let summary = SUMMARY.lock(|summary| {
+        let mut summary = summary.borrow_mut();
+        let last = summary.clone();
+        summary.clear();
+        last
+    });
+    info!("Publishing daily summary");
+    for stats in summary.sensors.iter() {
+        let mut topic: String<TOPIC_LEN> = String::new();
+        let mut payload: String<PAYLOAD_LEN> = String::new();
+        if write!(topic, "sensors/{}/{}/summary", stats.model, stats.id).is_err()
+            || write_sensor_summary(&mut payload, stats, timestamp).is_err()
+        {
+            warn!("Summary for {} doesn't fit into buffer", stats.model);
+            continue;
+        }
+        if let Err(e) = outputs
+            .publish_reading(topic.as_str(), payload.as_str())
+            .await
+        {
+            warn!("Failed to publish MQTT message: {:?}", e);
+        }
+    }
+    let mut payload: String<PAYLOAD_LEN> = String::new();
+    if write_summary(&mut payload, &summary, timestamp).is_err() {
+        warn!("Summary doesn't fit into buffer");
+        return;
+    }
+    if let Err(e) = outputs
+        .publish_diagnostics(MQTT_SUMMARY_TOPIC, payload.as_str(), Class::Diagnostics)
+        .await
+    {
+        warn!("Failed to publish MQTT message: {:?}", e);
+    }
+}
+
+/// Publish every value of the reading to its own ESPHome style state topic.
+/// Sensors not in `announced` yet are announced for discovery first.
+async fn publish_esphome(
+    mqtt: &mut Mqtt,
+    data: &SensorData,
+    announced: &mut Vec<(&'static str, u32), REGISTRY_LEN>,
+) {
+    let states = esphome::states(data);
+    let key = (data.model(), data.id);
+    if !announced.contains(&key) {
+        let mut availability: String<TOPIC_LEN> = String::new();
+        if write!(
+            availability,
+            "sensors/{}/{}/availability",
+            data.model(),
+            data.id
+        )
+        .is_err()
+        {
+            return;
+        }
+        // Event sensors never go offline
+        let availability = (!data.is_event()).then_some(availability.as_str());
+        let mut configs: Vec<(String<TOPIC_LEN>, String<PAYLOAD_LEN>), MAX_ENTITIES> = Vec::new();
+        for (entity, _) in &states {
+            let mut topic = String::new();
+            let mut payload = String::new();
+            if esphome::write_config_topic(&mut topic, data, entity).is_err()
+                || esphome::write_config(&mut payload, data, entity, availability).is_err()
+            {
+                warn!(
+                    "Discovery config for {} doesn't fit into buffer",
+                    data.model()
+                );
+                return;
+            }
+            let _ = configs.push((topic, payload));
+        }
+        let messages: Vec<(&str, &str), MAX_ENTITIES> = configs
+            .iter()
+            .map(|(topic, payload)| (topic.as_str(), payload.as_str()))
+            .collect();
+        if let Err(e) = mqtt.publish_all(&messages, Class::Diagnostics).await {
+            warn!("Failed to publish MQTT message: {:?}", e);
+            return;
+        }
+        // Forget everyone once full, announcing again does no harm
+        if announced.is_full() {
+            announced.clear();
+        }
+        let _ = announced.push(key);
+    }
+
+    let mut topics: Vec<String<TOPIC_LEN>, MAX_ENTITIES> = Vec::new();
+    for (entity, _) in &states {
+        let mut topic = String::new();
+        if esphome::write_state_topic(&mut topic, data, entity).is_err() {
+            warn!("MQTT topic for {} doesn't fit into buffer", data.model());
+            return;
+        }
+        let _ = topics.push(topic);
+    }
+    let messages: Vec<(&str, &str), MAX_ENTITIES> = topics
+        .iter()
+        .zip(&states)
+        .map(|(topic, (_, state))| (topic.as_str(), state.as_str()))
+        .collect();
+    if let Err(e) = mqtt.publish_all(&messages, Class::Readings).await {
+        warn!("Failed to publish MQTT message: {:?}", e);
+    }
+}
+
+fn write_error(out: &mut impl Write, report: &Report) -> fmt::Result {
+    write!(
+        out,
+        "{{\"module\" : \"{}\", \"code\" : {}, \"error\" : \"{}\", \"detail\" : \"{}\", \"count\" : {}, \"uptime\" : {} }}",
+        report.code.module(),
+        report.code as u16,
+        report.code.name(),
+        report.detail,
+        report.count,
+        get_timestamp()
+    )
+}
+
+/// Publish pending errors to `MQTT_ERRORS_TOPIC/<module>`, the ones that
+/// can't be published yet are kept for later
+async fn publish_errors<const N: usize>(outputs: &mut Outputs, pending: &mut Pending<N>) {
+    while let Some(report) = pending.first() {
+        let mut topic: String<TOPIC_LEN> = String::new();
+        let mut payload: String<PAYLOAD_LEN> = String::new();
+        if write!(topic, "{}/{}", MQTT_ERRORS_TOPIC, report.code.module()).is_err()
+            || write_error(&mut payload, report).is_err()
+        {
+            pending.remove_first();
+            continue;
+        }
+        if let Err(e) = outputs
+            .publish_diagnostics(topic.as_str(), payload.as_str(), Class::Alerts)
+            .await
+        {
+            warn!("Failed to publish MQTT message: {:?}", e);
+            return;
+        }
+        pending.remove_first();
+    }
+}
+
+/// Announce firmware build, active broker and where the unit is on the
+/// network, retained so every unit can be checked at any time. Returns the
+/// broker and network announced, if it went through.
+async fn publish_status(
+    outputs: &mut Outputs,
+    boot_time: i64,
+    self_test: &SelfTest,
+) -> Option<(&'static str, Link)> {
+    let broker = outputs.mqtt.server();
+    let link = outputs.mqtt.link().await;
+    let mut payload: String<DIAGNOSTICS_LEN> = String::new();
+    if write_status(&mut payload, boot_time, broker, &link, self_test).is_err() {
+        warn!("Status doesn't fit into buffer");
+        return None;
+    }
+    latest::set_status(payload.as_str());
+    if let Err(e) = outputs.announce(payload.as_str()).await {
+        warn!("Failed to publish MQTT message: {:?}", e);
+        return None;
+    }
+    // Firmware got as far as the broker, an update is good to keep
+    #[cfg(feature = "ota")]
+    ota::confirm();
+    Some((broker, link))
+}
+
+/// RMT peripheral, the pin the receiver is wired to and the one of the
+/// transmitter if there is one, or recorded dumps standing in for them
+#[cfg(not(feature = "replay"))]
+type Input = (
+    Rmt<'static, Blocking>,
+    AnyPin<'static>,
+    Option<AnyPin<'static>>,
+);
+#[cfg(feature = "replay")]
+type Input = Replay;
+
+/// Receives bursts and queues them up for the main loop. It runs on a higher
+/// priority executor, so that bursts aren't missed while the main loop or
+/// the network stack are busy. Its RX channel is rebuilt whenever the
+/// thresholds in rx::current() change.
+#[embassy_executor::task]
+async fn receiver(input: Input) {
+    #[cfg(feature = "replay")]
+    let mut channel = input;
+    #[cfg(not(feature = "replay"))]
+    let (mut rx_creator, mut pin, tx_channel) = {
+        let (rmt, pin, tx_pin) = input;
+        // Async driver takes the RMT interrupt on the executor it runs on
+        let rmt = rmt.into_async();
+        #[cfg(feature = "esp32")]
+        let rx_channel = rmt.channel0;
+        #[cfg(any(feature = "esp32c3", feature = "esp32c6"))]
+        let rx_channel = rmt.channel2;
+        #[cfg(feature = "esp32s3")]
+        let rx_channel = rmt.channel4;
+        // Channel 0 of ESP32 receives into the memory of channel 1 as well
+        #[cfg(feature = "esp32")]
+        let tx_creator = rmt.channel2;
+        #[cfg(not(feature = "esp32"))]
+        let tx_creator = rmt.channel0;
+        // Frames are transmitted as they're encoded, in 1 uS ticks
+        let tx_config = TxChannelConfig::default()
+            .with_clk_divider(RMT_SOURCE_MHZ as u8)
+            .with_idle_output(true)
+            .with_idle_output_level(Level::Low);
+        let tx_channel = tx_pin.map(|tx_pin| {
+            tx_creator
+                .configure_tx(tx_pin, tx_config)
+                .expect("Failed to configure RMT TX channel")
+        });
+        (rx_channel, pin, tx_channel)
+    };
+    // Received into while every pooled buffer is in flight, to be dropped
+    let mut spare: [Symbol; MAX_SYMBOLS] = [Symbol::default(); MAX_SYMBOLS];
+    // Errors are counted as they come, but reported once a minute at most
+    let mut report = Interval::immediate(Duration::from_secs(60));
+    airtime::start();
+    let receive = async {
+        loop {
+            let settings = rx::current();
+            // Dropping the previous channel has freed it up again
+            #[cfg(not(feature = "replay"))]
+            let mut channel = {
+                let rx_config = RxChannelConfig::default()
+                    .with_clk_divider(RMT_CLK_DIVIDER)
+                    // Validated along with the rest of the config
+                    .with_idle_threshold(settings.idle_ticks().unwrap_or_default())
+                    .with_filter_threshold(settings.filter_cycles)
+                    .with_memsize(2); // Hideki frames don't fit into a single 64 symbol block
+                let pin = InputSignal::from(pin.reborrow()).with_input_inverter(RX_INVERT);
+                rx_creator
+                    .reborrow()
+                    .configure_rx(pin, rx_config)
+                    .expect("Failed to configure RMT RX channel")
+            };
+            while rx::current() == settings {
+                // Receive the data as series of PulseCode. For Nexus-TH, it will be
+                // 36 symbols + terminator. High pulse (carrier present) has a fixed width of
+                // 350-650 uS (actual width likely depends on battery voltage),
+                // The actual data is encoded in the lenght of the low pulse (pauses)
+                // 1 is 1650-2150 uS, 0 is 800-1100 uS
+                //
+                // On ESP32 RMT can count the lenght of pulses for us, simplifying the decoding
+                let mut burst = Burst::alloc();
+                let data = match &mut burst {
+                    Some(burst) => burst.buffer(),
+                    None => &mut spare,
+                };
+                let received = channel.receive(data).await;
+                #[cfg(feature = "trace")]
+                let _span = trace::enter(Phase::Capture);
+                match (received, burst) {
+                    (Ok(0), _) => {
+                        count(&EMPTY_BURSTS);
+                        airtime::record(&[]);
+                    }
+                    (Ok(symbol_count), Some(mut burst)) => {
+                        let mut symbol_count = symbol_count.min(MAX_SYMBOLS);
+                        #[cfg(not(feature = "replay"))]
+                        decoder::normalize(
+                            &mut burst.buffer()[..symbol_count],
+                            RMT_CLK_DIVIDER.into(),
+                            RMT_SOURCE_MHZ,
+                        );
+                        if settings.glitch_us != 0 {
+                            symbol_count = decoder::merge_glitches(
+                                &mut burst.buffer()[..symbol_count],
+                                settings.glitch_us,
+                            );
+                        }
+                        burst.set_len(symbol_count);
+                        // Arrival times and lengths of noise are anyone's guess
+                        let first = burst.buffer()[0];
+                        entropy::stir(
+                            burst.received()
+                                ^ ((first.length1 as u64) << 48)
+                                ^ ((first.length2 as u64) << 32),
+                        );
+                        airtime::record(&burst.buffer()[..symbol_count]);
+                        // BURSTS has room for every pooled buffer
+                        let _ = BURSTS.try_send(burst);
+                    }
+                    (Ok(symbol_count), None) => {
+                        count(&DROPPED_BURSTS);
+                        let pulses = &mut spare[..symbol_count.min(MAX_SYMBOLS)];
+                        #[cfg(not(feature = "replay"))]
+                        decoder::normalize(pulses, RMT_CLK_DIVIDER.into(), RMT_SOURCE_MHZ);
+                        airtime::record(pulses);
+                    }
+                    (Err(e), _) => {
+                        match e {
+                            RmtError::Overflow | RmtError::ReceiverError => count(&RX_OVERFLOWS),
+                            _ => count(&RX_ERRORS),
+                        }
+                        if report.due() {
+                            warn!("RMT receive failed: {:?}", e);
+                            errors::report(Code::ReceiveFailed, format_args!("{:?}", e));
+                        }
+                    }
+                }
+            }
+            info!("Receiver thresholds changed, rebuilding RX channel");
+        }
+    };
+    #[cfg(not(feature = "replay"))]
+    let transmit = async {
+        let Some(mut tx_channel) = tx_channel else {
+            return core::future::pending().await;
+        };
+        loop {
+            let frame = TRANSMIT.wait().await;
+            for _ in 0..TX_REPEATS {
+                if let Err(e) = tx_channel.transmit(&frame).await {
+                    warn!("RMT transmit failed: {:?}", e);
+                    break;
+                }
+            }
+        }
+    };
+    #[cfg(feature = "replay")]
+    let transmit = core::future::pending::<()>();
+    // Frames transmitted are received like any other, a receiver in range or
+    // wired to the transmitter's pin picks them up
+    join(receive, transmit).await;
+}
+
+/// Bump a receiver counter. Only the receiver task writes them, so there's no
+/// need for an atomic add, which RISC-V chips without atomics lack.
+fn count(counter: &AtomicU32) {
+    counter.store(
+        counter.load(Ordering::Relaxed).wrapping_add(1),
+        Ordering::Relaxed,
+    );
+}
+
+/// Decodes bursts as they're received and hands them to the main loop, which
+/// keeps track of sensors and publishes readings
+#[embassy_executor::task]
+async fn decoder() {
+    loop {
+        let pulses = BURSTS.receive().await;
+        #[cfg(feature = "trace")]
+        let span = trace::enter(Phase::Decode);
+        #[cfg(not(feature = "bench"))]
+        let result = decoder::decode_ranked(&pulses, 1);
+        #[cfg(feature = "bench")]
+        let result = {
+            let now = || Instant::now().as_micros();
+            let result = decoder::decode_timed(&pulses, 1, now, bench::decode_time);
+            bench::decoded(pulses.received());
+            result
+        };
+        // Waiting for the main loop is its time, not the decoder's
+        #[cfg(feature = "trace")]
+        drop(span);
+        DECODED.send(Decoded { pulses, result }).await;
+    }
 }
 
 #[embassy_executor::task]
-async fn mqtt_publisher(mqtt: &'static mut Mqtt, now: i64) {
-    let mut last_publish: i64 = now;
+async fn publisher(mut outputs: Outputs, now: i64, self_test: SelfTest) {
+    // Uptime is used rather than wall clock, which may be set while running
+    let mut last_publish = Instant::now();
+    // Time the network was restarted at due to publish failures
+    let mut recovery: Option<Instant> = None;
+    info!("Firmware {} ({})", VERSION, GIT_HASH);
+    // Broker that has the current status and the network it announced, if any
+    let mut status = publish_status(&mut outputs, now, &self_test).await;
+    let mut poll = Interval::immediate(Duration::from_secs(COMMAND_POLL_SECS));
+    let mut events = events::subscribe();
+    let mut limiter = TokenBucket::new(PUBLISH_BURST as u32, PUBLISH_RATE_PER_MIN as u32);
+    let mut queue: FairQueue<QUEUE_LEN> = FairQueue::new();
+    // Sensors announced for ESPHome style discovery since boot
+    let mut announced = Vec::new();
+    let mut pending_errors: Pending<ERRORS_LEN> = Pending::new();
+    // Readings wait for the config retained on the broker, which is tried
+    // along with every command poll until the broker answers
+    let mut configs = load_configs(outputs.mqtt).await;
+    // Latched alarms are republished with every command poll until they
+    // all get through
+    let mut alarms_pending = false;
+    // So is the report of a panic the last boot ended with
+    #[cfg(feature = "postmortem")]
+    let mut panic_pending = !publish_panic(&mut outputs).await;
     loop {
-        let (data, timestamp) = MQTT_CHANNEL.receive().await;
+        // Take whatever is waiting on the bus before publishing queued readings,
+        // so they coalesce per sensor
+        let event = match events.try_next_message_pure() {
+            Some(event) => Some(event),
+            None if configs && !queue.is_empty() && !poll.is_due() => None,
+            None => match select(events.next_message_pure(), Timer::at(poll.deadline())).await {
+                Either::First(event) => Some(event),
+                Either::Second(_) => {
+                    if !configs {
+                        configs = load_configs(outputs.mqtt).await;
+                    }
+                    if alarms_pending {
+                        alarms_pending = !publish_alarms(&mut outputs).await;
+                    }
+                    #[cfg(feature = "postmortem")]
+                    if panic_pending {
+                        panic_pending = !publish_panic(&mut outputs).await;
+                    }
+                    // Roamed to another access point or got a new lease
+                    if let Some((_, announced)) = &status
+                        && *announced != outputs.mqtt.link().await
+                    {
+                        info!("Network changed, announcing it");
+                        if let Some(published) = publish_status(&mut outputs, now, &self_test).await
+                        {
+                            status = Some(published);
+                        }
+                    }
+                    if let Some(request) = handle_command(&mut outputs).await {
+                        restart(&mut outputs, &mut queue, &mut pending_errors, request).await;
+                    }
+                    poll.restart();
+                    // Fetch the next firmware chunk right away
+                    #[cfg(feature = "ota")]
+                    if ota::receiving() {
+                        poll.expire();
+                    }
+                    continue;
+                }
+            },
+        };
+        let reading = match event {
+            Some(Event::ReadingDecoded(reading)) => {
+                if !limiter.take(Instant::now().as_micros()) {
+                    warn!(
+                        "Publish rate exceeded, dropping {} reading",
+                        reading.data.model()
+                    );
+                    SUMMARY.lock(|summary| summary.borrow_mut().rate_limited += 1);
+                } else if let Err(reading) = queue.push(reading) {
+                    warn!(
+                        "Publish queue is full, dropping {} reading",
+                        reading.data.model()
+                    );
+                }
+                continue;
+            }
+            Some(Event::CaptureReady) => {
+                if let Ok((pulses, timestamp)) = CAPTURE_CHANNEL.try_receive() {
+                    publish_capture(&mut outputs, &pulses, timestamp).await;
+                }
+                continue;
+            }
+            Some(Event::LearnDone) => {
+                if let Ok((suggestion, bursts)) = LEARN_CHANNEL.try_receive() {
+                    publish_learned(&mut outputs, suggestion.as_ref(), bursts).await;
+                }
+                continue;
+            }
+            Some(Event::DebugReady) => {
+                if let Ok(context) = DEBUG_CHANNEL.try_receive() {
+                    publish_debug(&mut outputs, &context).await;
+                }
+                continue;
+            }
+            Some(Event::Availability(sensor)) => {
+                let mut topic: String<TOPIC_LEN> = String::new();
+                if write!(topic, "sensors/{}/{}/availability", sensor.model, sensor.id).is_err() {
+                    continue;
+                }
+                let state = if sensor.online { "online" } else { "offline" };
+                info!("{} {}: {}", sensor.model, sensor.id, state);
+                if let Err(e) = outputs
+                    .publish_diagnostics(topic.as_str(), state, Class::Alerts)
+                    .await
+                {
+                    warn!("Failed to publish MQTT message: {:?}", e);
+                }
+                continue;
+            }
+            Some(Event::Alarm(sensor)) => {
+                alarms_pending |= !publish_alarm(&mut outputs, &sensor).await;
+                continue;
+            }
+            Some(Event::ReceiverSilent(silent)) => {
+                let state = if silent { "silent" } else { "ok" };
+                match outputs
+                    .publish_diagnostics(MQTT_RECEIVER_TOPIC, state, Class::Alerts)
+                    .await
+                {
+                    // Broker is fine, the lack of readings to publish is
+                    // receiver's fault
+                    Ok(_) => {
+                        last_publish = Instant::now();
+                        recovery = None;
+                    }
+                    Err(e) => warn!("Failed to publish MQTT message: {:?}", e),
+                }
+                continue;
+            }
+            Some(Event::SummaryDue(timestamp)) => {
+                publish_summary(&mut outputs, timestamp).await;
+                continue;
+            }
+            Some(Event::Health(health)) => {
+                let mut payload: String<PAYLOAD_LEN> = String::new();
+                if write_health(&mut payload, &health).is_err() {
+                    continue;
+                }
+                latest::set_health(payload.as_str());
+                if let Err(e) = outputs
+                    .publish_diagnostics(MQTT_HEALTH_TOPIC, payload.as_str(), Class::Diagnostics)
+                    .await
+                {
+                    warn!("Failed to publish MQTT message: {:?}", e);
+                }
+                publish_publishes(&mut outputs).await;
+                #[cfg(feature = "bench")]
+                publish_bench(&mut outputs).await;
+                #[cfg(feature = "trace")]
+                publish_trace(&mut outputs).await;
+                continue;
+            }
+            Some(Event::WifiUp) => {
+                // Pick up commands sent while offline right away
+                poll.expire();
+                continue;
+            }
+            Some(Event::PublishFailed) => {
+                SUMMARY.lock(|summary| summary.borrow_mut().publish_failures += 1);
+                continue;
+            }
+            Some(Event::Error(report)) => {
+                pending_errors.push(report);
+                publish_errors(&mut outputs, &mut pending_errors).await;
+                continue;
+            }
+            Some(Event::WifiDown | Event::NtpSynced) => continue,
+            // Nothing new, publish the next queued reading
+            None => match queue.pop() {
+                Some(reading) => reading,
+                None => continue,
+            },
+        };
+        let Reading {
+            data,
+            timestamp,
+            expire_after,
+            repeats,
+            count,
+            #[cfg(feature = "bench")]
+            received,
+        } = reading;
+        #[cfg(feature = "trace")]
+        let _span = trace::enter(Phase::Publish);
 
-        if timestamp - last_publish > 300_000_000 {
-            // Last successful publish was over 5 minutes ago, so something is wrong.
-            // Panic and trigger watchdog reload to recover
-            panic!("No successful publishes in 300 seconds!");
+        let timeout = Duration::from_secs(PUBLISH_TIMEOUT_SECS);
+        if last_publish.elapsed() > timeout {
+            match recovery {
+                // Last successful publish was too long ago, so something is
+                // wrong. Try to recover by bringing the network up again
+                None => {
+                    warn!(
+                        "No successful publishes in {} seconds, restarting network",
+                        PUBLISH_TIMEOUT_SECS
+                    );
+                    wifi::restart();
+                    outputs.mqtt.reset();
+                    recovery = Some(Instant::now());
+                }
+                // That didn't help either, panic and trigger watchdog reload
+                Some(since) if since.elapsed() > timeout => {
+                    panic!("No successful publishes after restarting network!");
+                }
+                Some(_) => {}
+            }
         }
 
         info!("Publishing...");
-        let date_time = jiff::Timestamp::from_microsecond(timestamp)
-            .unwrap()
-            .strftime("%Y-%m-%d %H:%M:%S UTC");
-        let topic = format!("sensors/{}", data.model());
-        let data = format!(
-            "{{\"time\" : \"{}\", \"model\" : \"{}\", \"id\" : {}, \"channel\" : {}, \"battery_ok\" : {}, \"temperature_C\" : {}{}.{}, \"humidity\" : {}, \"uptime\" : {} }}",
-            date_time,
-            data.model(),
-            data.id,
-            data.channel,
-            data.battery_ok,
-            { if data.sign < 0 { "-" } else { "" } },
-            data.temp_int,
-            data.temp_decimal,
-            data.humidity,
-            get_timestamp(),
-        );
-        match mqtt.publish(topic.as_str(), data.as_str()).await {
+        let mut topic: String<TOPIC_LEN> = String::new();
+        let mut payload: String<PAYLOAD_LEN> = String::new();
+        if write!(topic, "sensors/{}/{}", data.model(), data.id).is_err()
+            || write_sensor_payload(&mut payload, &data, timestamp, expire_after, repeats, count)
+                .is_err()
+        {
+            warn!("MQTT message for {} doesn't fit into buffer", data.model());
+            continue;
+        }
+        // Logged whether or not it gets through, the log is for catching up
+        #[cfg(feature = "history")]
+        history::append(payload.as_str());
+        match outputs
+            .publish_sensor(&data, topic.as_str(), payload.as_str())
+            .await
+        {
             Ok(_) => {
-                last_publish = timestamp;
+                last_publish = Instant::now();
+                recovery = None;
+                #[cfg(feature = "bench")]
+                bench::published(received, data.is_event());
+                if status.as_ref().map(|(broker, _)| *broker) != Some(outputs.mqtt.server())
+                    && let Some(published) = publish_status(&mut outputs, now, &self_test).await
+                {
+                    status = Some(published);
+                }
+                if ESPHOME_SCHEMA {
+                    publish_esphome(outputs.mqtt, &data, &mut announced).await;
+                }
+                if ZONE_AVERAGES {
+                    publish_zone(&mut outputs, &data, timestamp).await;
+                }
+                if STATION_RECORDS {
+                    publish_station(&mut outputs, &data, timestamp).await;
+                }
+                // Automations ring the phone for any doorbell
+                if data.doorbell().is_some()
+                    && let Err(e) = outputs
+                        .publish_reading(MQTT_DOORBELL_TOPIC, payload.as_str())
+                        .await
+                {
+                    warn!("Failed to publish MQTT message: {:?}", e);
+                }
+                // Errors raised while the broker was unreachable
+                publish_errors(&mut outputs, &mut pending_errors).await;
                 info!(
                     "Published at {}",
-                    jiff::Timestamp::from_microsecond(timestamp).unwrap()
+                    jiff::Timestamp::from_microsecond(timestamp).unwrap_or_default()
                 );
             }
             Err(e) => {
                 warn!("Failed to publish MQTT message: {:?}", e);
+                events::notify(Event::PublishFailed);
             }
         };
     }
 }
 
+/// Next periodic reading whose burst of repeats is over, with readings of
+/// sensors that split them over frames merged
+fn next_finished(
+    suppressor: &mut Suppressor<SUPPRESSOR_LEN>,
+    cluster: &mut Cluster<CLUSTER_LEN>,
+) -> Option<Finished> {
+    let now = time::monotonic();
+    while let Some(finished) = suppressor.finished(now) {
+        if finished.data.is_event() {
+            continue;
+        }
+        if let Some(finished) = cluster.push(finished) {
+            return Some(finished);
+        }
+    }
+    cluster.finished(now)
+}
+
 #[esp_rtos::main]
 async fn main(spawner: Spawner) -> ! {
-    esp_println::logger::init_logger_from_env();
+    logger::init();
+    spawner.spawn(logger::console()).ok();
     let config = esp_hal::Config::default().with_cpu_clock(CpuClock::max());
     let peripherals = esp_hal::init(config);
 
+    #[cfg(any(feature = "esp32", feature = "esp32s3"))]
     esp_alloc::heap_allocator!(#[ram(reclaimed)] size: 72 * 1024);
+    // Reclaimed RAM is smaller on RISC-V chips, top up from regular RAM
+    #[cfg(any(feature = "esp32c3", feature = "esp32c6"))]
+    {
+        esp_alloc::heap_allocator!(#[ram(reclaimed)] size: 64 * 1024);
+        esp_alloc::heap_allocator!(size: 8 * 1024);
+    }
 
     let timg0 = TimerGroup::new(peripherals.TIMG0);
+    let sw_int = SoftwareInterruptControl::new(peripherals.SW_INTERRUPT);
+    // RISC-V chips need a software interrupt for context switching
+    #[cfg(any(feature = "esp32c3", feature = "esp32c6"))]
+    esp_rtos::start(timg0.timer0, sw_int.software_interrupt0);
+    #[cfg(any(feature = "esp32", feature = "esp32s3"))]
     esp_rtos::start(timg0.timer0);
 
     // Arm watchdog timer
-    let mut wdt = timg0.wdt;
-    wdt.set_timeout(
-        MwdtStage::Stage0,
-        esp_hal::time::Duration::from_millis(30_000),
+    let mut wdt = Watchdog::new(timg0.wdt);
+
+    // Cheap supplies cause resets that look like firmware bugs otherwise
+    if esp_hal::system::reset_reason() == Some(SocResetReason::SysBrownOut) {
+        warn!("Last reset was caused by brown-out, check the power supply");
+    }
+    #[cfg(feature = "postmortem")]
+    match postmortem::read() {
+        Some((Kind::Watchdog, report)) => {
+            warn!("Last reset was forced by the watchdog, it left:");
+            // Too long for a log line
+            esp_println::println!("{}", report);
+            postmortem::clear();
+        }
+        // Kept until the publisher gets it through to the broker
+        Some((Kind::Panic, report)) => {
+            warn!("Last reset followed a panic:");
+            esp_println::println!("{}", report);
+        }
+        None => {}
+    }
+    #[cfg(feature = "history")]
+    history::init();
+    #[cfg(feature = "rain-totals")]
+    rain::init();
+    #[cfg(feature = "ota")]
+    if ota::init() {
+        spawner.spawn(ota::countdown()).ok();
+    }
+    #[cfg(feature = "esp32")]
+    let supply_pin = peripherals.GPIO34;
+    #[cfg(any(feature = "esp32c3", feature = "esp32c6"))]
+    let supply_pin = peripherals.GPIO3;
+    #[cfg(feature = "esp32s3")]
+    let supply_pin = peripherals.GPIO4;
+    let monitor = Monitor::new(
+        peripherals.ADC1,
+        supply_pin,
+        #[cfg(any(feature = "esp32c3", feature = "esp32c6"))]
+        peripherals.TSENS,
     );
-    wdt.enable();
-    wdt.feed();
+    spawner.spawn(health::monitor(monitor)).ok();
 
-    let rtc = Rtc::new(peripherals.LPWR);
+    let mut clock = RtcClock::new(Rtc::new(peripherals.LPWR));
+    #[cfg(feature = "esp32")]
+    let (sda, scl) = (peripherals.GPIO23, peripherals.GPIO22);
+    #[cfg(feature = "esp32c3")]
+    let (sda, scl) = (peripherals.GPIO5, peripherals.GPIO6);
+    #[cfg(feature = "esp32c6")]
+    let (sda, scl) = (peripherals.GPIO6, peripherals.GPIO7);
+    #[cfg(feature = "esp32s3")]
+    let (sda, scl) = (peripherals.GPIO8, peripherals.GPIO9);
+    let mut rtc = if DS3231 {
+        Ds3231::new(peripherals.I2C0, sda, scl)
+            .inspect_err(|e| warn!("Failed to init DS3231: {:?}", e))
+            .ok()
+    } else {
+        None
+    };
+    // Timestamps are right from the start, even if NTP can't be reached
+    if let Some(time) = clock::read(rtc.as_mut()).await {
+        clock.set(time);
+        ntpc::mark_valid();
+    }
+    #[cfg(not(feature = "provisioning"))]
+    let credentials = Credentials::compiled();
+    #[cfg(feature = "provisioning")]
+    let (credentials, hmac) = {
+        // Kept for the setup page, should Wi-Fi fall back to it
+        let hmac = mk_static!(Hmac<'static>, Hmac::new(peripherals.HMAC));
+        let mut credentials = credentials::load(hmac)
+            .inspect(|_| info!("Using stored credentials"))
+            .unwrap_or_else(Credentials::compiled);
+        let mut serial = UsbSerialJtag::new(peripherals.USB_DEVICE);
+        provision::run(&mut serial, hmac, &mut credentials, || wdt.feed()).await;
+        (credentials, hmac)
+    };
+    let credentials = &*mk_static!(Credentials, credentials);
+    // RNG isn't fed by RF noise before the radio is up, the MAC address keeps
+    // units that boot in lockstep apart anyway
+    entropy::stir_mac(esp_radio::wifi::sta_mac());
+    if BOOT_JITTER_SECS != 0 {
+        let delay = Duration::from_millis(entropy::random() % (BOOT_JITTER_SECS * 1_000));
+        info!("Waiting {} ms before joining Wi-Fi", delay.as_millis());
+        let until = Instant::now() + delay;
+        while Instant::now() < until {
+            wdt.feed();
+            Timer::at(until.min(Instant::now() + Duration::from_secs(1))).await;
+        }
+    }
     let radio_init = &*mk_static!(
         Controller<'static>,
         esp_radio::init().expect("Failed to init radio")
     );
-    let wifi = Wifi::new(radio_init, peripherals.WIFI, Rng::new(), spawner)
+    let wifi = Wifi::new(radio_init, peripherals.WIFI, credentials, spawner)
         .await
         .expect("Failed to initialize Wi-Fi");
 
     wdt.feed();
+    // Nothing else to do without a network, until the setup page restarts
+    #[cfg(feature = "provisioning")]
+    if wifi.fallback {
+        spawner
+            .spawn(http::setup::serve(wifi.stack, hmac, credentials))
+            .ok();
+        loop {
+            wdt.feed();
+            Timer::after(Duration::from_secs(1)).await;
+        }
+    }
 
     let shared_stack = SHARED_STACK.init(Mutex::new(wifi.stack));
 
@@ -159,99 +2621,405 @@ async fn main(spawner: Spawner) -> ! {
     wdt.feed();
     let mut ntpc = Ntpc::new(shared_stack);
 
-    let time = ntpc.get_time().await.expect("Failed to get NTP time");
-    rtc.set_current_time_us(time * 1_000_000);
-
-    let mut last_time_sync = rtc.current_time_us();
-    let now = jiff::Timestamp::from_microsecond(rtc.current_time_us() as i64).unwrap();
+    let rng = Rng::new();
+    let mut time_sync = Schedule::new();
+    // Without NTP readings are timestamped with time since boot until time
+    // is set externally
+    let mut ntp = Check::Skipped;
+    if NTP_ENABLED {
+        match ntpc.time().await {
+            Some(time) => {
+                clock.set(time);
+                time_sync.synced(rng.random());
+                events::notify(Event::NtpSynced);
+                clock::write(rtc.as_mut(), time).await;
+                ntp = Check::Pass;
+            }
+            None => {
+                time_sync.failed();
+                ntp = Check::Fail;
+            }
+        }
+    }
+    let now = jiff::Timestamp::from_microsecond(clock.now() as i64).unwrap_or_default();
 
     info!("now is {now}");
 
-    let freq = Rate::from_mhz(80);
-
-    let rmt = Rmt::new(peripherals.RMT, freq).unwrap().into_async();
-    let rx_config = RxChannelConfig::default()
-        .with_clk_divider(80) // tick will be 1us (1MHz)
-        .with_idle_threshold(3000) // timeout after 3ms of inactivity
-        .with_filter_threshold(100); // filter out pulses shorter than 100us
-
-    let mut channel = rmt
-        .channel0
-        .configure_rx(peripherals.GPIO21, rx_config)
-        .expect("Failed to configure RMT RX channel");
-    let mut data: [PulseCode; 64] = [PulseCode::default(); 64];
+    pool::init();
+    #[cfg(feature = "replay")]
+    let input = {
+        info!("Replaying recorded pulse dumps instead of RMT input");
+        Replay::new(DUMPS)
+    };
+    // Only some of the RMT channels can receive, and GPIO21 is UART TX on C3
+    #[cfg(not(feature = "replay"))]
+    let input = {
+        let rmt = Rmt::new(peripherals.RMT, Rate::from_mhz(RMT_SOURCE_MHZ)).unwrap();
+        #[cfg(any(feature = "esp32", feature = "esp32c6", feature = "esp32s3"))]
+        let pin = AnyPin::from(peripherals.GPIO21);
+        #[cfg(feature = "esp32c3")]
+        let pin = AnyPin::from(peripherals.GPIO4);
+        // SAFETY: the pin is set aside for the transmitter by the build
+        // config, nothing else takes it
+        let tx_pin = TX_PIN.map(|pin| unsafe { AnyPin::steal(pin) });
+        (rmt, pin, tx_pin)
+    };
+    // Wi-Fi interrupts and TLS handshakes on the main executor can't hold
+    // up reception, the receiver preempts them
+    #[cfg(any(feature = "esp32c3", feature = "esp32c6"))]
+    {
+        let executor = mk_static!(
+            InterruptExecutor<2>,
+            InterruptExecutor::new(sw_int.software_interrupt2)
+        );
+        executor
+            .start(Priority::Priority2)
+            .spawn(receiver(input))
+            .expect("Failed to spawn receiver task");
+        spawner
+            .spawn(decoder())
+            .expect("Failed to spawn decoder task");
+    }
+    // Dual-core chips leave the PRO CPU to the network stack and MQTT, and
+    // capture and decode on the APP CPU. Interrupts are taken by the core
+    // that enabled them, so everything is set up from there.
+    #[cfg(any(feature = "esp32", feature = "esp32s3"))]
+    {
+        let swi = sw_int.software_interrupt2;
+        esp_rtos::start_second_core(
+            peripherals.CPU_CTRL,
+            sw_int.software_interrupt0,
+            sw_int.software_interrupt1,
+            mk_static!(Stack<APP_CPU_STACK_LEN>, Stack::new()),
+            move || {
+                let executor = mk_static!(InterruptExecutor<2>, InterruptExecutor::new(swi));
+                executor
+                    .start(Priority::Priority2)
+                    .spawn(receiver(input))
+                    .expect("Failed to spawn receiver task");
+                let executor = mk_static!(Executor, Executor::new());
+                executor.run(|spawner| {
+                    spawner
+                        .spawn(decoder())
+                        .expect("Failed to spawn decoder task");
+                });
+            },
+        );
+    }
 
-    let mqtt = &mut *mk_static!(Mqtt, Mqtt::new(shared_stack));
+    let mqtt = &mut *mk_static!(Mqtt, Mqtt::new(shared_stack, credentials.broker.as_str()));
+    wdt.feed();
+    let (dns, broker) = mqtt.probe().await;
+    wdt.feed();
+    let self_test = SelfTest {
+        heap: selftest::heap(),
+        dns,
+        broker,
+        ntp,
+    };
+    info!("Self-test: {:?}", self_test);
+    if let Some(pin) = STATUS_LED {
+        // SAFETY: the pin is set aside for the LED by the build config,
+        // nothing else takes it
+        let pin = unsafe { AnyPin::steal(pin) };
+        let led = Output::new(pin, Level::Low, OutputConfig::default());
+        spawner.spawn(selftest::indicate(led, self_test)).ok();
+    }
     spawner
-        .spawn(mqtt_publisher(mqtt, rtc.current_time_us() as i64))
-        .expect("Failed to spawn MQTT sender task");
+        .spawn(publisher(
+            Outputs::new(mqtt, shared_stack),
+            clock.now() as i64,
+            self_test,
+        ))
+        .expect("Failed to spawn publisher task");
+    if HTTP_PORT != 0 {
+        spawner.spawn(http::serve(wifi.stack)).ok();
+    }
 
-    let mut measurement = SensorData::default();
-    let mut measurement_cnt = 0;
-    let mut last_publish = rtc.current_time_us();
+    let mut suppressor: Suppressor<SUPPRESSOR_LEN> =
+        Suppressor::new(Duration::from_millis(SUPPRESS_WINDOW_MS));
+    let mut cluster: Cluster<CLUSTER_LEN> = Cluster::new(Duration::from_millis(CLUSTER_WINDOW_MS));
+    let mut collisions = Collisions::new();
+    // Restarted on every burst, silent receiver points at wiring or antenna
+    // rather than network problems
+    let mut silence = Interval::new(Duration::from_secs(RECEIVER_SILENT_SECS));
+    let mut receiver_silent = false;
+    // Decode failures in the current minute
+    let mut decode_window = Interval::new(Duration::from_secs(60));
+    let mut decode_errors = 0;
+    // Protocol being learned, until LEARN bursts have been seen
+    let mut learner: Option<Learner> = None;
+    let mut daily_summary = Daily::new(SUMMARY_HOUR, SUMMARY_MINUTE, UTC_OFFSET_MINUTES);
+    let mut motion_cooldown: Cooldown<COOLDOWN_LEN> =
+        Cooldown::new(Duration::from_secs(MOTION_COOLDOWN_SECS));
+    let mut replay_filter: ReplayFilter<REPLAY_LEN> =
+        ReplayFilter::new(Duration::from_millis(EVENT_REPLAY_MS));
+    let mut doorbell_cooldown: Cooldown<COOLDOWN_LEN> =
+        Cooldown::new(Duration::from_secs(DOORBELL_COOLDOWN_SECS));
+    if let Some(snapshot) = snapshot::restore() {
+        info!("Restored state from before reset");
+        REGISTRY.lock(|registry| *registry.borrow_mut() = snapshot.registry);
+        motion_cooldown = snapshot.motion_cooldown;
+        suppressor = snapshot.suppressor;
+        replay_filter = snapshot.replay_filter;
+        decoder::set_enabled_protocols(snapshot.protocols);
+        SUMMARY.lock(|summary| *summary.borrow_mut() = snapshot.summary);
+        time::resume(snapshot.monotonic);
+    }
+    let save_snapshot = |motion_cooldown: &Cooldown<COOLDOWN_LEN>,
+                         suppressor: &Suppressor<SUPPRESSOR_LEN>,
+                         replay_filter: &ReplayFilter<REPLAY_LEN>| {
+        snapshot::save(&Snapshot {
+            registry: REGISTRY.lock(|registry| registry.borrow().clone()),
+            motion_cooldown: motion_cooldown.clone(),
+            suppressor: suppressor.clone(),
+            replay_filter: replay_filter.clone(),
+            protocols: decoder::enabled_protocols(),
+            summary: SUMMARY.lock(|summary| summary.borrow().clone()),
+            monotonic: time::monotonic(),
+        })
+    };
 
     loop {
         wdt.feed();
-        // Re-sync time every 3_600 seconds (1 hour)
-        if rtc.current_time_us() - last_time_sync > 3_600_000_000 {
-            info!("Re-syncing time via NTP...");
-            let time = ntpc.get_time().await.expect("Failed to get NTP time");
-            rtc.set_current_time_us(time * 1_000_000);
-            last_time_sync = rtc.current_time_us();
-            let last_ts = jiff::Timestamp::from_microsecond(rtc.current_time_us() as i64).unwrap();
-
-            info!("now is {last_ts}");
-        }
-
-        // Receive the data as series of PulseCode. For Nexus-TH, it will be
-        // 36 symbols + terminator. High pulse (carrier present) has a fixed width of
-        // 350-650 uS (actual width likely depends on battery voltage),
-        // The actual data is encoded in the lenght of the low pulse (pauses)
-        // 1 is 1650-2150 uS, 0 is 800-1100 uS
-        //
-        // On ESP32 RMT can count the lenght of pulses for us, simplifying the decoding
-        let a = channel.receive(&mut data);
+        time::track_wall(clock.now());
+        if let Some(time) = SET_TIME.try_take() {
+            info!("Setting time externally");
+            clock.sync(time);
+            time_sync.synced(rng.random());
+            clock::write(rtc.as_mut(), time).await;
+        }
+        // Learning is over, or has been stopped
+        if LEARN.load(Ordering::Relaxed) == 0
+            && let Some(learner) = learner.take()
+        {
+            let suggestion = learner.suggest();
+            info!("Learned protocol: {:?}", suggestion);
+            LEARN_CHANNEL.send((suggestion, learner.bursts())).await;
+            events::publish(Event::LearnDone).await;
+        }
+        // Re-sync time about every hour, RTC keeps running if NTP is unavailable
+        if (NTP_ENABLED || rtc.is_some()) && time_sync.due() {
+            let time = if NTP_ENABLED {
+                info!("Re-syncing time via NTP...");
+                ntpc.time().await
+            } else {
+                None
+            };
+            match time {
+                Some(time) => {
+                    clock.sync(time);
+                    time_sync.synced(rng.random());
+                    events::notify(Event::NtpSynced);
+                    clock::write(rtc.as_mut(), time).await;
+                    let last_ts =
+                        jiff::Timestamp::from_microsecond(clock.now() as i64).unwrap_or_default();
+
+                    info!("now is {last_ts}");
+                }
+                None => {
+                    // DS3231 drifts a lot less than RTC, so it takes over
+                    // until NTP is back
+                    time_sync.failed();
+                    if let Some(time) = clock::read(rtc.as_mut()).await {
+                        clock.sync(time);
+                        ntpc::mark_valid();
+                    }
+                }
+            }
+        }
+
+        while let Some(sensor) =
+            REGISTRY.lock(|registry| registry.borrow_mut().expire(time::monotonic()))
+        {
+            save_snapshot(&motion_cooldown, &suppressor, &replay_filter);
+            events::publish(Event::Availability(sensor)).await;
+        }
+
+        // Summary needs wall clock time, skip it until the clock is set
+        if ntpc::time_valid() && daily_summary.due(&mut clock).await {
+            events::publish(Event::SummaryDue(clock.now() as i64)).await;
+        }
+
+        // Periodic readings are published once their burst of repeats is over,
+        // along with the number of repeats received
+        while let Some(finished) = next_finished(&mut suppressor, &mut cluster) {
+            let Finished {
+                data,
+                last_seen,
+                repeats,
+            } = finished;
+            let timestamp = time::wall_time(last_seen, time::monotonic(), clock.now());
+            let (changed, significant, sensor) = REGISTRY.lock(|registry| {
+                let mut registry = registry.borrow_mut();
+                let changed = registry.update(&data, last_seen, timestamp);
+                let significant = registry.significant(&data, last_seen, &HYSTERESIS);
+                (changed, significant, registry.get(&data).cloned())
+            });
+            SUMMARY.lock(|summary| summary.borrow_mut().record(&data));
+            save_snapshot(&motion_cooldown, &suppressor, &replay_filter);
+            let expire_after = sensor.as_ref().and_then(Sensor::expire_after);
+            let reading = Reading {
+                data,
+                timestamp: timestamp as i64,
+                expire_after,
+                repeats: Some(repeats),
+                count: None,
+                #[cfg(feature = "bench")]
+                received: last_seen,
+            };
+            latest::update(&reading);
+            trend::record(&reading.data, last_seen);
+            if significant {
+                events::publish(Event::ReadingDecoded(reading)).await;
+            }
+            if changed && let Some(sensor) = sensor {
+                events::publish(Event::Availability(sensor)).await;
+            }
+        }
+
+        if !receiver_silent && silence.due() {
+            warn!(
+                "No bursts in {} seconds, receiver silent - check wiring/antenna",
+                RECEIVER_SILENT_SECS
+            );
+            receiver_silent = true;
+            events::publish(Event::ReceiverSilent(true)).await;
+        }
+
+        let a = DECODED.receive();
         let b = Timer::after(Duration::from_secs(1));
 
         let either = select(a, b).await;
         wdt.feed();
-        let res = match either {
-            Either::First(res) => res,
+        let Decoded { pulses, result } = match either {
+            Either::First(decoded) => {
+                silence.restart();
+                if receiver_silent {
+                    info!("Receiver is back");
+                    receiver_silent = false;
+                    events::publish(Event::ReceiverSilent(false)).await;
+                }
+                decoded
+            }
             Either::Second(_) => {
                 continue;
             }
         };
-        match res {
-            Ok(symbol_count) => match decode(&data, 1, symbol_count) {
-                Ok(parsed) => {
-                    info!(
-                        "Temperature: {}{}.{}C, Humidity: {}%",
-                        { if parsed.sign < 0 { "-" } else { "" } },
-                        parsed.temp_int,
-                        parsed.temp_decimal,
-                        parsed.humidity
+        #[cfg(feature = "trace")]
+        let _span = trace::enter(Phase::Process);
+        let received = pulses.received();
+        let pulses = &pulses[..];
+        let picked = result
+            .as_ref()
+            .ok()
+            .and_then(Candidates::unambiguous)
+            .map(|candidate| candidate.data.model());
+        bursts::record(pulses, clock.now() as i64, picked);
+        let captures = CAPTURES.load(Ordering::Relaxed);
+        if captures > 0 {
+            CAPTURES.store(captures - 1, Ordering::Relaxed);
+            let capture = Vec::from_slice(pulses).unwrap_or_default();
+            CAPTURE_CHANNEL.send((capture, clock.now() as i64)).await;
+            events::publish(Event::CaptureReady).await;
+        }
+        let learn = LEARN.load(Ordering::Relaxed);
+        if learn > 0 {
+            LEARN.store(learn - 1, Ordering::Relaxed);
+            learner.get_or_insert_default().feed(pulses);
+        }
+        // Bursts of no known length are mostly noise, capture them instead
+        if DEBUG.load(Ordering::Relaxed) && !matches!(result, Err(DecodeError::WrongPayloadLen(_)))
+        {
+            let context = DecodeContext {
+                pulses: Vec::from_slice(pulses).unwrap_or_default(),
+                result: result.clone(),
+                timestamp: clock.now() as i64,
+            };
+            if DEBUG_CHANNEL.try_send(context).is_ok() {
+                events::notify(Event::DebugReady);
+            }
+        }
+        match result {
+            Ok(candidates) => {
+                let Some(mut parsed) = pick(&candidates) else {
+                    continue;
+                };
+                let settings = config::sensor(&parsed).unwrap_or_default();
+                settings.calibrate(&mut parsed);
+                info!("{}", parsed);
+                // Frame arrival rather than now, decoding may have been
+                // queued behind other bursts
+                let now = received;
+                if let Some((other, id)) = collisions.check(&parsed, now) {
+                    warn!(
+                        "{} {} and {} both report on channel {}",
+                        parsed.model(),
+                        other,
+                        id,
+                        parsed.channel
                     );
-                    if !measurement.equal(&parsed) {
-                        measurement = parsed;
-                        measurement_cnt = 1;
-                    } else {
-                        let now = rtc.current_time_us();
-                        if measurement_cnt == 3 && now - last_publish > 5_000_000 {
-                            MQTT_CHANNEL.send((parsed, now as i64)).await;
-                            last_publish = now;
-                        } else if measurement_cnt < 3 {
-                            measurement_cnt += 1;
-                        }
+                    errors::report(
+                        Code::ChannelCollision,
+                        format_args!(
+                            "{} ch {}: {}, {}",
+                            parsed.model(),
+                            parsed.channel,
+                            other,
+                            id
+                        ),
+                    );
+                }
+                rain::update(&parsed, clock.now() as i64);
+                wind::average(&mut parsed);
+                // Events are published as soon as they are confirmed, replays
+                // of the same code and the cooldowns keep a stuck button or a
+                // single movement from flooding MQTT
+                if suppressor.check(&parsed, now)
+                    && parsed.is_event()
+                    && let Some(count) = replay_filter.check(&parsed, now)
+                    && (parsed.motion().is_none() || motion_cooldown.check(parsed.id, now))
+                    && (parsed.doorbell().is_none() || doorbell_cooldown.check(parsed.id, now))
+                {
+                    if settings.chime && parsed.doorbell().is_some() {
+                        chime(&parsed, pulses);
+                    }
+                    SUMMARY.lock(|summary| summary.borrow_mut().record(&parsed));
+                    let timestamp = clock.now();
+                    let alarm = REGISTRY
+                        .lock(|registry| registry.borrow_mut().latch(&parsed, now, timestamp));
+                    save_snapshot(&motion_cooldown, &suppressor, &replay_filter);
+                    if let Some(sensor) = alarm {
+                        events::publish(Event::Alarm(sensor)).await;
                     }
+                    let reading = Reading {
+                        data: parsed,
+                        timestamp: clock.now() as i64,
+                        expire_after: None,
+                        repeats: None,
+                        count: Some(count),
+                        #[cfg(feature = "bench")]
+                        received: now,
+                    };
+                    latest::update(&reading);
+                    events::publish(Event::ReadingDecoded(reading)).await;
                 }
-                Err(e) => match e {
-                    DecodeError::WrongPayloadLen(_len) => {}
-                    _ => {
-                        warn!("Decode error: {:?}", e);
+            }
+            Err(e) => match e {
+                DecodeError::WrongPayloadLen(_len) => {}
+                _ => {
+                    warn!("Decode error: {:?}", e);
+                    SUMMARY.lock(|summary| summary.borrow_mut().decode_errors += 1);
+                    if decode_window.due() {
+                        decode_errors = 0;
                     }
-                },
+                    decode_errors += 1;
+                    // Only once per minute
+                    if decode_errors == DECODE_ERROR_RATE + 1 {
+                        errors::report(Code::DecodeRate, format_args!("{:?}", e));
+                    }
+                }
             },
-            Err(_e) => {}
         }
     }
 }
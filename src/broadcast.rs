@@ -0,0 +1,82 @@
+use embassy_net::{
+    Ipv4Address, Stack,
+    udp::{PacketMetadata, UdpSocket},
+};
+use embassy_sync::{blocking_mutex::raw::NoopRawMutex, mutex::Mutex};
+use heapless::Vec;
+use log::debug;
+
+use crate::MQTT_STATUS_TOPIC;
+use crate::mqtt::Class;
+use crate::output::Publisher;
+
+// Decode context of a burst takes up to 1.6 KiB
+const DATAGRAM_LEN: usize = 2048;
+
+#[derive(Debug)]
+pub enum Error {
+    BindFailed,
+    TooLong,
+    SendFailed,
+}
+
+/// Broadcasts every message on the local network as a `<topic> <payload>`
+/// datagram, same as `mosquitto_sub -v` prints them
+pub struct Broadcast {
+    stack: &'static Mutex<NoopRawMutex, Stack<'static>>,
+    port: u16,
+}
+
+impl Broadcast {
+    pub fn new(stack: &'static Mutex<NoopRawMutex, Stack<'static>>, port: u16) -> Self {
+        Broadcast { stack, port }
+    }
+
+    async fn send(&mut self, topic: &str, payload: &str) -> Result<(), Error> {
+        let mut datagram: Vec<u8, DATAGRAM_LEN> = Vec::new();
+        datagram
+            .extend_from_slice(topic.as_bytes())
+            .and_then(|_| datagram.push(b' ').map_err(|_| ()))
+            .and_then(|_| datagram.extend_from_slice(payload.as_bytes()))
+            .map_err(|_| Error::TooLong)?;
+
+        let stack = self.stack.lock().await;
+        let mut rx_meta = [PacketMetadata::EMPTY; 1];
+        let mut rx_buf = [0; 16];
+        let mut tx_meta = [PacketMetadata::EMPTY; 1];
+        let mut tx_buf = [0; DATAGRAM_LEN];
+        let mut socket =
+            UdpSocket::new(*stack, &mut rx_meta, &mut rx_buf, &mut tx_meta, &mut tx_buf);
+        // Any free local port will do
+        socket.bind(0).map_err(|_| Error::BindFailed)?;
+        socket
+            .send_to(&datagram, (Ipv4Address::BROADCAST, self.port))
+            .await
+            .map_err(|_| Error::SendFailed)?;
+        // Datagram has to leave before the socket is dropped
+        socket.flush().await;
+        debug!("Broadcast {}", topic);
+        Ok(())
+    }
+}
+
+impl Publisher for Broadcast {
+    type Error = Error;
+
+    async fn publish_reading(&mut self, topic: &str, payload: &str) -> Result<(), Error> {
+        self.send(topic, payload).await
+    }
+
+    async fn publish_diagnostics(
+        &mut self,
+        topic: &str,
+        payload: &str,
+        _class: Class,
+    ) -> Result<(), Error> {
+        self.send(topic, payload).await
+    }
+
+    async fn announce(&mut self, payload: &str) -> Result<(), Error> {
+        self.send(MQTT_STATUS_TOPIC, payload).await
+    }
+}
@@ -0,0 +1,97 @@
+use core::cell::RefCell;
+
+use embassy_sync::blocking_mutex::CriticalSectionMutex;
+use heapless::{Deque, Vec};
+
+use crate::RAW_BURSTS;
+use crate::decoder::{MAX_SYMBOLS, Symbol};
+
+// The latest raw bursts kept in RAM whatever the decoder made of them, so a
+// misdecode noticed after the fact can still be fetched and replayed. Unlike
+// captures, nothing has to be requested before the burst arrives. Nothing
+// survives a reset.
+
+#[derive(Clone, Debug)]
+pub struct Burst {
+    /// Bursts received since boot before this one
+    pub seq: u32,
+    /// Time it was received at, in us since Unix epoch
+    pub timestamp: i64,
+    /// Model the decoder picked, if it did
+    pub picked: Option<&'static str>,
+    pub pulses: Vec<Symbol, MAX_SYMBOLS>,
+}
+
+struct Ring {
+    received: u32,
+    /// Oldest first
+    bursts: Deque<Burst, RAW_BURSTS>,
+}
+
+static RING: CriticalSectionMutex<RefCell<Ring>> = CriticalSectionMutex::new(RefCell::new(Ring {
+    received: 0,
+    bursts: Deque::new(),
+}));
+
+/// Keep a burst received at `timestamp`, dropping the oldest one
+pub fn record(pulses: &[Symbol], timestamp: i64, picked: Option<&'static str>) {
+    let mut burst = Burst {
+        seq: 0,
+        timestamp,
+        picked,
+        pulses: Vec::from_slice(pulses).unwrap_or_default(),
+    };
+    RING.lock(|ring| {
+        let mut ring = ring.borrow_mut();
+        burst.seq = ring.received;
+        ring.received = ring.received.wrapping_add(1);
+        if ring.bursts.is_full() {
+            ring.bursts.pop_front();
+        }
+        ring.bursts.push_back(burst).ok();
+    });
+}
+
+/// Which of the kept bursts to fetch
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Query {
+    /// Counting back from the latest one, which is 0
+    Index(usize),
+    /// The latest one received at or before this time, in us since Unix epoch
+    Time(i64),
+}
+
+impl Query {
+    /// `<index>` or `@<seconds since Unix epoch>`, empty asks for the latest
+    pub fn parse(query: &str) -> Option<Self> {
+        let query = query.trim();
+        if query.is_empty() {
+            return Some(Query::Index(0));
+        }
+        match query.strip_prefix('@') {
+            Some(secs) => secs
+                .parse::<i64>()
+                .ok()
+                .and_then(|secs| secs.checked_mul(1_000_000))
+                .map(Query::Time),
+            None => query.parse().ok().map(Query::Index),
+        }
+    }
+}
+
+pub fn find(query: Query) -> Option<Burst> {
+    RING.lock(|ring| {
+        let ring = ring.borrow();
+        let mut newest_first = ring.bursts.iter().rev();
+        match query {
+            Query::Index(idx) => newest_first.nth(idx),
+            Query::Time(time) => newest_first.find(|burst| burst.timestamp <= time),
+        }
+        .cloned()
+    })
+}
+
+/// Number of bursts kept
+pub fn len() -> usize {
+    RING.lock(|ring| ring.borrow().bursts.len())
+}
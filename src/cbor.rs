@@ -0,0 +1,296 @@
+// JSON payloads re-encoded as CBOR (RFC 8949) for uplinks where every byte
+// counts. Payloads are built as JSON everywhere, so the encoding is a straight
+// translation of the JSON the app writes: objects, arrays, strings without
+// escapes, numbers, booleans and null. Integers stay integers, decimals become
+// single precision floats where that keeps all their digits. SenML packs get
+// the integer labels of RFC 8428 in place of their field names.
+
+#[derive(Debug)]
+pub struct Error;
+
+/// Encode `json` as CBOR into `out`, with SenML labels if `senml` is set,
+/// returning the length of the encoding
+pub fn from_json(json: &str, senml: bool, out: &mut [u8]) -> Result<usize, Error> {
+    let mut encoder = Encoder {
+        bytes: json.as_bytes(),
+        pos: 0,
+        out,
+        len: 0,
+        senml,
+    };
+    encoder.value()?;
+    encoder.skip_whitespace();
+    if encoder.pos != encoder.bytes.len() {
+        return Err(Error);
+    }
+    Ok(encoder.len)
+}
+
+const UNSIGNED: u8 = 0;
+const NEGATIVE: u8 = 1;
+const TEXT: u8 = 3;
+const ARRAY: u8 = 4;
+const MAP: u8 = 5;
+const SIMPLE: u8 = 7;
+/// Marks the end of an indefinite length array or map
+const BREAK: u8 = 0xff;
+const INDEFINITE: u8 = 31;
+
+/// SenML label of a field in CBOR
+fn senml_label(name: &[u8]) -> Option<i64> {
+    Some(match name {
+        b"bn" => -2,
+        b"bt" => -3,
+        b"bu" => -4,
+        b"bv" => -5,
+        b"n" => 0,
+        b"u" => 1,
+        b"v" => 2,
+        b"vs" => 3,
+        b"vb" => 4,
+        b"s" => 5,
+        b"t" => 6,
+        b"ut" => 7,
+        _ => return None,
+    })
+}
+
+struct Encoder<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+    out: &'a mut [u8],
+    /// Bytes of `out` written so far
+    len: usize,
+    senml: bool,
+}
+
+impl Encoder<'_> {
+    fn skip_whitespace(&mut self) {
+        while self
+            .bytes
+            .get(self.pos)
+            .is_some_and(|byte| byte.is_ascii_whitespace())
+        {
+            self.pos += 1;
+        }
+    }
+
+    fn peek(&mut self) -> Option<u8> {
+        self.skip_whitespace();
+        self.bytes.get(self.pos).copied()
+    }
+
+    fn expect(&mut self, byte: u8) -> Result<(), Error> {
+        if self.peek() != Some(byte) {
+            return Err(Error);
+        }
+        self.pos += 1;
+        Ok(())
+    }
+
+    /// Append `bytes` to the output, failing if they don't fit
+    fn write(&mut self, bytes: &[u8]) -> Result<(), Error> {
+        let end = self.len + bytes.len();
+        self.out
+            .get_mut(self.len..end)
+            .ok_or(Error)?
+            .copy_from_slice(bytes);
+        self.len = end;
+        Ok(())
+    }
+
+    /// Initial byte and argument in the shortest form
+    fn head(&mut self, major: u8, value: u64) -> Result<(), Error> {
+        let major = major << 5;
+        match value {
+            0..24 => self.write(&[major | value as u8]),
+            24..0x100 => self.write(&[major | 24, value as u8]),
+            0x100..0x1_0000 => {
+                self.write(&[major | 25])?;
+                self.write(&(value as u16).to_be_bytes())
+            }
+            0x1_0000..0x1_0000_0000 => {
+                self.write(&[major | 26])?;
+                self.write(&(value as u32).to_be_bytes())
+            }
+            _ => {
+                self.write(&[major | 27])?;
+                self.write(&value.to_be_bytes())
+            }
+        }
+    }
+
+    fn value(&mut self) -> Result<(), Error> {
+        match self.peek().ok_or(Error)? {
+            b'{' => {
+                self.pos += 1;
+                self.write(&[MAP << 5 | INDEFINITE])?;
+                self.members(b'}', |encoder| {
+                    encoder.key()?;
+                    encoder.expect(b':')?;
+                    encoder.value()
+                })
+            }
+            b'[' => {
+                self.pos += 1;
+                self.write(&[ARRAY << 5 | INDEFINITE])?;
+                self.members(b']', Self::value)
+            }
+            b'"' => self.string(),
+            b't' => self.literal(b"true", 21),
+            b'f' => self.literal(b"false", 20),
+            b'n' => self.literal(b"null", 22),
+            _ => self.number(),
+        }
+    }
+
+    /// Members of an array or object up to `end`, which is already open
+    fn members(
+        &mut self,
+        end: u8,
+        mut member: impl FnMut(&mut Self) -> Result<(), Error>,
+    ) -> Result<(), Error> {
+        if self.peek() != Some(end) {
+            loop {
+                member(self)?;
+                match self.peek() {
+                    Some(b',') => self.pos += 1,
+                    _ => break,
+                }
+            }
+        }
+        self.expect(end)?;
+        self.write(&[BREAK])
+    }
+
+    /// Key of an object member
+    fn key(&mut self) -> Result<(), Error> {
+        if self.senml && self.peek() == Some(b'"') {
+            let rest = &self.bytes[self.pos + 1..];
+            let label = rest
+                .iter()
+                .position(|&byte| byte == b'"')
+                .and_then(|len| Some((len, senml_label(&rest[..len])?)));
+            if let Some((len, label)) = label {
+                self.pos += len + 2;
+                return self.int(label);
+            }
+        }
+        self.string()
+    }
+
+    fn int(&mut self, value: i64) -> Result<(), Error> {
+        match u64::try_from(value) {
+            Ok(value) => self.head(UNSIGNED, value),
+            Err(_) => self.head(NEGATIVE, !(value as u64)),
+        }
+    }
+
+    fn string(&mut self) -> Result<(), Error> {
+        self.expect(b'"')?;
+        let start = self.pos;
+        let len = self.bytes[start..]
+            .iter()
+            .position(|&byte| byte == b'"')
+            .ok_or(Error)?;
+        let text = &self.bytes[start..start + len];
+        if text.contains(&b'\\') {
+            return Err(Error);
+        }
+        self.pos += len + 1;
+        self.head(TEXT, len as u64)?;
+        self.write(text)
+    }
+
+    fn literal(&mut self, literal: &[u8], simple: u8) -> Result<(), Error> {
+        if !self.bytes[self.pos..].starts_with(literal) {
+            return Err(Error);
+        }
+        self.pos += literal.len();
+        self.write(&[SIMPLE << 5 | simple])
+    }
+
+    fn number(&mut self) -> Result<(), Error> {
+        let start = self.pos;
+        while self
+            .bytes
+            .get(self.pos)
+            .is_some_and(|&byte| byte.is_ascii_digit() || b"+-.eE".contains(&byte))
+        {
+            self.pos += 1;
+        }
+        let text = core::str::from_utf8(&self.bytes[start..self.pos]).map_err(|_| Error)?;
+        if let Ok(value) = text.parse::<i64>() {
+            return self.int(value);
+        }
+        let value: f64 = text.parse().map_err(|_| Error)?;
+        // Single precision holds 6 significant digits exactly
+        let digits = text
+            .bytes()
+            .take_while(|&byte| byte != b'e' && byte != b'E')
+            .filter(u8::is_ascii_digit)
+            .skip_while(|&byte| byte == b'0')
+            .count();
+        if digits <= 6 {
+            self.write(&[SIMPLE << 5 | 26])?;
+            self.write(&(value as f32).to_bits().to_be_bytes())
+        } else {
+            self.write(&[SIMPLE << 5 | 27])?;
+            self.write(&value.to_bits().to_be_bytes())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exactly_full_buffer() {
+        let mut out = [0; 7];
+        assert_eq!(from_json("{\"id\" : 42}", false, &mut out).unwrap(), 7);
+        assert_eq!(out, [0xbf, 0x62, b'i', b'd', 0x18, 0x2a, 0xff]);
+        // One byte short fails rather than truncating
+        assert!(from_json("{\"id\" : 42}", false, &mut [0; 6]).is_err());
+    }
+
+    #[test]
+    fn values() {
+        let mut out = [0; 32];
+        let len = from_json("[-70000, 21.5, true, null]", false, &mut out).unwrap();
+        assert_eq!(
+            out[..len],
+            [
+                0x9f, 0x3a, 0x00, 0x01, 0x11, 0x6f, 0xfa, 0x41, 0xac, 0x00, 0x00, 0xf5, 0xf6, 0xff
+            ]
+        );
+        // Too many digits for single precision
+        let len = from_json("1.23456789", false, &mut out).unwrap();
+        assert_eq!(out[0], 0xfb);
+        assert_eq!(out[1..len], 1.23456789f64.to_bits().to_be_bytes());
+    }
+
+    #[test]
+    fn senml_labels() {
+        let json = "[{\"bn\" : \"a\", \"n\" : \"t\", \"v\" : 2, \"x\" : 1}]";
+        let mut out = [0; 32];
+        let len = from_json(json, true, &mut out).unwrap();
+        // bn is -2, n 0 and v 2, other names stay text
+        let labelled = [
+            0x9f, 0xbf, 0x21, 0x61, b'a', 0x00, 0x61, b't', 0x02, 0x02, 0x61, b'x', 0x01, 0xff,
+            0xff,
+        ];
+        assert_eq!(out[..len], labelled);
+        let len = from_json(json, false, &mut out).unwrap();
+        assert_eq!(out[..5], [0x9f, 0xbf, 0x62, b'b', b'n']);
+        assert_eq!(len, labelled.len() + 4);
+    }
+
+    #[test]
+    fn invalid() {
+        let mut out = [0; 32];
+        assert!(from_json("{\"a\" : \"\\n\"}", false, &mut out).is_err());
+        assert!(from_json("[1, 2", false, &mut out).is_err());
+        assert!(from_json("[1] 2", false, &mut out).is_err());
+    }
+}
@@ -0,0 +1,180 @@
+use esp_hal::rtc_cntl::Rtc;
+use log::info;
+
+use crate::time;
+
+/// Source of wall clock time, in us since Unix epoch. Reading or setting it
+/// may take a trip over a bus or the network, so both are async.
+// Futures are awaited by the task owning the clock, they needn't be Send
+#[allow(async_fn_in_trait)]
+pub trait Clock {
+    /// Current time, None if the source can't tell it right now
+    async fn time(&mut self) -> Option<u64>;
+
+    /// Set the time, for sources that keep it. Returns false if it wasn't
+    /// taken.
+    async fn set_time(&mut self, time: u64) -> bool {
+        let _ = time;
+        false
+    }
+}
+
+/// Time of `source`, if there is one and it can tell
+pub async fn read(source: Option<&mut impl Clock>) -> Option<u64> {
+    source?.time().await
+}
+
+/// Pass reference `time` on to `target`, if there is one
+pub async fn write(target: Option<&mut impl Clock>, time: u64) {
+    if let Some(target) = target {
+        target.set_time(time).await;
+    }
+}
+
+/// Time since boot, carried on across resets, for units with no other source
+pub struct Uptime;
+
+impl Clock for Uptime {
+    async fn time(&mut self) -> Option<u64> {
+        Some(time::monotonic())
+    }
+}
+
+/// Wall clock as the main loop last noted it, for tasks that don't own it.
+/// Slewing since is lost, which keeps it within a second or so.
+pub struct Tracked;
+
+impl Clock for Tracked {
+    async fn time(&mut self) -> Option<u64> {
+        time::wall()
+    }
+}
+
+/// Offsets up to this are slewed, larger ones step the clock, in us
+const MAX_SLEW_US: u64 = 2_000_000;
+/// Slewed offsets are applied at 1 ms per second of RTC time
+const SLEW_RATE_INV: u64 = 1_000;
+
+/// Correction being spread over time, none by default
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+struct Slew {
+    /// RTC time it started at, in us
+    start: u64,
+    /// Correction, in us
+    offset: i64,
+}
+
+impl Slew {
+    /// Slew from `now` towards reference `time`, both in us. None if they're
+    /// too far apart, the clock has to be stepped then.
+    fn towards(now: u64, time: u64) -> Option<Self> {
+        (now.abs_diff(time) <= MAX_SLEW_US).then(|| Slew {
+            start: now,
+            offset: time as i64 - now as i64,
+        })
+    }
+
+    /// Part of the correction applied by RTC time `rtc_now`
+    fn applied(&self, rtc_now: u64) -> i64 {
+        let elapsed = rtc_now.saturating_sub(self.start);
+        let applied = (elapsed / SLEW_RATE_INV).min(self.offset.unsigned_abs());
+        if self.offset < 0 {
+            -(applied as i64)
+        } else {
+            applied as i64
+        }
+    }
+}
+
+/// Wall clock on top of RTC. Small corrections from NTP are spread over time
+/// rather than applied at once, so published timestamps stay monotonic. It
+/// counts from boot until it's set.
+pub struct RtcClock<'d> {
+    rtc: Rtc<'d>,
+    slew: Slew,
+}
+
+impl<'d> RtcClock<'d> {
+    pub fn new(rtc: Rtc<'d>) -> Self {
+        RtcClock {
+            rtc,
+            slew: Slew::default(),
+        }
+    }
+
+    /// Current time, in us since Unix epoch
+    pub fn now(&self) -> u64 {
+        let rtc_now = self.rtc.current_time_us();
+        rtc_now.saturating_add_signed(self.slew.applied(rtc_now))
+    }
+
+    /// Step clock to `time` (in us), dropping any slew in progress
+    pub fn set(&mut self, time: u64) {
+        self.rtc.set_current_time_us(time);
+        self.slew = Slew::default();
+    }
+
+    /// Adjust clock to reference `time` (in us), slewing if it's close enough
+    pub fn sync(&mut self, time: u64) {
+        let now = self.now();
+        let Some(slew) = Slew::towards(now, time) else {
+            info!("Clock is off by {} us, stepping", time as i64 - now as i64);
+            self.set(time);
+            return;
+        };
+
+        // Fold the part slewed in so far into RTC, and slew the remainder
+        self.rtc.set_current_time_us(now);
+        self.slew = slew;
+    }
+}
+
+impl Clock for RtcClock<'_> {
+    async fn time(&mut self) -> Option<u64> {
+        Some(self.now())
+    }
+
+    async fn set_time(&mut self, time: u64) -> bool {
+        self.set(time);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOW: u64 = 1_700_000_000_000_000;
+
+    #[test]
+    fn slew_direction() {
+        // Behind the reference, so it runs fast
+        let slew = Slew::towards(NOW, NOW + 1_500_000).unwrap();
+        assert_eq!(slew.applied(NOW + 1_000_000), 1_000);
+        // Ahead of it, so it runs slow
+        let slew = Slew::towards(NOW, NOW - 1_500_000).unwrap();
+        assert_eq!(slew.applied(NOW + 1_000_000), -1_000);
+    }
+
+    #[test]
+    fn slew_clamped() {
+        let slew = Slew::towards(NOW, NOW - 500).unwrap();
+        assert_eq!(slew.applied(NOW), 0);
+        assert_eq!(slew.applied(NOW + 400 * SLEW_RATE_INV), -400);
+        // Never more than the offset, however long it's been
+        assert_eq!(slew.applied(NOW + 600 * SLEW_RATE_INV), -500);
+        assert_eq!(slew.applied(u64::MAX), -500);
+        // Nor anything for RTC time before the slew started
+        assert_eq!(slew.applied(NOW - 1_000_000), 0);
+        assert_eq!(Slew::default().applied(NOW), 0);
+    }
+
+    #[test]
+    fn step_when_too_far_off() {
+        assert!(Slew::towards(NOW, NOW + MAX_SLEW_US).is_some());
+        assert!(Slew::towards(NOW, NOW - MAX_SLEW_US).is_some());
+        assert!(Slew::towards(NOW, NOW + MAX_SLEW_US + 1).is_none());
+        assert!(Slew::towards(NOW, NOW - MAX_SLEW_US - 1).is_none());
+        assert!(Slew::towards(NOW, 0).is_none());
+    }
+}
@@ -0,0 +1,121 @@
+use embassy_time::Duration;
+use heapless::Vec;
+
+use crate::decoder::SensorData;
+use crate::snapshot::REGISTRY_LEN;
+use crate::time;
+
+// Two sensors of a model set to the same channel, like a neighbour's Nexus on
+// the same switch position, look like one sensor with impossible swings to
+// anything that goes by channel. Readings switching back and forth between two
+// ids on a channel, with temperatures further apart than CONFLICT_DECICELSIUS,
+// are taken as a collision.
+
+/// Longest gap between readings of the two ids that still counts as a switch
+const WINDOW: Duration = Duration::from_secs(10 * 60);
+/// Conflicting switches it takes
+const SWITCHES: u8 = 3;
+const CONFLICT_DECICELSIUS: u16 = 20;
+/// A collision on a channel is reported again at most this often
+const REPORT_INTERVAL: Duration = Duration::from_secs(6 * 60 * 60);
+
+#[derive(Clone, Copy, Debug)]
+struct Seen {
+    id: u32,
+    temp_decicelsius: i16,
+    /// Monotonic time of the reading
+    time: u64,
+}
+
+struct Channel {
+    model: &'static str,
+    channel: u8,
+    last: Seen,
+    /// Id the channel last switched from
+    other: Option<u32>,
+    switches: u8,
+    reported: Option<u64>,
+}
+
+pub struct Collisions {
+    channels: Vec<Channel, REGISTRY_LEN>,
+}
+
+impl Collisions {
+    pub const fn new() -> Self {
+        Collisions {
+            channels: Vec::new(),
+        }
+    }
+
+    /// Note a temperature reading received at monotonic `now`. Returns the
+    /// ids of the other sensor and this one once they are taken as colliding.
+    /// Sensors without a channel are left out.
+    pub fn check(&mut self, data: &SensorData, now: u64) -> Option<(u32, u32)> {
+        let climate = data.climate()?;
+        if data.channel == 0 {
+            return None;
+        }
+        let seen = Seen {
+            id: data.id,
+            temp_decicelsius: climate.temp_decicelsius,
+            time: now,
+        };
+        let found = self
+            .channels
+            .iter()
+            .position(|entry| entry.model == data.model() && entry.channel == data.channel);
+        let Some(idx) = found else {
+            if self.channels.is_full()
+                && let Some(oldest) = self
+                    .channels
+                    .iter()
+                    .enumerate()
+                    .min_by_key(|(_, entry)| entry.last.time)
+                    .map(|(idx, _)| idx)
+            {
+                self.channels.swap_remove(oldest);
+            }
+            let entry = Channel {
+                model: data.model(),
+                channel: data.channel,
+                last: seen,
+                other: None,
+                switches: 0,
+                reported: None,
+            };
+            self.channels.push(entry).ok();
+            return None;
+        };
+        let entry = &mut self.channels[idx];
+        let last = entry.last;
+        entry.last = seen;
+        if last.id == seen.id {
+            return None;
+        }
+        let conflicting = time::within(now, last.time, WINDOW)
+            && entry.other.is_none_or(|other| other == seen.id)
+            && last.temp_decicelsius.abs_diff(seen.temp_decicelsius) > CONFLICT_DECICELSIUS;
+        entry.switches = if conflicting {
+            entry.switches.saturating_add(1)
+        } else {
+            0
+        };
+        entry.other = Some(last.id);
+        if entry.switches < SWITCHES
+            || entry
+                .reported
+                .is_some_and(|reported| time::within(now, reported, REPORT_INTERVAL))
+        {
+            return None;
+        }
+        entry.reported = Some(now);
+        Some((last.id, seen.id))
+    }
+}
+
+impl Default for Collisions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,702 @@
+use core::cell::RefCell;
+use core::fmt::{self, Write};
+use core::ops::Range;
+
+use embassy_sync::blocking_mutex::CriticalSectionMutex;
+use heapless::{String, Vec};
+
+#[cfg(feature = "proto-generic")]
+use crate::decoder::TempUnit;
+#[cfg(feature = "proto-generic")]
+use crate::decoder::generic::{self, Checksum, Descriptor, Field, GENERIC_SLOTS};
+#[cfg(feature = "proto-generic")]
+use crate::decoder::learn::Modulation;
+use crate::decoder::timing::{TIMING_COUNT, TIMINGS};
+use crate::decoder::{self, PROTOCOLS, SensorData, SensorReading};
+use crate::derived::{self, Metric};
+use crate::rx;
+use crate::snapshot::REGISTRY_LEN;
+
+#[derive(Debug, PartialEq)]
+pub enum Error {
+    /// Not the JSON subset the export uses
+    Syntax,
+    UnknownKey,
+    InvalidRange,
+    /// Generic protocol is missing something or doesn't fit the decoder
+    InvalidProtocol,
+}
+
+/// Settings adjustable at runtime, exported and imported as a whole so they
+/// can be cloned across units
+#[derive(Clone)]
+pub struct Config {
+    pub timings: [Range<u16>; TIMING_COUNT],
+    /// Bitmask of enabled decoders
+    pub protocols: u32,
+    /// Valid temperatures, in 0.1 C
+    pub temperature: Range<i16>,
+    /// Bitmask of derived metrics published with readings
+    pub derived: u32,
+    /// RMT receive thresholds and glitch filter
+    pub receiver: rx::Settings,
+    /// Protocols defined at runtime, by slot
+    #[cfg(feature = "proto-generic")]
+    pub generic: [Option<Descriptor>; GENERIC_SLOTS],
+}
+
+impl Config {
+    pub fn current() -> Self {
+        Config {
+            timings: TIMINGS.map(|timing| timing.range()),
+            protocols: decoder::enabled_protocols(),
+            temperature: decoder::temp_range(),
+            derived: derived::enabled(),
+            receiver: rx::current(),
+            #[cfg(feature = "proto-generic")]
+            generic: core::array::from_fn(generic::descriptor),
+        }
+    }
+
+    /// Make the config active, it's been validated already
+    pub fn apply(&self) {
+        for (timing, range) in TIMINGS.iter().zip(&self.timings) {
+            timing.set(range.clone());
+        }
+        decoder::set_enabled_protocols(self.protocols);
+        decoder::set_temp_range(self.temperature.clone());
+        derived::set_enabled(self.derived);
+        rx::set(self.receiver);
+        #[cfg(feature = "proto-generic")]
+        for (slot, descriptor) in self.generic.iter().enumerate() {
+            generic::load(slot, descriptor.clone());
+        }
+    }
+
+    /// Parse `json` on top of this config. Every entry is validated before
+    /// anything is changed, so an invalid blob leaves the config as it was.
+    pub fn merge(&self, json: &str) -> Result<Config, Error> {
+        let mut config = self.clone();
+        let mut parser = Parser {
+            bytes: json.as_bytes(),
+            pos: 0,
+        };
+        parser.object(|parser, key| match key {
+            "timing" => parser.object(|parser, name| {
+                let idx = TIMINGS
+                    .iter()
+                    .position(|timing| timing.name() == name)
+                    .ok_or(Error::UnknownKey)?;
+                let (min, max) = parser.pair()?;
+                if min >= max {
+                    return Err(Error::InvalidRange);
+                }
+                config.timings[idx] = min..max;
+                Ok(())
+            }),
+            "protocols" => parser.object(|parser, key| {
+                let idx = decoder::find_protocol(key).ok_or(Error::UnknownKey)?;
+                if parser.boolean()? {
+                    config.protocols |= 1 << idx;
+                } else {
+                    config.protocols &= !(1 << idx);
+                }
+                Ok(())
+            }),
+            "temperature" => {
+                parser.expect(b'[')?;
+                let min = parser.signed()?;
+                parser.expect(b',')?;
+                let max = parser.signed()?;
+                parser.expect(b']')?;
+                if min >= max {
+                    return Err(Error::InvalidRange);
+                }
+                config.temperature = min..max;
+                Ok(())
+            }
+            "derived" => parser.object(|parser, key| {
+                let metric = Metric::find(key).ok_or(Error::UnknownKey)?;
+                if parser.boolean()? {
+                    config.derived |= metric.bit();
+                } else {
+                    config.derived &= !metric.bit();
+                }
+                Ok(())
+            }),
+            "receiver" => {
+                let mut receiver = config.receiver;
+                parser.object(|parser, key| {
+                    let value = parser.number()?;
+                    match key {
+                        "filter_cycles" => {
+                            receiver.filter_cycles =
+                                u8::try_from(value).map_err(|_| Error::InvalidRange)?
+                        }
+                        "idle_us" => receiver.idle_us = value,
+                        "glitch_us" => receiver.glitch_us = value,
+                        _ => return Err(Error::UnknownKey),
+                    }
+                    Ok(())
+                })?;
+                if !receiver.is_valid() {
+                    return Err(Error::InvalidRange);
+                }
+                config.receiver = receiver;
+                Ok(())
+            }
+            #[cfg(feature = "proto-generic")]
+            "generic" => parser.object(|parser, key| {
+                let slot = generic::find_slot(key).ok_or(Error::UnknownKey)?;
+                config.generic[slot] = parser.descriptor()?;
+                Ok(())
+            }),
+            _ => Err(Error::UnknownKey),
+        })?;
+        parser.skip_whitespace();
+        if parser.pos != parser.bytes.len() {
+            return Err(Error::Syntax);
+        }
+        Ok(config)
+    }
+
+    /// Same format merge() takes
+    pub fn write(&self, out: &mut impl Write) -> fmt::Result {
+        write!(out, "{{\"timing\" : {{")?;
+        for (idx, (timing, range)) in TIMINGS.iter().zip(&self.timings).enumerate() {
+            if idx > 0 {
+                write!(out, ", ")?;
+            }
+            write!(
+                out,
+                "\"{}\" : [{}, {}]",
+                timing.name(),
+                range.start,
+                range.end
+            )?;
+        }
+        write!(
+            out,
+            "}}, \"temperature\" : [{}, {}], \"protocols\" : {{",
+            self.temperature.start, self.temperature.end
+        )?;
+        for (idx, proto) in PROTOCOLS.iter().enumerate() {
+            if idx > 0 {
+                write!(out, ", ")?;
+            }
+            write!(
+                out,
+                "\"{}\" : {}",
+                proto.key,
+                self.protocols & (1 << idx) != 0
+            )?;
+        }
+        write!(out, "}}, \"derived\" : {{")?;
+        for (idx, metric) in Metric::ALL.iter().enumerate() {
+            if idx > 0 {
+                write!(out, ", ")?;
+            }
+            write!(
+                out,
+                "\"{}\" : {}",
+                metric.key(),
+                self.derived & metric.bit() != 0
+            )?;
+        }
+        write!(
+            out,
+            "}}, \"receiver\" : {{\"filter_cycles\" : {}, \"idle_us\" : {}, \"glitch_us\" : {}",
+            self.receiver.filter_cycles, self.receiver.idle_us, self.receiver.glitch_us
+        )?;
+        #[cfg(feature = "proto-generic")]
+        {
+            write!(out, "}}, \"generic\" : {{")?;
+            for (slot, descriptor) in self.generic.iter().enumerate() {
+                if slot > 0 {
+                    write!(out, ", ")?;
+                }
+                write!(out, "\"{}\" : ", generic::KEYS[slot])?;
+                match descriptor {
+                    Some(descriptor) => write_descriptor(out, descriptor)?,
+                    None => write!(out, "{{}}")?,
+                }
+            }
+        }
+        write!(out, "}} }}")
+    }
+}
+
+/// Longest alias of a sensor
+pub const ALIAS_LEN: usize = 32;
+/// Longest zone name
+pub const ZONE_LEN: usize = 16;
+const MODEL_LEN: usize = 24;
+
+/// Settings of a single sensor, kept on the broker as retained messages under
+/// MQTT_SENSOR_CONFIG_TOPICS rather than in the config blob, so each sensor
+/// can be managed on its own from any MQTT client
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SensorConfig {
+    /// Published along with readings, empty for none
+    pub alias: String<ALIAS_LEN>,
+    /// Room or area the sensor is in, empty for none
+    pub zone: String<ZONE_LEN>,
+    /// Added to temperatures, in 0.1 C
+    pub temperature: i16,
+    /// Added to humidity, in %
+    pub humidity: i16,
+    /// Sensor is a doorbell button, its motion events are presses
+    pub doorbell: bool,
+    /// Retransmit presses for the doorbell's own chime
+    pub chime: bool,
+}
+
+impl SensorConfig {
+    /// Parse `{"alias" : "porch", "zone" : "outside", "temperature" : -5,
+    /// "humidity" : 3, "doorbell" : true, "chime" : true}`, every key is
+    /// optional. Only doorbells have a chime.
+    pub fn parse(json: &str) -> Result<Self, Error> {
+        let mut config = SensorConfig::default();
+        let mut parser = Parser {
+            bytes: json.as_bytes(),
+            pos: 0,
+        };
+        parser.object(|parser, key| {
+            match key {
+                "alias" => {
+                    let alias = parser.string()?;
+                    // Written into payloads as is
+                    if alias.chars().any(|c| c == '\\' || c.is_control()) {
+                        return Err(Error::Syntax);
+                    }
+                    config.alias = alias.try_into().map_err(|_| Error::InvalidRange)?;
+                }
+                "zone" => {
+                    let zone = parser.string()?;
+                    // Ends up in topics
+                    if !zone
+                        .bytes()
+                        .all(|byte| byte.is_ascii_alphanumeric() || byte == b'_' || byte == b'-')
+                    {
+                        return Err(Error::Syntax);
+                    }
+                    config.zone = zone.try_into().map_err(|_| Error::InvalidRange)?;
+                }
+                "temperature" => config.temperature = parser.signed()?,
+                "humidity" => {
+                    config.humidity = parser.signed()?;
+                    if config.humidity.abs() > 100 {
+                        return Err(Error::InvalidRange);
+                    }
+                }
+                "doorbell" => config.doorbell = parser.boolean()?,
+                "chime" => config.chime = parser.boolean()?,
+                _ => return Err(Error::UnknownKey),
+            }
+            Ok(())
+        })?;
+        parser.skip_whitespace();
+        if parser.pos != parser.bytes.len() {
+            return Err(Error::Syntax);
+        }
+        if config.chime && !config.doorbell {
+            return Err(Error::InvalidRange);
+        }
+        Ok(config)
+    }
+
+    /// Add the offsets to the reading, and turn motion of a doorbell into a
+    /// press
+    pub fn calibrate(&self, data: &mut SensorData) {
+        if self.doorbell
+            && let SensorReading::Motion(code) = data.reading
+        {
+            data.reading = SensorReading::Doorbell(code);
+        }
+        let (SensorReading::Climate(climate) | SensorReading::Weather(climate, _, _)) =
+            &mut data.reading
+        else {
+            return;
+        };
+        climate.temp_decicelsius = climate.temp_decicelsius.saturating_add(self.temperature);
+        if let Some(humidity) = &mut climate.humidity {
+            *humidity = (*humidity as i16 + self.humidity).clamp(0, 100) as u8;
+        }
+    }
+}
+
+struct SensorEntry {
+    model: String<MODEL_LEN>,
+    id: u32,
+    config: SensorConfig,
+}
+
+static SENSORS: CriticalSectionMutex<RefCell<Vec<SensorEntry, REGISTRY_LEN>>> =
+    CriticalSectionMutex::new(RefCell::new(Vec::new()));
+
+/// Settings of sensor `id` of `model`, replacing earlier ones. Default
+/// settings remove them. Returns false if there are REGISTRY_LEN sensors
+/// with settings already.
+pub fn set_sensor(model: &str, id: u32, config: SensorConfig) -> bool {
+    SENSORS.lock(|sensors| {
+        let mut sensors = sensors.borrow_mut();
+        let found = sensors
+            .iter()
+            .position(|entry| entry.model == model && entry.id == id);
+        match (found, config == SensorConfig::default()) {
+            (Some(idx), true) => {
+                sensors.swap_remove(idx);
+                true
+            }
+            (Some(idx), false) => {
+                sensors[idx].config = config;
+                true
+            }
+            (None, true) => true,
+            (None, false) => {
+                let Ok(model) = model.try_into() else {
+                    return false;
+                };
+                sensors.push(SensorEntry { model, id, config }).is_ok()
+            }
+        }
+    })
+}
+
+/// Zone of the sensor `data` is from, empty if it has none
+pub fn zone(data: &SensorData) -> String<ZONE_LEN> {
+    SENSORS.lock(|sensors| {
+        sensors
+            .borrow()
+            .iter()
+            .find(|entry| entry.model == data.model() && entry.id == data.id)
+            .map(|entry| entry.config.zone.clone())
+            .unwrap_or_default()
+    })
+}
+
+/// Settings of the sensor `data` is from, if it has any
+pub fn sensor(data: &SensorData) -> Option<SensorConfig> {
+    SENSORS.lock(|sensors| {
+        sensors
+            .borrow()
+            .iter()
+            .find(|entry| entry.model == data.model() && entry.id == data.id)
+            .map(|entry| entry.config.clone())
+    })
+}
+
+/// Same format Parser::descriptor() takes, ranges are `[min, max]` with max
+/// exclusive and fields are `[offset, width]`
+#[cfg(feature = "proto-generic")]
+fn write_descriptor(out: &mut impl Write, descriptor: &Descriptor) -> fmt::Result {
+    write!(
+        out,
+        "{{\"modulation\" : \"{}\", \"bits\" : {}",
+        descriptor.modulation.key(),
+        descriptor.bits
+    )?;
+    // Pulse isn't checked for PWM
+    let ranges = [
+        (descriptor.modulation == Modulation::Ppm).then_some(("pulse", &descriptor.pulse)),
+        Some(("zero", &descriptor.zero)),
+        Some(("one", &descriptor.one)),
+        descriptor.gap.as_ref().map(|gap| ("gap", gap)),
+    ];
+    for (name, range) in ranges.into_iter().flatten() {
+        write!(out, ", \"{}\" : [{}, {}]", name, range.start, range.end)?;
+    }
+    let fields = [
+        ("id", descriptor.id),
+        ("channel", descriptor.channel),
+        ("battery", descriptor.battery),
+        ("temperature", descriptor.temperature),
+        ("humidity", descriptor.humidity),
+        ("code", descriptor.code),
+    ];
+    for (name, field) in fields {
+        if let Some(field) = field {
+            write!(out, ", \"{}\" : [{}, {}]", name, field.offset, field.width)?;
+        }
+    }
+    if descriptor.unit != TempUnit::Celsius {
+        write!(out, ", \"unit\" : \"{}\"", descriptor.unit.key())?;
+    }
+    match descriptor.checksum {
+        Some(Checksum::Crc8 { poly, init }) => write!(
+            out,
+            ", \"checksum\" : \"crc8\", \"poly\" : {}, \"init\" : {}",
+            poly, init
+        )?,
+        Some(checksum) => write!(out, ", \"checksum\" : \"{}\"", checksum.key())?,
+        None => {}
+    }
+    write!(out, "}}")
+}
+
+/// Just enough of JSON for the config: objects, strings without escapes,
+/// integers and booleans
+struct Parser<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn skip_whitespace(&mut self) {
+        while self
+            .bytes
+            .get(self.pos)
+            .is_some_and(|byte| byte.is_ascii_whitespace())
+        {
+            self.pos += 1;
+        }
+    }
+
+    fn peek(&mut self) -> Option<u8> {
+        self.skip_whitespace();
+        self.bytes.get(self.pos).copied()
+    }
+
+    fn expect(&mut self, byte: u8) -> Result<(), Error> {
+        if self.peek() != Some(byte) {
+            return Err(Error::Syntax);
+        }
+        self.pos += 1;
+        Ok(())
+    }
+
+    fn string(&mut self) -> Result<&'a str, Error> {
+        self.expect(b'"')?;
+        let start = self.pos;
+        let len = self.bytes[start..]
+            .iter()
+            .position(|&byte| byte == b'"')
+            .ok_or(Error::Syntax)?;
+        self.pos += len + 1;
+        core::str::from_utf8(&self.bytes[start..start + len]).map_err(|_| Error::Syntax)
+    }
+
+    fn number(&mut self) -> Result<u16, Error> {
+        self.skip_whitespace();
+        let start = self.pos;
+        while self
+            .bytes
+            .get(self.pos)
+            .is_some_and(|byte| byte.is_ascii_digit())
+        {
+            self.pos += 1;
+        }
+        core::str::from_utf8(&self.bytes[start..self.pos])
+            .ok()
+            .and_then(|digits| digits.parse().ok())
+            .ok_or(Error::Syntax)
+    }
+
+    /// Number with an optional minus sign
+    fn signed(&mut self) -> Result<i16, Error> {
+        let negative = self.peek() == Some(b'-');
+        if negative {
+            self.pos += 1;
+        }
+        let value = self.number()? as i32;
+        i16::try_from(if negative { -value } else { value }).map_err(|_| Error::InvalidRange)
+    }
+
+    /// `[a, b]`, used for ranges and fields
+    fn pair(&mut self) -> Result<(u16, u16), Error> {
+        self.expect(b'[')?;
+        let a = self.number()?;
+        self.expect(b',')?;
+        let b = self.number()?;
+        self.expect(b']')?;
+        Ok((a, b))
+    }
+
+    /// Generic protocol, `{}` for none. `modulation`, `bits`, `zero`, `one`
+    /// and for PPM `pulse` are required.
+    #[cfg(feature = "proto-generic")]
+    fn descriptor(&mut self) -> Result<Option<Descriptor>, Error> {
+        let mut modulation = None;
+        let mut bits = None;
+        let [mut pulse, mut zero, mut one, mut gap] = [None, None, None, None];
+        let [mut id, mut channel, mut battery] = [None, None, None];
+        let [mut temperature, mut humidity, mut code] = [None, None, None];
+        let mut unit = TempUnit::Celsius;
+        let mut checksum = None;
+        // CRC-8/MAXIM, the one sensors use the most
+        let (mut poly, mut init) = (0x31, 0);
+        let mut empty = true;
+        let byte = |value: u16| u8::try_from(value).map_err(|_| Error::InvalidProtocol);
+        self.object(|parser, key| {
+            empty = false;
+            match key {
+                "modulation" => {
+                    modulation = Some(match parser.string()? {
+                        "ppm" => Modulation::Ppm,
+                        "pwm" => Modulation::Pwm,
+                        _ => return Err(Error::InvalidProtocol),
+                    })
+                }
+                "bits" => bits = Some(byte(parser.number()?)?),
+                "pulse" | "zero" | "one" | "gap" => {
+                    let (min, max) = parser.pair()?;
+                    let range = Some(min..max);
+                    match key {
+                        "pulse" => pulse = range,
+                        "zero" => zero = range,
+                        "one" => one = range,
+                        _ => gap = range,
+                    }
+                }
+                "id" | "channel" | "battery" | "temperature" | "humidity" | "code" => {
+                    let (offset, width) = parser.pair()?;
+                    let field = Some(Field {
+                        offset: byte(offset)?,
+                        width: byte(width)?,
+                    });
+                    match key {
+                        "id" => id = field,
+                        "channel" => channel = field,
+                        "battery" => battery = field,
+                        "temperature" => temperature = field,
+                        "humidity" => humidity = field,
+                        _ => code = field,
+                    }
+                }
+                "unit" => {
+                    unit = match parser.string()? {
+                        "c" => TempUnit::Celsius,
+                        "f" => TempUnit::Fahrenheit,
+                        _ => return Err(Error::InvalidProtocol),
+                    }
+                }
+                "checksum" => checksum = Some(parser.string()?),
+                "poly" => poly = byte(parser.number()?)?,
+                "init" => init = byte(parser.number()?)?,
+                _ => return Err(Error::UnknownKey),
+            }
+            Ok(())
+        })?;
+        if empty {
+            return Ok(None);
+        }
+        let (Some(modulation), Some(bits), Some(zero), Some(one)) = (modulation, bits, zero, one)
+        else {
+            return Err(Error::InvalidProtocol);
+        };
+        let pulse = match (modulation, pulse) {
+            (_, Some(pulse)) => pulse,
+            // Isn't checked, both bit pulses fit it
+            (Modulation::Pwm, None) => zero.start.min(one.start)..zero.end.max(one.end),
+            (Modulation::Ppm, None) => return Err(Error::InvalidProtocol),
+        };
+        let checksum = match checksum {
+            None => None,
+            Some("sum8") => Some(Checksum::Sum8),
+            Some("xor8") => Some(Checksum::Xor8),
+            Some("crc8") => Some(Checksum::Crc8 { poly, init }),
+            Some(_) => return Err(Error::InvalidProtocol),
+        };
+        let descriptor = Descriptor {
+            modulation,
+            bits,
+            pulse,
+            zero,
+            one,
+            gap,
+            id,
+            channel,
+            battery,
+            temperature,
+            unit,
+            humidity,
+            code,
+            checksum,
+        };
+        if !descriptor.is_valid() {
+            return Err(Error::InvalidProtocol);
+        }
+        Ok(Some(descriptor))
+    }
+
+    fn boolean(&mut self) -> Result<bool, Error> {
+        self.skip_whitespace();
+        for (literal, value) in [(&b"true"[..], true), (&b"false"[..], false)] {
+            if self.bytes[self.pos..].starts_with(literal) {
+                self.pos += literal.len();
+                return Ok(value);
+            }
+        }
+        Err(Error::Syntax)
+    }
+
+    /// Parse an object, calling `member` to parse the value of every key
+    fn object(
+        &mut self,
+        mut member: impl FnMut(&mut Self, &'a str) -> Result<(), Error>,
+    ) -> Result<(), Error> {
+        self.expect(b'{')?;
+        if self.peek() == Some(b'}') {
+            self.pos += 1;
+            return Ok(());
+        }
+        loop {
+            let key = self.string()?;
+            self.expect(b':')?;
+            member(self, key)?;
+            match self.peek() {
+                Some(b',') => self.pos += 1,
+                Some(b'}') => {
+                    self.pos += 1;
+                    return Ok(());
+                }
+                _ => return Err(Error::Syntax),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn merge() {
+        let config = Config::current();
+        let merged = config
+            .merge("{\"temperature\" : [-100, 500], \"derived\" : {\"wind_chill\" : true}}")
+            .unwrap();
+        assert_eq!(merged.temperature, -100..500);
+        assert_ne!(merged.derived & Metric::WindChill.bit(), 0);
+        // Keys left out keep their values
+        assert_eq!(merged.protocols, config.protocols);
+        assert_eq!(merged.timings, config.timings);
+    }
+
+    #[test]
+    fn merge_rejects_invalid_blob() {
+        let config = Config::current();
+        // The valid temperature before the invalid entry isn't taken either
+        for (json, error) in [
+            (
+                "{\"temperature\" : [-100, 500], \"nope\" : 1}",
+                Error::UnknownKey,
+            ),
+            (
+                "{\"temperature\" : [-100, 500], \"derived\" : {\"nope\" : true}}",
+                Error::UnknownKey,
+            ),
+            ("{\"temperature\" : [500, -100]}", Error::InvalidRange),
+            (
+                "{\"temperature\" : [-100, 500], \"receiver\" : {\"glitch_us\" : 60000}}",
+                Error::InvalidRange,
+            ),
+            ("{\"temperature\" : [-100, 500]", Error::Syntax),
+            ("{\"temperature\" : [-100, 500]} {}", Error::Syntax),
+        ] {
+            assert_eq!(config.merge(json).err(), Some(error), "{json}");
+        }
+        // Nothing is applied by merging
+        assert_eq!(Config::current().temperature, config.temperature);
+    }
+}
@@ -0,0 +1,290 @@
+use core::fmt;
+
+use heapless::String;
+
+use crate::{MQTT_SERVER, PASSWORD, SSID};
+
+/// Longest SSID Wi-Fi allows
+pub const SSID_LEN: usize = 32;
+/// Longest WPA2 passphrase
+pub const PASSWORD_LEN: usize = 64;
+/// Longest broker list
+pub const BROKER_LEN: usize = 128;
+
+/// Wi-Fi network and brokers to connect to. Compiled in from SSID, PASSWORD
+/// and MQTT_SERVER, unless other ones were provisioned over serial.
+#[derive(Clone, Default, PartialEq)]
+pub struct Credentials {
+    pub ssid: String<SSID_LEN>,
+    /// Empty for an open network
+    pub password: String<PASSWORD_LEN>,
+    /// Same format as MQTT_SERVER
+    pub broker: String<BROKER_LEN>,
+}
+
+impl Credentials {
+    pub fn compiled() -> Self {
+        // Lengths are checked at compile time
+        Credentials {
+            ssid: SSID.try_into().unwrap_or_default(),
+            password: PASSWORD.try_into().unwrap_or_default(),
+            broker: MQTT_SERVER.try_into().unwrap_or_default(),
+        }
+    }
+
+    /// Enough to connect with
+    pub fn is_complete(&self) -> bool {
+        !self.ssid.is_empty() && !self.broker.is_empty()
+    }
+}
+
+// Password stays out of the logs
+impl fmt::Debug for Credentials {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Credentials")
+            .field("ssid", &self.ssid)
+            .field("password", &!self.password.is_empty())
+            .field("broker", &self.broker)
+            .finish()
+    }
+}
+
+#[cfg(feature = "provisioning")]
+pub use storage::{Error, PARTITION_LABEL, clear, load, save};
+
+/// Credentials kept in the first sector of a flash partition: magic and
+/// length, then the fields separated by NUL. With CREDENTIALS_KEY_BLOCK set
+/// the fields are encrypted and authenticated with keys the HMAC peripheral
+/// derives from that eFuse key block, which software can't read. Otherwise
+/// they're in plain text with a CRC-8.
+#[cfg(feature = "provisioning")]
+mod storage {
+    use embedded_storage::{ReadStorage, Storage};
+    use esp_hal::hmac::{Hmac, HmacPurpose, KeyId};
+    use esp_hal::rng::Rng;
+    use log::{info, warn};
+
+    use super::{BROKER_LEN, Credentials, PASSWORD_LEN, SSID_LEN};
+    use crate::CREDENTIALS_KEY_BLOCK;
+    use crate::decoder::bits::crc8;
+    use crate::flash::{self, Flash, SECTOR_LEN};
+
+    /// Data partition credentials are kept in
+    pub const PARTITION_LABEL: &str = "provision";
+
+    const MAGIC: u32 = 0x564f_5250;
+    const SEALED_MAGIC: u32 = 0x5356_5250;
+    const HEADER_LEN: usize = 8;
+    const RECORD_LEN: usize = SSID_LEN + PASSWORD_LEN + BROKER_LEN + 3;
+    // Encrypted record: header, nonce, tag, then the fields XORed with
+    // HMAC(nonce, 0, block number) blocks. The tag is HMAC(nonce, 1, fields).
+    const NONCE_LEN: usize = 16;
+    const TAG_LEN: usize = 32;
+    const SEALED_AT: usize = HEADER_LEN + NONCE_LEN + TAG_LEN;
+    const SECTOR_USED: usize = SEALED_AT + RECORD_LEN;
+
+    #[derive(Debug)]
+    pub enum Error {
+        Flash(flash::Error),
+        /// No HMAC_UP key in CREDENTIALS_KEY_BLOCK
+        Key,
+    }
+
+    impl Error {
+        /// For the provisioning console
+        pub fn reason(&self) -> &'static str {
+            match self {
+                Error::Flash(_) => "flash",
+                Error::Key => "no encryption key",
+            }
+        }
+    }
+
+    impl From<flash::Error> for Error {
+        fn from(e: flash::Error) -> Self {
+            Error::Flash(e)
+        }
+    }
+
+    fn partition() -> Option<u32> {
+        let Some((offset, len)) = flash::find_partition(PARTITION_LABEL) else {
+            info!(
+                "No {} partition, credentials can't be stored",
+                PARTITION_LABEL
+            );
+            return None;
+        };
+        if !offset.is_multiple_of(SECTOR_LEN) || len < SECTOR_LEN {
+            warn!("{} partition must be at least 1 sector", PARTITION_LABEL);
+            return None;
+        }
+        Some(offset)
+    }
+
+    /// Stored credentials, None if there are none or they're corrupt. Ones
+    /// stored in plain text are encrypted if CREDENTIALS_KEY_BLOCK is set.
+    pub fn load(hmac: &mut Hmac<'_>) -> Option<Credentials> {
+        let addr = partition()?;
+        let mut sector = [0u8; SECTOR_USED];
+        Flash.read(addr, &mut sector[..HEADER_LEN]).ok()?;
+        let magic = u32::from_le_bytes([sector[0], sector[1], sector[2], sector[3]]);
+        let header = u32::from_le_bytes([sector[4], sector[5], sector[6], sector[7]]);
+        let len = (header & 0xffff) as usize;
+        if len > RECORD_LEN {
+            return None;
+        }
+        let record = match magic {
+            MAGIC => {
+                let record = &mut sector[HEADER_LEN..HEADER_LEN + len];
+                Flash.read(addr + HEADER_LEN as u32, record).ok()?;
+                if crc8(record, 0x07, 0) as u32 != header >> 16 {
+                    warn!("Stored credentials are corrupt, ignoring them");
+                    return None;
+                }
+                &sector[HEADER_LEN..HEADER_LEN + len]
+            }
+            SEALED_MAGIC => {
+                let Some(key) = key() else {
+                    warn!("Stored credentials are encrypted, ignoring them");
+                    return None;
+                };
+                Flash
+                    .read(
+                        addr + HEADER_LEN as u32,
+                        &mut sector[HEADER_LEN..SEALED_AT + len],
+                    )
+                    .ok()?;
+                let (head, record) = sector.split_at_mut(SEALED_AT);
+                let nonce = &head[HEADER_LEN..HEADER_LEN + NONCE_LEN];
+                let tag = &head[HEADER_LEN + NONCE_LEN..];
+                let record = &mut record[..len];
+                match mac(hmac, key, &[nonce, &[1], record]) {
+                    Ok(expected) if same(&expected, tag) => {}
+                    Ok(_) => {
+                        warn!(
+                            "Stored credentials are corrupt or from another device, ignoring them"
+                        );
+                        return None;
+                    }
+                    Err(e) => {
+                        warn!("Failed to decrypt stored credentials: {:?}", e);
+                        return None;
+                    }
+                }
+                apply_keystream(hmac, key, nonce, record).ok()?;
+                &sector[SEALED_AT..SEALED_AT + len]
+            }
+            _ => return None,
+        };
+        let mut fields = core::str::from_utf8(record).ok()?.split('\0');
+        let credentials = Credentials {
+            ssid: fields.next()?.try_into().ok()?,
+            password: fields.next()?.try_into().ok()?,
+            broker: fields.next()?.try_into().ok()?,
+        };
+        if magic == MAGIC && key().is_some() {
+            match save(&credentials, hmac) {
+                Ok(()) => info!("Encrypted stored credentials"),
+                Err(e) => warn!("Failed to encrypt stored credentials: {:?}", e),
+            }
+        }
+        Some(credentials)
+    }
+
+    /// Replace the stored credentials
+    pub fn save(credentials: &Credentials, hmac: &mut Hmac<'_>) -> Result<(), Error> {
+        let addr = partition().ok_or(Error::Flash(flash::Error::Flash))?;
+        let mut record = [0u8; RECORD_LEN];
+        let mut len = 0;
+        for (idx, field) in [
+            credentials.ssid.as_str(),
+            credentials.password.as_str(),
+            credentials.broker.as_str(),
+        ]
+        .iter()
+        .enumerate()
+        {
+            if idx > 0 {
+                record[len] = 0;
+                len += 1;
+            }
+            record[len..len + field.len()].copy_from_slice(field.as_bytes());
+            len += field.len();
+        }
+        let record = &mut record[..len];
+        let mut header = [0u8; SEALED_AT];
+        let (magic, check, head_len) = match key() {
+            Some(key) => {
+                let (nonce, tag) = header[HEADER_LEN..].split_at_mut(NONCE_LEN);
+                Rng::new().read(nonce);
+                apply_keystream(hmac, key, nonce, record)?;
+                tag.copy_from_slice(&mac(hmac, key, &[nonce, &[1], record])?);
+                (SEALED_MAGIC, 0, SEALED_AT)
+            }
+            None => (MAGIC, crc8(record, 0x07, 0) as u32, HEADER_LEN),
+        };
+        header[..4].copy_from_slice(&magic.to_le_bytes());
+        header[4..8].copy_from_slice(&(len as u32 | check << 16).to_le_bytes());
+        Flash::erase_sector(addr)?;
+        Flash.write(addr + head_len as u32, record)?;
+        // Header last, an interrupted save leaves no record rather than a
+        // partial one
+        Flash.write(addr + HEADER_LEN as u32, &header[HEADER_LEN..head_len])?;
+        Flash.write(addr, &header[..HEADER_LEN])?;
+        Ok(())
+    }
+
+    /// Forget the stored credentials, the compiled in ones are used again
+    pub fn clear() -> Result<(), Error> {
+        let addr = partition().ok_or(Error::Flash(flash::Error::Flash))?;
+        Ok(Flash::erase_sector(addr)?)
+    }
+
+    fn key() -> Option<KeyId> {
+        Some(match CREDENTIALS_KEY_BLOCK? {
+            0 => KeyId::Key0,
+            1 => KeyId::Key1,
+            2 => KeyId::Key2,
+            3 => KeyId::Key3,
+            4 => KeyId::Key4,
+            _ => KeyId::Key5,
+        })
+    }
+
+    /// HMAC-SHA-256 of `parts` with the eFuse key
+    fn mac(hmac: &mut Hmac<'_>, key: KeyId, parts: &[&[u8]]) -> Result<[u8; 32], Error> {
+        hmac.init();
+        nb::block!(hmac.configure(HmacPurpose::ToUser, key)).map_err(|_| Error::Key)?;
+        for part in parts {
+            let mut remaining = *part;
+            while !remaining.is_empty() {
+                let Ok(rest) = nb::block!(hmac.update(remaining));
+                remaining = rest;
+            }
+        }
+        let mut output = [0; 32];
+        let Ok(()) = nb::block!(hmac.finalize(&mut output));
+        Ok(output)
+    }
+
+    /// Encrypt or decrypt `record` in place
+    fn apply_keystream(
+        hmac: &mut Hmac<'_>,
+        key: KeyId,
+        nonce: &[u8],
+        record: &mut [u8],
+    ) -> Result<(), Error> {
+        for (block, chunk) in record.chunks_mut(32).enumerate() {
+            let stream = mac(hmac, key, &[nonce, &[0, block as u8]])?;
+            for (byte, mask) in chunk.iter_mut().zip(stream) {
+                *byte ^= mask;
+            }
+        }
+        Ok(())
+    }
+
+    /// Compare without an early exit
+    fn same(a: &[u8], b: &[u8]) -> bool {
+        a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (a, b)| acc | (a ^ b)) == 0
+    }
+}
@@ -0,0 +1,335 @@
+use embassy_time::Duration;
+use heapless::Vec;
+
+use crate::decoder::SensorData;
+use crate::time;
+
+/// Per-device retrigger cooldown for event sensors. Tracks last accepted event
+/// time for up to N devices, the oldest entry is evicted when the table is full.
+#[derive(Clone)]
+pub struct Cooldown<const N: usize> {
+    period: Duration,
+    entries: Vec<(u32, u64), N>,
+}
+
+impl<const N: usize> Cooldown<N> {
+    pub const fn new(period: Duration) -> Self {
+        Cooldown {
+            period,
+            entries: Vec::new(),
+        }
+    }
+
+    /// Returns true if the event from device `id` at monotonic `now` should be
+    /// published, i.e. the device has been quiet for the whole cooldown period.
+    pub fn check(&mut self, id: u32, now: u64) -> bool {
+        if let Some(entry) = self.entries.iter_mut().find(|(dev, _)| *dev == id) {
+            if time::within(now, entry.1, self.period) {
+                return false;
+            }
+            entry.1 = now;
+            return true;
+        }
+
+        if self.entries.is_full()
+            && let Some(oldest) = self
+                .entries
+                .iter()
+                .enumerate()
+                .min_by_key(|(_, (_, time))| *time)
+                .map(|(idx, _)| idx)
+        {
+            self.entries.swap_remove(oldest);
+        }
+        self.entries.push((id, now)).ok();
+        true
+    }
+}
+
+/// Last event of a sensor
+#[derive(Clone)]
+struct Seen {
+    data: SensorData,
+    /// Monotonic time it was last received at
+    last_seen: u64,
+    /// Events accepted so far
+    count: u32,
+}
+
+/// Replay suppression for event sensors. A stuck or bouncing button keeps
+/// sending the same code, that counts as one event as long as it comes back
+/// less than `window` after the last time. The window rolls, every repeat
+/// extends it. Also counts the events of every sensor, so consumers can tell
+/// how many they missed. Tracks up to N sensors, the least recently seen one
+/// is evicted when the table is full.
+#[derive(Clone)]
+pub struct ReplayFilter<const N: usize> {
+    window: Duration,
+    seen: Vec<Seen, N>,
+}
+
+impl<const N: usize> ReplayFilter<N> {
+    pub const fn new(window: Duration) -> Self {
+        ReplayFilter {
+            window,
+            seen: Vec::new(),
+        }
+    }
+
+    /// Number of the event in `data` received at monotonic `now`, counting
+    /// from 1 since the sensor was first seen. None if it's a replay.
+    pub fn check(&mut self, data: &SensorData, now: u64) -> Option<u32> {
+        if let Some(seen) = self.seen.iter_mut().find(|seen| {
+            seen.data.model() == data.model()
+                && seen.data.id == data.id
+                && seen.data.channel == data.channel
+        }) {
+            let replay =
+                seen.data.reading == data.reading && time::within(now, seen.last_seen, self.window);
+            seen.last_seen = now;
+            if replay {
+                return None;
+            }
+            seen.data = data.clone();
+            seen.count = seen.count.wrapping_add(1);
+            return Some(seen.count);
+        }
+
+        if self.seen.is_full()
+            && let Some(oldest) = self
+                .seen
+                .iter()
+                .enumerate()
+                .min_by_key(|(_, seen)| seen.last_seen)
+                .map(|(idx, _)| idx)
+        {
+            self.seen.swap_remove(oldest);
+        }
+        self.seen
+            .push(Seen {
+                data: data.clone(),
+                last_seen: now,
+                count: 1,
+            })
+            .ok();
+        Some(1)
+    }
+}
+
+/// Frames of a transmission need to be confirmed by this many identical ones,
+/// since some protocols have no checksum
+const CONFIRMATIONS: u8 = 2;
+
+#[derive(Clone)]
+struct Frame {
+    /// Last frame heard from the sensor, until it's confirmed
+    candidate: SensorData,
+    /// Monotonic time of the last identical candidate
+    candidate_seen: u64,
+    matches: u8,
+    /// Last confirmed transmission
+    accepted: Option<SensorData>,
+    /// Monotonic time of the last repeat of the accepted transmission
+    accepted_seen: u64,
+    /// Frames of the accepted transmission received so far
+    repeats: u8,
+    /// Accepted transmission has been returned by `finished()`
+    reported: bool,
+}
+
+impl Frame {
+    fn matches(&self, data: &SensorData) -> bool {
+        self.candidate.model() == data.model()
+            && self.candidate.id == data.id
+            && self.candidate.channel == data.channel
+    }
+}
+
+/// Transmission whose burst of repeats is over
+pub struct Finished {
+    pub data: SensorData,
+    /// Time the last repeat was received at, in us
+    pub last_seen: u64,
+    /// Number of repeats received, rough indicator of link quality
+    pub repeats: u8,
+}
+
+/// Duplicate burst suppression. A single transmission arrives as several
+/// identical frames from repeats and echoes, this accepts it once and ignores
+/// the rest. Identical frames belong to the same transmission as long as they
+/// are less than `window` apart. Tracks last frame of up to N sensors, the
+/// least recently seen one is evicted when the table is full.
+#[derive(Clone)]
+pub struct Suppressor<const N: usize> {
+    window: Duration,
+    frames: Vec<Frame, N>,
+}
+
+impl<const N: usize> Suppressor<N> {
+    pub const fn new(window: Duration) -> Self {
+        Suppressor {
+            window,
+            frames: Vec::new(),
+        }
+    }
+
+    /// Returns true if the frame received at monotonic `now` confirms a new
+    /// transmission. Repeats of it are counted until the burst is over.
+    pub fn check(&mut self, data: &SensorData, now: u64) -> bool {
+        let window = self.window;
+        let Some(frame) = self.frames.iter_mut().find(|frame| frame.matches(data)) else {
+            self.insert(data, now);
+            return false;
+        };
+
+        if let Some(accepted) = &frame.accepted
+            && *accepted == *data
+            && time::within(now, frame.accepted_seen, window)
+        {
+            frame.accepted_seen = now;
+            frame.repeats = frame.repeats.saturating_add(1);
+            return false;
+        }
+
+        if frame.candidate == *data && time::within(now, frame.candidate_seen, window) {
+            frame.matches = frame.matches.saturating_add(1);
+        } else {
+            frame.candidate = data.clone();
+            frame.matches = 1;
+        }
+        frame.candidate_seen = now;
+        if frame.matches != CONFIRMATIONS {
+            return false;
+        }
+
+        // A transmission that didn't finish yet is superseded by this one
+        frame.accepted = Some(data.clone());
+        frame.accepted_seen = now;
+        frame.repeats = frame.matches;
+        frame.reported = false;
+        true
+    }
+
+    fn insert(&mut self, data: &SensorData, now: u64) {
+        if self.frames.is_full()
+            && let Some(oldest) = self
+                .frames
+                .iter()
+                .enumerate()
+                .min_by_key(|(_, frame)| frame.candidate_seen.max(frame.accepted_seen))
+                .map(|(idx, _)| idx)
+        {
+            self.frames.swap_remove(oldest);
+        }
+        self.frames
+            .push(Frame {
+                candidate: data.clone(),
+                candidate_seen: now,
+                matches: 1,
+                accepted: None,
+                accepted_seen: 0,
+                repeats: 0,
+                reported: false,
+            })
+            .ok();
+    }
+
+    /// Next accepted transmission that hasn't been repeated for the whole
+    /// window by monotonic `now`, each one is returned once
+    pub fn finished(&mut self, now: u64) -> Option<Finished> {
+        let window = self.window;
+        let frame = self.frames.iter_mut().find(|frame| {
+            frame.accepted.is_some()
+                && !frame.reported
+                && time::passed(now, frame.accepted_seen, window)
+        })?;
+        frame.reported = true;
+        Some(Finished {
+            data: frame.accepted.clone()?,
+            last_seen: frame.accepted_seen,
+            repeats: frame.repeats,
+        })
+    }
+}
+
+/// Frames of a split reading waiting for the rest
+struct Partial {
+    finished: Finished,
+    /// Monotonic time of the first frame
+    first_seen: u64,
+}
+
+/// Merges readings of stations that send quantities in separate frames, e.g.
+/// temperature in one and humidity in the next. A reading is held for
+/// `window` after it finishes, readings of the same sensor finishing in the
+/// meantime are merged into it. A zero window passes readings straight
+/// through. Tracks up to N sensors, the longest held one is let go when the
+/// table is full.
+pub struct Cluster<const N: usize> {
+    window: Duration,
+    pending: Vec<Partial, N>,
+}
+
+impl<const N: usize> Cluster<N> {
+    pub const fn new(window: Duration) -> Self {
+        Cluster {
+            window,
+            pending: Vec::new(),
+        }
+    }
+
+    /// Hold `finished` for merging, returns whatever has to be published
+    /// right away instead: `finished` itself without a window, or a held
+    /// reading it doesn't go together with or that had to make room.
+    pub fn push(&mut self, finished: Finished) -> Option<Finished> {
+        if self.window.as_ticks() == 0 {
+            return Some(finished);
+        }
+        if let Some(partial) = self.pending.iter_mut().find(|partial| {
+            partial.finished.data.model() == finished.data.model()
+                && partial.finished.data.id == finished.data.id
+                && partial.finished.data.channel == finished.data.channel
+        }) {
+            let Some(data) = partial.finished.data.merge(&finished.data) else {
+                // Different kind of reading, the held one is complete
+                let first_seen = finished.last_seen;
+                let held = core::mem::replace(&mut partial.finished, finished);
+                partial.first_seen = first_seen;
+                return Some(held);
+            };
+            partial.finished = Finished {
+                data,
+                last_seen: finished.last_seen,
+                repeats: partial.finished.repeats.max(finished.repeats),
+            };
+            return None;
+        }
+        let oldest = self
+            .pending
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, partial)| partial.first_seen)
+            .map(|(idx, _)| idx);
+        let evicted = match oldest {
+            Some(idx) if self.pending.is_full() => Some(self.pending.swap_remove(idx).finished),
+            _ => None,
+        };
+        let first_seen = finished.last_seen;
+        self.pending
+            .push(Partial {
+                finished,
+                first_seen,
+            })
+            .ok();
+        evicted
+    }
+
+    /// Next held reading whose window is over by monotonic `now`
+    pub fn finished(&mut self, now: u64) -> Option<Finished> {
+        let idx = self
+            .pending
+            .iter()
+            .position(|partial| time::passed(now, partial.first_seen, self.window))?;
+        Some(self.pending.swap_remove(idx).finished)
+    }
+}
@@ -0,0 +1,123 @@
+use core::sync::atomic::{AtomicU32, Ordering};
+
+use crate::decoder::{Climate, SensorData, Wind};
+
+/// Quantities computed from readings rather than sent by the sensor, each
+/// published only once it's enabled in the config
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Metric {
+    /// Felt temperature in wind, for weather stations
+    WindChill,
+    /// Water vapour per volume of air, which unlike relative humidity doesn't
+    /// change with temperature
+    AbsoluteHumidity,
+}
+
+impl Metric {
+    pub const ALL: [Metric; 2] = [Metric::WindChill, Metric::AbsoluteHumidity];
+
+    pub fn key(self) -> &'static str {
+        match self {
+            Metric::WindChill => "wind_chill",
+            Metric::AbsoluteHumidity => "absolute_humidity",
+        }
+    }
+
+    pub fn find(key: &str) -> Option<Metric> {
+        Metric::ALL.into_iter().find(|metric| metric.key() == key)
+    }
+
+    pub fn bit(self) -> u32 {
+        1 << self as u32
+    }
+}
+
+static ENABLED: AtomicU32 = AtomicU32::new(0);
+
+/// Bitmask of enabled metrics, bit N stands for `Metric::ALL[N]`
+pub fn enabled() -> u32 {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+pub fn set_enabled(mask: u32) {
+    ENABLED.store(mask, Ordering::Relaxed);
+}
+
+pub fn is_enabled(metric: Metric) -> bool {
+    enabled() & metric.bit() != 0
+}
+
+// Saturation vapour pressure over water in Pa, Magnus formula, -40 C to 60 C
+// in 1 C steps
+const MIN_TEMP: i16 = -40;
+const VAPOUR_PRESSURE: [u16; 101] = [
+    19, 21, 23, 26, 29, 32, 35, 38, 42, 47, 51, 56, 62, 68, 74, 81, 89, 97, 106, 116, 126, 137,
+    149, 163, 177, 192, 208, 226, 245, 265, 287, 310, 336, 363, 391, 422, 455, 490, 528, 568, 611,
+    657, 706, 758, 813, 872, 934, 1001, 1071, 1146, 1226, 1310, 1400, 1495, 1595, 1702, 1814, 1933,
+    2059, 2192, 2333, 2481, 2637, 2803, 2977, 3160, 3353, 3557, 3771, 3997, 4234, 4483, 4745, 5020,
+    5309, 5613, 5931, 6265, 6616, 6983, 7367, 7770, 8192, 8634, 9096, 9580, 10085, 10614, 11166,
+    11743, 12345, 12974, 13630, 14315, 15029, 15774, 16550, 17359, 18202, 19080, 19993,
+];
+
+// Wind speed to the power of 0.16, * 1000, 0 to 120 km/h in 1 km/h steps
+const WIND_POWER: [u16; 121] = [
+    0, 1000, 1117, 1192, 1248, 1294, 1332, 1365, 1395, 1421, 1445, 1468, 1488, 1507, 1525, 1542,
+    1558, 1574, 1588, 1602, 1615, 1628, 1640, 1651, 1663, 1674, 1684, 1694, 1704, 1714, 1723, 1732,
+    1741, 1750, 1758, 1766, 1774, 1782, 1790, 1797, 1804, 1812, 1819, 1825, 1832, 1839, 1845, 1852,
+    1858, 1864, 1870, 1876, 1882, 1887, 1893, 1899, 1904, 1910, 1915, 1920, 1925, 1930, 1935, 1940,
+    1945, 1950, 1955, 1960, 1964, 1969, 1973, 1978, 1982, 1987, 1991, 1995, 2000, 2004, 2008, 2012,
+    2016, 2020, 2024, 2028, 2032, 2036, 2039, 2043, 2047, 2051, 2054, 2058, 2062, 2065, 2069, 2072,
+    2076, 2079, 2083, 2086, 2089, 2093, 2096, 2099, 2102, 2106, 2109, 2112, 2115, 2118, 2121, 2124,
+    2128, 2131, 2134, 2137, 2140, 2142, 2145, 2148, 2151,
+];
+
+/// Linear interpolation in a table with steps of 10 tenths, clamped to it
+fn interpolate(table: &[u16], tenths: i32) -> i64 {
+    let last = (table.len() - 1) as i32 * 10;
+    let tenths = tenths.clamp(0, last);
+    let idx = (tenths / 10) as usize;
+    let frac = (tenths % 10) as i64;
+    let low = table[idx] as i64;
+    let high = table[(idx + 1).min(table.len() - 1)] as i64;
+    low + (high - low) * frac / 10
+}
+
+/// Wind chill in 0.1 C, Environment Canada formula. Only defined at 10 C and
+/// below with wind of at least 4.8 km/h.
+pub fn wind_chill(climate: &Climate, wind: &Wind) -> Option<i16> {
+    let temp = climate.temp_decicelsius as i64;
+    // 0.1 m/s to 0.1 km/h
+    let speed = wind.avg_speed as i32 * 36 / 10;
+    if temp > 100 || speed < 48 {
+        return None;
+    }
+    let power = interpolate(&WIND_POWER, speed);
+    // 13.12 + 0.6215 T - 11.37 V^0.16 + 0.3965 T V^0.16, scaled by 10^4
+    let chill = 1_312_000 + 6215 * temp - 1137 * power + 3965 * temp * power / 1000;
+    Some((chill / 10_000) as i16)
+}
+
+/// Absolute humidity in 0.1 g/m3
+pub fn absolute_humidity(climate: &Climate) -> Option<u16> {
+    let humidity = climate.humidity? as i64;
+    let temp = climate.temp_decicelsius as i32;
+    let saturation = interpolate(&VAPOUR_PRESSURE, temp - MIN_TEMP as i32 * 10);
+    // 2.1674 e / T, e in Pa and T in K
+    let kelvin = 2732 + temp as i64;
+    Some((21_674 * saturation * humidity / (10_000 * kelvin)) as u16)
+}
+
+/// Enabled metrics the reading has what it takes for, in 0.1 of their unit
+pub fn compute(data: &SensorData) -> impl Iterator<Item = (Metric, i32)> {
+    let climate = data.climate();
+    let wind_chill = climate
+        .zip(data.wind())
+        .and_then(|(climate, wind)| wind_chill(climate, wind))
+        .filter(|_| is_enabled(Metric::WindChill))
+        .map(|chill| (Metric::WindChill, chill as i32));
+    let absolute_humidity = climate
+        .and_then(absolute_humidity)
+        .filter(|_| is_enabled(Metric::AbsoluteHumidity))
+        .map(|humidity| (Metric::AbsoluteHumidity, humidity as i32));
+    wind_chill.into_iter().chain(absolute_humidity)
+}
@@ -0,0 +1,141 @@
+use esp_hal::Blocking;
+use esp_hal::gpio::interconnect::PeripheralOutput;
+use esp_hal::i2c::master::{self, Config, ConfigError, I2c, Instance};
+use jiff::civil::DateTime;
+use jiff::tz::Offset;
+use log::warn;
+
+use crate::clock::Clock;
+
+const ADDRESS: u8 = 0x68;
+// Time registers are seconds to year, followed by alarms
+const REG_TIME: u8 = 0x00;
+const REG_STATUS: u8 = 0x0f;
+// Oscillator stopped at some point, e.g. battery ran out, so time is lost
+const STATUS_OSF: u8 = 0x80;
+// Set in the month register once the year wraps past 99
+const MONTH_CENTURY: u8 = 0x80;
+
+#[derive(Debug)]
+pub enum Error {
+    Config(ConfigError),
+    Bus(master::Error),
+    /// Oscillator stopped, time has to be set again
+    TimeLost,
+    /// Registers don't hold a valid date, or time is out of range
+    InvalidTime,
+}
+
+impl From<master::Error> for Error {
+    fn from(e: master::Error) -> Self {
+        Error::Bus(e)
+    }
+}
+
+fn from_bcd(value: u8) -> u8 {
+    (value >> 4) * 10 + (value & 0x0f)
+}
+
+fn to_bcd(value: u8) -> u8 {
+    ((value / 10) << 4) | (value % 10)
+}
+
+/// DS3231 battery backed RTC, which keeps time through power cycles and
+/// network outages. It's kept in UTC, 2000 to 2199.
+pub struct Ds3231 {
+    i2c: I2c<'static, Blocking>,
+}
+
+impl Ds3231 {
+    pub fn new(
+        i2c: impl Instance + 'static,
+        sda: impl PeripheralOutput<'static>,
+        scl: impl PeripheralOutput<'static>,
+    ) -> Result<Self, Error> {
+        let i2c = I2c::new(i2c, Config::default())
+            .map_err(Error::Config)?
+            .with_sda(sda)
+            .with_scl(scl);
+        Ok(Ds3231 { i2c })
+    }
+
+    /// Current time, in us since Unix epoch
+    pub fn read(&mut self) -> Result<u64, Error> {
+        let mut status = [0];
+        self.i2c.write_read(ADDRESS, &[REG_STATUS], &mut status)?;
+        if status[0] & STATUS_OSF != 0 {
+            return Err(Error::TimeLost);
+        }
+        let mut regs = [0; 7];
+        self.i2c.write_read(ADDRESS, &[REG_TIME], &mut regs)?;
+        let century = if regs[5] & MONTH_CENTURY != 0 {
+            2100
+        } else {
+            2000
+        };
+        // 24-hour mode is the only one ever written
+        let datetime = DateTime::new(
+            century + from_bcd(regs[6]) as i16,
+            from_bcd(regs[5] & !MONTH_CENTURY) as i8,
+            from_bcd(regs[4]) as i8,
+            from_bcd(regs[2] & 0x3f) as i8,
+            from_bcd(regs[1]) as i8,
+            from_bcd(regs[0]) as i8,
+            0,
+        )
+        .map_err(|_| Error::InvalidTime)?;
+        let timestamp = Offset::UTC
+            .to_timestamp(datetime)
+            .map_err(|_| Error::InvalidTime)?;
+        u64::try_from(timestamp.as_microsecond()).map_err(|_| Error::InvalidTime)
+    }
+
+    /// Set time, in us since Unix epoch. Sub-second part is dropped.
+    pub fn write(&mut self, time: u64) -> Result<(), Error> {
+        let timestamp =
+            jiff::Timestamp::from_microsecond(time as i64).map_err(|_| Error::InvalidTime)?;
+        let datetime = Offset::UTC.to_datetime(timestamp);
+        if !(2000..2200).contains(&datetime.year()) {
+            return Err(Error::InvalidTime);
+        }
+        let century = if datetime.year() >= 2100 {
+            MONTH_CENTURY
+        } else {
+            0
+        };
+        self.i2c.write(
+            ADDRESS,
+            &[
+                REG_TIME,
+                to_bcd(datetime.second() as u8),
+                to_bcd(datetime.minute() as u8),
+                to_bcd(datetime.hour() as u8),
+                // Day of week isn't used, 1 is Monday
+                datetime.weekday().to_monday_one_offset() as u8,
+                to_bcd(datetime.day() as u8),
+                to_bcd(datetime.month() as u8) | century,
+                to_bcd((datetime.year() % 100) as u8),
+            ],
+        )?;
+        // Time is good again
+        let mut status = [0];
+        self.i2c.write_read(ADDRESS, &[REG_STATUS], &mut status)?;
+        self.i2c
+            .write(ADDRESS, &[REG_STATUS, status[0] & !STATUS_OSF])?;
+        Ok(())
+    }
+}
+
+impl Clock for Ds3231 {
+    async fn time(&mut self) -> Option<u64> {
+        self.read()
+            .inspect_err(|e| warn!("Failed to read DS3231 time: {:?}", e))
+            .ok()
+    }
+
+    async fn set_time(&mut self, time: u64) -> bool {
+        self.write(time)
+            .inspect_err(|e| warn!("Failed to set DS3231 time: {:?}", e))
+            .is_ok()
+    }
+}
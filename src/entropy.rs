@@ -0,0 +1,54 @@
+use core::cell::Cell;
+
+use critical_section::Mutex;
+use esp_hal::rng::Rng;
+
+// Units flashed with the same image start out alike, and the hardware RNG
+// only draws on RF noise once the radio is up. Whatever the network stack and
+// the MQTT client pick at random, TCP ports and sequence numbers or packet
+// IDs, is drawn from a pool instead, which the MAC address, arrival times of
+// received bursts and the hardware RNG are all stirred into. Any of them
+// keeps units apart even if the others happen to agree.
+
+// Added at every stir, so that stirring in the same value twice still moves
+// the pool on
+const GAMMA: u64 = 0x9e37_79b9_7f4a_7c15;
+
+static POOL: Mutex<Cell<u64>> = Mutex::new(Cell::new(0));
+
+/// SplitMix64 finalizer, every input bit affects every output bit
+fn mix(mut value: u64) -> u64 {
+    value = (value ^ (value >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    value = (value ^ (value >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    value ^ (value >> 31)
+}
+
+/// Stir `value` into the pool, returns the new state
+fn stir_state(value: u64) -> u64 {
+    critical_section::with(|cs| {
+        let pool = POOL.borrow(cs);
+        let state = mix(pool.get().wrapping_add(GAMMA) ^ value);
+        pool.set(state);
+        state
+    })
+}
+
+/// Stir `value` into the pool. Only its unpredictable bits help, the rest
+/// don't hurt either.
+pub fn stir(value: u64) {
+    stir_state(value);
+}
+
+/// Stir in the station MAC address, which no two units share
+pub fn stir_mac(mac: [u8; 6]) {
+    let [a, b, c, d, e, f] = mac;
+    stir(u64::from_le_bytes([a, b, c, d, e, f, 0, 0]));
+}
+
+/// Random value drawn from the pool, with the hardware RNG stirred in first
+pub fn random() -> u64 {
+    let rng = Rng::new();
+    let state = stir_state((rng.random() as u64) << 32 | rng.random() as u64);
+    // The pool itself is never handed out
+    mix(state ^ GAMMA)
+}
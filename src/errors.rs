@@ -0,0 +1,117 @@
+use core::fmt::{Display, Write};
+
+use heapless::{String, Vec};
+use log::warn;
+
+use crate::events::{self, Event};
+
+pub const DETAIL_LEN: usize = 48;
+
+/// Machine readable error taxonomy for monitoring. Codes are grouped by module
+/// in hundreds, and a code never changes its meaning once released: new errors
+/// get new codes.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Code {
+    /// More than DECODE_ERROR_RATE decode failures in a minute, detail is the
+    /// last one
+    DecodeRate = 100,
+    /// RMT receive failed, detail is the driver error
+    ReceiveFailed = 101,
+    /// Two sensors of a model report on the same channel, detail is the
+    /// model, channel and both ids
+    ChannelCollision = 102,
+    /// Broker host name didn't resolve, detail is the host
+    DnsFailed = 200,
+    /// Broker didn't accept connection, detail is the host
+    ConnectFailed = 201,
+    /// Switched to the next broker, detail is the new one
+    BrokerFailover = 202,
+    /// Wi-Fi connection was lost
+    WifiDisconnected = 300,
+    /// Wi-Fi connect attempt failed, detail is the reason
+    WifiConnectFailed = 301,
+}
+
+impl Code {
+    pub fn module(self) -> &'static str {
+        match self as u16 / 100 {
+            1 => "decoder",
+            2 => "mqtt",
+            _ => "wifi",
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Code::DecodeRate => "decode_rate",
+            Code::ReceiveFailed => "receive_failed",
+            Code::ChannelCollision => "channel_collision",
+            Code::DnsFailed => "dns_failed",
+            Code::ConnectFailed => "connect_failed",
+            Code::BrokerFailover => "broker_failover",
+            Code::WifiDisconnected => "wifi_disconnected",
+            Code::WifiConnectFailed => "wifi_connect_failed",
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Report {
+    pub code: Code,
+    /// Detail of the latest occurrence, cut short if it doesn't fit
+    pub detail: String<DETAIL_LEN>,
+    /// Occurrences since the last published report
+    pub count: u32,
+}
+
+/// Report error to whoever monitors the unit
+pub fn report(code: Code, detail: impl Display) {
+    let mut report = Report {
+        code,
+        detail: String::new(),
+        count: 1,
+    };
+    // Detail is informational, partial one is still useful
+    let _ = write!(report.detail, "{}", detail);
+    events::notify(Event::Error(report));
+}
+
+/// Reports waiting to be published, at most one per code: repeats only bump
+/// the count. Errors tend to come in bursts while the broker can't be reached.
+pub struct Pending<const N: usize> {
+    reports: Vec<Report, N>,
+}
+
+impl<const N: usize> Pending<N> {
+    pub const fn new() -> Self {
+        Pending {
+            reports: Vec::new(),
+        }
+    }
+
+    pub fn push(&mut self, report: Report) {
+        if let Some(pending) = self.reports.iter_mut().find(|r| r.code == report.code) {
+            pending.count += report.count;
+            pending.detail = report.detail;
+        } else if let Err(report) = self.reports.push(report) {
+            warn!("Too many pending errors, dropping {}", report.code.name());
+        }
+    }
+
+    pub fn first(&self) -> Option<&Report> {
+        self.reports.first()
+    }
+
+    /// Drop the first report once it's been published
+    pub fn remove_first(&mut self) {
+        if !self.reports.is_empty() {
+            self.reports.remove(0);
+        }
+    }
+}
+
+impl<const N: usize> Default for Pending<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,247 @@
+use core::fmt::{self, Write};
+
+use heapless::{String, Vec};
+
+use crate::decoder::{Hazard, SensorData};
+use crate::{DOORBELL_COOLDOWN_SECS, MOTION_COOLDOWN_SECS, MQTT_TOPIC, VERSION};
+
+/// Discovery configs go under this prefix, the Home Assistant default
+pub const DISCOVERY_PREFIX: &str = "homeassistant";
+/// Most entities a single reading maps to, a weather station has the most
+pub const MAX_ENTITIES: usize = 8;
+pub const STATE_LEN: usize = 16;
+
+/// One value of a sensor, exposed the way an ESPHome `sensor` or
+/// `binary_sensor` component would be
+pub struct Entity {
+    /// `sensor` or `binary_sensor`
+    pub component: &'static str,
+    /// Suffix of the object id
+    pub key: &'static str,
+    pub name: &'static str,
+    pub unit: Option<&'static str>,
+    pub device_class: Option<&'static str>,
+    pub state_class: Option<&'static str>,
+}
+
+const fn sensor(
+    key: &'static str,
+    name: &'static str,
+    unit: &'static str,
+    device_class: Option<&'static str>,
+    state_class: &'static str,
+) -> Entity {
+    Entity {
+        component: "sensor",
+        key,
+        name,
+        unit: Some(unit),
+        device_class,
+        state_class: Some(state_class),
+    }
+}
+
+const fn binary_sensor(
+    key: &'static str,
+    name: &'static str,
+    device_class: &'static str,
+) -> Entity {
+    Entity {
+        component: "binary_sensor",
+        key,
+        name,
+        unit: None,
+        device_class: Some(device_class),
+        state_class: None,
+    }
+}
+
+static TEMPERATURE: Entity = sensor(
+    "temperature",
+    "Temperature",
+    "°C",
+    Some("temperature"),
+    "measurement",
+);
+static HUMIDITY: Entity = sensor("humidity", "Humidity", "%", Some("humidity"), "measurement");
+static WIND_SPEED: Entity = sensor(
+    "wind_speed",
+    "Wind speed",
+    "m/s",
+    Some("wind_speed"),
+    "measurement",
+);
+static WIND_GUST: Entity = sensor(
+    "wind_gust",
+    "Wind gust",
+    "m/s",
+    Some("wind_speed"),
+    "measurement",
+);
+static WIND_DIRECTION: Entity =
+    sensor("wind_direction", "Wind direction", "°", None, "measurement");
+static RAIN: Entity = sensor(
+    "rain",
+    "Rain",
+    "mm",
+    Some("precipitation"),
+    "total_increasing",
+);
+static RAIN_RATE: Entity = sensor(
+    "rain_rate",
+    "Rain rate",
+    "mm/h",
+    Some("precipitation_intensity"),
+    "measurement",
+);
+static BATTERY_LOW: Entity = binary_sensor("battery_low", "Battery low", "battery");
+static CONTACT: Entity = binary_sensor("contact", "Contact", "door");
+static TAMPER: Entity = binary_sensor("tamper", "Tamper", "tamper");
+static MOTION: Entity = binary_sensor("motion", "Motion", "motion");
+static DOORBELL: Entity = binary_sensor("doorbell", "Doorbell", "sound");
+static LEAK: Entity = binary_sensor("leak", "Leak", "moisture");
+static SMOKE: Entity = binary_sensor("smoke", "Smoke", "smoke");
+static CO: Entity = binary_sensor("co", "Carbon monoxide", "carbon_monoxide");
+
+/// Value with one decimal place from tenths
+fn tenths(value: u32) -> String<STATE_LEN> {
+    let mut out = String::new();
+    // Can't overflow, u32 takes 10 digits at most
+    let _ = write!(out, "{}.{}", value / 10, value % 10);
+    out
+}
+
+fn number(value: impl fmt::Display) -> String<STATE_LEN> {
+    let mut out = String::new();
+    let _ = write!(out, "{}", value);
+    out
+}
+
+fn on_off(value: bool) -> String<STATE_LEN> {
+    String::try_from(if value { "ON" } else { "OFF" }).unwrap()
+}
+
+/// Entities the reading carries, along with their states formatted the way
+/// ESPHome publishes them
+pub fn states(data: &SensorData) -> Vec<(&'static Entity, String<STATE_LEN>), MAX_ENTITIES> {
+    let mut states = Vec::new();
+    // Can't overflow, a weather station takes all of MAX_ENTITIES
+    let mut add = |entity: &'static Entity, state| {
+        let _ = states.push((entity, state));
+    };
+    if let Some(climate) = data.climate() {
+        add(&TEMPERATURE, number(climate.celsius()));
+        if let Some(humidity) = climate.humidity {
+            add(&HUMIDITY, number(humidity));
+        }
+    }
+    if let Some(wind) = data.wind() {
+        add(&WIND_SPEED, tenths(wind.avg_speed as u32));
+        add(&WIND_GUST, tenths(wind.gust_speed as u32));
+        add(&WIND_DIRECTION, number(wind.direction));
+    }
+    if let Some(rain) = data.rain() {
+        add(&RAIN, tenths(rain.total));
+        if let Some(rate) = rain.rate {
+            add(&RAIN_RATE, tenths(rate as u32));
+        }
+    }
+    if let Some(contact) = data.contact() {
+        add(&CONTACT, on_off(contact.open));
+        add(&TAMPER, on_off(contact.tamper));
+    }
+    if data.motion().is_some() {
+        add(&MOTION, on_off(true));
+    }
+    if data.doorbell().is_some() {
+        add(&DOORBELL, on_off(true));
+    }
+    if let Some(leak) = data.leak() {
+        add(&LEAK, on_off(leak.water));
+    }
+    match data.hazard() {
+        Some(Hazard::Smoke) => add(&SMOKE, on_off(true)),
+        Some(Hazard::CarbonMonoxide) => add(&CO, on_off(true)),
+        None => {}
+    }
+    add(&BATTERY_LOW, on_off(!data.battery_ok));
+    states
+}
+
+/// `<model>_<id>_<key>`, with the model lowercased and anything but letters
+/// and digits replaced, as ESPHome does for object ids
+pub fn write_object_id(out: &mut impl Write, data: &SensorData, entity: &Entity) -> fmt::Result {
+    for c in data.model().chars() {
+        out.write_char(if c.is_ascii_alphanumeric() {
+            c.to_ascii_lowercase()
+        } else {
+            '_'
+        })?;
+    }
+    write!(out, "_{}_{}", data.id, entity.key)
+}
+
+/// `MQTT_TOPIC/<component>/<object id>/state`
+pub fn write_state_topic(out: &mut impl Write, data: &SensorData, entity: &Entity) -> fmt::Result {
+    write!(out, "{}/{}/", MQTT_TOPIC, entity.component)?;
+    write_object_id(out, data, entity)?;
+    write!(out, "/state")
+}
+
+/// `DISCOVERY_PREFIX/<component>/esp-rf-ook2/<object id>/config`
+pub fn write_config_topic(out: &mut impl Write, data: &SensorData, entity: &Entity) -> fmt::Result {
+    write!(
+        out,
+        "{}/{}/esp-rf-ook2/",
+        DISCOVERY_PREFIX, entity.component
+    )?;
+    write_object_id(out, data, entity)?;
+    write!(out, "/config")
+}
+
+/// Discovery config in the abbreviated form ESPHome publishes, every sensor
+/// is a device of its own. Readings of periodic sensors are tied to
+/// `availability`, event sensors have none.
+pub fn write_config(
+    out: &mut impl Write,
+    data: &SensorData,
+    entity: &Entity,
+    availability: Option<&str>,
+) -> fmt::Result {
+    write!(out, "{{\"name\":\"{}\",\"stat_t\":\"", entity.name)?;
+    write_state_topic(out, data, entity)?;
+    write!(out, "\",\"uniq_id\":\"esp-rf-ook2-")?;
+    write_object_id(out, data, entity)?;
+    if let Some(unit) = entity.unit {
+        write!(out, "\",\"unit_of_meas\":\"{}", unit)?;
+    }
+    if let Some(device_class) = entity.device_class {
+        write!(out, "\",\"dev_cla\":\"{}", device_class)?;
+    }
+    if let Some(state_class) = entity.state_class {
+        write!(out, "\",\"stat_cla\":\"{}", state_class)?;
+    }
+    if let Some(availability) = availability {
+        write!(out, "\",\"avty_t\":\"{}", availability)?;
+    }
+    write!(out, "\"")?;
+    // Motion sensors never send the end of motion, nor smoke alarms the end
+    // of the alarm, they only stop transmitting
+    if [MOTION.key, SMOKE.key, CO.key].contains(&entity.key) {
+        write!(out, ",\"off_dly\":{}", MOTION_COOLDOWN_SECS)?;
+    }
+    // Nor doorbells the end of the press
+    if entity.key == DOORBELL.key {
+        write!(out, ",\"off_dly\":{}", DOORBELL_COOLDOWN_SECS)?;
+    }
+    write!(
+        out,
+        ",\"dev\":{{\"ids\":\"esp-rf-ook2-{}-{}\",\"name\":\"{} {}\",\"mdl\":\"{}\",\"mf\":\"esp-rf-ook2\",\"sw\":\"{}\"}}}}",
+        data.model(),
+        data.id,
+        data.model(),
+        data.id,
+        data.model(),
+        VERSION
+    )
+}
@@ -0,0 +1,88 @@
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::pubsub::{PubSubChannel, Subscriber};
+use log::warn;
+
+use crate::decoder::SensorData;
+use crate::errors::Report;
+use crate::health::Health;
+use crate::registry::Sensor;
+
+/// Reading accepted for publishing
+#[derive(Clone)]
+pub struct Reading {
+    pub data: SensorData,
+    /// Time the reading was taken at, in us
+    pub timestamp: i64,
+    /// How long the reading stays valid, in seconds
+    pub expire_after: Option<u64>,
+    /// Frames of the transmission received
+    pub repeats: Option<u8>,
+    /// Events of the sensor so far, for event sensors
+    pub count: Option<u32>,
+    /// Time the last frame of the reading was received at, monotonic in us
+    #[cfg(feature = "bench")]
+    pub received: u64,
+}
+
+/// Something that happened, for any module interested to react on. Producers
+/// don't need to know who consumes their events.
+#[derive(Clone)]
+pub enum Event {
+    ReadingDecoded(Reading),
+    /// Sensor came online or stopped transmitting
+    Availability(Sensor),
+    /// Alarm of a leak detector was latched or cleared
+    Alarm(Sensor),
+    /// Raw burst is waiting to be published
+    CaptureReady,
+    /// Decode context of a burst is waiting to be published
+    DebugReady,
+    /// Protocol learning is over, suggestion is waiting to be published
+    LearnDone,
+    /// No bursts received for RECEIVER_SILENT_SECS, or they are back
+    ReceiverSilent(bool),
+    /// Daily summary is due, timestamp is in us
+    SummaryDue(i64),
+    /// Supply voltage and die temperature report
+    Health(Health),
+    /// Something went wrong, see errors::Code
+    Error(Report),
+    WifiUp,
+    WifiDown,
+    NtpSynced,
+    PublishFailed,
+}
+
+const CAPACITY: usize = 4;
+const SUBSCRIBERS: usize = 4;
+// Only the main loop waits for room, everyone else uses notify()
+const PUBLISHERS: usize = 1;
+
+static EVENTS: PubSubChannel<CriticalSectionRawMutex, Event, CAPACITY, SUBSCRIBERS, PUBLISHERS> =
+    PubSubChannel::new();
+
+pub type Events =
+    Subscriber<'static, CriticalSectionRawMutex, Event, CAPACITY, SUBSCRIBERS, PUBLISHERS>;
+
+/// Receive every event published from now on
+pub fn subscribe() -> Events {
+    EVENTS.subscriber().expect("Too many event subscribers")
+}
+
+/// Publish event that must not be lost, waiting until every subscriber has
+/// room for it
+pub async fn publish(event: Event) {
+    match EVENTS.publisher() {
+        Ok(publisher) => publisher.publish(event).await,
+        Err(_) => warn!("Too many event publishers, dropping event"),
+    }
+}
+
+/// Publish status event without blocking, it's dropped if a subscriber is
+/// lagging behind. Subscribers may publish too, waiting for room would
+/// deadlock them.
+pub fn notify(event: Event) {
+    if EVENTS.immediate_publisher().try_publish(event).is_err() {
+        warn!("Event bus is full, dropping event");
+    }
+}
@@ -0,0 +1,184 @@
+use embedded_storage::{ReadStorage, Storage};
+use esp_hal::ram;
+use esp_rom_sys::rom::spiflash::{
+    ESP_ROM_SPIFLASH_RESULT_OK, esp_rom_spiflash_erase_sector, esp_rom_spiflash_read,
+    esp_rom_spiflash_unlock, esp_rom_spiflash_write,
+};
+
+#[cfg(not(any(feature = "esp32c3", feature = "esp32c6")))]
+compile_error!("flash storage is only supported on ESP32-C3 and ESP32-C6");
+
+pub const SECTOR_LEN: u32 = 4096;
+const CHUNK_WORDS: usize = 64;
+
+#[derive(Debug)]
+pub enum Error {
+    Flash,
+    Alignment,
+}
+
+/// SPI flash accessed through the ROM functions, which run with interrupts
+/// disabled since the flash can't be read while it's busy. Writes only clear
+/// bits, sectors have to be erased first.
+pub(crate) struct Flash;
+
+impl Flash {
+    #[ram]
+    pub(crate) fn read_words(addr: u32, words: &mut [u32]) -> Result<(), Error> {
+        let result = critical_section::with(|_| unsafe {
+            esp_rom_spiflash_read(addr, words.as_mut_ptr(), (words.len() * 4) as u32)
+        });
+        (result == ESP_ROM_SPIFLASH_RESULT_OK)
+            .then_some(())
+            .ok_or(Error::Flash)
+    }
+
+    #[ram]
+    pub(crate) fn write_words(addr: u32, words: &[u32]) -> Result<(), Error> {
+        let result = critical_section::with(|_| unsafe {
+            esp_rom_spiflash_write(addr, words.as_ptr(), (words.len() * 4) as u32)
+        });
+        (result == ESP_ROM_SPIFLASH_RESULT_OK)
+            .then_some(())
+            .ok_or(Error::Flash)
+    }
+
+    #[ram]
+    pub(crate) fn erase_sector(addr: u32) -> Result<(), Error> {
+        let result =
+            critical_section::with(|_| unsafe { esp_rom_spiflash_erase_sector(addr / SECTOR_LEN) });
+        (result == ESP_ROM_SPIFLASH_RESULT_OK)
+            .then_some(())
+            .ok_or(Error::Flash)
+    }
+
+    #[cfg(any(feature = "history", feature = "rain-totals"))]
+    pub(crate) fn read_word(addr: u32) -> Result<u32, Error> {
+        let mut word = [0];
+        Flash::read_words(addr, &mut word)?;
+        Ok(word[0])
+    }
+}
+
+impl ReadStorage for Flash {
+    type Error = Error;
+
+    fn read(&mut self, offset: u32, bytes: &mut [u8]) -> Result<(), Error> {
+        if !offset.is_multiple_of(4) {
+            return Err(Error::Alignment);
+        }
+        let mut words = [0u32; CHUNK_WORDS];
+        for (idx, chunk) in bytes.chunks_mut(CHUNK_WORDS * 4).enumerate() {
+            let words = &mut words[..chunk.len().div_ceil(4)];
+            Flash::read_words(offset + (idx * CHUNK_WORDS * 4) as u32, words)?;
+            for (dst, src) in chunk
+                .iter_mut()
+                .zip(words.iter().flat_map(|w| w.to_le_bytes()))
+            {
+                *dst = src;
+            }
+        }
+        Ok(())
+    }
+
+    fn capacity(&self) -> usize {
+        usize::MAX
+    }
+}
+
+impl Storage for Flash {
+    /// Programs `bytes` without erasing, only meant for what's been erased
+    fn write(&mut self, offset: u32, bytes: &[u8]) -> Result<(), Error> {
+        if !offset.is_multiple_of(4) {
+            return Err(Error::Alignment);
+        }
+        let mut words = [0u32; CHUNK_WORDS];
+        for (idx, chunk) in bytes.chunks(CHUNK_WORDS * 4).enumerate() {
+            let words = &mut words[..chunk.len().div_ceil(4)];
+            for (word, src) in words.iter_mut().zip(chunk.chunks(4)) {
+                // Padding is left erased
+                let mut bytes = [0xff; 4];
+                bytes[..src.len()].copy_from_slice(src);
+                *word = u32::from_le_bytes(bytes);
+            }
+            Flash::write_words(offset + (idx * CHUNK_WORDS * 4) as u32, words)?;
+        }
+        Ok(())
+    }
+}
+
+/// Offset and length of the data partition labeled `label`, with the flash
+/// unlocked for writing
+#[cfg(any(
+    feature = "history",
+    feature = "postmortem",
+    feature = "provisioning",
+    feature = "rain-totals"
+))]
+pub(crate) fn find_partition(label: &str) -> Option<(u32, u32)> {
+    use esp_bootloader_esp_idf::partitions::{self, PARTITION_TABLE_MAX_LEN};
+    use log::warn;
+
+    let mut table = [0; PARTITION_TABLE_MAX_LEN];
+    let partition = match partitions::read_partition_table(&mut Flash, &mut table) {
+        Ok(table) => table
+            .iter()
+            .find(|entry| entry.label_as_str() == label)
+            .map(|entry| (entry.offset(), entry.len())),
+        Err(e) => {
+            warn!("Failed to read partition table: {:?}", e);
+            return None;
+        }
+    };
+    if partition.is_some() {
+        unlock();
+    }
+    partition
+}
+
+/// Clear the write protection, once before the first write
+pub(crate) fn unlock() {
+    // SAFETY: ROM function, only clears write protection
+    unsafe { esp_rom_spiflash_unlock() };
+}
+
+/// Flash for small writes that may set bits again, like the OTA data: every
+/// sector written to is read, erased and programmed again whole
+#[cfg(feature = "ota")]
+pub(crate) struct SectorFlash;
+
+#[cfg(feature = "ota")]
+impl ReadStorage for SectorFlash {
+    type Error = Error;
+
+    fn read(&mut self, offset: u32, bytes: &mut [u8]) -> Result<(), Error> {
+        Flash.read(offset, bytes)
+    }
+
+    fn capacity(&self) -> usize {
+        usize::MAX
+    }
+}
+
+#[cfg(feature = "ota")]
+impl Storage for SectorFlash {
+    fn write(&mut self, mut offset: u32, mut bytes: &[u8]) -> Result<(), Error> {
+        let mut sector = [0u32; SECTOR_LEN as usize / 4];
+        while !bytes.is_empty() {
+            let start = offset - offset % SECTOR_LEN;
+            let skip = (offset - start) as usize;
+            let len = bytes.len().min(SECTOR_LEN as usize - skip);
+            Flash::read_words(start, &mut sector)?;
+            for (pos, byte) in (skip..).zip(&bytes[..len]) {
+                let mut word = sector[pos / 4].to_le_bytes();
+                word[pos % 4] = *byte;
+                sector[pos / 4] = u32::from_le_bytes(word);
+            }
+            Flash::erase_sector(start)?;
+            Flash::write_words(start, &sector)?;
+            offset += len as u32;
+            bytes = &bytes[len..];
+        }
+        Ok(())
+    }
+}
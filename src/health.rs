@@ -0,0 +1,152 @@
+use embassy_time::{Duration, Instant, Timer};
+#[cfg(not(feature = "esp32"))]
+use esp_hal::analog::adc::AdcCalCurve;
+use esp_hal::analog::adc::{Adc, AdcConfig, AdcPin, Attenuation};
+use esp_hal::peripherals::ADC1;
+#[cfg(any(feature = "esp32c3", feature = "esp32c6"))]
+use esp_hal::{peripherals::TSENS, tsens::TemperatureSensor};
+use log::warn;
+
+use crate::airtime::{self, Traffic};
+use crate::events::{self, Event};
+use crate::{HEALTH_INTERVAL_SECS, SUPPLY_DIVIDER, SUPPLY_LOW_MV};
+
+/// ADC1 pin the supply voltage divider is connected to
+#[cfg(feature = "esp32")]
+pub type SupplyPin = esp_hal::peripherals::GPIO34<'static>;
+#[cfg(any(feature = "esp32c3", feature = "esp32c6"))]
+pub type SupplyPin = esp_hal::peripherals::GPIO3<'static>;
+#[cfg(feature = "esp32s3")]
+pub type SupplyPin = esp_hal::peripherals::GPIO4<'static>;
+
+// ESP32 has no ADC calibration, readings are scaled to the nominal range
+#[cfg(feature = "esp32")]
+type Calibration = ();
+#[cfg(not(feature = "esp32"))]
+type Calibration = AdcCalCurve<ADC1<'static>>;
+#[cfg(feature = "esp32")]
+const FULL_SCALE_MV: u32 = 3900;
+
+const SAMPLE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Supply voltage over the last report interval, in mV
+#[derive(Clone, Copy, Debug)]
+pub struct Supply {
+    pub avg_mv: u16,
+    /// Lowest sample, sags are usually too short to show in the average
+    pub min_mv: u16,
+}
+
+impl Supply {
+    pub fn low(&self) -> bool {
+        (self.min_mv as u64) < SUPPLY_LOW_MV
+    }
+}
+
+/// Health of the unit itself, whatever the chip and wiring allow to measure
+#[derive(Clone, Copy, Debug)]
+pub struct Health {
+    pub supply: Option<Supply>,
+    /// Die temperature * 10 in C, averaged over the report interval
+    pub die_temp: Option<i16>,
+    /// Band use over the report interval
+    pub traffic: Traffic,
+}
+
+type SupplyAdcPin = AdcPin<SupplyPin, ADC1<'static>, Calibration>;
+
+/// Sensors to sample. The supply is only measured if there's a divider, die
+/// temperature only on chips esp-hal has a driver for.
+pub struct Monitor {
+    supply: Option<(Adc<'static, ADC1<'static>, esp_hal::Blocking>, SupplyAdcPin)>,
+    #[cfg(any(feature = "esp32c3", feature = "esp32c6"))]
+    die: Option<TemperatureSensor<'static>>,
+}
+
+impl Monitor {
+    pub fn new(
+        adc: ADC1<'static>,
+        pin: SupplyPin,
+        #[cfg(any(feature = "esp32c3", feature = "esp32c6"))] tsens: TSENS<'static>,
+    ) -> Self {
+        let supply = (SUPPLY_DIVIDER != 0).then(|| {
+            let mut config = AdcConfig::new();
+            #[cfg(feature = "esp32")]
+            let pin = config.enable_pin(pin, Attenuation::_11dB);
+            #[cfg(not(feature = "esp32"))]
+            let pin = config.enable_pin_with_cal::<_, Calibration>(pin, Attenuation::_11dB);
+            (Adc::new(adc, config), pin)
+        });
+        Monitor {
+            supply,
+            #[cfg(any(feature = "esp32c3", feature = "esp32c6"))]
+            die: TemperatureSensor::new(tsens, Default::default())
+                .inspect_err(|e| warn!("Failed to init temperature sensor: {:?}", e))
+                .ok(),
+        }
+    }
+
+    /// Supply voltage in mV
+    fn supply_mv(&mut self) -> Option<u16> {
+        let (adc, pin) = self.supply.as_mut()?;
+        // Conversion takes a few us
+        let value = loop {
+            if let Ok(value) = adc.read_oneshot(pin) {
+                break value as u32;
+            }
+        };
+        #[cfg(feature = "esp32")]
+        let value = value * FULL_SCALE_MV / 4095;
+        Some((value * SUPPLY_DIVIDER as u32).min(u16::MAX as u32) as u16)
+    }
+
+    /// Die temperature * 10 in C
+    fn die_temp(&self) -> Option<i16> {
+        #[cfg(any(feature = "esp32c3", feature = "esp32c6"))]
+        if let Some(die) = &self.die {
+            return Some((die.get_temperature().to_celsius() * 10.0) as i16);
+        }
+        None
+    }
+}
+
+/// Sample every second and report every HEALTH_INTERVAL_SECS, even with
+/// nothing to sample, since the report carries the dropped burst count. A
+/// supply sag below SUPPLY_LOW_MV is reported right away.
+#[embassy_executor::task]
+pub async fn monitor(mut monitor: Monitor) {
+    let interval = Duration::from_secs(HEALTH_INTERVAL_SECS);
+    // Only the first sample of a sag is reported early
+    let mut sagging = false;
+    loop {
+        let start = Instant::now();
+        let (mut supply_sum, mut supply_min, mut temp_sum, mut count) = (None, u16::MAX, None, 0);
+        while start.elapsed() < interval {
+            Timer::after(SAMPLE_INTERVAL).await;
+            count += 1;
+            if let Some(temp) = monitor.die_temp() {
+                *temp_sum.get_or_insert(0) += temp as i32;
+            }
+            let Some(value) = monitor.supply_mv() else {
+                continue;
+            };
+            *supply_sum.get_or_insert(0) += value as u32;
+            supply_min = supply_min.min(value);
+            let low = (value as u64) < SUPPLY_LOW_MV;
+            if low && !sagging {
+                warn!("Supply voltage sagged to {} mV", value);
+                sagging = true;
+                break;
+            }
+            sagging = low;
+        }
+        events::notify(Event::Health(Health {
+            supply: supply_sum.map(|sum| Supply {
+                avg_mv: (sum / count) as u16,
+                min_mv: supply_min,
+            }),
+            die_temp: temp_sum.map(|sum| (sum / count as i32) as i16),
+            traffic: airtime::take(),
+        }));
+    }
+}
@@ -0,0 +1,234 @@
+use core::cell::RefCell;
+
+use critical_section::Mutex;
+use embassy_futures::yield_now;
+use embedded_storage::{ReadStorage, Storage};
+use log::{info, warn};
+
+use crate::decoder::bits::crc8;
+use crate::flash::{self, Error, Flash, SECTOR_LEN};
+
+/// Data partition the log is kept in
+pub const PARTITION_LABEL: &str = "history";
+/// Longest record, readings are stored the way they're published
+pub const RECORD_LEN: usize = 512;
+
+// Sector starts with its sequence number and this magic
+const SECTOR_MAGIC: u32 = 0x5453_4948;
+const SECTOR_HEADER_LEN: u32 = 8;
+// Record header is magic, CRC-8 of the record and its length
+const RECORD_MAGIC: u32 = 0xa5;
+const ERASED: u32 = u32::MAX;
+
+/// Position in the log, oldest record first
+#[derive(Default)]
+pub struct Cursor {
+    /// Sectors visited so far, starting after the newest one
+    step: u32,
+    offset: u32,
+}
+
+/// Circular log of records in a flash partition. Records never span sectors,
+/// once the newest sector is full the oldest one is erased and takes over.
+struct Log {
+    start: u32,
+    sectors: u32,
+    /// Sector records are appended to, and its sequence number
+    newest: u32,
+    seq: u32,
+    /// Offset of the next record within the newest sector
+    offset: u32,
+}
+
+impl Log {
+    fn sector_addr(&self, sector: u32) -> u32 {
+        self.start + sector * SECTOR_LEN
+    }
+
+    /// Sequence number of the sector, if it's been initialized
+    fn sector_seq(&self, sector: u32) -> Result<Option<u32>, Error> {
+        let mut header = [0; 2];
+        Flash::read_words(self.sector_addr(sector), &mut header)?;
+        Ok((header[1] == SECTOR_MAGIC).then_some(header[0]))
+    }
+
+    /// Offset just past the last record in the sector
+    fn sector_end(&self, sector: u32) -> Result<u32, Error> {
+        let addr = self.sector_addr(sector);
+        let mut offset = SECTOR_HEADER_LEN;
+        while offset < SECTOR_LEN {
+            let header = Flash::read_word(addr + offset)?;
+            if header == ERASED {
+                break;
+            }
+            if header & 0xff != RECORD_MAGIC {
+                // Torn record, don't append after it
+                return Ok(SECTOR_LEN);
+            }
+            offset += 4 + (header >> 16).next_multiple_of(4);
+        }
+        Ok(offset.min(SECTOR_LEN))
+    }
+
+    fn open(start: u32, len: u32) -> Result<Self, Error> {
+        let mut log = Log {
+            start,
+            sectors: len / SECTOR_LEN,
+            newest: 0,
+            seq: 0,
+            offset: SECTOR_LEN,
+        };
+        let mut found = false;
+        for sector in 0..log.sectors {
+            if let Some(seq) = log.sector_seq(sector)?
+                && (!found || seq > log.seq)
+            {
+                (log.newest, log.seq, found) = (sector, seq, true);
+            }
+        }
+        if found {
+            log.offset = log.sector_end(log.newest)?;
+        } else {
+            // Empty partition, first append erases sector 0
+            log.newest = log.sectors - 1;
+        }
+        Ok(log)
+    }
+
+    /// Move on to the next sector, dropping its records
+    fn advance(&mut self) -> Result<(), Error> {
+        let sector = (self.newest + 1) % self.sectors;
+        let addr = self.sector_addr(sector);
+        Flash::erase_sector(addr)?;
+        self.seq = self.seq.wrapping_add(1);
+        Flash::write_words(addr, &[self.seq, SECTOR_MAGIC])?;
+        self.newest = sector;
+        self.offset = SECTOR_HEADER_LEN;
+        Ok(())
+    }
+
+    fn append(&mut self, record: &[u8]) -> Result<(), Error> {
+        let len = record.len().min(RECORD_LEN) as u32;
+        if self.offset + 4 + len > SECTOR_LEN {
+            self.advance()?;
+        }
+        let record = &record[..len as usize];
+        let header = RECORD_MAGIC | (crc8(record, 0x07, 0) as u32) << 8 | len << 16;
+        let addr = self.sector_addr(self.newest) + self.offset;
+        Flash.write(addr + 4, record)?;
+        // Header goes last, so a record torn by a reset is never valid
+        Flash::write_words(addr, &[header])?;
+        self.offset += 4 + len.next_multiple_of(4);
+        Ok(())
+    }
+
+    /// Read the record at `cursor` into `buf` and move past it, returns its
+    /// length. Records that fail the CRC are skipped.
+    fn next(
+        &mut self,
+        cursor: &mut Cursor,
+        buf: &mut [u8; RECORD_LEN],
+    ) -> Result<Option<usize>, Error> {
+        while cursor.step < self.sectors {
+            let sector = (self.newest + 1 + cursor.step) % self.sectors;
+            if cursor.offset == 0 {
+                if self.sector_seq(sector)?.is_none() {
+                    cursor.step += 1;
+                    continue;
+                }
+                cursor.offset = SECTOR_HEADER_LEN;
+            }
+            let addr = self.sector_addr(sector) + cursor.offset;
+            let header = if cursor.offset < SECTOR_LEN {
+                Flash::read_word(addr)?
+            } else {
+                ERASED
+            };
+            let len = (header >> 16) as usize;
+            if header == ERASED || header & 0xff != RECORD_MAGIC || len > RECORD_LEN {
+                cursor.step += 1;
+                cursor.offset = 0;
+                continue;
+            }
+            cursor.offset += 4 + (len as u32).next_multiple_of(4);
+            Flash.read(addr + 4, &mut buf[..len])?;
+            if crc8(&buf[..len], 0x07, 0) as u32 == (header >> 8) & 0xff {
+                return Ok(Some(len));
+            }
+        }
+        Ok(None)
+    }
+}
+
+static LOG: Mutex<RefCell<Option<Log>>> = Mutex::new(RefCell::new(None));
+
+/// Find the history partition and pick up where the log left off. Without the
+/// partition nothing is logged.
+pub fn init() {
+    let Some((offset, len)) = flash::find_partition(PARTITION_LABEL) else {
+        info!("No {} partition, readings aren't logged", PARTITION_LABEL);
+        return;
+    };
+    if !offset.is_multiple_of(SECTOR_LEN) || len < 2 * SECTOR_LEN {
+        warn!("{} partition must be at least 2 sectors", PARTITION_LABEL);
+        return;
+    }
+    match Log::open(offset, len) {
+        Ok(log) => {
+            info!("Logging readings to {} sectors of flash", log.sectors);
+            critical_section::with(|cs| LOG.replace(cs, Some(log)));
+        }
+        Err(e) => warn!("Failed to open reading log: {:?}", e),
+    }
+}
+
+fn with_log<R>(f: impl FnOnce(&mut Log) -> Result<R, Error>) -> Option<R> {
+    critical_section::with(|cs| {
+        let mut log = LOG.borrow_ref_mut(cs);
+        let log = log.as_mut()?;
+        f(log)
+            .inspect_err(|e| warn!("Reading log failed: {:?}", e))
+            .ok()
+    })
+}
+
+/// Log the record, dropping the oldest ones if the log is full
+pub fn append(record: &str) {
+    with_log(|log| log.append(record.as_bytes()));
+}
+
+/// Drop all records. Erasing takes a while, so other tasks get to run in
+/// between sectors.
+pub async fn clear() {
+    let Some(sectors) = with_log(|log| {
+        // Next append starts over with a fresh sector
+        log.newest = log.sectors - 1;
+        log.offset = SECTOR_LEN;
+        Ok(log.sectors)
+    }) else {
+        return;
+    };
+    for sector in 0..sectors {
+        if with_log(|log| Flash::erase_sector(log.sector_addr(sector))).is_none() {
+            return;
+        }
+        yield_now().await;
+    }
+}
+
+/// Number of records in the log
+pub fn len() -> usize {
+    let mut buf = [0; RECORD_LEN];
+    let mut cursor = Cursor::default();
+    let mut count = 0;
+    while next(&mut cursor, &mut buf).is_some() {
+        count += 1;
+    }
+    count
+}
+
+/// Read the record at `cursor` into `buf` and move past it, returns its
+/// length or None at the end of the log
+pub fn next(cursor: &mut Cursor, buf: &mut [u8; RECORD_LEN]) -> Option<usize> {
+    with_log(|log| log.next(cursor, buf)).flatten()
+}
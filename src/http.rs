@@ -0,0 +1,618 @@
+use core::fmt::{self, Write};
+
+use embassy_net::{Stack, tcp::TcpSocket};
+use embassy_time::{Duration, Timer};
+use heapless::String;
+use log::{debug, info, warn};
+
+use crate::HTTP_PORT;
+use crate::latest::{self, REGISTRY};
+use crate::registry::Sensor;
+use crate::time;
+use crate::trend;
+
+const REQUEST_LEN: usize = 512;
+const SENSOR_LEN: usize = 256 + trend::LEN * 8;
+
+/// Dashboard page, gzipped by the build script
+const DASHBOARD: &[u8] = include_bytes!(concat!(env!("OUT_DIR"), "/dashboard.html.gz"));
+
+#[derive(Debug)]
+enum Error {
+    ReadFailed,
+    WriteFailed,
+}
+
+/// Minimal HTTP/1.0 server on HTTP_PORT. It takes one connection at a time
+/// and closes it after the response, which is streamed as it's produced. One
+/// runs on the network and another on the unit's own access point, if it
+/// keeps one up.
+#[embassy_executor::task(pool_size = 2)]
+pub async fn serve(stack: Stack<'static>) {
+    let mut rx_buf = [0; 1024];
+    let mut tx_buf = [0; 1536];
+    info!("Serving HTTP on port {}", HTTP_PORT);
+    loop {
+        let mut socket = TcpSocket::new(stack, &mut rx_buf, &mut tx_buf);
+        socket.set_timeout(Some(Duration::from_secs(10)));
+        if let Err(e) = socket.accept(HTTP_PORT).await {
+            warn!("Failed to accept HTTP connection: {:?}", e);
+            Timer::after(Duration::from_secs(1)).await;
+            continue;
+        }
+        if let Err(e) = handle(&mut socket).await {
+            debug!("HTTP request failed: {:?}", e);
+        }
+        socket.close();
+        // Give stack some time to process the socket closure
+        Timer::after(Duration::from_millis(100)).await;
+        socket.abort();
+    }
+}
+
+async fn handle(socket: &mut TcpSocket<'_>) -> Result<(), Error> {
+    let mut request = [0; REQUEST_LEN];
+    let mut len = 0;
+    // Headers past the request line don't matter, they're read as far as they
+    // fit
+    while len < request.len() && !request[..len].windows(4).any(|end| end == b"\r\n\r\n") {
+        match socket.read(&mut request[len..]).await {
+            Ok(0) | Err(_) => return Err(Error::ReadFailed),
+            Ok(read) => len += read,
+        }
+    }
+    let request = core::str::from_utf8(&request[..len]).unwrap_or_default();
+    let mut line = request.lines().next().unwrap_or_default().split(' ');
+    let (Some(method), Some(target)) = (line.next(), line.next()) else {
+        return error(socket, "400 Bad Request").await;
+    };
+    if method != "GET" {
+        return error(socket, "405 Method Not Allowed").await;
+    }
+    let path = target.split('?').next().unwrap_or_default();
+    debug!("HTTP GET {}", path);
+    match path {
+        "/" => {
+            head(socket, "200 OK", "text/html", "Content-Encoding: gzip\r\n").await?;
+            write(socket, DASHBOARD).await?;
+            flush(socket).await
+        }
+        "/api/readings" => readings(socket).await,
+        "/api/status" => status(socket).await,
+        #[cfg(feature = "history")]
+        "/history" => history::get(socket, target).await,
+        _ => error(socket, "404 Not Found").await,
+    }
+}
+
+async fn write(socket: &mut TcpSocket<'_>, mut buf: &[u8]) -> Result<(), Error> {
+    while !buf.is_empty() {
+        match socket.write(buf).await {
+            Ok(0) | Err(_) => return Err(Error::WriteFailed),
+            Ok(len) => buf = &buf[len..],
+        }
+    }
+    Ok(())
+}
+
+async fn flush(socket: &mut TcpSocket<'_>) -> Result<(), Error> {
+    socket.flush().await.map_err(|_| Error::WriteFailed)
+}
+
+/// Status line and headers of a response, `headers` are any more lines to
+/// send. The body follows until the connection is closed.
+async fn head(
+    socket: &mut TcpSocket<'_>,
+    status: &str,
+    content_type: &str,
+    headers: &str,
+) -> Result<(), Error> {
+    for part in [
+        "HTTP/1.0 ",
+        status,
+        "\r\nContent-Type: ",
+        content_type,
+        "\r\n",
+        headers,
+        "Connection: close\r\n\r\n",
+    ] {
+        write(socket, part.as_bytes()).await?;
+    }
+    Ok(())
+}
+
+async fn error(socket: &mut TcpSocket<'_>, status: &str) -> Result<(), Error> {
+    head(socket, status, "text/plain", "").await?;
+    write(socket, status.as_bytes()).await?;
+    write(socket, b"\n").await?;
+    flush(socket).await
+}
+
+/// Sensors heard since boot with their last periodic reading and its trend,
+/// as a JSON array
+async fn readings(socket: &mut TcpSocket<'_>) -> Result<(), Error> {
+    head(socket, "200 OK", "application/json", "").await?;
+    write(socket, b"[").await?;
+    let now = time::monotonic();
+    let mut first = true;
+    for idx in 0.. {
+        let Some(sensor) = REGISTRY.lock(|registry| registry.borrow().iter().nth(idx).cloned())
+        else {
+            break;
+        };
+        let mut json: String<SENSOR_LEN> = String::new();
+        if write_sensor(&mut json, &sensor, now).is_err() {
+            warn!("Reading of {} doesn't fit into buffer", sensor.model);
+            continue;
+        }
+        write(socket, if first { b"\n" } else { b",\n" }).await?;
+        write(socket, json.as_bytes()).await?;
+        first = false;
+    }
+    write(socket, b"\n]\n").await?;
+    flush(socket).await
+}
+
+fn write_sensor(out: &mut impl Write, sensor: &Sensor, now: u64) -> fmt::Result {
+    let data = &sensor.reading;
+    write!(
+        out,
+        "{{\"model\" : \"{}\", \"id\" : {}, \"channel\" : {}, \"online\" : {}, \"battery_ok\" : {}",
+        sensor.model, sensor.id, sensor.channel, sensor.online, data.battery_ok
+    )?;
+    if let Some(age) = time::since(now, sensor.last_seen) {
+        write!(out, ", \"age_s\" : {}", age.as_secs())?;
+    }
+    if let Some(climate) = data.climate() {
+        write!(out, ", \"temperature_C\" : {}", climate.celsius())?;
+        if let Some(humidity) = climate.humidity {
+            write!(out, ", \"humidity\" : {}", humidity)?;
+        }
+    }
+    if let Some(wind) = data.wind() {
+        write!(
+            out,
+            ", \"wind_avg_m_s\" : {}.{}, \"wind_max_m_s\" : {}.{}, \"wind_dir_deg\" : {}",
+            wind.avg_speed / 10,
+            wind.avg_speed % 10,
+            wind.gust_speed / 10,
+            wind.gust_speed % 10,
+            wind.direction
+        )?;
+    }
+    if let Some(rain) = data.rain() {
+        write!(
+            out,
+            ", \"rain_mm\" : {}.{}",
+            rain.total / 10,
+            rain.total % 10
+        )?;
+    }
+    if data.leak().is_some() || data.hazard().is_some() {
+        write!(out, ", \"alarm\" : {}", sensor.alarm.is_some())?;
+    }
+    // In tenths of temperature, wind speed or rain, whichever comes first
+    write!(out, ", \"trend\" : [")?;
+    for (idx, point) in trend::points(sensor.model, sensor.id, sensor.channel)
+        .iter()
+        .enumerate()
+    {
+        write!(out, "{}{}", if idx == 0 { "" } else { ", " }, point)?;
+    }
+    write!(out, "] }}")
+}
+
+/// Last status and health as they were published, null until they were
+async fn status(socket: &mut TcpSocket<'_>) -> Result<(), Error> {
+    head(socket, "200 OK", "application/json", "").await?;
+    for (prefix, payload) in [
+        ("{\"status\" : ", latest::status()),
+        (", \"health\" : ", latest::health()),
+    ] {
+        write(socket, prefix.as_bytes()).await?;
+        let payload = if payload.is_empty() {
+            "null"
+        } else {
+            payload.as_str()
+        };
+        write(socket, payload.as_bytes()).await?;
+    }
+    write(socket, b" }\n").await?;
+    flush(socket).await
+}
+
+/// `/history?sensor=<model>/<id>&hours=24&format=json`: logged readings of the
+/// last `hours`, oldest first, either as a JSON array of the payloads as they
+/// were published or as CSV with a fixed set of columns. All sensors without
+/// `sensor`, only readings with a time of their own are filtered by `hours`.
+#[cfg(feature = "history")]
+mod history {
+    use embassy_net::tcp::TcpSocket;
+    use jiff::civil::DateTime;
+    use jiff::tz::Offset;
+
+    use super::{Error, error, flush, head, write};
+    use crate::clock::{Clock, Tracked};
+    use crate::history::{self, Cursor, RECORD_LEN};
+
+    const DEFAULT_HOURS: u64 = 24;
+    const COLUMNS: [&str; 12] = [
+        "time",
+        "model",
+        "id",
+        "channel",
+        "battery_ok",
+        "temperature_C",
+        "humidity",
+        "wind_avg_m_s",
+        "wind_max_m_s",
+        "wind_dir_deg",
+        "rain_mm",
+        "rain_rate_mm_h",
+    ];
+
+    pub async fn get(socket: &mut TcpSocket<'_>, target: &str) -> Result<(), Error> {
+        let query = target.split_once('?').map_or("", |(_, query)| query);
+        let sensor = match param(query, "sensor") {
+            Some(sensor) => match split_sensor(sensor) {
+                Some(sensor) => Some(sensor),
+                None => return error(socket, "400 Bad Request").await,
+            },
+            None => None,
+        };
+        let hours = match param(query, "hours").map(str::parse::<u64>) {
+            None => DEFAULT_HOURS,
+            Some(Ok(hours)) if hours > 0 => hours,
+            Some(_) => return error(socket, "400 Bad Request").await,
+        };
+        let csv = match param(query, "format") {
+            None | Some("json") => false,
+            Some("csv") => true,
+            Some(_) => return error(socket, "400 Bad Request").await,
+        };
+        let since = Tracked
+            .time()
+            .await
+            .map(|now| now.saturating_sub(hours.saturating_mul(3_600_000_000)));
+
+        if csv {
+            head(socket, "200 OK", "text/csv", "").await?;
+            write_row(socket, |column| column).await?;
+        } else {
+            head(socket, "200 OK", "application/json", "").await?;
+            write(socket, b"[").await?;
+        }
+        let mut cursor = Cursor::default();
+        let mut buf = [0; RECORD_LEN];
+        let mut first = true;
+        while let Some(len) = history::next(&mut cursor, &mut buf) {
+            let Ok(record) = core::str::from_utf8(&buf[..len]) else {
+                continue;
+            };
+            if sensor.is_some_and(|(model, id)| {
+                field(record, "model") != Some(model) || field(record, "id") != Some(id)
+            }) || since.is_some_and(|since| timestamp(record).is_some_and(|time| time < since))
+            {
+                continue;
+            }
+            if csv {
+                write_row(socket, |column| field(record, column).unwrap_or_default()).await?;
+            } else {
+                write(socket, if first { b"\n" } else { b",\n" }).await?;
+                write(socket, record.as_bytes()).await?;
+                first = false;
+            }
+        }
+        if !csv {
+            write(socket, b"\n]\n").await?;
+        }
+        flush(socket).await
+    }
+
+    /// Value of `name` in a query string, as it's sent
+    fn param<'a>(query: &'a str, name: &str) -> Option<&'a str> {
+        query
+            .split('&')
+            .filter_map(|pair| pair.split_once('='))
+            .find(|(key, _)| *key == name)
+            .map(|(_, value)| value)
+    }
+
+    /// `<model>/<id>`, the slash may come escaped
+    fn split_sensor(sensor: &str) -> Option<(&str, &str)> {
+        let (model, id) = sensor
+            .split_once('/')
+            .or_else(|| sensor.split_once("%2F"))
+            .or_else(|| sensor.split_once("%2f"))?;
+        (!model.is_empty() && !id.is_empty()).then_some((model, id))
+    }
+
+    /// CSV line of `value` of every column
+    async fn write_row<'a>(
+        socket: &mut TcpSocket<'_>,
+        value: impl Fn(&'static str) -> &'a str,
+    ) -> Result<(), Error> {
+        for (idx, column) in COLUMNS.iter().enumerate() {
+            if idx > 0 {
+                write(socket, b",").await?;
+            }
+            let value = value(column);
+            if value.contains(',') {
+                write(socket, b"\"").await?;
+                write(socket, value.as_bytes()).await?;
+                write(socket, b"\"").await?;
+            } else {
+                write(socket, value.as_bytes()).await?;
+            }
+        }
+        write(socket, b"\n").await
+    }
+
+    /// Value of top level `key` in a logged record, strings without their
+    /// quotes
+    fn field<'a>(record: &'a str, key: &str) -> Option<&'a str> {
+        let mut rest = record;
+        loop {
+            let idx = rest.find(key)?;
+            let after = &rest[idx + key.len()..];
+            if rest[..idx].ends_with('"')
+                && let Some(value) = after.strip_prefix("\" : ")
+            {
+                return Some(match value.strip_prefix('"') {
+                    Some(value) => value.split('"').next().unwrap_or_default(),
+                    None => value.split([',', '}', ' ']).next().unwrap_or_default(),
+                });
+            }
+            rest = after;
+        }
+    }
+
+    /// Time of a logged record, in us since Unix epoch
+    fn timestamp(record: &str) -> Option<u64> {
+        let time = field(record, "time")?.strip_suffix(" UTC")?;
+        let datetime: DateTime = time.parse().ok()?;
+        let timestamp = Offset::UTC.to_timestamp(datetime).ok()?;
+        timestamp.as_microsecond().try_into().ok()
+    }
+}
+
+/// Setup page of the fallback access point, see AP_FALLBACK_SECS. `GET` shows
+/// the network and brokers the unit was given in a form, `POST /` saves what
+/// the form sends and restarts to join that network instead.
+#[cfg(feature = "provisioning")]
+pub mod setup {
+    use core::fmt::{self, Write};
+
+    use embassy_net::{Stack, tcp::TcpSocket};
+    use embassy_time::{Duration, Timer};
+    use esp_hal::hmac::Hmac;
+    use heapless::{String, Vec};
+    use log::{debug, info};
+
+    use super::{Error, REQUEST_LEN, error, flush, head, write};
+    use crate::credentials::Credentials;
+    use crate::provision;
+    use crate::{GIT_HASH, VERSION};
+
+    const PORT: u16 = 80;
+    const PAGE_LEN: usize = 2048;
+
+    #[embassy_executor::task]
+    pub async fn serve(
+        stack: Stack<'static>,
+        hmac: &'static mut Hmac<'static>,
+        credentials: &'static Credentials,
+    ) {
+        let mut rx_buf = [0; 1024];
+        let mut tx_buf = [0; 1536];
+        info!("Serving setup page on port {}", PORT);
+        loop {
+            let mut socket = TcpSocket::new(stack, &mut rx_buf, &mut tx_buf);
+            socket.set_timeout(Some(Duration::from_secs(10)));
+            if socket.accept(PORT).await.is_err() {
+                Timer::after(Duration::from_secs(1)).await;
+                continue;
+            }
+            let saved = handle(&mut socket, hmac, credentials).await;
+            if let Err(e) = &saved {
+                debug!("HTTP request failed: {:?}", e);
+            }
+            socket.close();
+            Timer::after(Duration::from_millis(100)).await;
+            socket.abort();
+            if let Ok(true) = saved {
+                info!("Restarting to join the new network");
+                Timer::after(Duration::from_secs(1)).await;
+                esp_hal::system::software_reset();
+            }
+        }
+    }
+
+    /// True once new credentials are saved
+    async fn handle(
+        socket: &mut TcpSocket<'_>,
+        hmac: &mut Hmac<'_>,
+        credentials: &Credentials,
+    ) -> Result<bool, Error> {
+        let mut request = [0; REQUEST_LEN];
+        let mut len = 0;
+        // Headers, then as much of the body as they say there is
+        let body = loop {
+            if let Some(end) = request[..len].windows(4).position(|end| end == b"\r\n\r\n") {
+                let headers = core::str::from_utf8(&request[..end]).unwrap_or_default();
+                let content_len = headers
+                    .lines()
+                    .filter_map(|line| line.split_once(':'))
+                    .find(|(name, _)| name.eq_ignore_ascii_case("content-length"))
+                    .and_then(|(_, value)| value.trim().parse::<usize>().ok())
+                    .unwrap_or(0);
+                // A huge length would otherwise wrap around past the check
+                let Some(body_end) = (end + 4)
+                    .checked_add(content_len)
+                    .filter(|&body_end| body_end <= request.len())
+                else {
+                    error(socket, "413 Content Too Large").await?;
+                    return Ok(false);
+                };
+                if len >= body_end {
+                    break end + 4..body_end;
+                }
+            } else if len == request.len() {
+                error(socket, "431 Request Header Fields Too Large").await?;
+                return Ok(false);
+            }
+            match socket.read(&mut request[len..]).await {
+                Ok(0) | Err(_) => return Err(Error::ReadFailed),
+                Ok(read) => len += read,
+            }
+        };
+        let text = core::str::from_utf8(&request[..len]).unwrap_or_default();
+        let mut line = text.lines().next().unwrap_or_default().split(' ');
+        let (Some(method), Some(target)) = (line.next(), line.next()) else {
+            error(socket, "400 Bad Request").await?;
+            return Ok(false);
+        };
+        debug!("HTTP {} {}", method, target);
+        match method {
+            // Whatever a phone checks for a captive portal gets the page too
+            "GET" => page(socket, "200 OK", |out| write_form(out, credentials, None)).await?,
+            "POST" if target == "/" => {
+                let form = core::str::from_utf8(&request[body]).unwrap_or_default();
+                let result = parse(form, credentials)
+                    .and_then(|updated| provision::store(hmac, &updated).map(|_| updated));
+                return match result {
+                    Ok(updated) => {
+                        page(socket, "200 OK", |out| {
+                            write!(
+                                out,
+                                "<p>Saved, restarting to join <b>{}</b>.</p>",
+                                Html(&updated.ssid)
+                            )
+                        })
+                        .await?;
+                        Ok(true)
+                    }
+                    Err(reason) => {
+                        page(socket, "400 Bad Request", |out| {
+                            write_form(out, credentials, Some(reason))
+                        })
+                        .await?;
+                        Ok(false)
+                    }
+                };
+            }
+            "POST" => error(socket, "404 Not Found").await?,
+            _ => error(socket, "405 Method Not Allowed").await?,
+        }
+        Ok(false)
+    }
+
+    /// Page with the body `content` writes
+    async fn page(
+        socket: &mut TcpSocket<'_>,
+        status: &str,
+        content: impl FnOnce(&mut String<PAGE_LEN>) -> fmt::Result,
+    ) -> Result<(), Error> {
+        let mut html: String<PAGE_LEN> = String::new();
+        let written = write!(
+            html,
+            "<!DOCTYPE html><html><head><meta name=\"viewport\" content=\"width=device-width\">\
+             <title>esp-rf-ook2 setup</title></head><body><h1>esp-rf-ook2 setup</h1>"
+        )
+        .and_then(|_| content(&mut html))
+        .and_then(|_| write!(html, "</body></html>"));
+        if written.is_err() {
+            return error(socket, "500 Internal Server Error").await;
+        }
+        head(socket, status, "text/html; charset=utf-8", "").await?;
+        write(socket, html.as_bytes()).await?;
+        flush(socket).await
+    }
+
+    /// Form to enter the network and brokers with, and why the last one was
+    /// rejected
+    fn write_form(
+        out: &mut impl Write,
+        credentials: &Credentials,
+        rejected: Option<&str>,
+    ) -> fmt::Result {
+        write!(
+            out,
+            "<p>Firmware {} ({}). Couldn't join <b>{}</b>, enter the network to use instead.</p>",
+            VERSION,
+            GIT_HASH,
+            Html(&credentials.ssid)
+        )?;
+        if let Some(reason) = rejected {
+            write!(out, "<p><b>Not saved: {}</b></p>", Html(reason))?;
+        }
+        write!(
+            out,
+            "<form method=\"post\" action=\"/\">\
+             <p>Network<br><input name=\"ssid\" value=\"{}\" maxlength=\"32\" required></p>\
+             <p>Password<br><input name=\"password\" type=\"password\" maxlength=\"64\" \
+             placeholder=\"empty for an open network\"></p>\
+             <p>MQTT brokers<br><input name=\"broker\" value=\"{}\" maxlength=\"128\" required></p>\
+             <p><button>Save and restart</button></p></form>",
+            Html(&credentials.ssid),
+            Html(&credentials.broker)
+        )
+    }
+
+    /// `credentials` with the fields of a URL encoded form
+    fn parse(form: &str, credentials: &Credentials) -> Result<Credentials, &'static str> {
+        let mut updated = credentials.clone();
+        updated.ssid = field(form, "ssid").ok_or("ssid is too long or invalid")?;
+        updated.password = field(form, "password").ok_or("password is too long or invalid")?;
+        updated.broker = field(form, "broker").ok_or("broker is too long or invalid")?;
+        Ok(updated)
+    }
+
+    /// Decoded value of `name` in a URL encoded form, empty if it's not
+    /// there. None if it doesn't fit or isn't valid, which includes NUL as
+    /// that separates the fields of the stored credentials.
+    fn field<const N: usize>(form: &str, name: &str) -> Option<String<N>> {
+        let Some((_, value)) = form
+            .split('&')
+            .filter_map(|pair| pair.split_once('='))
+            .find(|(key, _)| *key == name)
+        else {
+            return Some(String::new());
+        };
+        let mut decoded: Vec<u8, N> = Vec::new();
+        let mut bytes = value.bytes();
+        while let Some(byte) = bytes.next() {
+            let byte = match byte {
+                b'+' => b' ',
+                b'%' => {
+                    let hex = [bytes.next()?, bytes.next()?];
+                    u8::from_str_radix(core::str::from_utf8(&hex).ok()?, 16).ok()?
+                }
+                byte => byte,
+            };
+            if byte == 0 {
+                return None;
+            }
+            decoded.push(byte).ok()?;
+        }
+        String::from_utf8(decoded).ok()
+    }
+
+    /// Text with the characters that mean something in HTML escaped
+    struct Html<'a>(&'a str);
+
+    impl fmt::Display for Html<'_> {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            for c in self.0.chars() {
+                match c {
+                    '&' => f.write_str("&amp;")?,
+                    '<' => f.write_str("&lt;")?,
+                    '>' => f.write_str("&gt;")?,
+                    '"' => f.write_str("&quot;")?,
+                    c => f.write_char(c)?,
+                }
+            }
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,63 @@
+use embassy_time::Duration;
+
+use crate::decoder::{Climate, SensorData};
+use crate::time;
+
+/// What the filter needs of the last published reading of a sensor
+#[derive(Clone, Debug)]
+pub struct Published {
+    climate: Option<Climate>,
+    battery_ok: bool,
+    /// Monotonic time it was published at
+    at: u64,
+}
+
+impl Published {
+    pub fn new(data: &SensorData, now: u64) -> Self {
+        Published {
+            climate: data.climate().cloned(),
+            battery_ok: data.battery_ok,
+            at: now,
+        }
+    }
+}
+
+/// Significant-change filter for periodic readings. A reading is published
+/// when temperature or humidity moved far enough from the last published one,
+/// battery state changed, or after `heartbeat` without publishing, so
+/// consumers can still tell the sensor is alive.
+pub struct Hysteresis {
+    /// In 0.1 C, 0 ignores temperature
+    pub temperature: u16,
+    /// In %, 0 ignores humidity
+    pub humidity: u8,
+    pub heartbeat: Duration,
+}
+
+impl Hysteresis {
+    /// Filter is off unless a threshold is set, every reading is published
+    pub fn is_enabled(&self) -> bool {
+        self.temperature > 0 || self.humidity > 0
+    }
+
+    /// Whether the reading accepted at monotonic `now` is worth publishing
+    pub fn check(&self, last: Option<&Published>, data: &SensorData, now: u64) -> bool {
+        let Some(last) = last.filter(|_| self.is_enabled()) else {
+            return true;
+        };
+        if time::passed(now, last.at, self.heartbeat) || last.battery_ok != data.battery_ok {
+            return true;
+        }
+        // Filter only knows climate readings, the rest always go through
+        let (Some(last), Some(climate)) = (&last.climate, data.climate()) else {
+            return true;
+        };
+        let temperature = last.temp_decicelsius.abs_diff(climate.temp_decicelsius);
+        let humidity = match (last.humidity, climate.humidity) {
+            (Some(last), Some(humidity)) => last.abs_diff(humidity),
+            _ => 0,
+        };
+        (self.temperature > 0 && temperature >= self.temperature)
+            || (self.humidity > 0 && humidity >= self.humidity)
+    }
+}
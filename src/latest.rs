@@ -0,0 +1,76 @@
+use core::cell::RefCell;
+
+use embassy_sync::blocking_mutex::{CriticalSectionMutex, raw::CriticalSectionRawMutex};
+use embassy_sync::watch::{Receiver, Watch};
+use heapless::String;
+
+use crate::events::Reading;
+use crate::registry::{Registry, Sensor};
+use crate::snapshot::REGISTRY_LEN;
+
+// Latest state of the sensors, for consumers like a display, an HTTP page or
+// alerting. They read it whenever they like instead of following the event bus,
+// so they're never in the way of decoding or publishing.
+
+/// Longest diagnostics payload kept
+pub const DIAGNOSTICS_LEN: usize = 768;
+
+/// Tasks that can wait for readings at once
+pub const RECEIVERS: usize = 4;
+
+/// Sensors heard so far with their last periodic reading. The main loop keeps
+/// it up to date, the publisher dumps it on request.
+pub static REGISTRY: CriticalSectionMutex<RefCell<Registry<REGISTRY_LEN>>> =
+    CriticalSectionMutex::new(RefCell::new(Registry::new()));
+
+static LATEST: Watch<CriticalSectionRawMutex, Reading, RECEIVERS> = Watch::new();
+
+// Last status and health payloads as they were published, empty until then
+static STATUS: CriticalSectionMutex<RefCell<String<DIAGNOSTICS_LEN>>> =
+    CriticalSectionMutex::new(RefCell::new(String::new()));
+static HEALTH: CriticalSectionMutex<RefCell<String<DIAGNOSTICS_LEN>>> =
+    CriticalSectionMutex::new(RefCell::new(String::new()));
+
+pub type LatestReceiver = Receiver<'static, CriticalSectionRawMutex, Reading, RECEIVERS>;
+
+/// Follow readings as they're accepted, events included. Readings that come
+/// while the receiver isn't looking are skipped, only the latest one is kept.
+/// None if there are RECEIVERS already.
+pub fn receiver() -> Option<LatestReceiver> {
+    LATEST.receiver()
+}
+
+/// Most recent reading of any sensor
+pub fn reading() -> Option<Reading> {
+    LATEST.try_get()
+}
+
+/// Sensor with its last periodic reading, if it's been heard since boot
+pub fn sensor(model: &str, id: u32, channel: u8) -> Option<Sensor> {
+    REGISTRY.lock(|registry| registry.borrow().find(model, id, channel).cloned())
+}
+
+/// Reading has been accepted for publishing
+pub fn update(reading: &Reading) {
+    LATEST.sender().send(reading.clone());
+}
+
+/// Status payload is being published
+pub fn set_status(payload: &str) {
+    STATUS.lock(|status| *status.borrow_mut() = payload.try_into().unwrap_or_default());
+}
+
+/// Last status payload, empty if there was none yet
+pub fn status() -> String<DIAGNOSTICS_LEN> {
+    STATUS.lock(|status| status.borrow().clone())
+}
+
+/// Health payload is being published
+pub fn set_health(payload: &str) {
+    HEALTH.lock(|health| *health.borrow_mut() = payload.try_into().unwrap_or_default());
+}
+
+/// Last health payload, empty if there was none yet
+pub fn health() -> String<DIAGNOSTICS_LEN> {
+    HEALTH.lock(|health| health.borrow().clone())
+}
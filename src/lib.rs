@@ -1,29 +1,638 @@
 #![no_std]
 
-pub mod decoder;
+extern crate alloc;
+
+pub mod airtime;
+#[cfg(feature = "bench")]
+pub mod bench;
+pub mod broadcast;
+pub mod bursts;
+pub mod cbor;
+pub mod clock;
+pub mod collision;
+pub mod config;
+pub mod credentials;
+pub mod dedup;
+pub mod derived;
+pub mod ds3231;
+pub mod entropy;
+pub mod errors;
+pub mod esphome;
+pub mod events;
+#[cfg(any(
+    feature = "history",
+    feature = "ota",
+    feature = "postmortem",
+    feature = "provisioning",
+    feature = "rain-totals"
+))]
+pub mod flash;
+pub mod health;
+#[cfg(feature = "history")]
+pub mod history;
+pub mod http;
+pub mod hysteresis;
+pub mod latest;
+pub mod logger;
 pub mod mqtt;
 pub mod ntpc;
+#[cfg(feature = "ota")]
+pub mod ota;
+pub mod output;
+pub mod panic;
+pub mod pool;
+#[cfg(feature = "postmortem")]
+pub mod postmortem;
+#[cfg(feature = "provisioning")]
+pub mod provision;
+pub mod pubstats;
+pub mod queue;
+pub mod rain;
+pub mod ratelimit;
+pub mod registry;
+#[cfg(feature = "replay")]
+pub mod replay;
+pub mod rx;
+pub mod schedule;
+pub mod selftest;
+pub mod snapshot;
+#[cfg(feature = "provisioning")]
+pub mod softap;
+pub mod station;
+pub mod summary;
+pub mod time;
+#[cfg(feature = "trace")]
+pub mod trace;
+pub mod trend;
+pub mod watchdog;
+pub mod webhook;
 pub mod wifi;
+pub mod wind;
+pub mod zone;
 
-extern crate alloc;
+pub use ook_decoder as decoder;
 
 pub const RX_BUFFER_SIZE: usize = 2048;
 pub const TX_BUFFER_SIZE: usize = 2048;
 
 pub const SSID: &str = env!("SSID");
 pub const PASSWORD: &str = env!("PASSWORD");
+const _: () = assert!(SSID.len() <= credentials::SSID_LEN, "SSID is too long");
+const _: () = assert!(
+    PASSWORD.len() <= credentials::PASSWORD_LEN,
+    "Password is too long"
+);
+// Roam to a stronger access point of the same network, e.g. a mesh, once the
+// signal drops below ROAM_RSSI_DBM. Checked every ROAM_CHECK_SECS, a candidate
+// has to be ROAM_MARGIN_DB stronger. 0 stays with the access point until the
+// connection fails.
+pub const ROAM_RSSI_DBM: i8 = {
+    let rssi = env_or_signed(option_env!("ROAM_RSSI_DBM"), 0);
+    assert!(rssi >= -100 && rssi <= 0, "Invalid roaming threshold");
+    rssi as i8
+};
+pub const ROAM_CHECK_SECS: u64 = env_or(option_env!("ROAM_CHECK_SECS"), 60);
+const _: () = assert!(
+    ROAM_CHECK_SECS > 0,
+    "Roaming check interval must be positive"
+);
+pub const ROAM_MARGIN_DB: u8 = {
+    let margin = env_or(option_env!("ROAM_MARGIN_DB"), 8);
+    assert!(margin <= 100, "Invalid roaming margin");
+    margin as u8
+};
+// With the provisioning feature, the USB serial port takes commands for this
+// long at boot before Wi-Fi comes up
+pub const PROVISION_WINDOW_SECS: u64 = env_or(option_env!("PROVISION_WINDOW_SECS"), 3);
+// With the provisioning feature, a unit that can't join Wi-Fi for this long
+// after boot opens an access point of its own with a setup page instead of
+// trying forever. 0 never gives up.
+pub const AP_FALLBACK_SECS: u64 = env_or(option_env!("AP_FALLBACK_SECS"), 300);
+// Longest random delay before joining Wi-Fi at boot, so a building full of
+// units coming back after a power outage doesn't hit the access point, NTP
+// server and broker all at once. 0 starts right away.
+pub const BOOT_JITTER_SECS: u64 = env_or(option_env!("BOOT_JITTER_SECS"), 0);
+// WPA2 passphrase of that access point, it's open if empty
+pub const AP_PASSWORD: &str = match option_env!("AP_PASSWORD") {
+    Some(password) => password,
+    None => "",
+};
+const _: () = assert!(
+    AP_PASSWORD.is_empty() || (AP_PASSWORD.len() >= 8 && AP_PASSWORD.len() <= 63),
+    "AP_PASSWORD must be 8 to 63 characters"
+);
+// With the provisioning feature, 1 keeps that access point up next to the
+// network connection all along, so the dashboard stays reachable on it while
+// the network is down
+pub const AP_STA: bool = match env_or(option_env!("AP_STA"), 0) {
+    0 => false,
+    1 => true,
+    _ => panic!("AP_STA must be 0 or 1"),
+};
+const _: () = assert!(
+    !AP_STA || HTTP_PORT != 80,
+    "HTTP_PORT can't be 80 with AP_STA, the setup page takes it"
+);
+// Stored credentials are encrypted with keys
+// derived from this eFuse key block (0 to 5), which must hold a key burned
+// for the HMAC_UP purpose. They're stored in plain text if it's not set.
+pub const CREDENTIALS_KEY_BLOCK: Option<u8> = match option_env!("CREDENTIALS_KEY_BLOCK") {
+    Some(block) => {
+        let block = env_or(Some(block), 0);
+        assert!(block <= 5, "eFuse key block must be 0 to 5");
+        Some(block as u8)
+    }
+    None => None,
+};
 
-pub const NTP_SERVER: &str = "pool.ntp.org";
+pub const NTP_SERVER: &str = match option_env!("NTP_SERVER") {
+    Some(server) => server,
+    None => "pool.ntp.org",
+};
+// NTP_SERVER=off runs without NTP, e.g. on networks without internet access
+pub const NTP_ENABLED: bool = !matches!(NTP_SERVER.as_bytes(), b"off");
 pub const TIMEZONE: &str = "UTC";
 
+// Payloads carry time as "2024-11-03 00:00:00 UTC" in `time`. 1 adds Unix
+// epoch seconds as `epoch`, and RFC 3339 as `time_rfc3339` respectively.
+pub const TIME_EPOCH: bool = match env_or(option_env!("TIME_EPOCH"), 0) {
+    0 => false,
+    1 => true,
+    _ => panic!("TIME_EPOCH must be 0 or 1"),
+};
+pub const TIME_RFC3339: bool = match env_or(option_env!("TIME_RFC3339"), 0) {
+    0 => false,
+    1 => true,
+    _ => panic!("TIME_RFC3339 must be 0 or 1"),
+};
+
+// 1 keeps time in a DS3231 RTC module as well, which sets the clock on boot
+// and whenever NTP can't be reached, and is set from NTP in turn
+pub const DS3231: bool = match env_or(option_env!("DS3231"), 0) {
+    0 => false,
+    1 => true,
+    _ => panic!("DS3231 must be 0 or 1"),
+};
+
 pub const MQTT_SERVER: &str = env!("MQTT_SERVER");
-pub const MQTT_LOGIN: &str = env!("MQTT_LOGIN");
-pub const MQTT_PASSWORD: &str = env!("MQTT_PASSWORD");
+const _: () = assert!(
+    MQTT_SERVER.len() <= credentials::BROKER_LEN,
+    "MQTT_SERVER is too long"
+);
+// Empty or unset login connects anonymously
+pub const MQTT_LOGIN: &str = match option_env!("MQTT_LOGIN") {
+    Some(login) => login,
+    None => "",
+};
+pub const MQTT_PASSWORD: &str = match option_env!("MQTT_PASSWORD") {
+    Some(password) => password,
+    None => "",
+};
 
 pub const MQTT_TOPIC: &str = env!("MQTT_TOPIC");
 
+// Everything published to MQTT is also POSTed to WEBHOOK_URL/<topic> if set,
+// and broadcast over UDP to UDP_BROADCAST_PORT unless it's 0
+pub const WEBHOOK_URL: Option<&str> = option_env!("WEBHOOK_URL");
+pub const UDP_BROADCAST_PORT: u16 = {
+    let port = env_or(option_env!("UDP_BROADCAST_PORT"), 0);
+    assert!(port <= u16::MAX as u64, "Invalid UDP broadcast port");
+    port as u16
+};
+// A dashboard of the sensors is served over HTTP on this port, and with the
+// history feature the logged readings too. 0 disables the server.
+pub const HTTP_PORT: u16 = {
+    let port = env_or(option_env!("HTTP_PORT"), 0);
+    assert!(port <= u16::MAX as u64, "Invalid HTTP port");
+    port as u16
+};
+// Commands are retained messages under MQTT_TOPIC/cmd/
+pub const MQTT_COMMAND_TOPICS: &str = concat!(env!("MQTT_TOPIC"), "/cmd/+");
+pub const MQTT_GET_READINGS_TOPIC: &str = concat!(env!("MQTT_TOPIC"), "/cmd/get_readings");
+pub const MQTT_READINGS_TOPIC: &str = concat!(env!("MQTT_TOPIC"), "/readings");
+pub const MQTT_REBOOT_TOPIC: &str = concat!(env!("MQTT_TOPIC"), "/cmd/reboot");
+pub const MQTT_FACTORY_RESET_TOPIC: &str = concat!(env!("MQTT_TOPIC"), "/cmd/factory_reset");
+pub const MQTT_SET_TIMING_TOPIC: &str = concat!(env!("MQTT_TOPIC"), "/cmd/timing");
+pub const MQTT_TIMING_TOPIC: &str = concat!(env!("MQTT_TOPIC"), "/timing");
+pub const MQTT_CAPTURE_TOPIC: &str = concat!(env!("MQTT_TOPIC"), "/cmd/capture");
+pub const MQTT_CAPTURES_TOPIC: &str = concat!(env!("MQTT_TOPIC"), "/capture");
+pub const MQTT_GET_BURST_TOPIC: &str = concat!(env!("MQTT_TOPIC"), "/cmd/burst");
+pub const MQTT_BURST_TOPIC: &str = concat!(env!("MQTT_TOPIC"), "/burst");
+pub const MQTT_SET_DEBUG_TOPIC: &str = concat!(env!("MQTT_TOPIC"), "/cmd/debug");
+pub const MQTT_DEBUG_TOPIC: &str = concat!(env!("MQTT_TOPIC"), "/debug");
+pub const MQTT_LEARN_TOPIC: &str = concat!(env!("MQTT_TOPIC"), "/cmd/learn");
+pub const MQTT_LEARNED_TOPIC: &str = concat!(env!("MQTT_TOPIC"), "/learned");
+pub const MQTT_SET_TIME_TOPIC: &str = concat!(env!("MQTT_TOPIC"), "/cmd/set_time");
+pub const MQTT_SET_PROTOCOLS_TOPIC: &str = concat!(env!("MQTT_TOPIC"), "/cmd/protocols");
+pub const MQTT_PROTOCOLS_TOPIC: &str = concat!(env!("MQTT_TOPIC"), "/protocols");
+pub const MQTT_SET_CONFIG_TOPIC: &str = concat!(env!("MQTT_TOPIC"), "/cmd/config");
+pub const MQTT_CONFIG_TOPIC: &str = concat!(env!("MQTT_TOPIC"), "/config");
+pub const MQTT_ZONES_TOPIC: &str = concat!(env!("MQTT_TOPIC"), "/zones");
+pub const MQTT_STATIONS_TOPIC: &str = concat!(env!("MQTT_TOPIC"), "/stations");
+pub const MQTT_SENSOR_CONFIG_TOPICS: &str = concat!(env!("MQTT_TOPIC"), "/config/sensors/+/+");
+pub const MQTT_GET_HISTORY_TOPIC: &str = concat!(env!("MQTT_TOPIC"), "/cmd/history");
+pub const MQTT_HISTORY_TOPIC: &str = concat!(env!("MQTT_TOPIC"), "/history");
+pub const MQTT_DIAGNOSE_TOPIC: &str = concat!(env!("MQTT_TOPIC"), "/cmd/diagnose");
+pub const MQTT_NETWORK_TOPIC: &str = concat!(env!("MQTT_TOPIC"), "/network");
+pub const MQTT_TRANSMIT_TOPIC: &str = concat!(env!("MQTT_TOPIC"), "/cmd/transmit");
+pub const MQTT_SET_OTA_TOPIC: &str = concat!(env!("MQTT_TOPIC"), "/cmd/ota");
+pub const MQTT_CLEAR_ALARM_TOPIC: &str = concat!(env!("MQTT_TOPIC"), "/cmd/clear_alarm");
+pub const MQTT_OTA_TOPIC: &str = concat!(env!("MQTT_TOPIC"), "/ota");
+
+// Reboot and factory reset commands must carry this token as payload, and
+// firmware updates in their first chunk
+pub const COMMAND_TOKEN: &str = env!("COMMAND_TOKEN");
+
+pub const MQTT_STATUS_TOPIC: &str = concat!(env!("MQTT_TOPIC"), "/status");
+// Latched alarms go under MQTT_TOPIC/alarms/<model>/<id>
+pub const MQTT_ALARMS_TOPIC: &str = concat!(env!("MQTT_TOPIC"), "/alarms");
+// Doorbell presses, whichever doorbell it was
+pub const MQTT_DOORBELL_TOPIC: &str = concat!(env!("MQTT_TOPIC"), "/button/doorbell");
+pub const MQTT_RECEIVER_TOPIC: &str = concat!(env!("MQTT_TOPIC"), "/receiver");
+pub const MQTT_SUMMARY_TOPIC: &str = concat!(env!("MQTT_TOPIC"), "/summary");
+pub const MQTT_HEALTH_TOPIC: &str = concat!(env!("MQTT_TOPIC"), "/health");
+pub const MQTT_BENCH_TOPIC: &str = concat!(env!("MQTT_TOPIC"), "/bench");
+pub const MQTT_TRACE_TOPIC: &str = concat!(env!("MQTT_TOPIC"), "/trace");
+// Report of the panic the unit last reset after, uploaded by the next boot
+pub const MQTT_PANICS_TOPIC: &str = concat!(env!("MQTT_TOPIC"), "/panics");
+pub const MQTT_PUBLISHES_TOPIC: &str = concat!(env!("MQTT_TOPIC"), "/publishes");
+// Errors are published under MQTT_TOPIC/errors/<module>
+pub const MQTT_ERRORS_TOPIC: &str = concat!(env!("MQTT_TOPIC"), "/errors");
+
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+pub const GIT_HASH: &str = env!("GIT_HASH");
+// Seconds since Unix epoch
+pub const BUILD_TIMESTAMP: &str = env!("BUILD_TIMESTAMP");
+
+/// Parse optional numeric build time setting, falling back to `default`
+const fn env_or(value: Option<&str>, default: u64) -> u64 {
+    let Some(value) = value else {
+        return default;
+    };
+    parse_digits(value.as_bytes(), 0)
+}
+
+/// Same as env_or(), but the setting may be negative
+const fn env_or_signed(value: Option<&str>, default: i64) -> i64 {
+    let Some(value) = value else {
+        return default;
+    };
+    let bytes = value.as_bytes();
+    if !bytes.is_empty() && bytes[0] == b'-' {
+        -(parse_digits(bytes, 1) as i64)
+    } else {
+        parse_digits(bytes, 0) as i64
+    }
+}
+
+/// 32 byte key setting in hex, None if it's not set
+#[cfg(feature = "ota")]
+const fn parse_key(value: Option<&str>) -> Option<[u8; 32]> {
+    let bytes = match value {
+        Some(value) if !value.is_empty() => value.as_bytes(),
+        _ => return None,
+    };
+    assert!(bytes.len() == 64, "Key must be 64 hex digits");
+    let mut key = [0; 32];
+    let mut idx = 0;
+    while idx < bytes.len() {
+        let digit = match bytes[idx] {
+            b'0'..=b'9' => bytes[idx] - b'0',
+            b'a'..=b'f' => bytes[idx] - b'a' + 10,
+            b'A'..=b'F' => bytes[idx] - b'A' + 10,
+            _ => panic!("Key must be 64 hex digits"),
+        };
+        key[idx / 2] = key[idx / 2] << 4 | digit;
+        idx += 1;
+    }
+    Some(key)
+}
+
+const fn parse_digits(bytes: &[u8], start: usize) -> u64 {
+    let mut result = 0;
+    let mut idx = start;
+    while idx < bytes.len() {
+        assert!(bytes[idx].is_ascii_digit(), "Setting must be a number");
+        result = result * 10 + (bytes[idx] - b'0') as u64;
+        idx += 1;
+    }
+    result
+}
+
+// Watchdog restarts Wi-Fi if the main loop is stuck for this long, in case
+// it's stuck on the network. If it's still stuck WATCHDOG_DUMP_SECS later the
+// diagnostics are written out, and the unit resets WATCHDOG_RESET_SECS after
+// that.
+pub const WATCHDOG_TIMEOUT_SECS: u64 = env_or(option_env!("WATCHDOG_TIMEOUT_SECS"), 30);
+pub const WATCHDOG_DUMP_SECS: u64 = env_or(option_env!("WATCHDOG_DUMP_SECS"), 30);
+pub const WATCHDOG_RESET_SECS: u64 = env_or(option_env!("WATCHDOG_RESET_SECS"), 2);
+const _: () = assert!(
+    WATCHDOG_TIMEOUT_SECS > 0 && WATCHDOG_DUMP_SECS > 0 && WATCHDOG_RESET_SECS > 0,
+    "Watchdog stages must be positive"
+);
+// With the ota feature, an updated firmware is rolled back unless its status
+// announce gets through to the broker within OTA_CONFIRM_SECS, or if it resets
+// more than OTA_TRIAL_BOOTS times before
+pub const OTA_CONFIRM_SECS: u64 = env_or(option_env!("OTA_CONFIRM_SECS"), 600);
+const _: () = assert!(
+    OTA_CONFIRM_SECS > 0,
+    "OTA confirmation time must be positive"
+);
+pub const OTA_TRIAL_BOOTS: u32 = {
+    let boots = env_or(option_env!("OTA_TRIAL_BOOTS"), 3);
+    assert!(
+        boots > 0 && boots <= 100,
+        "Invalid number of OTA trial boots"
+    );
+    boots as u32
+};
+// Firmware sent over MQTT is only taken if it's signed with the Ed25519 key
+// this is the public half of, given as 64 hex digits. Required with the ota
+// feature, a build that takes unsigned firmware would let anyone who can
+// publish to the broker run their own code.
+#[cfg(feature = "ota")]
+pub const OTA_PUBLIC_KEY: [u8; 32] = match parse_key(option_env!("OTA_PUBLIC_KEY")) {
+    Some(key) => key,
+    None => panic!("OTA_PUBLIC_KEY must be set with the ota feature"),
+};
+// A firmware update sent over MQTT is abandoned if the next chunk doesn't
+// arrive within this long
+pub const OTA_CHUNK_TIMEOUT_SECS: u64 = env_or(option_env!("OTA_CHUNK_TIMEOUT_SECS"), 60);
+const _: () = assert!(
+    OTA_CHUNK_TIMEOUT_SECS > 0,
+    "OTA chunk timeout must be positive"
+);
+// Without a successful publish for this long Wi-Fi and MQTT are restarted, and
+// if that doesn't help within the same period again the unit is reset. Has to
+// be longer than the transmit interval of the sensors.
+pub const PUBLISH_TIMEOUT_SECS: u64 = env_or(option_env!("PUBLISH_TIMEOUT_SECS"), 300);
+
+// Readings are published at most this many per minute on average, with bursts
+// of up to PUBLISH_BURST, so a chattering sensor or noise can't flood MQTT
+pub const PUBLISH_RATE_PER_MIN: u64 = env_or(option_env!("PUBLISH_RATE_PER_MIN"), 30);
+pub const PUBLISH_BURST: u64 = env_or(option_env!("PUBLISH_BURST"), 10);
+const _: () = assert!(
+    PUBLISH_RATE_PER_MIN > 0 && PUBLISH_BURST > 0,
+    "Publish rate and burst must be positive"
+);
+
+// QoS (0 or 1) and retain flag (0 or 1) for each class of messages
+pub const MQTT_READINGS_QOS: u64 = env_or(option_env!("MQTT_READINGS_QOS"), 0);
+pub const MQTT_READINGS_RETAIN: u64 = env_or(option_env!("MQTT_READINGS_RETAIN"), 0);
+pub const MQTT_DIAGNOSTICS_QOS: u64 = env_or(option_env!("MQTT_DIAGNOSTICS_QOS"), 0);
+pub const MQTT_DIAGNOSTICS_RETAIN: u64 = env_or(option_env!("MQTT_DIAGNOSTICS_RETAIN"), 1);
+pub const MQTT_ALERTS_QOS: u64 = env_or(option_env!("MQTT_ALERTS_QOS"), 0);
+pub const MQTT_ALERTS_RETAIN: u64 = env_or(option_env!("MQTT_ALERTS_RETAIN"), 1);
+pub const MQTT_ALARMS_QOS: u64 = env_or(option_env!("MQTT_ALARMS_QOS"), 1);
+pub const MQTT_ALARMS_RETAIN: u64 = env_or(option_env!("MQTT_ALARMS_RETAIN"), 1);
+pub const MQTT_CAPTURES_QOS: u64 = env_or(option_env!("MQTT_CAPTURES_QOS"), 0);
+pub const MQTT_CAPTURES_RETAIN: u64 = env_or(option_env!("MQTT_CAPTURES_RETAIN"), 0);
+
+// 1 additionally publishes every value to its own topic and announces it for
+// Home Assistant discovery, the way ESPHome's MQTT component does
+pub const ESPHOME_SCHEMA: bool = match env_or(option_env!("ESPHOME_SCHEMA"), 0) {
+    0 => false,
+    1 => true,
+    _ => panic!("ESPHOME_SCHEMA must be 0 or 1"),
+};
+
+/// Encoding of published readings
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PayloadFormat {
+    Json,
+    /// Same structure as the JSON, in CBOR
+    Cbor,
+    /// Sensor readings as SenML packs (RFC 8428) in JSON
+    SenmlJson,
+    /// Same, in CBOR
+    SenmlCbor,
+}
+
+impl PayloadFormat {
+    /// Media type, without the `application/` prefix
+    pub fn key(self) -> &'static str {
+        match self {
+            PayloadFormat::Json => "json",
+            PayloadFormat::Cbor => "cbor",
+            PayloadFormat::SenmlJson => "senml+json",
+            PayloadFormat::SenmlCbor => "senml+cbor",
+        }
+    }
+
+    pub fn senml(self) -> bool {
+        matches!(self, PayloadFormat::SenmlJson | PayloadFormat::SenmlCbor)
+    }
+
+    pub fn cbor(self) -> bool {
+        matches!(self, PayloadFormat::Cbor | PayloadFormat::SenmlCbor)
+    }
+}
+
+// `cbor` publishes readings over MQTT in CBOR rather than JSON, around a third
+// smaller, for narrowband or LTE-M uplinks billed by the byte. `senml` and
+// `senml-cbor` publish sensor readings as SenML packs instead, for platforms
+// that ingest standard measurement records. Status and diagnostics stay JSON,
+// the status says which format readings are in.
+pub const PAYLOAD_FORMAT: PayloadFormat = match option_env!("PAYLOAD_FORMAT") {
+    None => PayloadFormat::Json,
+    Some(format) => match format.as_bytes() {
+        b"json" => PayloadFormat::Json,
+        b"cbor" => PayloadFormat::Cbor,
+        b"senml" => PayloadFormat::SenmlJson,
+        b"senml-cbor" => PayloadFormat::SenmlCbor,
+        _ => panic!("PAYLOAD_FORMAT must be json, cbor, senml or senml-cbor"),
+    },
+};
+
+// 1 publishes the average climate of a zone to MQTT_ZONES_TOPIC/<zone> after
+// every reading of a sensor in it
+pub const ZONE_AVERAGES: bool = match env_or(option_env!("ZONE_AVERAGES"), 0) {
+    0 => false,
+    1 => true,
+    _ => panic!("ZONE_AVERAGES must be 0 or 1"),
+};
+
+// 1 publishes the current conditions of a weather station, assembled from all
+// of its frames, to MQTT_STATIONS_TOPIC/<model>/<id> after every reading of it
+pub const STATION_RECORDS: bool = match env_or(option_env!("STATION_RECORDS"), 0) {
+    0 => false,
+    1 => true,
+    _ => panic!("STATION_RECORDS must be 0 or 1"),
+};
+
+// Most bursts that can be requested with a single capture command
+pub const MAX_CAPTURES: u8 = 16;
+
+// Latest raw bursts kept in RAM for the burst command, a little under 800
+// bytes each
+pub const RAW_BURSTS: usize = {
+    let bursts = env_or(option_env!("RAW_BURSTS"), 8);
+    assert!(bursts >= 1 && bursts <= 64, "RAW_BURSTS must be 1 to 64");
+    bursts as usize
+};
+
+// Bursts protocol learning takes, unless the learn command asks for more
+pub const LEARN_BURSTS: u8 = 10;
+pub const MAX_LEARN_BURSTS: u8 = 64;
+
+// Identical frames less than this apart are repeats of the same transmission
+pub const SUPPRESS_WINDOW_MS: u64 = env_or(option_env!("SUPPRESS_WINDOW_MS"), 2_000);
+// Readings of the same sensor finishing less than this apart are merged into
+// one, for stations that send quantities in separate frames. 0 publishes
+// every frame on its own.
+pub const CLUSTER_WINDOW_MS: u64 = env_or(option_env!("CLUSTER_WINDOW_MS"), 0);
+
+// How often to check for pending commands
+pub const COMMAND_POLL_SECS: u64 = 30;
+
+// More decode failures than this in a minute are reported as an error, noise
+// alone causes a few
+pub const DECODE_ERROR_RATE: u64 = env_or(option_env!("DECODE_ERROR_RATE"), 30);
+
+// Minimum time between published events from the same motion sensor
+pub const MOTION_COOLDOWN_SECS: u64 = 30;
+// Minimum time between published presses of the same doorbell. Also keeps the
+// chime's retransmission, which the receiver hears too, from being a press.
+pub const DOORBELL_COOLDOWN_SECS: u64 = 5;
+// The same event code from a sensor less than this after the last time it was
+// received is a replay, e.g. of a stuck button, and isn't published. 0 takes
+// every transmission as a new event.
+pub const EVENT_REPLAY_MS: u64 = env_or(option_env!("EVENT_REPLAY_MS"), 0);
+
+// Daily summary is published at this local time, local time is UTC shifted by
+// UTC_OFFSET_MINUTES
+pub const SUMMARY_HOUR: u64 = env_or(option_env!("SUMMARY_HOUR"), 0);
+pub const SUMMARY_MINUTE: u64 = env_or(option_env!("SUMMARY_MINUTE"), 0);
+pub const UTC_OFFSET_MINUTES: i64 = env_or_signed(option_env!("UTC_OFFSET_MINUTES"), 0);
+const _: () = assert!(
+    SUMMARY_HOUR < 24 && SUMMARY_MINUTE < 60,
+    "Invalid summary time"
+);
+
+// Without any bursts, not even noise, for this long the receiver is considered
+// dead. Has to be longer than the transmit interval of the sensors.
+pub const RECEIVER_SILENT_SECS: u64 = env_or(option_env!("RECEIVER_SILENT_SECS"), 900);
+
+// Supply voltage is measured through a divider with this ratio, e.g. 2 for two
+// equal resistors, 0 if there is none. It's reported every
+// HEALTH_INTERVAL_SECS along with die temperature, with a warning when it drops
+// below SUPPLY_LOW_MV.
+pub const SUPPLY_DIVIDER: u64 = env_or(option_env!("SUPPLY_DIVIDER"), 0);
+pub const SUPPLY_LOW_MV: u64 = env_or(option_env!("SUPPLY_LOW_MV"), 4500);
+pub const HEALTH_INTERVAL_SECS: u64 = env_or(option_env!("HEALTH_INTERVAL_SECS"), 300);
+const _: () = assert!(HEALTH_INTERVAL_SECS > 0, "Health interval must be positive");
+
+// GPIO of an LED showing the boot self-test, unset if there is none
+pub const STATUS_LED: Option<u8> = match option_env!("STATUS_LED") {
+    Some(pin) => {
+        let pin = parse_digits(pin.as_bytes(), 0);
+        assert!(pin < 64, "Invalid status LED pin");
+        Some(pin as u8)
+    }
+    None => None,
+};
+
+// RMT counts pulse lengths in ticks of RMT_CLK_DIVIDER cycles of its source
+// clock, 1 uS by default. Finer ticks resolve pulses better, coarser ones let
+// bursts run past longer pauses. Lengths are rescaled to uS for decoding, so
+// decoder timings don't depend on it.
+pub const RMT_SOURCE_MHZ: u32 = 80;
+pub const RMT_CLK_DIVIDER: u8 = {
+    let divider = env_or(option_env!("RMT_CLK_DIVIDER"), RMT_SOURCE_MHZ as u64);
+    assert!(
+        divider >= 1 && divider <= 255,
+        "RMT clock divider must be 1 to 255"
+    );
+    divider as u8
+};
+
+/// RMT receive thresholds suiting the output of a receiver module
+pub struct ReceiverProfile {
+    /// Pulses shorter than this many cycles of the RMT source clock are
+    /// ignored, 255 at most
+    pub filter_cycles: u8,
+    /// Bursts end after this long without an edge, in uS. Has to stay above
+    /// the longest pause within a frame and below the one between repeats.
+    pub idle_us: u32,
+}
+
+// Receiver module the thresholds are tuned for. `rxb6` (the default) idles
+// with AGC noise between transmissions, `srx882` has a clean squelched output
+// so takes the shortest pulses and longest pauses, `syn480r` is spiky on weak
+// signals and filters hardest.
+pub const RECEIVER: ReceiverProfile = match option_env!("RECEIVER") {
+    None => RXB6,
+    Some(name) => match name.as_bytes() {
+        b"rxb6" => RXB6,
+        b"srx882" => ReceiverProfile {
+            filter_cycles: 60,
+            idle_us: 3500,
+        },
+        b"syn480r" => ReceiverProfile {
+            filter_cycles: 255,
+            idle_us: 3000,
+        },
+        _ => panic!("RECEIVER must be rxb6, srx882 or syn480r"),
+    },
+};
+const RXB6: ReceiverProfile = ReceiverProfile {
+    filter_cycles: 100,
+    idle_us: 3000,
+};
+
+// 1 inverts the receiver's output before it reaches RMT, for modules that
+// output low level while they detect carrier
+pub const RX_INVERT: bool = match env_or(option_env!("RX_INVERT"), 0) {
+    0 => false,
+    1 => true,
+    _ => panic!("RX_INVERT must be 0 or 1"),
+};
+
+// Pulses and pauses shorter than this many uS are taken for glitches and
+// merged into the ones around them before decoding. Every protocol's shortest
+// segment is longer, 0 keeps bursts as received.
+pub const GLITCH_US: u16 = {
+    let us = env_or(option_env!("GLITCH_US"), 40);
+    assert!(us <= 200, "GLITCH_US must be at most 200");
+    us as u16
+};
+
+// GPIO of a 433 MHz transmitter for the transmit test command, unset if there
+// is none. Wired straight to the receiver's pin instead, it tests the receive
+// path without a radio.
+pub const TX_PIN: Option<u8> = match option_env!("TX_PIN") {
+    Some(pin) => {
+        let pin = parse_digits(pin.as_bytes(), 0);
+        assert!(pin < 64, "Invalid transmitter pin");
+        Some(pin as u8)
+    }
+    None => None,
+};
+
+// Periodic readings are only published once temperature changed by at least
+// PUBLISH_TEMP_DELTA (in 0.1 C) or humidity by PUBLISH_HUMIDITY_DELTA (in %),
+// or after PUBLISH_HEARTBEAT_SECS without publishing. Both 0 publishes every
+// reading.
+pub const PUBLISH_TEMP_DELTA: u16 = {
+    let delta = env_or(option_env!("PUBLISH_TEMP_DELTA"), 0);
+    assert!(delta <= u16::MAX as u64, "Invalid temperature threshold");
+    delta as u16
+};
+pub const PUBLISH_HUMIDITY_DELTA: u8 = {
+    let delta = env_or(option_env!("PUBLISH_HUMIDITY_DELTA"), 0);
+    assert!(delta <= 100, "Invalid humidity threshold");
+    delta as u8
+};
+pub const PUBLISH_HEARTBEAT_SECS: u64 = env_or(option_env!("PUBLISH_HEARTBEAT_SECS"), 900);
+const _: () = assert!(PUBLISH_HEARTBEAT_SECS > 0, "Heartbeat must be positive");
+// Wind is published as averaged over this long rather than as the anemometer
+// sent it, 0 publishes it as sent
+pub const WIND_AVERAGE_SECS: u64 = env_or(option_env!("WIND_AVERAGE_SECS"), 600);
+
 #[unsafe(no_mangle)]
 pub fn custom_halt() -> ! {
+    // Lines logged before the panic are still queued
+    log::logger().flush();
     esp_hal::system::software_reset();
 }
 
@@ -0,0 +1,111 @@
+use core::cell::Cell;
+use core::fmt::Write;
+
+use critical_section::Mutex;
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::channel::Channel;
+use heapless::String;
+use log::{Level, LevelFilter, Log, Metadata, Record};
+
+// Log lines are formatted where they're logged, then queued for the console.
+// Writing to the UART or USB serial port blocks until there's room in its
+// FIFO, which would hold up the receiver on its own executor whenever decode
+// logging is verbose. Lines that don't fit into the queue are counted and
+// dropped instead.
+
+/// Longer lines are cut short
+const LINE_LEN: usize = 192;
+const QUEUE_LEN: usize = 32;
+
+static LINES: Channel<CriticalSectionRawMutex, String<LINE_LEN>, QUEUE_LEN> = Channel::new();
+static DROPPED: Mutex<Cell<u32>> = Mutex::new(Cell::new(0));
+
+struct Logger;
+
+static LOGGER: Logger = Logger;
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let mut line = String::new();
+        // Stamped now, it may be printed a while later
+        let _ = write!(
+            line,
+            "{} ({}) - {}",
+            record.level(),
+            esp_hal::time::Instant::now()
+                .duration_since_epoch()
+                .as_millis(),
+            record.args()
+        );
+        if LINES.try_send(line).is_err() {
+            critical_section::with(|cs| {
+                let dropped = DROPPED.borrow(cs);
+                dropped.set(dropped.get().wrapping_add(1));
+            });
+        }
+    }
+
+    /// Print whatever is queued right away, blocking
+    fn flush(&self) {
+        while let Ok(line) = LINES.try_receive() {
+            esp_println::println!("{}", line);
+        }
+    }
+}
+
+/// Install the logger, at the first plain level in ESP_LOG, `info` if it has
+/// none. Lines are queued until [`console`] runs, or the logger is flushed.
+pub fn init() {
+    let level = option_env!("ESP_LOG")
+        .unwrap_or_default()
+        .split(',')
+        .find_map(|directive| directive.trim().parse().ok())
+        .unwrap_or(LevelFilter::Info);
+    // SAFETY: called once at boot, before anything else runs that could log
+    unsafe {
+        log::set_logger_racy(&LOGGER).ok();
+        log::set_max_level_racy(level);
+    }
+}
+
+/// Lines dropped since boot because the queue was full
+pub fn dropped() -> u32 {
+    critical_section::with(|cs| DROPPED.borrow(cs).get())
+}
+
+/// Hand the queued lines to `f` rather than the console, oldest first
+pub fn drain(mut f: impl FnMut(&str)) {
+    while let Ok(line) = LINES.try_receive() {
+        f(&line);
+    }
+}
+
+/// Print queued lines to the console. Runs on the main executor, so the
+/// receiver is never the one waiting on the serial port.
+#[embassy_executor::task]
+pub async fn console() {
+    let mut reported = 0;
+    loop {
+        let line = LINES.receive().await;
+        esp_println::println!("{}", line);
+        let dropped = dropped();
+        if dropped != reported && LINES.is_empty() {
+            esp_println::println!(
+                "{} ({}) - {} log lines dropped",
+                Level::Warn,
+                esp_hal::time::Instant::now()
+                    .duration_since_epoch()
+                    .as_millis(),
+                dropped.wrapping_sub(reported)
+            );
+            reported = dropped;
+        }
+    }
+}
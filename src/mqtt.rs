@@ -1,80 +1,430 @@
 use embassy_net::{IpAddress, Stack, dns::DnsQueryType, tcp::TcpSocket};
 use embassy_sync::{blocking_mutex::raw::NoopRawMutex, mutex::Mutex};
-use embassy_time::{Duration, Timer};
+use embassy_time::{Duration, Timer, with_timeout};
+use heapless::{String, Vec};
 
-use crate::{MQTT_LOGIN, MQTT_PASSWORD, MQTT_SERVER, RX_BUFFER_SIZE, TX_BUFFER_SIZE};
+use crate::cbor;
+use crate::entropy;
+use crate::errors::{self, Code};
+use crate::output::Publisher;
+use crate::selftest::{Check, Connectivity};
+use crate::wifi::Link;
+use crate::{
+    MQTT_ALARMS_QOS, MQTT_ALARMS_RETAIN, MQTT_ALERTS_QOS, MQTT_ALERTS_RETAIN, MQTT_CAPTURES_QOS,
+    MQTT_CAPTURES_RETAIN, MQTT_DIAGNOSTICS_QOS, MQTT_DIAGNOSTICS_RETAIN, MQTT_LOGIN, MQTT_PASSWORD,
+    MQTT_READINGS_QOS, MQTT_READINGS_RETAIN, MQTT_STATUS_TOPIC, NTP_ENABLED, NTP_SERVER,
+    PAYLOAD_FORMAT, RX_BUFFER_SIZE, TX_BUFFER_SIZE,
+};
 
-use log::{debug, warn};
+use log::{debug, info, warn};
 
 use rust_mqtt::{
     client::{client::MqttClient, client_config::ClientConfig as MqttClientConfig},
-    packet::v5::publish_packet::QualityOfService::QoS0,
+    packet::v5::publish_packet::QualityOfService::{self, QoS0, QoS1},
     utils::rng_generator::CountingRng,
 };
 
+/// Switch to the next broker after this many failed connect attempts in a row
+const MAX_CONNECT_FAILURES: u32 = 3;
+const DEFAULT_PORT: u16 = 1883;
+const TLS_PORT: u16 = 8883;
+
+/// How long to wait for a retained command after subscribing
+const COMMAND_TIMEOUT: Duration = Duration::from_millis(500);
+
+pub const COMMAND_TOPIC_LEN: usize = 64;
+// Large enough for a full config blob, with every generic protocol defined
+pub const COMMAND_PAYLOAD_LEN: usize = 2048;
+
+pub struct Command {
+    pub topic: String<COMMAND_TOPIC_LEN>,
+    pub payload: Vec<u8, COMMAND_PAYLOAD_LEN>,
+}
+
 #[derive(Debug)]
 pub enum Error {
     DnsResolveFailed,
     ConnectionFailed,
     PublishFailed,
+    SubscribeFailed,
+    ReceiveFailed,
     DisconnectFailed,
     FlushError,
+    /// Payload isn't the JSON PAYLOAD_FORMAT can be converted from
+    EncodeFailed,
 }
 
+/// Kind of message, each one is published with its own QoS and retain flag
+#[derive(Clone, Copy, Debug)]
+pub enum Class {
+    /// Sensor readings
+    Readings,
+    /// Firmware status and active configuration
+    Diagnostics,
+    /// Sensor availability
+    Alerts,
+    /// Leak, smoke and CO alarms
+    Alarms,
+    /// Raw pulse captures
+    Captures,
+}
+
+const fn settings(qos: u64, retain: u64) -> (QualityOfService, bool) {
+    let qos = match qos {
+        0 => QoS0,
+        1 => QoS1,
+        _ => panic!("QoS must be 0 or 1"),
+    };
+    assert!(retain <= 1, "Retain must be 0 or 1");
+    (qos, retain == 1)
+}
+
+const READINGS: (QualityOfService, bool) = settings(MQTT_READINGS_QOS, MQTT_READINGS_RETAIN);
+const DIAGNOSTICS: (QualityOfService, bool) =
+    settings(MQTT_DIAGNOSTICS_QOS, MQTT_DIAGNOSTICS_RETAIN);
+const ALERTS: (QualityOfService, bool) = settings(MQTT_ALERTS_QOS, MQTT_ALERTS_RETAIN);
+const ALARMS: (QualityOfService, bool) = settings(MQTT_ALARMS_QOS, MQTT_ALARMS_RETAIN);
+const CAPTURES: (QualityOfService, bool) = settings(MQTT_CAPTURES_QOS, MQTT_CAPTURES_RETAIN);
+
+impl Class {
+    fn settings(self) -> (QualityOfService, bool) {
+        match self {
+            Class::Readings => READINGS,
+            Class::Diagnostics => DIAGNOSTICS,
+            Class::Alerts => ALERTS,
+            Class::Alarms => ALARMS,
+            Class::Captures => CAPTURES,
+        }
+    }
+}
+
+type Client<'a, 'b, 'c> = MqttClient<'a, &'b mut TcpSocket<'c>, 5, CountingRng>;
+
+/// Client for one of the brokers listed in `servers`, which is a comma
+/// separated list of `host` or `host:port` entries like MQTT_SERVER. Fails
+/// over to the next one in a round-robin fashion when a broker can't be
+/// reached.
 pub struct Mqtt {
     stack: &'static Mutex<NoopRawMutex, Stack<'static>>,
+    servers: &'static str,
     addr: Option<IpAddress>,
+    server: usize,
+    failures: u32,
 }
 
 impl Mqtt {
-    pub fn new(stack: &'static Mutex<NoopRawMutex, Stack<'static>>) -> Self {
-        Mqtt { stack, addr: None }
+    pub fn new(stack: &'static Mutex<NoopRawMutex, Stack<'static>>, servers: &'static str) -> Self {
+        Mqtt {
+            stack,
+            servers,
+            addr: None,
+            server: 0,
+            failures: 0,
+        }
+    }
+
+    /// Active broker endpoint
+    pub fn server(&self) -> &'static str {
+        self.servers
+            .split(',')
+            .nth(self.server)
+            .unwrap_or_default()
+            .trim()
+    }
+
+    fn host_port(&self) -> (&'static str, u16) {
+        let server = self.server();
+        match server.rsplit_once(':') {
+            Some((host, port)) => (host, port.parse().unwrap_or(DEFAULT_PORT)),
+            None => (server, DEFAULT_PORT),
+        }
+    }
+
+    /// Forget cached broker address and failure count, e.g. after network
+    /// has been restarted
+    pub fn reset(&mut self) {
+        self.addr = None;
+        self.failures = 0;
+    }
+
+    /// Resolve the active broker and open a TCP connection to it without
+    /// speaking MQTT, for the boot self-test. Returns the DNS and connection
+    /// checks, the address is cached for the first publish.
+    pub async fn probe(&mut self) -> (Check, Check) {
+        let (host, port) = self.host_port();
+        let stack = self.stack.lock().await;
+        let Some(addr) = resolve(&stack, host).await else {
+            return (Check::Fail, Check::Skipped);
+        };
+        self.addr = Some(addr);
+        (
+            Check::Pass,
+            Check::from_ok(reachable(&stack, addr, port).await),
+        )
+    }
+
+    /// Where the unit currently is on the network
+    pub async fn link(&self) -> Link {
+        Link::current(&*self.stack.lock().await)
+    }
+
+    /// Check the network step by step, from the Wi-Fi link to the broker
+    /// ports and the NTP server, to tell the failing one apart remotely
+    pub async fn diagnose(&mut self) -> Connectivity {
+        let (host, _) = self.host_port();
+        let stack = self.stack.lock().await;
+        let config = stack.config_v4();
+        let mut result = Connectivity {
+            address: config.as_ref().map(|config| config.address),
+            gateway: config.and_then(|config| config.gateway),
+            dns: Check::Skipped,
+            mqtt: Check::Skipped,
+            mqtts: Check::Skipped,
+            ntp: Check::Skipped,
+        };
+        if let Some(addr) = resolve(&stack, host).await {
+            result.dns = Check::Pass;
+            result.mqtt = Check::from_ok(reachable(&stack, addr, DEFAULT_PORT).await);
+            result.mqtts = Check::from_ok(reachable(&stack, addr, TLS_PORT).await);
+        } else {
+            result.dns = Check::Fail;
+        }
+        if NTP_ENABLED {
+            result.ntp = Check::from_ok(resolve(&stack, NTP_SERVER).await.is_some());
+        }
+        result
+    }
+
+    fn connect_failed(&mut self) {
+        self.failures += 1;
+        let servers = self.servers.split(',').count();
+        if self.failures >= MAX_CONNECT_FAILURES && servers > 1 {
+            self.server = (self.server + 1) % servers;
+            self.failures = 0;
+            self.addr = None;
+            info!("Failing over to MQTT broker {}", self.server());
+            errors::report(Code::BrokerFailover, self.server());
+        }
+    }
+
+    /// Readings are published in PAYLOAD_FORMAT, everything else as is
+    pub async fn publish(&mut self, topic: &str, data: &str, class: Class) -> Result<(), Error> {
+        let (qos, retain) = class.settings();
+        match class {
+            Class::Readings if PAYLOAD_FORMAT.cbor() => {
+                // Anything longer wouldn't fit into the send buffer anyway
+                let mut buf = [0; TX_BUFFER_SIZE];
+                let len =
+                    cbor::from_json(data, PAYLOAD_FORMAT.senml(), &mut buf).map_err(|_| {
+                        warn!("Payload on {} can't be encoded as CBOR", topic);
+                        Error::EncodeFailed
+                    })?;
+                self.send(topic, &buf[..len], qos, retain).await
+            }
+            _ => self.send(topic, data.as_bytes(), qos, retain).await,
+        }
+    }
+
+    /// Publish `(topic, data)` pairs in a single session
+    pub async fn publish_all(
+        &mut self,
+        messages: &[(&str, &str)],
+        class: Class,
+    ) -> Result<(), Error> {
+        let (qos, retain) = class.settings();
+        self.session(async |client| {
+            for (topic, data) in messages {
+                client
+                    .send_message(topic, data.as_bytes(), qos, retain)
+                    .await
+                    .map_err(|e| {
+                        warn!("Error: {:?}", e);
+                        Error::PublishFailed
+                    })?;
+                debug!("Published to topic {}", topic);
+            }
+            Ok(())
+        })
+        .await
+    }
+
+    /// Remove retained message from `topic`
+    pub async fn clear(&mut self, topic: &str) -> Result<(), Error> {
+        self.send(topic, &[], QoS0, true).await
     }
 
-    pub async fn publish(&mut self, topic: &str, data: &str) -> Result<(), Error> {
+    async fn send(
+        &mut self,
+        topic: &str,
+        data: &[u8],
+        qos: QualityOfService,
+        retain: bool,
+    ) -> Result<(), Error> {
+        self.session(async |client| {
+            client
+                .send_message(topic, data, qos, retain)
+                .await
+                .map_err(|e| {
+                    warn!("Error: {:?}", e);
+                    Error::PublishFailed
+                })?;
+
+            debug!("Published to topic {}", topic);
+            Ok(())
+        })
+        .await
+    }
+
+    /// Fetch one of the retained commands pending under `topic_filter` and
+    /// clear it, so it's only handled once. Commands have to be retained since
+    /// the client only connects to the broker to publish.
+    pub async fn take_command(&mut self, topic_filter: &str) -> Result<Option<Command>, Error> {
+        self.session(async |client| {
+            client.subscribe_to_topic(topic_filter).await.map_err(|e| {
+                warn!("Error: {:?}", e);
+                Error::SubscribeFailed
+            })?;
+
+            // Retained messages are delivered right after subscribing
+            let (topic, payload) =
+                match with_timeout(COMMAND_TIMEOUT, client.receive_message()).await {
+                    Err(_) => return Ok(None),
+                    Ok(Err(e)) => {
+                        warn!("Error: {:?}", e);
+                        return Err(Error::ReceiveFailed);
+                    }
+                    // Empty payload is how a retained message gets cleared
+                    Ok(Ok((_, []))) => return Ok(None),
+                    Ok(Ok(message)) => message,
+                };
+
+            let mut command = Command {
+                topic: String::new(),
+                payload: Vec::new(),
+            };
+            if command.topic.push_str(topic).is_err()
+                || command.payload.extend_from_slice(payload).is_err()
+            {
+                warn!("Command on {} doesn't fit into buffer", topic);
+                return Ok(None);
+            }
+
+            client
+                .send_message(command.topic.as_str(), &[], QoS0, true)
+                .await
+                .map_err(|e| {
+                    warn!("Error: {:?}", e);
+                    Error::PublishFailed
+                })?;
+
+            debug!("Received command on topic {}", command.topic);
+            Ok(Some(command))
+        })
+        .await
+    }
+
+    /// Call `f` with the topic and payload of every retained message under
+    /// `topic_filter`. Unlike commands they're left in place, to be read again
+    /// on the next boot.
+    pub async fn retained(
+        &mut self,
+        topic_filter: &str,
+        mut f: impl FnMut(&str, &[u8]),
+    ) -> Result<(), Error> {
+        self.session(async |client| {
+            client.subscribe_to_topic(topic_filter).await.map_err(|e| {
+                warn!("Error: {:?}", e);
+                Error::SubscribeFailed
+            })?;
+            // Retained messages are delivered right after subscribing, the
+            // last one is followed by silence
+            loop {
+                match with_timeout(COMMAND_TIMEOUT, client.receive_message()).await {
+                    Err(_) => return Ok(()),
+                    Ok(Err(e)) => {
+                        warn!("Error: {:?}", e);
+                        return Err(Error::ReceiveFailed);
+                    }
+                    Ok(Ok((_, []))) => {}
+                    Ok(Ok((topic, payload))) => f(topic, payload),
+                }
+            }
+        })
+        .await
+    }
+
+    /// Connect to the broker, run `f` and disconnect
+    async fn session<R>(
+        &mut self,
+        f: impl AsyncFnOnce(&mut Client<'_, '_, '_>) -> Result<R, Error>,
+    ) -> Result<R, Error> {
+        let result = self.run(f).await;
+        match result {
+            Err(Error::DnsResolveFailed | Error::ConnectionFailed) => self.connect_failed(),
+            Ok(_) => self.failures = 0,
+            Err(_) => {}
+        }
+        result
+    }
+
+    async fn run<R>(
+        &mut self,
+        f: impl AsyncFnOnce(&mut Client<'_, '_, '_>) -> Result<R, Error>,
+    ) -> Result<R, Error> {
+        let (host, port) = self.host_port();
         let stack = self.stack.lock().await;
         let mut tx_buf: [u8; TX_BUFFER_SIZE] = [0; TX_BUFFER_SIZE];
         let mut rx_buf: [u8; RX_BUFFER_SIZE] = [0; RX_BUFFER_SIZE];
 
         // Cache address after first resolution
         if self.addr.is_none() {
-            let addr = stack
-                .dns_query(MQTT_SERVER, DnsQueryType::A)
-                .await
-                .map_err(|e| {
+            let addr = match stack.dns_query(host, DnsQueryType::A).await {
+                Ok(addrs) => addrs.first().copied(),
+                Err(e) => {
                     warn!("DNS resolve failed: {:?}", e);
-                    Error::DnsResolveFailed
-                })?
-                .first()
-                .copied()
-                .ok_or(Error::DnsResolveFailed)?;
-            self.addr = Some(addr);
+                    None
+                }
+            };
+            if addr.is_none() {
+                errors::report(Code::DnsFailed, host);
+                return Err(Error::DnsResolveFailed);
+            }
+            self.addr = addr;
         }
 
         let addr = self.addr.unwrap();
 
         let mut socket = TcpSocket::new(*stack, &mut rx_buf, &mut tx_buf);
         socket.set_timeout(Some(Duration::from_secs(10)));
-        socket.connect((addr, 1883)).await.map_err(|e| {
+        socket.connect((addr, port)).await.map_err(|e| {
             self.addr = None; // Clear cached address on failure
             warn!("Error: {:?}", e);
+            errors::report(Code::ConnectFailed, host);
             Error::ConnectionFailed
         })?;
 
         let mut config = MqttClientConfig::new(
             rust_mqtt::client::client_config::MqttVersion::MQTTv5,
-            CountingRng(20000),
+            // Packet IDs count up from a random one, IDs of units flashed
+            // alike don't run in step
+            CountingRng(entropy::random() % u16::MAX as u64),
         );
         config.add_max_subscribe_qos(rust_mqtt::packet::v5::publish_packet::QualityOfService::QoS1);
         config.add_client_id("esp-rf-ook2");
-        config.max_packet_size = 100;
+        // Decode context of a burst takes up to 1.6 KiB
+        let mut writebuf = [0; 2048];
+        // Commands are the only thing received, a config blob being the largest
+        let mut readbuf = [0; COMMAND_PAYLOAD_LEN + 256];
+        config.max_packet_size = readbuf.len() as u32;
         config.keep_alive = 30;
 
-        config.add_username(MQTT_LOGIN);
-        config.add_password(MQTT_PASSWORD);
+        if !MQTT_LOGIN.is_empty() {
+            config.add_username(MQTT_LOGIN);
+            if !MQTT_PASSWORD.is_empty() {
+                config.add_password(MQTT_PASSWORD);
+            }
+        }
 
-        let mut writebuf = [0; 256];
-        let mut readbuf = [0; 256];
         let mut client = {
             let writebuf_len = writebuf.len();
             let readbuf_len = readbuf.len();
@@ -91,21 +441,15 @@ impl Mqtt {
         client.connect_to_broker().await.map_err(|e| {
             self.addr = None; // Clear cached address on failure
             warn!("Error: {:?}", e);
+            errors::report(Code::ConnectFailed, host);
             Error::ConnectionFailed
         })?;
 
         debug!("Connected to MQTT broker");
 
-        client
-            .send_message(topic, data.as_bytes(), QoS0, false)
-            .await
-            .map_err(|e| {
-                self.addr = None; // Clear cached address on failure
-                warn!("Error: {:?}", e);
-                Error::PublishFailed
-            })?;
-
-        debug!("Published to topic {}", topic);
+        let result = f(&mut client).await.inspect_err(|_| {
+            self.addr = None; // Clear cached address on failure
+        })?;
 
         client.disconnect().await.map_err(|e| {
             self.addr = None; // Clear cached address on failure
@@ -124,6 +468,54 @@ impl Mqtt {
         Timer::after(Duration::from_millis(100)).await;
         socket.abort();
 
-        Ok(())
+        Ok(result)
+    }
+}
+
+async fn resolve(stack: &Stack<'_>, host: &str) -> Option<IpAddress> {
+    match stack.dns_query(host, DnsQueryType::A).await {
+        Ok(addrs) => addrs.first().copied(),
+        Err(e) => {
+            warn!("Failed to resolve {}: {:?}", host, e);
+            None
+        }
+    }
+}
+
+/// Open a TCP connection and close it right away, without sending anything
+async fn reachable(stack: &Stack<'_>, addr: IpAddress, port: u16) -> bool {
+    let mut tx_buf = [0; 64];
+    let mut rx_buf = [0; 64];
+    let mut socket = TcpSocket::new(*stack, &mut rx_buf, &mut tx_buf);
+    socket.set_timeout(Some(Duration::from_secs(10)));
+    let connected = socket
+        .connect((addr, port))
+        .await
+        .inspect_err(|e| warn!("Failed to connect to {}:{}: {:?}", addr, port, e))
+        .is_ok();
+    socket.close();
+    let _ = socket.flush().await;
+    connected
+}
+
+impl Publisher for Mqtt {
+    type Error = Error;
+
+    async fn publish_reading(&mut self, topic: &str, payload: &str) -> Result<(), Error> {
+        self.publish(topic, payload, Class::Readings).await
+    }
+
+    async fn publish_diagnostics(
+        &mut self,
+        topic: &str,
+        payload: &str,
+        class: Class,
+    ) -> Result<(), Error> {
+        self.publish(topic, payload, class).await
+    }
+
+    async fn announce(&mut self, payload: &str) -> Result<(), Error> {
+        self.publish(MQTT_STATUS_TOPIC, payload, Class::Diagnostics)
+            .await
     }
 }
@@ -1,12 +1,14 @@
 use core::net::SocketAddr;
+use core::sync::atomic::{AtomicU32, Ordering};
 use embassy_net::{
     IpAddress, Stack,
     dns::DnsQueryType,
     udp::{PacketMetadata, UdpSocket},
 };
 use embassy_sync::{blocking_mutex::raw::NoopRawMutex, mutex::Mutex};
-use embassy_time::{Duration, Timer};
+use embassy_time::{Duration, Instant, Timer};
 
+use crate::clock::Clock;
 use crate::{NTP_SERVER, RX_BUFFER_SIZE, TX_BUFFER_SIZE};
 
 use sntpc::{NtpContext, NtpTimestampGenerator, get_time};
@@ -15,6 +17,81 @@ use embassy_futures::select::{Either, select};
 
 use log::warn;
 
+/// How long the Clock implementation waits for the server to answer
+pub const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Regular resync interval, jittered by up to RESYNC_JITTER in both directions
+/// so a fleet of units doesn't hit the NTP server at the same moment
+const RESYNC_INTERVAL: Duration = Duration::from_secs(3_600);
+const RESYNC_JITTER_SECS: u32 = 300;
+/// Failed resyncs are retried with exponential backoff between these
+const RETRY_MIN: Duration = Duration::from_secs(30);
+const RETRY_MAX: Duration = Duration::from_secs(1_800);
+/// RTC free-runs during NTP outages, its time is flagged as not valid once it
+/// hasn't been synced for this long
+const STALE_AFTER_SECS: u32 = 24 * 3_600;
+
+const NEVER: u32 = u32::MAX;
+
+/// Uptime of the last successful sync, in seconds
+static LAST_SYNC: AtomicU32 = AtomicU32::new(NEVER);
+
+/// Whether RTC has been synced recently enough to be trusted. Until the first
+/// sync RTC only counts time since boot.
+pub fn time_valid() -> bool {
+    let last_sync = LAST_SYNC.load(Ordering::Relaxed);
+    last_sync != NEVER
+        && (Instant::now().as_secs() as u32).saturating_sub(last_sync) < STALE_AFTER_SECS
+}
+
+/// Trust RTC time from now on, e.g. after it's been set from another source
+/// than NTP
+pub fn mark_valid() {
+    LAST_SYNC.store(Instant::now().as_secs() as u32, Ordering::Relaxed);
+}
+
+/// When to resync time next
+pub struct Schedule {
+    next_sync: Instant,
+    retry: Duration,
+}
+
+impl Default for Schedule {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Schedule {
+    /// Sync is due right away
+    pub fn new() -> Self {
+        Schedule {
+            next_sync: Instant::now(),
+            retry: RETRY_MIN,
+        }
+    }
+
+    pub fn due(&self) -> bool {
+        Instant::now() >= self.next_sync
+    }
+
+    /// Schedule the next regular resync after time has been set from NTP or
+    /// externally, `jitter` is a random value
+    pub fn synced(&mut self, jitter: u32) {
+        let now = Instant::now();
+        mark_valid();
+        let jitter = (jitter % (2 * RESYNC_JITTER_SECS)) as u64;
+        self.next_sync = now + RESYNC_INTERVAL - Duration::from_secs(RESYNC_JITTER_SECS as u64)
+            + Duration::from_secs(jitter);
+        self.retry = RETRY_MIN;
+    }
+
+    pub fn failed(&mut self) {
+        self.next_sync = Instant::now() + self.retry;
+        self.retry = (self.retry * 2).min(RETRY_MAX);
+    }
+}
+
 #[derive(Clone, Copy)]
 struct Timestamp {
     current_time_us: u64,
@@ -39,12 +116,29 @@ pub struct Ntpc {
     addr: Option<IpAddress>,
 }
 
-#[derive(Debug)]
-pub enum NtpcError {
-    DnsResolveFailed,
-    SocketBindFailed,
-    NetworkError,
+/// Time the NTP server told
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct NtpTime {
+    micros: u64,
+}
+
+impl NtpTime {
+    /// In us since Unix epoch
+    pub fn as_micros(self) -> u64 {
+        self.micros
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Error {
+    /// Neither NTP_SERVER nor the gateway could be resolved
+    Dns,
+    /// No socket could be bound to the NTP port
+    Bind,
+    /// The server didn't answer in time
     Timeout,
+    /// The request couldn't be sent or the answer was rejected
+    Protocol(sntpc::Error),
 }
 
 impl Ntpc {
@@ -52,7 +146,11 @@ impl Ntpc {
         Ntpc { stack, addr: None }
     }
 
-    pub async fn get_time(&mut self) -> Result<u64, NtpcError> {
+    /// Current time, waiting up to `timeout` for the server to answer. The
+    /// server address is resolved again after any failure. Dropping the
+    /// future at any point releases the socket and the stack, so callers can
+    /// race it against their own events.
+    pub async fn get_time(&mut self, timeout: Duration) -> Result<NtpTime, Error> {
         let stack = self.stack.lock().await;
         let mut tx_buf: [u8; TX_BUFFER_SIZE] = [0; TX_BUFFER_SIZE];
         let mut rx_buf: [u8; RX_BUFFER_SIZE] = [0; RX_BUFFER_SIZE];
@@ -61,18 +159,31 @@ impl Ntpc {
         let mut tx_meta = [PacketMetadata::EMPTY; 16];
 
         // Cache address after first resolution
-        if self.addr.is_none() {
-            let addr = stack
-                .dns_query(NTP_SERVER, DnsQueryType::A)
-                .await
-                .map_err(|_| NtpcError::DnsResolveFailed)?
-                .first()
-                .copied()
-                .ok_or(NtpcError::DnsResolveFailed)?;
-            self.addr = Some(addr);
-        }
-
-        let addr = self.addr.unwrap();
+        let addr = match self.addr {
+            Some(addr) => addr,
+            None => {
+                let addr = match stack.dns_query(NTP_SERVER, DnsQueryType::A).await {
+                    Ok(addrs) => addrs.first().copied(),
+                    Err(_) => None,
+                };
+                // embassy-net doesn't expose NTP servers from the DHCP lease
+                // (option 42), so on isolated networks where NTP_SERVER can't
+                // be resolved try the gateway, routers usually run NTP server
+                // too
+                let addr = addr
+                    .or_else(|| {
+                        let gateway = stack.config_v4()?.gateway?;
+                        warn!(
+                            "Failed to resolve {}, trying gateway {}",
+                            NTP_SERVER, gateway
+                        );
+                        Some(IpAddress::Ipv4(gateway))
+                    })
+                    .ok_or(Error::Dns)?;
+                self.addr = Some(addr);
+                addr
+            }
+        };
 
         let mut socket =
             UdpSocket::new(*stack, &mut rx_meta, &mut rx_buf, &mut tx_meta, &mut tx_buf);
@@ -80,7 +191,7 @@ impl Ntpc {
         socket.bind(123).map_err(|e| {
             self.addr = None; // Clear cached address on failure
             warn!("Failed to bind NTP socket: {:?}", e);
-            NtpcError::SocketBindFailed
+            Error::Bind
         })?;
 
         let a = get_time(
@@ -88,19 +199,26 @@ impl Ntpc {
             &socket,
             NtpContext::new(Timestamp { current_time_us: 0 }),
         );
-        let b = Timer::after(Duration::from_secs(5));
+        let b = Timer::after(timeout);
 
         let result = select(a, b).await;
 
         let result = match result {
             Either::First(res) => {
-                res.map(|r| r.sec() as u64).map_err(|e| {
+                res.map(|r| NtpTime {
+                    // Fraction is in 1/2^32 s, keep sub-second part for slewing
+                    micros: r.sec() as u64 * 1_000_000
+                        + ((r.sec_fraction() as u64 * 1_000_000) >> 32),
+                })
+                .map_err(|e| {
                     self.addr = None; // Clear cached address on failure
-                    warn!("NTP get_time error: {:?}", e);
-                    NtpcError::Timeout
+                    Error::Protocol(e)
                 })
             }
-            Either::Second(_) => Err(NtpcError::Timeout),
+            Either::Second(_) => {
+                self.addr = None;
+                Err(Error::Timeout)
+            }
         };
 
         socket.flush().await;
@@ -112,3 +230,14 @@ impl Ntpc {
         result
     }
 }
+
+/// Time as the NTP server tells it, it can't be set
+impl Clock for Ntpc {
+    async fn time(&mut self) -> Option<u64> {
+        self.get_time(TIMEOUT)
+            .await
+            .map(NtpTime::as_micros)
+            .inspect_err(|e| warn!("Failed to get NTP time: {:?}", e))
+            .ok()
+    }
+}
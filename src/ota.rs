@@ -0,0 +1,384 @@
+use core::cell::RefCell;
+use core::ptr::{addr_of_mut, read_volatile, write_volatile};
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use critical_section::Mutex;
+use ed25519_dalek::{Signature, VerifyingKey};
+use embassy_time::{Duration, Instant, Timer};
+use embedded_storage::{ReadStorage, Storage};
+use esp_bootloader_esp_idf::ota::OtaImageState;
+use esp_bootloader_esp_idf::ota_updater::OtaUpdater;
+use esp_bootloader_esp_idf::partitions::{self, Error, PARTITION_TABLE_MAX_LEN, PartitionType};
+use esp_hal::ram;
+use log::{info, warn};
+use sha2::{Digest, Sha256};
+
+use crate::decoder::bits::crc32le;
+use crate::flash::{self, Flash, SECTOR_LEN, SectorFlash};
+use crate::{
+    COMMAND_TOKEN, OTA_CHUNK_TIMEOUT_SECS, OTA_CONFIRM_SECS, OTA_PUBLIC_KEY, OTA_TRIAL_BOOTS,
+};
+
+// An update boots on trial: it's kept once a publish succeeds, and rolled
+// back to the previous app slot if that doesn't happen within
+// OTA_CONFIRM_SECS, or if it resets OTA_TRIAL_BOOTS times before. That
+// doesn't depend on the bootloader being built with rollback support.
+
+/// Running image is on trial
+static PENDING: AtomicBool = AtomicBool::new(false);
+
+// Trial boots so far, kept across resets but not power loss. The tag tells it
+// from what's in RTC memory on power up.
+const TRIAL_TAG: u32 = 0x4c41_4952;
+#[ram(unstable(rtc_fast, persistent))]
+static mut TAG: u32 = 0;
+#[ram(unstable(rtc_fast, persistent))]
+static mut BOOTS: u32 = 0;
+
+fn with_updater<R>(
+    f: impl FnOnce(&mut OtaUpdater<'_, SectorFlash>) -> Result<R, Error>,
+) -> Result<R, Error> {
+    let mut flash = SectorFlash;
+    let mut table = [0; PARTITION_TABLE_MAX_LEN];
+    let mut updater = OtaUpdater::new(&mut flash, &mut table)?;
+    f(&mut updater)
+}
+
+/// Count this boot as a trial one, returns how many there were
+fn trial_boot() -> u32 {
+    // SAFETY: only touched from the main task at boot and when the trial ends
+    unsafe {
+        let boots = if read_volatile(addr_of_mut!(TAG)) == TRIAL_TAG {
+            read_volatile(addr_of_mut!(BOOTS)) + 1
+        } else {
+            1
+        };
+        write_volatile(addr_of_mut!(BOOTS), boots);
+        write_volatile(addr_of_mut!(TAG), TRIAL_TAG);
+        boots
+    }
+}
+
+fn end_trial() {
+    // SAFETY: see trial_boot()
+    unsafe { write_volatile(addr_of_mut!(TAG), 0) };
+}
+
+/// Check whether the running image is an update on trial, and roll it back
+/// right away if it keeps resetting. Returns true if `countdown` has to run.
+pub fn init() -> bool {
+    flash::unlock();
+    let state = match with_updater(|updater| updater.current_ota_state()) {
+        Ok(state) => state,
+        Err(e) => {
+            info!("No OTA data, updates aren't rolled back: {:?}", e);
+            end_trial();
+            return false;
+        }
+    };
+    if !matches!(state, OtaImageState::New | OtaImageState::PendingVerify) {
+        end_trial();
+        return false;
+    }
+    let boots = trial_boot();
+    if boots > OTA_TRIAL_BOOTS {
+        warn!("Updated firmware reset {} times, rolling back", boots - 1);
+        roll_back();
+    }
+    info!(
+        "Updated firmware on trial, rolled back unless its status is published within {} s",
+        OTA_CONFIRM_SECS
+    );
+    PENDING.store(true, Ordering::Relaxed);
+    true
+}
+
+/// Keep the running image, called once the status announce gets through to
+/// the broker
+pub fn confirm() {
+    // Only the publisher confirms
+    if !PENDING.load(Ordering::Relaxed) {
+        return;
+    }
+    PENDING.store(false, Ordering::Relaxed);
+    match with_updater(|updater| updater.set_current_ota_state(OtaImageState::Valid)) {
+        Ok(()) => info!("Updated firmware confirmed"),
+        Err(e) => warn!("Failed to confirm updated firmware: {:?}", e),
+    }
+    end_trial();
+}
+
+/// Mark the running image as bad and boot the other app slot
+fn roll_back() -> ! {
+    if let Err(e) = with_updater(|updater| {
+        updater.set_current_ota_state(OtaImageState::Invalid)?;
+        updater.activate_next_partition()
+    }) {
+        warn!("Failed to roll back: {:?}", e);
+    }
+    end_trial();
+    esp_hal::system::software_reset();
+}
+
+// Updates can also be sent over MQTT in chunks, each one a retained command
+// that's acknowledged before the next one is sent. Every chunk starts with
+// a little endian header: sequence number and chunk count (u16), image length
+// and CRC-32 of the chunk's data (u32). The data of chunk 0 starts with the
+// length of COMMAND_TOKEN (u8) and the token, like the other commands that
+// can take the unit over. The Ed25519 signature of the image's SHA-256 with
+// the OTA_PUBLIC_KEY key pair follows, which is checked before the image is
+// activated.
+const HEADER_LEN: usize = 12;
+const _: () = assert!(
+    COMMAND_TOKEN.len() <= u8::MAX as usize,
+    "Command token must be at most 255 bytes to take updates"
+);
+// First byte of an app image
+const IMAGE_MAGIC: u8 = 0xe9;
+// Read back to verify what was written, in pieces this long
+const VERIFY_LEN: usize = 256;
+
+/// Update being received over MQTT
+struct Download {
+    /// Start of the app slot written to
+    offset: u32,
+    /// Image length and chunk count, the same in every chunk
+    len: u32,
+    chunks: u16,
+    /// Sequence number of the chunk expected next
+    next: u16,
+    written: u32,
+    /// The slot is erased up to here
+    erased: u32,
+    received: Instant,
+    /// Of what was written so far
+    digest: Sha256,
+    signature: Signature,
+}
+
+static DOWNLOAD: Mutex<RefCell<Option<Download>>> = Mutex::new(RefCell::new(None));
+
+/// Progress of an update received over MQTT
+#[derive(Clone, Copy, Debug)]
+pub enum Progress {
+    /// Chunk written, the next one can be sent
+    Received { chunk: u16, chunks: u16 },
+    /// Whole image written and booted next, on trial
+    Complete,
+}
+
+/// Why a chunk was rejected. Apart from `Crc`, which only needs the same
+/// chunk to be sent again, the update has to start over from chunk 0.
+#[derive(Clone, Copy, Debug)]
+pub enum UpdateError {
+    /// Header missing or not matching the first chunk
+    Malformed,
+    Crc,
+    /// Not the chunk expected next, or no update in progress
+    Sequence,
+    /// Chunk 0 doesn't carry COMMAND_TOKEN
+    Token,
+    /// No OTA data or second app slot
+    Partition,
+    TooLarge,
+    /// Doesn't start like an app image
+    Image,
+    /// Signature missing or not matching OTA_PUBLIC_KEY
+    Signature,
+    Flash,
+}
+
+impl UpdateError {
+    pub fn key(self) -> &'static str {
+        match self {
+            UpdateError::Malformed => "malformed",
+            UpdateError::Crc => "crc",
+            UpdateError::Sequence => "sequence",
+            UpdateError::Token => "token",
+            UpdateError::Partition => "partition",
+            UpdateError::TooLarge => "too_large",
+            UpdateError::Image => "image",
+            UpdateError::Signature => "signature",
+            UpdateError::Flash => "flash",
+        }
+    }
+}
+
+/// Write one chunk of an update to the app slot that isn't running, and
+/// activate it once the last one is in. Chunk 0 always starts over, a chunk
+/// that was already written is acknowledged again without writing it.
+pub fn receive(chunk: &[u8]) -> Result<Progress, UpdateError> {
+    let Some((header, data)) = chunk.split_first_chunk::<HEADER_LEN>() else {
+        return Err(UpdateError::Malformed);
+    };
+    let seq = u16::from_le_bytes([header[0], header[1]]);
+    let chunks = u16::from_le_bytes([header[2], header[3]]);
+    let len = u32::from_le_bytes([header[4], header[5], header[6], header[7]]);
+    let crc = u32::from_le_bytes([header[8], header[9], header[10], header[11]]);
+    if seq >= chunks || data.is_empty() {
+        return Err(UpdateError::Malformed);
+    }
+    if !crc32le(data, 0x04c1_1db7, !0) != crc {
+        return Err(UpdateError::Crc);
+    }
+    let download = critical_section::with(|cs| DOWNLOAD.borrow_ref_mut(cs).take());
+    let (mut download, data) = match download {
+        _ if seq == 0 => start(len, chunks, data)?,
+        Some(download) if download.len == len && download.chunks == chunks => (download, data),
+        Some(_) => return Err(UpdateError::Malformed),
+        None => return Err(UpdateError::Sequence),
+    };
+    if seq + 1 == download.next {
+        // Acknowledgement got lost
+        download.received = Instant::now();
+        critical_section::with(|cs| DOWNLOAD.borrow_ref_mut(cs).replace(download));
+        return Ok(Progress::Received { chunk: seq, chunks });
+    }
+    if seq != download.next {
+        warn!("Expected firmware chunk {}, got {}", download.next, seq);
+        return Err(UpdateError::Sequence);
+    }
+    let last = seq + 1 == chunks;
+    let end = download.written + data.len() as u32;
+    // Only the last chunk may leave a partial flash word
+    if end > len || last != (end == len) || (!last && !data.len().is_multiple_of(4)) {
+        return Err(UpdateError::Malformed);
+    }
+    write(&mut download, data).map_err(|e| {
+        warn!("Failed to write firmware chunk {}: {:?}", seq, e);
+        UpdateError::Flash
+    })?;
+    if !last {
+        download.next += 1;
+        download.received = Instant::now();
+        critical_section::with(|cs| DOWNLOAD.borrow_ref_mut(cs).replace(download));
+        return Ok(Progress::Received { chunk: seq, chunks });
+    }
+    let digest = download.digest.finalize();
+    let verified = VerifyingKey::from_bytes(&OTA_PUBLIC_KEY)
+        .is_ok_and(|key| key.verify_strict(&digest, &download.signature).is_ok());
+    if !verified {
+        warn!("Firmware update signature doesn't match, not activating it");
+        return Err(UpdateError::Signature);
+    }
+    with_updater(|updater| {
+        updater.activate_next_partition()?;
+        updater.set_current_ota_state(OtaImageState::New)
+    })
+    .map_err(|e| {
+        warn!("Failed to activate firmware update: {:?}", e);
+        UpdateError::Flash
+    })?;
+    info!("Firmware update of {} bytes received", len);
+    Ok(Progress::Complete)
+}
+
+/// Set up the download for chunk 0, returns it with what's left of `data`
+/// after the token and signature
+fn start(len: u32, chunks: u16, data: &[u8]) -> Result<(Download, &[u8]), UpdateError> {
+    let (token, data) = data
+        .split_first()
+        .and_then(|(&token_len, data)| data.split_at_checked(token_len as usize))
+        .ok_or(UpdateError::Token)?;
+    if COMMAND_TOKEN.is_empty() || token != COMMAND_TOKEN.as_bytes() {
+        warn!("Ignoring firmware update: wrong confirmation token");
+        return Err(UpdateError::Token);
+    }
+    let Some((signature, data)) = data.split_first_chunk::<{ Signature::BYTE_SIZE }>() else {
+        return Err(UpdateError::Signature);
+    };
+    if data.first() != Some(&IMAGE_MAGIC) {
+        return Err(UpdateError::Image);
+    }
+    let slot =
+        with_updater(|updater| updater.next_partition().map(|(_, slot)| slot)).map_err(|e| {
+            warn!("No app slot to update: {:?}", e);
+            UpdateError::Partition
+        })?;
+    let mut table = [0; PARTITION_TABLE_MAX_LEN];
+    let (offset, slot_len) = partitions::read_partition_table(&mut Flash, &mut table)
+        .and_then(|table| table.find_partition(PartitionType::App(slot)))
+        .ok()
+        .flatten()
+        .map(|entry| (entry.offset(), entry.len()))
+        .ok_or(UpdateError::Partition)?;
+    if len > slot_len {
+        warn!(
+            "Firmware update of {} bytes doesn't fit {} byte slot",
+            len, slot_len
+        );
+        return Err(UpdateError::TooLarge);
+    }
+    info!(
+        "Receiving {} byte firmware update in {} chunks into {:?}",
+        len, chunks, slot
+    );
+    let download = Download {
+        offset,
+        len,
+        chunks,
+        next: 0,
+        written: 0,
+        erased: 0,
+        received: Instant::now(),
+        digest: Sha256::new(),
+        signature: Signature::from_bytes(signature),
+    };
+    Ok((download, data))
+}
+
+fn write(download: &mut Download, data: &[u8]) -> Result<(), flash::Error> {
+    let addr = download.offset + download.written;
+    while download.erased < download.written + data.len() as u32 {
+        Flash::erase_sector(download.offset + download.erased)?;
+        download.erased += SECTOR_LEN;
+    }
+    Flash.write(addr, data)?;
+    let mut buf = [0; VERIFY_LEN];
+    for (idx, expected) in data.chunks(VERIFY_LEN).enumerate() {
+        let buf = &mut buf[..expected.len()];
+        Flash.read(addr + (idx * VERIFY_LEN) as u32, buf)?;
+        if buf != expected {
+            return Err(flash::Error::Flash);
+        }
+    }
+    download.digest.update(data);
+    download.written += data.len() as u32;
+    Ok(())
+}
+
+/// An update is being received over MQTT, so the next chunk is likely
+/// waiting already. Gives up on it after OTA_CHUNK_TIMEOUT_SECS without one.
+pub fn receiving() -> bool {
+    let timeout = Duration::from_secs(OTA_CHUNK_TIMEOUT_SECS);
+    let (receiving, expired) = critical_section::with(|cs| {
+        let mut download = DOWNLOAD.borrow_ref_mut(cs);
+        let expired = download
+            .as_ref()
+            .is_some_and(|download| download.received.elapsed() > timeout);
+        if expired {
+            *download = None;
+        }
+        (download.is_some(), expired)
+    });
+    if expired {
+        warn!(
+            "No firmware chunk for {} s, update abandoned",
+            OTA_CHUNK_TIMEOUT_SECS
+        );
+    }
+    receiving
+}
+
+/// Roll the update back if it isn't confirmed in time
+#[embassy_executor::task]
+pub async fn countdown() {
+    Timer::after(Duration::from_secs(OTA_CONFIRM_SECS)).await;
+    if PENDING.load(Ordering::Relaxed) {
+        warn!(
+            "Updated firmware not confirmed within {} s, rolling back",
+            OTA_CONFIRM_SECS
+        );
+        roll_back();
+    }
+}
@@ -0,0 +1,119 @@
+use core::fmt::Debug;
+
+use embassy_net::Stack;
+use embassy_sync::{blocking_mutex::raw::NoopRawMutex, mutex::Mutex};
+use log::warn;
+
+use crate::broadcast::Broadcast;
+use crate::decoder::SensorData;
+use crate::mqtt::{self, Class, Mqtt};
+use crate::pubstats::{self, Backend};
+use crate::webhook::Webhook;
+use crate::{UDP_BROADCAST_PORT, WEBHOOK_URL};
+
+/// Backend everything the unit reports goes to. `topic` is the MQTT topic of
+/// the message, backends without topics use it to tell messages apart.
+// Executor is single threaded, so futures don't have to be Send
+#[allow(async_fn_in_trait)]
+pub trait Publisher {
+    type Error: Debug;
+
+    /// Sensor reading as JSON
+    async fn publish_reading(&mut self, topic: &str, payload: &str) -> Result<(), Self::Error>;
+
+    /// Status, alerts and statistics of the unit itself
+    async fn publish_diagnostics(
+        &mut self,
+        topic: &str,
+        payload: &str,
+        class: Class,
+    ) -> Result<(), Self::Error>;
+
+    /// Firmware status, on boot and whenever the backend fails over
+    async fn announce(&mut self, payload: &str) -> Result<(), Self::Error>;
+}
+
+/// Call `$method` on every enabled secondary backend, their failures are only
+/// logged. `$record` is told how each one went.
+macro_rules! fan_out {
+    ($self:ident.$method:ident($($arg:expr),*)) => {
+        fan_out!($self.$method($($arg),*), |_, _| {})
+    };
+    ($self:ident.$method:ident($($arg:expr),*), $record:expr) => {
+        if let Some(webhook) = &mut $self.webhook {
+            let result = webhook.$method($($arg),*).await;
+            $record(Backend::Webhook, result.is_ok());
+            if let Err(e) = result {
+                warn!("Webhook failed: {:?}", e);
+            }
+        }
+        if let Some(broadcast) = &mut $self.broadcast {
+            let result = broadcast.$method($($arg),*).await;
+            $record(Backend::Broadcast, result.is_ok());
+            if let Err(e) = result {
+                warn!("UDP broadcast failed: {:?}", e);
+            }
+        }
+    };
+}
+
+/// Fans out to MQTT and whichever of the webhook and UDP broadcast are
+/// enabled. MQTT is the primary backend: it also carries commands, and its
+/// result is what's reported back.
+pub struct Outputs {
+    pub mqtt: &'static mut Mqtt,
+    webhook: Option<Webhook>,
+    broadcast: Option<Broadcast>,
+}
+
+impl Outputs {
+    pub fn new(
+        mqtt: &'static mut Mqtt,
+        stack: &'static Mutex<NoopRawMutex, Stack<'static>>,
+    ) -> Self {
+        Outputs {
+            mqtt,
+            webhook: WEBHOOK_URL.and_then(|url| Webhook::new(stack, url)),
+            broadcast: (UDP_BROADCAST_PORT != 0).then(|| Broadcast::new(stack, UDP_BROADCAST_PORT)),
+        }
+    }
+
+    /// Same as publish_reading(), counting how every backend did in the
+    /// publish statistics of the sensor `data` is from
+    pub async fn publish_sensor(
+        &mut self,
+        data: &SensorData,
+        topic: &str,
+        payload: &str,
+    ) -> Result<(), mqtt::Error> {
+        let record = |backend, ok| pubstats::record(data, backend, ok);
+        fan_out!(self.publish_reading(topic, payload), record);
+        let result = self.mqtt.publish_reading(topic, payload).await;
+        record(Backend::Mqtt, result.is_ok());
+        result
+    }
+}
+
+impl Publisher for Outputs {
+    type Error = mqtt::Error;
+
+    async fn publish_reading(&mut self, topic: &str, payload: &str) -> Result<(), Self::Error> {
+        fan_out!(self.publish_reading(topic, payload));
+        self.mqtt.publish_reading(topic, payload).await
+    }
+
+    async fn publish_diagnostics(
+        &mut self,
+        topic: &str,
+        payload: &str,
+        class: Class,
+    ) -> Result<(), Self::Error> {
+        fan_out!(self.publish_diagnostics(topic, payload, class));
+        self.mqtt.publish_diagnostics(topic, payload, class).await
+    }
+
+    async fn announce(&mut self, payload: &str) -> Result<(), Self::Error> {
+        fan_out!(self.announce(payload));
+        self.mqtt.announce(payload).await
+    }
+}
@@ -0,0 +1,110 @@
+#[cfg(feature = "postmortem")]
+use core::fmt::{self, Write};
+use core::panic::PanicInfo;
+#[cfg(feature = "postmortem")]
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use esp_backtrace::Backtrace;
+
+#[cfg(feature = "postmortem")]
+use crate::postmortem::{Kind, Report};
+#[cfg(feature = "postmortem")]
+use crate::{GIT_HASH, VERSION};
+
+// Panics are how the firmware gets out of states it can't recover from, the
+// watchdog resets the unit once the handler is done. Besides printing the
+// message and backtrace to the console like esp-backtrace does, the handler
+// keeps them in the postmortem partition with the postmortem feature, as a
+// JSON document the next boot uploads once it gets through to the broker.
+
+/// Set once a panic is being handled, one that happens while writing the
+/// report doesn't get to write another
+#[cfg(feature = "postmortem")]
+static PANICKING: AtomicBool = AtomicBool::new(false);
+
+/// Escapes what can't go into a JSON string as is
+#[cfg(feature = "postmortem")]
+struct Escaped<'a, W: Write>(&'a mut W);
+
+#[cfg(feature = "postmortem")]
+impl<W: Write> Write for Escaped<'_, W> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for c in s.chars() {
+            match c {
+                '"' => self.0.write_str("\\\"")?,
+                '\\' => self.0.write_str("\\\\")?,
+                '\n' => self.0.write_str("\\n")?,
+                c if c.is_control() => write!(self.0, "\\u{:04x}", c as u32)?,
+                c => self.0.write_char(c)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Report the next boot uploads, `message` and `location` of the panic,
+/// return addresses of the `backtrace` and the firmware it happened in
+#[cfg(feature = "postmortem")]
+fn write_report(out: &mut impl Write, info: &PanicInfo, backtrace: &Backtrace) -> fmt::Result {
+    write!(out, "{{\"message\" : \"")?;
+    write!(Escaped(out), "{}", info.message())?;
+    write!(out, "\", \"location\" : \"")?;
+    if let Some(location) = info.location() {
+        write!(Escaped(out), "{}", location)?;
+    }
+    write!(out, "\", \"backtrace\" : [")?;
+    for (idx, frame) in backtrace.frames().iter().enumerate() {
+        if idx > 0 {
+            write!(out, ", ")?;
+        }
+        write!(out, "\"0x{:x}\"", frame.program_counter())?;
+    }
+    write!(
+        out,
+        "], \"uptime\" : {}, \"version\" : \"{}\", \"build\" : \"{}\" }}",
+        esp_hal::time::Instant::now()
+            .duration_since_epoch()
+            .as_millis(),
+        VERSION,
+        GIT_HASH
+    )
+}
+
+/// Keep the report of the panic in place of the one kept, if there's a
+/// partition for it
+#[cfg(feature = "postmortem")]
+fn keep(info: &PanicInfo, backtrace: &Backtrace) {
+    let Some(mut report) = Report::begin(Kind::Panic) else {
+        return;
+    };
+    if write_report(&mut report, info, backtrace).is_err() || report.finish().is_err() {
+        esp_println::println!("Failed to write panic report");
+    }
+}
+
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    esp_println::println!("");
+    esp_println::println!("====================== PANIC ======================");
+    esp_println::println!("{}", info);
+    esp_println::println!("");
+    esp_println::println!("Backtrace:");
+    esp_println::println!("");
+    let backtrace = Backtrace::capture();
+    if backtrace.frames().is_empty() {
+        esp_println::println!("No backtrace available, frame pointers are needed for one");
+    }
+    for frame in backtrace.frames() {
+        esp_println::println!("0x{:x}", frame.program_counter());
+    }
+    #[cfg(feature = "postmortem")]
+    if !PANICKING.load(Ordering::Relaxed) {
+        PANICKING.store(true, Ordering::Relaxed);
+        keep(info, &backtrace);
+    }
+    // Interrupts stay off, so only the watchdog's reset stage gets to run
+    critical_section::with(|_| {
+        #[allow(clippy::empty_loop)]
+        loop {}
+    })
+}
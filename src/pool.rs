@@ -0,0 +1,78 @@
+use core::ops::Deref;
+
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::channel::Channel;
+use static_cell::StaticCell;
+
+use crate::decoder::{MAX_SYMBOLS, Symbol};
+use crate::time;
+
+/// Bursts that can be in flight between the receiver and the main loop,
+/// including the one being received
+pub const POOL_LEN: usize = 4;
+
+type Buffer = &'static mut [Symbol; MAX_SYMBOLS];
+
+static BUFFERS: StaticCell<[[Symbol; MAX_SYMBOLS]; POOL_LEN]> = StaticCell::new();
+static FREE: Channel<CriticalSectionRawMutex, Buffer, POOL_LEN> = Channel::new();
+
+/// Fill the pool, once at boot
+pub fn init() {
+    let buffers = BUFFERS.init([[Symbol::default(); MAX_SYMBOLS]; POOL_LEN]);
+    for buffer in buffers {
+        // Room for every buffer
+        let _ = FREE.try_send(buffer);
+    }
+}
+
+/// Burst received into a pooled buffer. It's handed from task to task by
+/// reference rather than copied, and goes back to the pool when dropped.
+pub struct Burst {
+    buffer: Option<Buffer>,
+    len: usize,
+    received: u64,
+}
+
+impl Burst {
+    /// Take a buffer from the pool, None if they're all in flight
+    pub fn alloc() -> Option<Self> {
+        FREE.try_receive().ok().map(|buffer| Burst {
+            buffer: Some(buffer),
+            len: 0,
+            received: 0,
+        })
+    }
+
+    /// Whole buffer to receive into, `set_len` has to follow
+    pub fn buffer(&mut self) -> &mut [Symbol; MAX_SYMBOLS] {
+        self.buffer.as_mut().unwrap()
+    }
+
+    /// Burst is complete, stamps it with the time
+    pub fn set_len(&mut self, len: usize) {
+        self.len = len.min(MAX_SYMBOLS);
+        self.received = time::monotonic();
+    }
+
+    /// Time the burst was complete at, monotonic in us
+    pub fn received(&self) -> u64 {
+        self.received
+    }
+}
+
+impl Deref for Burst {
+    type Target = [Symbol];
+
+    fn deref(&self) -> &[Symbol] {
+        &self.buffer.as_ref().unwrap()[..self.len]
+    }
+}
+
+impl Drop for Burst {
+    fn drop(&mut self) {
+        if let Some(buffer) = self.buffer.take() {
+            // Room for every buffer
+            let _ = FREE.try_send(buffer);
+        }
+    }
+}
@@ -0,0 +1,171 @@
+use core::fmt;
+use core::sync::atomic::{AtomicU32, Ordering};
+
+use embedded_storage::ReadStorage;
+use heapless::{String, Vec};
+use log::{info, warn};
+
+use crate::decoder::bits::crc8;
+use crate::flash::{self, Flash, SECTOR_LEN};
+
+// Report of what the unit was up to right before it reset itself, kept in the
+// first sector of a flash partition for the next boot to pick up. The sector
+// starts with magic telling what kind of report it is, then the CRC-8 and
+// length of the text. The header is written last, a reset halfway through
+// leaves no report rather than a torn one.
+
+/// Data partition the report is kept in
+pub const PARTITION_LABEL: &str = "postmortem";
+/// Longer reports are cut short
+pub const REPORT_LEN: usize = 2048;
+
+const MAGIC: u32 = 0x4d54_5350;
+const PANIC_MAGIC: u32 = 0x4349_4e50;
+const HEADER_LEN: u32 = 8;
+const ERASED: u32 = u32::MAX;
+const NONE: u32 = u32::MAX;
+
+/// What made the unit reset
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Kind {
+    /// Main loop got stuck, the report is diagnostics as text
+    Watchdog,
+    /// Firmware panicked, the report is a JSON document
+    Panic,
+}
+
+impl Kind {
+    fn magic(self) -> u32 {
+        match self {
+            Kind::Watchdog => MAGIC,
+            Kind::Panic => PANIC_MAGIC,
+        }
+    }
+}
+
+/// Start of the partition, NONE without one
+static ADDR: AtomicU32 = AtomicU32::new(NONE);
+
+/// Look up the partition, once at boot before a report is taken or written
+pub fn init() {
+    let Some((offset, len)) = flash::find_partition(PARTITION_LABEL) else {
+        info!(
+            "No {} partition, watchdog and panic reports can't be kept",
+            PARTITION_LABEL
+        );
+        return;
+    };
+    if !offset.is_multiple_of(SECTOR_LEN) || len < SECTOR_LEN {
+        warn!("{} partition must be at least 1 sector", PARTITION_LABEL);
+        return;
+    }
+    ADDR.store(offset, Ordering::Relaxed);
+}
+
+fn addr() -> Option<u32> {
+    let addr = ADDR.load(Ordering::Relaxed);
+    (addr != NONE).then_some(addr)
+}
+
+/// Report left before the last reset, if there's one. It's kept until
+/// clear(), a corrupt one is dropped right away.
+pub fn read() -> Option<(Kind, String<REPORT_LEN>)> {
+    let addr = addr()?;
+    let mut header = [0; 2];
+    Flash::read_words(addr, &mut header).ok()?;
+    if header == [ERASED; 2] {
+        return None;
+    }
+    let kind = [Kind::Watchdog, Kind::Panic]
+        .into_iter()
+        .find(|kind| kind.magic() == header[0]);
+    let len = (header[1] & 0xffff) as usize;
+    let report = match kind {
+        Some(kind) if len <= REPORT_LEN => {
+            let mut bytes: Vec<u8, REPORT_LEN> = Vec::new();
+            bytes.resize(len, 0).ok()?;
+            Flash.read(addr + HEADER_LEN, &mut bytes).ok()?;
+            if crc8(&bytes, 0x07, 0) as u32 == header[1] >> 16 {
+                String::from_utf8(bytes).ok().map(|report| (kind, report))
+            } else {
+                None
+            }
+        }
+        _ => None,
+    };
+    if report.is_none() {
+        warn!("Postmortem report is corrupt, dropping it");
+        clear();
+    }
+    report
+}
+
+/// Erase the report, once it's been dealt with
+pub fn clear() {
+    let Some(addr) = addr() else {
+        return;
+    };
+    if let Err(e) = Flash::erase_sector(addr) {
+        warn!("Failed to erase postmortem report: {:?}", e);
+    }
+}
+
+/// Report in the making, text goes straight to flash so there's no need for
+/// a buffer when memory may be short
+pub struct Report {
+    kind: Kind,
+    addr: u32,
+    len: usize,
+    crc: u8,
+    /// Bytes that don't make a whole word yet
+    tail: [u8; 4],
+}
+
+impl Report {
+    /// Start a report in place of the one kept, None without a partition
+    pub fn begin(kind: Kind) -> Option<Self> {
+        let addr = addr()?;
+        Flash::erase_sector(addr).ok()?;
+        Some(Report {
+            kind,
+            addr,
+            len: 0,
+            crc: 0,
+            tail: [0xff; 4],
+        })
+    }
+
+    pub fn finish(self) -> Result<(), flash::Error> {
+        if !self.len.is_multiple_of(4) {
+            self.write_word(self.len / 4, self.tail)?;
+        }
+        let check = self.len as u32 | (self.crc as u32) << 16;
+        Flash::write_words(self.addr, &[self.kind.magic(), check])
+    }
+
+    fn write_word(&self, idx: usize, bytes: [u8; 4]) -> Result<(), flash::Error> {
+        let addr = self.addr + HEADER_LEN + idx as u32 * 4;
+        Flash::write_words(addr, &[u32::from_le_bytes(bytes)])
+    }
+}
+
+impl fmt::Write for Report {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let mut len = s.len().min(REPORT_LEN - self.len);
+        while !s.is_char_boundary(len) {
+            len -= 1;
+        }
+        let bytes = &s.as_bytes()[..len];
+        for &byte in bytes {
+            self.tail[self.len % 4] = byte;
+            self.len += 1;
+            if self.len.is_multiple_of(4) {
+                self.write_word(self.len / 4 - 1, self.tail)
+                    .map_err(|_| fmt::Error)?;
+                self.tail = [0xff; 4];
+            }
+        }
+        self.crc = crc8(bytes, 0x07, self.crc);
+        Ok(())
+    }
+}
@@ -0,0 +1,181 @@
+use core::fmt::Write;
+
+use embassy_time::{Duration, Instant, Timer};
+use esp_hal::hmac::Hmac;
+use esp_hal::{Blocking, usb_serial_jtag::UsbSerialJtag};
+use heapless::String;
+use log::{info, warn};
+
+use crate::PROVISION_WINDOW_SECS;
+use crate::credentials::{self, BROKER_LEN, Credentials};
+
+const LINE_LEN: usize = BROKER_LEN + 16;
+// Session ends without `done` after this long without input, so a unit
+// someone forgot to finish provisioning still comes up
+const IDLE_SECS: u64 = 120;
+const POLL: Duration = Duration::from_millis(10);
+
+const HELP: &str = "commands: ssid <name>, password [<secret>], broker <host[:port][,...]>, show, save, clear, done";
+
+/// Line-oriented provisioning over the USB serial port, offered for
+/// PROVISION_WINDOW_SECS at boot before Wi-Fi comes up. Any line opens a
+/// session that lasts until `done`, every command is answered with `ok`,
+/// `error: <reason>` or the requested status. `credentials` holds what to
+/// connect with afterwards, whether saved or not, `hmac` encrypts them.
+pub async fn run(
+    serial: &mut UsbSerialJtag<'_, Blocking>,
+    hmac: &mut Hmac<'_>,
+    credentials: &mut Credentials,
+    mut feed: impl FnMut(),
+) {
+    info!(
+        "Send a line over USB serial within {} s to provision",
+        PROVISION_WINDOW_SECS
+    );
+    let mut line: String<LINE_LEN> = String::new();
+    let mut overflow = false;
+    let mut session = false;
+    let mut deadline = Instant::now() + Duration::from_secs(PROVISION_WINDOW_SECS);
+    while Instant::now() < deadline {
+        feed();
+        let Ok(byte) = serial.read_byte() else {
+            Timer::after(POLL).await;
+            continue;
+        };
+        deadline = Instant::now() + Duration::from_secs(IDLE_SECS);
+        match byte {
+            b'\r' => {}
+            b'\n' => {
+                if !session {
+                    info!("Provisioning over USB serial");
+                    session = true;
+                }
+                let reply = if overflow {
+                    Reply::Error("line too long")
+                } else {
+                    handle(line.trim(), hmac, credentials)
+                };
+                line.clear();
+                overflow = false;
+                let _ = match reply {
+                    Reply::Ok => writeln!(serial, "ok\r"),
+                    Reply::Error(reason) => writeln!(serial, "error: {}\r", reason),
+                    Reply::Show => show(serial, hmac, credentials),
+                    Reply::Help => writeln!(serial, "{}\r", HELP),
+                    Reply::Done => {
+                        let _ = writeln!(serial, "ok\r");
+                        break;
+                    }
+                };
+            }
+            byte => overflow |= line.push(byte as char).is_err(),
+        }
+    }
+    if session {
+        info!("Provisioning done");
+    }
+}
+
+enum Reply {
+    Ok,
+    Error(&'static str),
+    Show,
+    Help,
+    Done,
+}
+
+fn handle(line: &str, hmac: &mut Hmac<'_>, credentials: &mut Credentials) -> Reply {
+    let (command, arg) = line.split_once(' ').unwrap_or((line, ""));
+    let arg = arg.trim();
+    match command {
+        "" | "help" => Reply::Help,
+        // NUL separates the fields of the stored credentials
+        "ssid" | "password" | "broker" if arg.contains('\0') => Reply::Error("NUL isn't allowed"),
+        "ssid" if arg.is_empty() => Reply::Error("ssid can't be empty"),
+        "ssid" => match arg.try_into() {
+            Ok(ssid) => {
+                credentials.ssid = ssid;
+                Reply::Ok
+            }
+            Err(_) => Reply::Error("ssid is too long"),
+        },
+        "password" => match arg.try_into() {
+            Ok(password) => {
+                credentials.password = password;
+                Reply::Ok
+            }
+            Err(_) => Reply::Error("password is too long"),
+        },
+        "broker" if arg.is_empty() => Reply::Error("broker can't be empty"),
+        "broker" => match arg.try_into() {
+            Ok(broker) => {
+                credentials.broker = broker;
+                Reply::Ok
+            }
+            Err(_) => Reply::Error("broker is too long"),
+        },
+        "show" => Reply::Show,
+        "save" => save(hmac, credentials),
+        "clear" => match credentials::clear() {
+            Ok(()) => {
+                *credentials = Credentials::compiled();
+                Reply::Ok
+            }
+            Err(e) => {
+                warn!("Failed to clear credentials: {:?}", e);
+                Reply::Error(e.reason())
+            }
+        },
+        "done" => Reply::Done,
+        _ => Reply::Error("unknown command"),
+    }
+}
+
+fn save(hmac: &mut Hmac<'_>, credentials: &Credentials) -> Reply {
+    match store(hmac, credentials) {
+        Ok(()) => Reply::Ok,
+        Err(reason) => Reply::Error(reason),
+    }
+}
+
+/// Check and save `credentials`, the error is why they weren't
+pub fn store(hmac: &mut Hmac<'_>, credentials: &Credentials) -> Result<(), &'static str> {
+    if !credentials.is_complete() {
+        return Err("ssid and broker are required");
+    }
+    // Access point only rejects a short password after a timeout
+    if !credentials.password.is_empty() && credentials.password.len() < 8 {
+        return Err("password must be at least 8 characters");
+    }
+    match credentials::save(credentials, hmac) {
+        Ok(()) => {
+            info!("Saved credentials: {:?}", credentials);
+            Ok(())
+        }
+        Err(e) => {
+            warn!("Failed to save credentials: {:?}", e);
+            Err(e.reason())
+        }
+    }
+}
+
+fn show(out: &mut impl Write, hmac: &mut Hmac<'_>, credentials: &Credentials) -> core::fmt::Result {
+    let stored = credentials::load(hmac);
+    let source = match &stored {
+        Some(stored) if stored == credentials => "stored",
+        _ if *credentials == Credentials::compiled() => "compiled in",
+        _ => "not saved",
+    };
+    writeln!(out, "ssid: {}\r", credentials.ssid)?;
+    writeln!(
+        out,
+        "password: {}\r",
+        if credentials.password.is_empty() {
+            "none"
+        } else {
+            "set"
+        }
+    )?;
+    writeln!(out, "broker: {}\r", credentials.broker)?;
+    writeln!(out, "source: {}\r", source)
+}
@@ -0,0 +1,149 @@
+use core::cell::RefCell;
+
+use embassy_sync::blocking_mutex::CriticalSectionMutex;
+use heapless::Vec;
+
+use crate::decoder::SensorData;
+use crate::snapshot::REGISTRY_LEN;
+use crate::time;
+
+// Publish attempts of sensor readings by sensor and backend since boot, so a
+// flaky path to the broker or webhook shows up in diagnostics. The success
+// rate is taken over the latest RECENT attempts, the counts over all of them.
+
+/// Attempts the success rate is taken over
+const RECENT: u32 = 32;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Backend {
+    Mqtt,
+    Webhook,
+    Broadcast,
+}
+
+pub const BACKENDS: [Backend; 3] = [Backend::Mqtt, Backend::Webhook, Backend::Broadcast];
+
+impl Backend {
+    pub fn key(self) -> &'static str {
+        match self {
+            Backend::Mqtt => "mqtt",
+            Backend::Webhook => "webhook",
+            Backend::Broadcast => "broadcast",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Counts {
+    pub attempted: u32,
+    pub succeeded: u32,
+    /// Outcomes of the latest attempts, newest in bit 0, set if it succeeded
+    recent: u32,
+}
+
+impl Counts {
+    const fn new() -> Self {
+        Counts {
+            attempted: 0,
+            succeeded: 0,
+            recent: 0,
+        }
+    }
+
+    fn add(&mut self, ok: bool) {
+        self.attempted = self.attempted.wrapping_add(1);
+        self.succeeded = self.succeeded.wrapping_add(ok.into());
+        self.recent = self.recent << 1 | ok as u32;
+    }
+
+    pub fn failed(&self) -> u32 {
+        self.attempted.wrapping_sub(self.succeeded)
+    }
+
+    /// Share of the latest attempts that succeeded in %, None before the
+    /// first one
+    pub fn success_pct(&self) -> Option<u32> {
+        let len = self.attempted.min(RECENT);
+        let mask = if len == RECENT {
+            u32::MAX
+        } else {
+            (1 << len) - 1
+        };
+        (len > 0).then(|| (self.recent & mask).count_ones() * 100 / len)
+    }
+}
+
+/// Counts of one sensor, by backend in BACKENDS order
+#[derive(Clone, Debug)]
+pub struct SensorPublishes {
+    pub model: &'static str,
+    pub id: u32,
+    pub channel: u8,
+    /// Monotonic time of the last attempt
+    last: u64,
+    pub backends: [Counts; BACKENDS.len()],
+}
+
+struct Stats {
+    sensors: Vec<SensorPublishes, REGISTRY_LEN>,
+    /// Every sensor's, evicted ones included
+    total: [Counts; BACKENDS.len()],
+}
+
+static STATS: CriticalSectionMutex<RefCell<Stats>> =
+    CriticalSectionMutex::new(RefCell::new(Stats {
+        sensors: Vec::new(),
+        total: [Counts::new(); BACKENDS.len()],
+    }));
+
+/// Count an attempt to publish a reading of `data` to `backend`. The sensor
+/// attempted least recently is dropped to make room for a new one.
+pub fn record(data: &SensorData, backend: Backend, ok: bool) {
+    let idx = backend as usize;
+    let now = time::monotonic();
+    STATS.lock(|stats| {
+        let mut stats = stats.borrow_mut();
+        stats.total[idx].add(ok);
+        let sensors = &mut stats.sensors;
+        let found = sensors.iter().position(|sensor| {
+            sensor.model == data.model() && sensor.id == data.id && sensor.channel == data.channel
+        });
+        let sensor = match found {
+            Some(found) => &mut sensors[found],
+            None => {
+                if sensors.is_full()
+                    && let Some(oldest) = sensors
+                        .iter()
+                        .enumerate()
+                        .min_by_key(|(_, sensor)| sensor.last)
+                        .map(|(idx, _)| idx)
+                {
+                    sensors.swap_remove(oldest);
+                }
+                let sensor = SensorPublishes {
+                    model: data.model(),
+                    id: data.id,
+                    channel: data.channel,
+                    last: now,
+                    backends: [Counts::new(); BACKENDS.len()],
+                };
+                if sensors.push(sensor).is_err() {
+                    return;
+                }
+                sensors.last_mut().unwrap()
+            }
+        };
+        sensor.last = now;
+        sensor.backends[idx].add(ok);
+    });
+}
+
+/// Counts of every backend, for all sensors together
+pub fn total() -> [Counts; BACKENDS.len()] {
+    STATS.lock(|stats| stats.borrow().total)
+}
+
+/// Counts of the sensors attempted to publish recently
+pub fn sensors() -> Vec<SensorPublishes, REGISTRY_LEN> {
+    STATS.lock(|stats| stats.borrow().sensors.clone())
+}
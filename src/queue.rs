@@ -0,0 +1,49 @@
+use heapless::Deque;
+
+use crate::events::Reading;
+
+/// Readings waiting to be published, in order of arrival. Holds at most one
+/// periodic reading per sensor, a newer one replaces the queued one in place,
+/// so when the broker is slow a chattering sensor takes a single slot rather
+/// than holding up everyone else. Events are never coalesced.
+pub struct FairQueue<const N: usize> {
+    readings: Deque<Reading, N>,
+}
+
+impl<const N: usize> FairQueue<N> {
+    pub const fn new() -> Self {
+        FairQueue {
+            readings: Deque::new(),
+        }
+    }
+
+    /// Queue `reading`, gives it back if the queue is full
+    pub fn push(&mut self, reading: Reading) -> Result<(), Reading> {
+        if !reading.data.is_event()
+            && let Some(queued) = self.readings.iter_mut().find(|queued| {
+                !queued.data.is_event()
+                    && queued.data.model() == reading.data.model()
+                    && queued.data.id == reading.data.id
+                    && queued.data.channel == reading.data.channel
+            })
+        {
+            *queued = reading;
+            return Ok(());
+        }
+        self.readings.push_back(reading)
+    }
+
+    pub fn pop(&mut self) -> Option<Reading> {
+        self.readings.pop_front()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.readings.is_empty()
+    }
+}
+
+impl<const N: usize> Default for FairQueue<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
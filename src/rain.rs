@@ -0,0 +1,390 @@
+use core::cell::RefCell;
+
+use embassy_sync::blocking_mutex::CriticalSectionMutex;
+use heapless::{String, Vec};
+
+use crate::UTC_OFFSET_MINUTES;
+use crate::decoder::SensorData;
+use crate::ntpc;
+use crate::time;
+
+// Rain gauges count rainfall since they were powered up, so every battery
+// change starts their total over. Totals here carry on across those: once a
+// gauge's count drops, what it had counted is added to its offset. Rain since
+// local midnight is taken from the total at the end of the previous day, which
+// takes the wall clock to be set. With the rain-totals feature they're kept in
+// flash, so resets and power loss don't start them over either.
+
+/// Gauges tracked at once, the one heard least recently makes room
+pub const GAUGES: usize = 4;
+const MODEL_LEN: usize = 24;
+const DAY_SECS: i64 = 24 * 60 * 60;
+/// Totals are saved at least this often while they change, and right away
+/// when a gauge starts over or a day ends
+const SAVE_INTERVAL_US: u64 = 60 * 60 * 1_000_000;
+
+#[derive(Clone, Debug)]
+struct Gauge {
+    model: String<MODEL_LEN>,
+    id: u32,
+    channel: u8,
+    /// Count the gauge reported last, in 0.1 mm
+    last: u32,
+    /// Rain counted before the gauge last started over, in 0.1 mm
+    offset: u32,
+    /// Total at the end of the previous day and that day, in days since Unix
+    /// epoch in local time. None until the clock is set.
+    midnight: Option<(u32, i64)>,
+    /// Monotonic time it was last heard
+    heard: u64,
+}
+
+impl Gauge {
+    fn total(&self) -> u32 {
+        self.offset.wrapping_add(self.last)
+    }
+
+    fn matches(&self, data: &SensorData) -> bool {
+        self.model == data.model() && self.id == data.id && self.channel == data.channel
+    }
+
+    /// Take the gauge's latest `count`. True if it started over.
+    fn count(&mut self, count: u32) -> bool {
+        let restarted = count < self.last;
+        if restarted {
+            // Batteries were changed, or the counter wrapped
+            self.offset = self.total();
+        }
+        self.last = count;
+        restarted
+    }
+
+    /// Rain since local midnight, None until the clock is set
+    fn today(&self) -> Option<u32> {
+        self.midnight
+            .map(|(midnight, _)| self.total().wrapping_sub(midnight))
+    }
+}
+
+/// Rainfall of a gauge, in 0.1 mm
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Totals {
+    /// Since the gauge was first heard
+    pub total: u32,
+    /// Since local midnight, None until the clock is set
+    pub today: Option<u32>,
+}
+
+struct Gauges {
+    gauges: Vec<Gauge, GAUGES>,
+    /// Monotonic time totals were last saved at, and whether they've changed
+    /// since
+    saved: Option<u64>,
+    dirty: bool,
+}
+
+static GAUGES_STATE: CriticalSectionMutex<RefCell<Gauges>> =
+    CriticalSectionMutex::new(RefCell::new(Gauges {
+        gauges: Vec::new(),
+        saved: None,
+        dirty: false,
+    }));
+
+/// Local day of `timestamp` in us since Unix epoch
+fn day(timestamp: i64) -> i64 {
+    (timestamp / 1_000_000 + UTC_OFFSET_MINUTES * 60).div_euclid(DAY_SECS)
+}
+
+/// Load totals saved before the last reset, once at boot
+#[cfg(feature = "rain-totals")]
+pub fn init() {
+    let gauges = storage::load();
+    GAUGES_STATE.lock(|state| state.borrow_mut().gauges = gauges);
+}
+
+/// Note a reading received at `timestamp`, in us since Unix epoch. Readings
+/// without rain are left out.
+pub fn update(data: &SensorData, timestamp: i64) {
+    let Some(rain) = data.rain() else {
+        return;
+    };
+    let now = time::monotonic();
+    let today = ntpc::time_valid().then(|| day(timestamp));
+    let mut save = false;
+    GAUGES_STATE.lock(|state| {
+        let mut state = state.borrow_mut();
+        let gauges = &mut state.gauges;
+        let found = gauges.iter().position(|gauge| gauge.matches(data));
+        let gauge = match found {
+            Some(found) => &mut gauges[found],
+            None => {
+                if gauges.is_full()
+                    && let Some(oldest) = gauges
+                        .iter()
+                        .enumerate()
+                        .min_by_key(|(_, gauge)| gauge.heard)
+                        .map(|(idx, _)| idx)
+                {
+                    gauges.swap_remove(oldest);
+                }
+                let gauge = Gauge {
+                    model: data.model().try_into().unwrap_or_default(),
+                    id: data.id,
+                    channel: data.channel,
+                    last: rain.total,
+                    offset: 0,
+                    midnight: None,
+                    heard: now,
+                };
+                if gauges.push(gauge).is_err() {
+                    return;
+                }
+                save = true;
+                gauges.last_mut().unwrap()
+            }
+        };
+        gauge.heard = now;
+        // Rain until now fell on the previous day
+        let previous = gauge.total();
+        let changed = rain.total != gauge.last;
+        save |= gauge.count(rain.total);
+        if let Some(today) = today {
+            // Rain before the clock was set can't be told apart by day, it
+            // counts as the previous day's
+            if gauge.midnight.is_none_or(|(_, day)| day != today) {
+                gauge.midnight = Some((previous, today));
+                save = true;
+            }
+        }
+        state.dirty |= changed || save;
+        save |= state.dirty
+            && state
+                .saved
+                .is_none_or(|saved| now.saturating_sub(saved) >= SAVE_INTERVAL_US);
+        if save {
+            state.saved = Some(now);
+            state.dirty = false;
+        }
+    });
+    if save {
+        let gauges = GAUGES_STATE.lock(|state| state.borrow().gauges.clone());
+        storage::save(&gauges);
+    }
+}
+
+/// Forget the totals of all gauges, in flash as well
+pub fn clear() {
+    GAUGES_STATE.lock(|state| {
+        let mut state = state.borrow_mut();
+        state.gauges.clear();
+        state.dirty = false;
+    });
+    storage::clear();
+}
+
+/// Totals of the gauge `data` came from, as of the latest reading noted
+pub fn totals(data: &SensorData) -> Option<Totals> {
+    GAUGES_STATE.lock(|state| {
+        let state = state.borrow();
+        let gauge = state.gauges.iter().find(|gauge| gauge.matches(data))?;
+        Some(Totals {
+            total: gauge.total(),
+            today: gauge.today(),
+        })
+    })
+}
+
+#[cfg(not(feature = "rain-totals"))]
+mod storage {
+    use super::Gauge;
+
+    /// Totals start over at boot without the rain-totals feature
+    pub fn save(_gauges: &[Gauge]) {}
+
+    pub fn clear() {}
+}
+
+/// Totals kept in the first sector of a flash partition, as text records of a
+/// line per gauge appended one after another. Every record holds all gauges,
+/// the last one is current. Each starts with a header word of magic, CRC-8
+/// and length, written after the text, so a reset in the middle of a save
+/// leaves the previous record. The sector is erased on the first save after
+/// boot, so nothing is ever appended after a torn record.
+#[cfg(feature = "rain-totals")]
+mod storage {
+    use core::fmt::Write;
+    use core::sync::atomic::{AtomicU32, Ordering};
+
+    use embedded_storage::{ReadStorage, Storage};
+    use heapless::{String, Vec};
+    use log::{info, warn};
+
+    use super::{GAUGES, Gauge};
+    use crate::decoder::bits::crc8;
+    use crate::flash::{self, Flash, SECTOR_LEN};
+
+    /// Data partition totals are kept in
+    pub const PARTITION_LABEL: &str = "rain";
+
+    const MAGIC: u32 = 0x5a;
+    const ERASED: u32 = u32::MAX;
+    const RECORD_LEN: usize = GAUGES * 80;
+    const NONE: u32 = u32::MAX;
+
+    /// Start of the partition once it's been found, NONE without one
+    static ADDR: AtomicU32 = AtomicU32::new(NONE);
+    /// Offset the next record goes to, NONE until the sector's been erased
+    static END: AtomicU32 = AtomicU32::new(NONE);
+
+    /// Gauges in the last complete record
+    pub fn load() -> Vec<Gauge, GAUGES> {
+        let mut gauges = Vec::new();
+        let Some((addr, len)) = flash::find_partition(PARTITION_LABEL) else {
+            info!("No {} partition, rain totals aren't kept", PARTITION_LABEL);
+            return gauges;
+        };
+        if !addr.is_multiple_of(SECTOR_LEN) || len < SECTOR_LEN {
+            warn!("{} partition must be at least 1 sector", PARTITION_LABEL);
+            return gauges;
+        }
+        ADDR.store(addr, Ordering::Relaxed);
+        let mut offset = 0;
+        let mut record = [0u8; RECORD_LEN];
+        while offset + 4 < SECTOR_LEN {
+            let Ok(header) = Flash::read_word(addr + offset) else {
+                break;
+            };
+            let len = (header >> 16) as usize;
+            if header == ERASED || header & 0xff != MAGIC || len > RECORD_LEN {
+                break;
+            }
+            let text = &mut record[..len];
+            if Flash.read(addr + offset + 4, text).is_err() {
+                break;
+            }
+            if crc8(text, 0x07, 0) as u32 == (header >> 8) & 0xff
+                && let Ok(text) = core::str::from_utf8(text)
+            {
+                gauges = text.lines().filter_map(parse).collect();
+            }
+            offset += 4 + (len as u32).next_multiple_of(4);
+        }
+        if !gauges.is_empty() {
+            info!("Loaded rain totals of {} gauges", gauges.len());
+        }
+        gauges
+    }
+
+    /// `<model>\t<id>\t<channel>\t<last>\t<offset>[\t<midnight>\t<day>]`
+    fn parse(line: &str) -> Option<Gauge> {
+        let mut fields = line.split('\t');
+        let model = fields.next()?.try_into().ok()?;
+        let mut number = || fields.next()?.parse::<i64>().ok();
+        let (id, channel, last, offset) = (number()?, number()?, number()?, number()?);
+        let midnight = number().zip(number());
+        Some(Gauge {
+            model,
+            id: id as u32,
+            channel: channel as u8,
+            last: last as u32,
+            offset: offset as u32,
+            midnight: midnight.map(|(midnight, day)| (midnight as u32, day)),
+            heard: 0,
+        })
+    }
+
+    pub fn save(gauges: &[Gauge]) {
+        let addr = ADDR.load(Ordering::Relaxed);
+        if addr == NONE {
+            return;
+        }
+        let mut text: String<RECORD_LEN> = String::new();
+        for gauge in gauges {
+            let _ = write!(
+                text,
+                "{}\t{}\t{}\t{}\t{}",
+                gauge.model, gauge.id, gauge.channel, gauge.last, gauge.offset
+            );
+            if let Some((midnight, day)) = gauge.midnight {
+                let _ = write!(text, "\t{}\t{}", midnight, day);
+            }
+            let _ = writeln!(text);
+        }
+        let len = text.len() as u32;
+        let mut offset = END.load(Ordering::Relaxed);
+        if offset == NONE || offset + 4 + len.next_multiple_of(4) > SECTOR_LEN {
+            if let Err(e) = Flash::erase_sector(addr) {
+                warn!("Failed to erase rain totals: {:?}", e);
+                return;
+            }
+            offset = 0;
+        }
+        let header = MAGIC | (crc8(text.as_bytes(), 0x07, 0) as u32) << 8 | len << 16;
+        let result = Flash
+            .write(addr + offset + 4, text.as_bytes())
+            .and_then(|_| Flash::write_words(addr + offset, &[header]));
+        match result {
+            Ok(()) => END.store(offset + 4 + len.next_multiple_of(4), Ordering::Relaxed),
+            Err(e) => {
+                warn!("Failed to save rain totals: {:?}", e);
+                // Start over on a clean sector next time
+                END.store(NONE, Ordering::Relaxed);
+            }
+        }
+    }
+
+    pub fn clear() {
+        let addr = ADDR.load(Ordering::Relaxed);
+        if addr == NONE {
+            return;
+        }
+        match Flash::erase_sector(addr) {
+            Ok(()) => END.store(0, Ordering::Relaxed),
+            Err(e) => warn!("Failed to erase rain totals: {:?}", e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gauge_at(last: u32) -> Gauge {
+        Gauge {
+            model: String::new(),
+            id: 0,
+            channel: 0,
+            last,
+            offset: 0,
+            midnight: None,
+            heard: 0,
+        }
+    }
+
+    #[test]
+    fn counts_on_across_restarts() {
+        let mut gauge = gauge_at(100);
+        assert!(!gauge.count(250));
+        assert_eq!(gauge.total(), 250);
+        // New batteries, the gauge counts from 0 again
+        assert!(gauge.count(20));
+        assert_eq!(gauge.total(), 270);
+        assert!(!gauge.count(20));
+        assert_eq!(gauge.total(), 270);
+    }
+
+    #[test]
+    fn counter_wrap() {
+        // A 16-bit counter wrapping over
+        let mut gauge = gauge_at(0xfff0);
+        assert!(gauge.count(0x0010));
+        assert_eq!(gauge.total(), 0x1_0000);
+        // The total itself wraps around, rain today still comes out right
+        let mut gauge = gauge_at(u32::MAX - 10);
+        gauge.midnight = Some((u32::MAX - 20, 0));
+        assert!(gauge.count(5));
+        assert!(!gauge.count(30));
+        assert_eq!(gauge.total(), 19);
+        assert_eq!(gauge.today(), Some(40));
+    }
+}
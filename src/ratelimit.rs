@@ -0,0 +1,43 @@
+/// Token bucket, allows bursts of up to `capacity` and `rate` per minute on
+/// average after that
+pub struct TokenBucket {
+    capacity: u32,
+    /// Time it takes to earn one token, in us
+    period_us: u64,
+    tokens: u32,
+    /// Time the last token was earned at, in us
+    last: u64,
+}
+
+impl TokenBucket {
+    pub const fn new(capacity: u32, rate: u32) -> Self {
+        TokenBucket {
+            capacity,
+            period_us: 60_000_000 / rate as u64,
+            tokens: capacity,
+            last: 0,
+        }
+    }
+
+    /// Take a token at `now` (in us), returns false if there are none left
+    pub fn take(&mut self, now: u64) -> bool {
+        let earned = now.saturating_sub(self.last) / self.period_us;
+        if earned > 0 {
+            self.tokens = self
+                .tokens
+                .saturating_add(earned.min(self.capacity as u64) as u32)
+                .min(self.capacity);
+            // Full bucket doesn't keep earning
+            self.last = if self.tokens == self.capacity {
+                now
+            } else {
+                self.last + earned * self.period_us
+            };
+        }
+        if self.tokens == 0 {
+            return false;
+        }
+        self.tokens -= 1;
+        true
+    }
+}
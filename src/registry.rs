@@ -0,0 +1,196 @@
+use embassy_time::Duration;
+use heapless::Vec;
+
+use crate::decoder::SensorData;
+use crate::hysteresis::{Hysteresis, Published};
+use crate::time;
+
+/// Gaps shorter than this are repeats of the same transmission
+const MIN_INTERVAL: Duration = Duration::from_secs(10);
+/// Sensor is considered gone after missing this many transmissions
+const MISSED_INTERVALS: u64 = 3;
+
+#[derive(Clone, Debug)]
+pub struct Sensor {
+    pub model: &'static str,
+    pub id: u32,
+    pub channel: u8,
+    /// Monotonic time of the last accepted reading
+    pub last_seen: u64,
+    /// Wall clock time of it, in us since Unix epoch
+    pub timestamp: u64,
+    /// Shortest observed gap between transmissions
+    pub interval: Option<Duration>,
+    pub online: bool,
+    /// Last accepted reading
+    pub reading: SensorData,
+    /// Last published reading, for the significant-change filter
+    pub published: Option<Published>,
+    /// Alarm raised and not cleared by command since, see Registry::latch()
+    pub alarm: Option<&'static str>,
+}
+
+impl Sensor {
+    /// How long a reading stays valid, in seconds. Unknown until the sensor
+    /// has been heard twice.
+    pub fn expire_after(&self) -> Option<u64> {
+        self.interval
+            .map(|interval| (interval.as_micros() * MISSED_INTERVALS).div_ceil(1_000_000))
+    }
+
+    fn matches(&self, data: &SensorData) -> bool {
+        self.model == data.model() && self.id == data.id && self.channel == data.channel
+    }
+}
+
+/// Alarm the reading raises, `leak`, `smoke` or `co`
+pub fn alarm(data: &SensorData) -> Option<&'static str> {
+    match (data.leak(), data.hazard()) {
+        (Some(leak), _) if leak.water => Some(leak.event()),
+        (_, Some(hazard)) => Some(hazard.key()),
+        _ => None,
+    }
+}
+
+/// Sensors heard so far along with their transmit intervals, for up to N
+/// sensors. The least recently seen sensor is evicted when the table is full,
+/// ones with an alarm latched only once there's no other.
+#[derive(Clone)]
+pub struct Registry<const N: usize> {
+    sensors: Vec<Sensor, N>,
+}
+
+impl<const N: usize> Registry<N> {
+    pub const fn new() -> Self {
+        Registry {
+            sensors: Vec::new(),
+        }
+    }
+
+    /// Record a reading accepted at monotonic `now`, wall clock `timestamp`.
+    /// Returns true if the sensor is new or was offline, i.e. its availability
+    /// has changed.
+    pub fn update(&mut self, data: &SensorData, now: u64, timestamp: u64) -> bool {
+        if let Some(sensor) = self.sensors.iter_mut().find(|sensor| sensor.matches(data)) {
+            if let Some(gap) = time::since(now, sensor.last_seen)
+                && gap >= MIN_INTERVAL
+            {
+                sensor.interval = Some(sensor.interval.map_or(gap, |interval| interval.min(gap)));
+            }
+            sensor.last_seen = now;
+            sensor.timestamp = timestamp;
+            sensor.reading = data.clone();
+            let changed = !sensor.online;
+            sensor.online = true;
+            return changed;
+        }
+
+        self.insert(data, now, timestamp);
+        true
+    }
+
+    fn insert(&mut self, data: &SensorData, now: u64, timestamp: u64) -> Option<&mut Sensor> {
+        if self.sensors.is_full()
+            && let Some(oldest) = self
+                .sensors
+                .iter()
+                .enumerate()
+                .min_by_key(|(_, sensor)| (sensor.alarm.is_some(), sensor.last_seen))
+                .map(|(idx, _)| idx)
+        {
+            self.sensors.swap_remove(oldest);
+        }
+        self.sensors
+            .push(Sensor {
+                model: data.model(),
+                id: data.id,
+                channel: data.channel,
+                last_seen: now,
+                timestamp,
+                interval: None,
+                online: true,
+                reading: data.clone(),
+                published: None,
+                alarm: None,
+            })
+            .ok()?;
+        self.sensors.last_mut()
+    }
+
+    /// Latch the alarm `data` raises, if it does, received at monotonic `now`,
+    /// wall clock `timestamp`. A single event can't be left to get through, so
+    /// the alarm stays until cleared with clear_alarm(). Event sensors have no
+    /// transmit interval, this doesn't make them expire.
+    pub fn latch(&mut self, data: &SensorData, now: u64, timestamp: u64) -> Option<Sensor> {
+        let alarm = alarm(data)?;
+        let found = self.sensors.iter().position(|sensor| sensor.matches(data));
+        let sensor = match found {
+            Some(idx) => &mut self.sensors[idx],
+            None => self.insert(data, now, timestamp)?,
+        };
+        sensor.last_seen = now;
+        sensor.timestamp = timestamp;
+        sensor.reading = data.clone();
+        sensor.alarm = Some(alarm);
+        Some(sensor.clone())
+    }
+
+    /// Clear the latched alarm of sensor `id` of `model`, returns the sensor
+    /// if it had one
+    pub fn clear_alarm(&mut self, model: &str, id: u32) -> Option<Sensor> {
+        let sensor = self
+            .sensors
+            .iter_mut()
+            .find(|sensor| sensor.alarm.is_some() && sensor.model == model && sensor.id == id)?;
+        sensor.alarm = None;
+        Some(sensor.clone())
+    }
+
+    /// Whether the reading accepted at monotonic `now` passes `filter`, it's
+    /// remembered as published if it does. Readings of unknown sensors always
+    /// pass.
+    pub fn significant(&mut self, data: &SensorData, now: u64, filter: &Hysteresis) -> bool {
+        let Some(sensor) = self.sensors.iter_mut().find(|sensor| sensor.matches(data)) else {
+            return true;
+        };
+        let significant = filter.check(sensor.published.as_ref(), data, now);
+        if significant {
+            sensor.published = Some(Published::new(data, now));
+        }
+        significant
+    }
+
+    pub fn get(&self, data: &SensorData) -> Option<&Sensor> {
+        self.sensors.iter().find(|sensor| sensor.matches(data))
+    }
+
+    pub fn find(&self, model: &str, id: u32, channel: u8) -> Option<&Sensor> {
+        self.sensors
+            .iter()
+            .find(|sensor| sensor.model == model && sensor.id == id && sensor.channel == channel)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Sensor> {
+        self.sensors.iter()
+    }
+
+    /// Mark the next sensor that has been silent for longer than its
+    /// expire_after period by monotonic `now` as offline and return it
+    pub fn expire(&mut self, now: u64) -> Option<Sensor> {
+        let sensor = self.sensors.iter_mut().find(|sensor| {
+            sensor.online
+                && sensor.expire_after().is_some_and(|expire_after| {
+                    time::since(now, sensor.last_seen)
+                        .is_some_and(|silent| silent > Duration::from_secs(expire_after))
+                })
+        })?;
+        sensor.online = false;
+        Some(sensor.clone())
+    }
+}
+
+impl<const N: usize> Default for Registry<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,192 @@
+use embassy_time::{Duration, Instant, Timer};
+use esp_hal::rmt::Error;
+
+use crate::decoder::{Symbol, pulses_from_edges};
+
+// Replay of recorded pulse trains, used instead of the RMT RX channel when the
+// `replay` feature is enabled. Every dump is a list of (level, duration in uS)
+// edges as captured by the receiver, starting with the first carrier pulse and
+// ending with the last one (the idle pause that terminates the capture is not
+// recorded). Dumps are compiled in, so they live in flash.
+//
+// Like a real sensor, every dump is transmitted several times in a row, then
+// the next one follows after a pause.
+
+const REPEATS: usize = 6;
+const REPEAT_INTERVAL: Duration = Duration::from_millis(100);
+const DUMP_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Nexus-TH, ID 174, channel 1, 21.7C, 45%
+static NEXUS_TH: &[(bool, u16)] = &[
+    (true, 491),
+    (false, 2689),
+    (true, 493),
+    (false, 1913),
+    (true, 508),
+    (false, 964),
+    (true, 509),
+    (false, 1910),
+    (true, 493),
+    (false, 944),
+    (true, 507),
+    (false, 1957),
+    (true, 507),
+    (false, 1903),
+    (true, 524),
+    (false, 1907),
+    (true, 480),
+    (false, 933),
+    (true, 474),
+    (false, 1937),
+    (true, 478),
+    (false, 989),
+    (true, 474),
+    (false, 987),
+    (true, 515),
+    (false, 985),
+    (true, 501),
+    (false, 987),
+    (true, 481),
+    (false, 945),
+    (true, 506),
+    (false, 962),
+    (true, 493),
+    (false, 963),
+    (true, 498),
+    (false, 1912),
+    (true, 507),
+    (false, 1916),
+    (true, 483),
+    (false, 937),
+    (true, 506),
+    (false, 1936),
+    (true, 509),
+    (false, 1914),
+    (true, 487),
+    (false, 946),
+    (true, 517),
+    (false, 954),
+    (true, 481),
+    (false, 1953),
+    (true, 490),
+    (false, 1930),
+    (true, 490),
+    (false, 1921),
+    (true, 500),
+    (false, 1938),
+    (true, 509),
+    (false, 1905),
+    (true, 522),
+    (false, 952),
+    (true, 499),
+    (false, 958),
+    (true, 507),
+    (false, 1904),
+    (true, 526),
+    (false, 958),
+    (true, 480),
+    (false, 1956),
+    (true, 494),
+    (false, 1953),
+    (true, 524),
+    (false, 939),
+    (true, 518),
+    (false, 1946),
+    (true, 476),
+];
+/// EV1527 PIR, address 0x5a3c1, key code 8
+static EV1527_PIR: &[(bool, u16)] = &[
+    (true, 366),
+    (false, 1029),
+    (true, 1080),
+    (false, 365),
+    (true, 337),
+    (false, 1061),
+    (true, 1059),
+    (false, 379),
+    (true, 1035),
+    (false, 335),
+    (true, 336),
+    (false, 1064),
+    (true, 1035),
+    (false, 380),
+    (true, 356),
+    (false, 1022),
+    (true, 380),
+    (false, 1054),
+    (true, 367),
+    (false, 1051),
+    (true, 1060),
+    (false, 325),
+    (true, 1041),
+    (false, 322),
+    (true, 1072),
+    (false, 341),
+    (true, 1050),
+    (false, 344),
+    (true, 364),
+    (false, 1054),
+    (true, 341),
+    (false, 1033),
+    (true, 354),
+    (false, 1033),
+    (true, 346),
+    (false, 1060),
+    (true, 334),
+    (false, 1059),
+    (true, 1072),
+    (false, 370),
+    (true, 1020),
+    (false, 330),
+    (true, 364),
+    (false, 1029),
+    (true, 320),
+    (false, 1042),
+    (true, 368),
+    (false, 1057),
+    (true, 336),
+];
+
+/// Dumps replayed by default, in order
+pub static DUMPS: &[&[(bool, u16)]] = &[NEXUS_TH, EV1527_PIR];
+
+pub struct Replay {
+    dumps: &'static [&'static [(bool, u16)]],
+    dump: usize,
+    repeat: usize,
+    next_at: Instant,
+}
+
+impl Replay {
+    pub fn new(dumps: &'static [&'static [(bool, u16)]]) -> Self {
+        Replay {
+            dumps,
+            dump: 0,
+            repeat: 0,
+            next_at: Instant::now(),
+        }
+    }
+
+    /// Wait for the next transmission and write it into `data` as RMT would.
+    /// Returns number of symbols, including the terminator. Safe to cancel,
+    /// the dump is only consumed once it has been written out.
+    pub async fn receive(&mut self, data: &mut [Symbol]) -> Result<usize, Error> {
+        Timer::at(self.next_at).await;
+
+        let len = match self.dumps.get(self.dump) {
+            Some(dump) => pulses_from_edges(dump, data).ok_or(Error::Overflow),
+            None => Err(Error::InvalidArgument),
+        };
+
+        self.repeat += 1;
+        if self.repeat < REPEATS {
+            self.next_at += REPEAT_INTERVAL;
+        } else {
+            self.repeat = 0;
+            self.dump = (self.dump + 1) % self.dumps.len().max(1);
+            self.next_at += DUMP_INTERVAL;
+        }
+
+        len
+    }
+}
@@ -0,0 +1,66 @@
+use core::cell::Cell;
+
+use critical_section::Mutex;
+
+use crate::{GLITCH_US, RECEIVER, RMT_CLK_DIVIDER, RMT_SOURCE_MHZ};
+
+// Receive thresholds start out as the compiled-in receiver profile, but are
+// part of the runtime config so a unit can be tuned to its module and site
+// without a rebuild. The receiver task compares them with the ones its RX
+// channel was configured with after every burst and rebuilds the channel when
+// they differ, the network side and everything learnt about sensors carry on.
+
+/// Receive thresholds in effect
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Settings {
+    /// Pulses shorter than this many cycles of the RMT source clock are
+    /// ignored
+    pub filter_cycles: u8,
+    /// Bursts end after this long without an edge, in uS
+    pub idle_us: u16,
+    /// Shorter pulses and pauses are merged into the ones around them, in
+    /// uS, 0 keeps bursts as received
+    pub glitch_us: u16,
+}
+
+impl Settings {
+    pub const DEFAULT: Settings = Settings {
+        filter_cycles: RECEIVER.filter_cycles,
+        idle_us: RECEIVER.idle_us as u16,
+        glitch_us: GLITCH_US,
+    };
+
+    /// Idle threshold in RMT ticks, None if it's out of the channel's range
+    pub const fn idle_ticks(&self) -> Option<u16> {
+        let ticks = self.idle_us as u32 * RMT_SOURCE_MHZ / RMT_CLK_DIVIDER as u32;
+        if self.idle_us == 0 || ticks > esp_hal::rmt::MAX_RX_IDLE_THRESHOLD as u32 {
+            return None;
+        }
+        Some(ticks as u16)
+    }
+
+    /// Whether the RX channel can be configured with these
+    pub fn is_valid(&self) -> bool {
+        self.idle_ticks().is_some() && self.glitch_us <= MAX_GLITCH_US
+    }
+}
+
+/// Longest glitch_us, the limit GLITCH_US has as well
+pub const MAX_GLITCH_US: u16 = 200;
+
+const _: () = assert!(
+    Settings::DEFAULT.idle_ticks().is_some(),
+    "RMT clock divider is too small for the idle threshold"
+);
+
+static SETTINGS: Mutex<Cell<Settings>> = Mutex::new(Cell::new(Settings::DEFAULT));
+
+pub fn current() -> Settings {
+    critical_section::with(|cs| SETTINGS.borrow(cs).get())
+}
+
+/// Change the thresholds, they've been validated already. The receiver task
+/// picks them up once the burst it's waiting for has been received.
+pub fn set(settings: Settings) {
+    critical_section::with(|cs| SETTINGS.borrow(cs).set(settings));
+}
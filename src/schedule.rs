@@ -0,0 +1,105 @@
+use embassy_time::{Duration, Instant};
+
+use crate::clock::Clock;
+
+// Periodic work of the main loop and the publisher. Intervals run on
+// monotonic time since boot, so setting the clock doesn't stall or rush them.
+// Only things tied to the time of day go by the wall clock.
+
+const SECS_PER_DAY: i64 = 24 * 60 * 60;
+
+/// Fires every `period`
+pub struct Interval {
+    period: Duration,
+    next: Instant,
+}
+
+impl Interval {
+    /// First fires one period from now
+    pub fn new(period: Duration) -> Self {
+        Interval {
+            period,
+            next: Instant::now() + period,
+        }
+    }
+
+    /// First fires right away
+    pub fn immediate(period: Duration) -> Self {
+        Interval {
+            period,
+            next: Instant::now(),
+        }
+    }
+
+    pub fn is_due(&self) -> bool {
+        Instant::now() >= self.next
+    }
+
+    /// Returns true once the period is over, and starts the next one. Periods
+    /// missed in between aren't made up for.
+    pub fn due(&mut self) -> bool {
+        let due = self.is_due();
+        if due {
+            self.restart();
+        }
+        due
+    }
+
+    /// Start the period over from now, e.g. on activity it times out
+    pub fn restart(&mut self) {
+        self.next = Instant::now() + self.period;
+    }
+
+    /// Fire on the next check
+    pub fn expire(&mut self) {
+        self.next = Instant::now();
+    }
+
+    /// Time it's due at, to wait for
+    pub fn deadline(&self) -> Instant {
+        self.next
+    }
+}
+
+/// Fires once a day at given local time
+pub struct Daily {
+    /// Seconds after local midnight
+    at: i64,
+    /// Local time offset from UTC, in seconds
+    offset: i64,
+    /// Local day it last fired on
+    last_day: Option<i64>,
+}
+
+impl Daily {
+    pub const fn new(hour: u64, minute: u64, offset_minutes: i64) -> Self {
+        Daily {
+            at: (hour * 3600 + minute * 60) as i64,
+            offset: offset_minutes * 60,
+            last_day: None,
+        }
+    }
+
+    /// Returns true once per day, when time of `clock` passes the time of
+    /// day. Doesn't fire for the day it's first called on if the time has
+    /// already passed, e.g. right after boot.
+    pub async fn due(&mut self, clock: &mut impl Clock) -> bool {
+        let Some(now) = clock.time().await else {
+            return false;
+        };
+        let local = (now / 1_000_000) as i64 + self.offset;
+        let day = local.div_euclid(SECS_PER_DAY);
+        let passed = local.rem_euclid(SECS_PER_DAY) >= self.at;
+        match self.last_day {
+            None => {
+                self.last_day = Some(if passed { day } else { day - 1 });
+                false
+            }
+            Some(last_day) if day > last_day && passed => {
+                self.last_day = Some(day);
+                true
+            }
+            _ => false,
+        }
+    }
+}
@@ -0,0 +1,151 @@
+use core::fmt::{self, Write};
+
+use embassy_net::{Ipv4Address, Ipv4Cidr};
+use embassy_time::{Duration, Instant, Timer};
+use esp_hal::gpio::Output;
+
+// Block allocated and freed to check the heap, about what a Wi-Fi reconnect
+// takes
+const HEAP_PROBE_LEN: usize = 8 * 1024;
+// Failure is blinked for this long after boot, long enough to walk up to the
+// unit once it's mounted
+const BLINK_SECS: u64 = 600;
+
+/// Outcome of a boot check
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Check {
+    Pass,
+    Fail,
+    /// Not applicable to this build, e.g. NTP is off
+    Skipped,
+}
+
+impl Check {
+    pub fn from_ok(ok: bool) -> Self {
+        if ok { Check::Pass } else { Check::Fail }
+    }
+
+    pub fn key(self) -> &'static str {
+        match self {
+            Check::Pass => "pass",
+            Check::Fail => "fail",
+            Check::Skipped => "skipped",
+        }
+    }
+}
+
+/// Checks run once at boot to diagnose field installs. Results are published
+/// with the status and blinked on the status LED, for units that never make
+/// it to the broker.
+#[derive(Clone, Copy, Debug)]
+pub struct SelfTest {
+    pub heap: Check,
+    /// Broker host resolves
+    pub dns: Check,
+    /// Broker accepts TCP connections
+    pub broker: Check,
+    pub ntp: Check,
+}
+
+impl SelfTest {
+    /// Checks in blink order, the first one is blinked once
+    pub fn checks(&self) -> [(&'static str, Check); 4] {
+        [
+            ("heap", self.heap),
+            ("dns", self.dns),
+            ("broker", self.broker),
+            ("ntp", self.ntp),
+        ]
+    }
+
+    /// 1-based position of the first failed check
+    pub fn first_failure(&self) -> Option<usize> {
+        self.checks()
+            .iter()
+            .position(|(_, check)| *check == Check::Fail)
+            .map(|idx| idx + 1)
+    }
+
+    /// As a JSON object
+    pub fn write(&self, out: &mut impl Write) -> fmt::Result {
+        write!(out, "{{")?;
+        for (idx, (name, check)) in self.checks().iter().enumerate() {
+            if idx > 0 {
+                write!(out, ", ")?;
+            }
+            write!(out, "\"{}\" : \"{}\"", name, check.key())?;
+        }
+        write!(out, "}}")
+    }
+}
+
+/// Network diagnostics run on request. Wi-Fi is up if there's an address,
+/// a broker that resolves but refuses connections points at the broker
+/// rather than the network.
+#[derive(Clone, Copy, Debug)]
+pub struct Connectivity {
+    /// Address and gateway from DHCP or the static config
+    pub address: Option<Ipv4Cidr>,
+    pub gateway: Option<Ipv4Address>,
+    /// Active broker resolves
+    pub dns: Check,
+    /// Broker accepts TCP connections on port 1883
+    pub mqtt: Check,
+    /// And on port 8883, for MQTT over TLS
+    pub mqtts: Check,
+    /// NTP server resolves, skipped if NTP is off
+    pub ntp: Check,
+}
+
+impl Connectivity {
+    /// As a JSON object, without the closing brace
+    pub fn write(&self, out: &mut impl Write) -> fmt::Result {
+        write!(out, "{{")?;
+        match self.address {
+            Some(address) => write!(out, "\"address\" : \"{}\", ", address)?,
+            None => write!(out, "\"address\" : null, ")?,
+        }
+        match self.gateway {
+            Some(gateway) => write!(out, "\"gateway\" : \"{}\", ", gateway)?,
+            None => write!(out, "\"gateway\" : null, ")?,
+        }
+        write!(
+            out,
+            "\"dns\" : \"{}\", \"mqtt\" : \"{}\", \"mqtts\" : \"{}\", \"ntp\" : \"{}\"",
+            self.dns.key(),
+            self.mqtt.key(),
+            self.mqtts.key(),
+            self.ntp.key()
+        )
+    }
+}
+
+/// Allocate and free a block, the heap is only used by Wi-Fi so a failure
+/// points at a leak or a misconfigured heap
+pub fn heap() -> Check {
+    let mut probe = alloc::vec::Vec::<u8>::new();
+    Check::from_ok(probe.try_reserve_exact(HEAP_PROBE_LEN).is_ok())
+}
+
+/// Show the self-test on the status LED: a 2 s flash if everything passed,
+/// otherwise the position of the first failed check as short blinks,
+/// repeated for BLINK_SECS
+#[embassy_executor::task]
+pub async fn indicate(mut led: Output<'static>, test: SelfTest) {
+    let Some(count) = test.first_failure() else {
+        led.set_high();
+        Timer::after(Duration::from_secs(2)).await;
+        led.set_low();
+        return;
+    };
+    let end = Instant::now() + Duration::from_secs(BLINK_SECS);
+    while Instant::now() < end {
+        for _ in 0..count {
+            led.set_high();
+            Timer::after(Duration::from_millis(200)).await;
+            led.set_low();
+            Timer::after(Duration::from_millis(300)).await;
+        }
+        Timer::after(Duration::from_secs(2)).await;
+    }
+}
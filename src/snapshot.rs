@@ -0,0 +1,84 @@
+use core::mem::size_of;
+use core::ptr::{addr_of_mut, read_unaligned, write_unaligned, write_volatile};
+
+use esp_hal::ram;
+
+use crate::dedup::{Cooldown, ReplayFilter, Suppressor};
+use crate::registry::Registry;
+use crate::summary::Summary;
+use crate::{BUILD_TIMESTAMP, GIT_HASH};
+
+pub const REGISTRY_LEN: usize = 16;
+pub const COOLDOWN_LEN: usize = 16;
+pub const SUPPRESSOR_LEN: usize = 16;
+pub const REPLAY_LEN: usize = 16;
+pub const SUMMARY_LEN: usize = 16;
+
+/// State that has to survive the intentional panic/watchdog reset cycle, so
+/// sensors aren't announced as new and events aren't re-published after it.
+#[derive(Clone)]
+pub struct Snapshot {
+    pub registry: Registry<REGISTRY_LEN>,
+    pub motion_cooldown: Cooldown<COOLDOWN_LEN>,
+    pub suppressor: Suppressor<SUPPRESSOR_LEN>,
+    pub replay_filter: ReplayFilter<REPLAY_LEN>,
+    /// Bitmask of enabled decoders
+    pub protocols: u32,
+    pub summary: Summary<SUMMARY_LEN>,
+    /// Monotonic time it was saved at, times in it are relative to
+    pub monotonic: u64,
+}
+
+const WORDS: usize = size_of::<Snapshot>().div_ceil(4);
+
+/// Snapshot is stored as is, which is only valid for the binary that wrote it.
+/// Storage is zeroed on power up, and the tag is cleared while the snapshot is
+/// being updated, so a reset in the middle of it doesn't leave a torn copy.
+#[ram(unstable(rtc_fast, persistent))]
+static mut TAG: u32 = 0;
+#[ram(unstable(rtc_fast, persistent))]
+static mut STORAGE: [u32; WORDS] = [0; WORDS];
+
+/// FNV-1a of the build info and snapshot size, never 0
+const fn build_tag() -> u32 {
+    let mut hash: u32 = 0x811c_9dc5;
+    let mut idx = 0;
+    while idx < GIT_HASH.len() + BUILD_TIMESTAMP.len() {
+        let byte = if idx < GIT_HASH.len() {
+            GIT_HASH.as_bytes()[idx]
+        } else {
+            BUILD_TIMESTAMP.as_bytes()[idx - GIT_HASH.len()]
+        };
+        hash = (hash ^ byte as u32).wrapping_mul(0x0100_0193);
+        idx += 1;
+    }
+    (hash ^ WORDS as u32) | 1
+}
+
+pub fn save(snapshot: &Snapshot) {
+    // SAFETY: only the main task touches the storage, and the snapshot is only
+    // written and read back as a whole
+    unsafe {
+        write_volatile(addr_of_mut!(TAG), 0);
+        write_unaligned(addr_of_mut!(STORAGE).cast::<Snapshot>(), snapshot.clone());
+        write_volatile(addr_of_mut!(TAG), build_tag());
+    }
+}
+
+/// Drop the snapshot, so the next boot starts with empty state
+pub fn clear() {
+    // SAFETY: only the main task touches the storage
+    unsafe { write_volatile(addr_of_mut!(TAG), 0) };
+}
+
+/// Snapshot saved before the last reset, if it was written by this build
+pub fn restore() -> Option<Snapshot> {
+    // SAFETY: the tag matches only if a complete snapshot was written by this
+    // very binary, so the bytes hold a valid Snapshot
+    unsafe {
+        if *addr_of_mut!(TAG) != build_tag() {
+            return None;
+        }
+        Some(read_unaligned(addr_of_mut!(STORAGE).cast::<Snapshot>()))
+    }
+}
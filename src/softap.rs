@@ -0,0 +1,225 @@
+use core::fmt::Write;
+
+use embassy_net::{
+    Ipv4Address, Ipv4Cidr, Stack, StaticConfigV4,
+    udp::{PacketMetadata, UdpSocket},
+};
+use esp_radio::wifi::{AccessPointConfig, AuthMethod, ModeConfig};
+use heapless::{String, Vec};
+use log::{debug, info, warn};
+
+use crate::AP_PASSWORD;
+use crate::credentials::SSID_LEN;
+
+// Access point a unit falls back to when it can't join its network, see
+// AP_FALLBACK_SECS, or keeps up next to it with AP_STA. It's named after the
+// unit's MAC address and hands out addresses to a few clients, so a phone can
+// get to the setup page at ADDRESS without configuring anything.
+
+const ADDRESS: [u8; 4] = [192, 168, 4, 1];
+const PREFIX_LEN: u8 = 24;
+/// Clients get addresses from .2 on
+const POOL_START: u8 = 2;
+const POOL_LEN: usize = 8;
+const LEASE_SECS: u32 = 60 * 60;
+
+const SERVER_PORT: u16 = 67;
+const CLIENT_PORT: u16 = 68;
+/// Largest message clients have to accept, replies are padded to the
+/// smallest one BOOTP allows
+const MESSAGE_LEN: usize = 576;
+const MIN_REPLY_LEN: usize = 300;
+const OPTIONS: usize = 240;
+const MAGIC_COOKIE: [u8; 4] = [99, 130, 83, 99];
+
+const DISCOVER: u8 = 1;
+const OFFER: u8 = 2;
+const REQUEST: u8 = 3;
+const ACK: u8 = 5;
+
+const OPTION_SUBNET_MASK: u8 = 1;
+const OPTION_ROUTER: u8 = 3;
+const OPTION_REQUESTED_ADDRESS: u8 = 50;
+const OPTION_LEASE_TIME: u8 = 51;
+const OPTION_MESSAGE_TYPE: u8 = 53;
+const OPTION_SERVER_ID: u8 = 54;
+const OPTION_END: u8 = 255;
+
+fn address() -> Ipv4Address {
+    let [a, b, c, d] = ADDRESS;
+    Ipv4Address::new(a, b, c, d)
+}
+
+/// `esp-rf-ook2-` and the last 3 bytes of the access point MAC address
+pub fn ssid() -> String<SSID_LEN> {
+    let [.., d, e, f] = esp_radio::wifi::ap_mac();
+    let mut ssid = String::new();
+    let _ = write!(ssid, "esp-rf-ook2-{:02x}{:02x}{:02x}", d, e, f);
+    ssid
+}
+
+pub fn access_point_config() -> AccessPointConfig {
+    let config = AccessPointConfig::default().with_ssid(ssid().as_str().into());
+    if AP_PASSWORD.is_empty() {
+        config
+    } else {
+        config
+            .with_auth_method(AuthMethod::Wpa2Personal)
+            .with_password(AP_PASSWORD.into())
+    }
+}
+
+pub fn wifi_config() -> ModeConfig {
+    ModeConfig::AccessPoint(access_point_config())
+}
+
+pub fn net_config() -> embassy_net::Config {
+    embassy_net::Config::ipv4_static(StaticConfigV4 {
+        address: Ipv4Cidr::new(address(), PREFIX_LEN),
+        gateway: None,
+        dns_servers: Default::default(),
+    })
+}
+
+/// Client hardware addresses by pool offset
+struct Leases {
+    clients: [Option<[u8; 6]>; POOL_LEN],
+    /// Offset given out next once every one is taken
+    next: usize,
+}
+
+impl Leases {
+    /// Pool offset of `client`, taking the oldest lease if it has none
+    fn lease(&mut self, client: [u8; 6]) -> usize {
+        if let Some(offset) = self.clients.iter().position(|c| *c == Some(client)) {
+            return offset;
+        }
+        let offset = self
+            .clients
+            .iter()
+            .position(Option::is_none)
+            .unwrap_or(self.next);
+        self.clients[offset] = Some(client);
+        self.next = (offset + 1) % POOL_LEN;
+        offset
+    }
+}
+
+/// Minimal DHCP server for the clients of the access point, it answers
+/// discovers with an offer and requests with an ack of the client's lease
+#[embassy_executor::task]
+pub async fn dhcp(stack: Stack<'static>) {
+    let mut rx_meta = [PacketMetadata::EMPTY; 2];
+    let mut rx_buf = [0; MESSAGE_LEN * 2];
+    let mut tx_meta = [PacketMetadata::EMPTY; 2];
+    let mut tx_buf = [0; MESSAGE_LEN];
+    let mut socket = UdpSocket::new(stack, &mut rx_meta, &mut rx_buf, &mut tx_meta, &mut tx_buf);
+    if let Err(e) = socket.bind(SERVER_PORT) {
+        warn!("Failed to bind DHCP server: {:?}", e);
+        return;
+    }
+    info!("Serving DHCP on {}", address());
+    let mut leases = Leases {
+        clients: [None; POOL_LEN],
+        next: 0,
+    };
+    let mut request = [0; MESSAGE_LEN];
+    loop {
+        let Ok((len, _)) = socket.recv_from(&mut request).await else {
+            continue;
+        };
+        let Some(reply) = reply(&request[..len], &mut leases) else {
+            continue;
+        };
+        // Clients don't have an address to send to yet
+        if let Err(e) = socket
+            .send_to(&reply, (Ipv4Address::BROADCAST, CLIENT_PORT))
+            .await
+        {
+            warn!("Failed to send DHCP reply: {:?}", e);
+        }
+    }
+}
+
+/// Type and options of a client message, None if it isn't one
+fn options(request: &[u8]) -> Option<impl Iterator<Item = (u8, &[u8])>> {
+    if request.len() < OPTIONS
+        || request[0] != 1
+        || request[1] != 1
+        || request[2] != 6
+        || request[236..OPTIONS] != MAGIC_COOKIE
+    {
+        return None;
+    }
+    let mut rest = &request[OPTIONS..];
+    Some(core::iter::from_fn(move || {
+        loop {
+            let (&code, tail) = rest.split_first()?;
+            match code {
+                0 => rest = tail,
+                OPTION_END => return None,
+                _ => {
+                    let (&len, tail) = tail.split_first()?;
+                    let value = tail.get(..len.into())?;
+                    rest = &tail[len.into()..];
+                    return Some((code, value));
+                }
+            }
+        }
+    }))
+}
+
+fn reply(request: &[u8], leases: &mut Leases) -> Option<Vec<u8, MESSAGE_LEN>> {
+    let mut message_type = None;
+    let mut server_id = None;
+    for (code, value) in options(request)? {
+        match code {
+            OPTION_MESSAGE_TYPE => message_type = value.first().copied(),
+            OPTION_SERVER_ID => server_id = Some(value),
+            OPTION_REQUESTED_ADDRESS => debug!("DHCP client asks for {:?}", value),
+            _ => {}
+        }
+    }
+    let reply_type = match message_type? {
+        DISCOVER => OFFER,
+        // Client took another server's offer
+        REQUEST if server_id.is_some_and(|id| id != ADDRESS) => return None,
+        REQUEST => ACK,
+        _ => return None,
+    };
+    let client: [u8; 6] = request[28..34].try_into().ok()?;
+    let offset = leases.lease(client);
+    let [a, b, c, _] = ADDRESS;
+    let leased = [a, b, c, POOL_START + offset as u8];
+    if reply_type == ACK {
+        info!("Leased {:?} to {:02x?}", leased, client);
+    }
+
+    let mut reply: Vec<u8, MESSAGE_LEN> = Vec::new();
+    let mut header = [0; OPTIONS];
+    header[..4].copy_from_slice(&[2, 1, 6, 0]);
+    // Transaction id, and the broadcast flag
+    header[4..8].copy_from_slice(&request[4..8]);
+    header[10..12].copy_from_slice(&request[10..12]);
+    header[16..20].copy_from_slice(&leased);
+    header[20..24].copy_from_slice(&ADDRESS);
+    // Relay agent and client hardware address
+    header[24..44].copy_from_slice(&request[24..44]);
+    header[236..].copy_from_slice(&MAGIC_COOKIE);
+    reply.extend_from_slice(&header).ok()?;
+    for option in [
+        &[OPTION_MESSAGE_TYPE, 1, reply_type][..],
+        &[OPTION_SERVER_ID, 4],
+        &ADDRESS,
+        &[OPTION_LEASE_TIME, 4],
+        &LEASE_SECS.to_be_bytes(),
+        &[OPTION_SUBNET_MASK, 4, 255, 255, 255, 0],
+        &[OPTION_ROUTER, 4],
+        &ADDRESS,
+        &[OPTION_END],
+    ] {
+        reply.extend_from_slice(option).ok()?;
+    }
+    reply.resize(reply.len().max(MIN_REPLY_LEN), 0).ok()?;
+    Some(reply)
+}
@@ -0,0 +1,146 @@
+use core::cell::RefCell;
+
+use embassy_sync::blocking_mutex::CriticalSectionMutex;
+use heapless::Vec;
+
+use crate::decoder::{Climate, Rain, SensorData, SensorReading, Wind};
+use crate::time;
+
+// Some stations send quantities in frames of their own, wind in one and rain
+// in another say, and readings of a single frame never show the whole
+// picture. Current conditions of every station are assembled here instead,
+// each quantity from the latest frame that carried it, to be published as one
+// document alongside the frames. Quantities not heard for STALE_US are left
+// out rather than passed off as current.
+
+/// Stations tracked at once, the one heard least recently makes room
+pub const STATIONS: usize = 4;
+/// Quantities older than this are left out, 30 minutes
+const STALE_US: u64 = 30 * 60 * 1_000_000;
+
+#[derive(Clone, Debug)]
+struct Field<T> {
+    value: T,
+    /// Monotonic time it was received
+    at: u64,
+}
+
+impl<T: Clone> Field<T> {
+    fn new(value: &T, at: u64) -> Self {
+        Field {
+            value: value.clone(),
+            at,
+        }
+    }
+
+    fn fresh(&self, now: u64) -> Option<&T> {
+        (self.at.saturating_add(STALE_US) > now).then_some(&self.value)
+    }
+}
+
+/// Current conditions of a station
+#[derive(Clone, Debug)]
+pub struct Station {
+    /// Latest frame, for the station's model, ID, channel and battery
+    pub data: SensorData,
+    climate: Option<Field<Climate>>,
+    wind: Option<Field<Wind>>,
+    rain: Option<Field<Rain>>,
+    /// Monotonic time of the latest frame
+    heard: u64,
+}
+
+impl Station {
+    fn matches(&self, data: &SensorData) -> bool {
+        self.data.model() == data.model()
+            && self.data.id == data.id
+            && self.data.channel == data.channel
+    }
+
+    /// Readings that make up the conditions as of monotonic `now`, the way
+    /// frames of the station would carry them: a single weather reading if
+    /// every quantity is current, one reading per current quantity otherwise
+    pub fn readings(&self, now: u64) -> Vec<SensorData, 3> {
+        let climate = self.climate.as_ref().and_then(|field| field.fresh(now));
+        let wind = self.wind.as_ref().and_then(|field| field.fresh(now));
+        let rain = self.rain.as_ref().and_then(|field| field.fresh(now));
+        let mut readings = Vec::new();
+        // Can't overflow, there are three quantities
+        let mut add = |reading| {
+            let mut data = self.data.clone();
+            data.reading = reading;
+            let _ = readings.push(data);
+        };
+        if let (Some(climate), Some(wind), Some(rain)) = (climate, wind, rain) {
+            add(SensorReading::Weather(
+                climate.clone(),
+                wind.clone(),
+                rain.clone(),
+            ));
+            return readings;
+        }
+        if let Some(climate) = climate {
+            add(SensorReading::Climate(climate.clone()));
+        }
+        if let Some(wind) = wind {
+            add(SensorReading::Wind(wind.clone()));
+        }
+        if let Some(rain) = rain {
+            add(SensorReading::Rain(rain.clone()));
+        }
+        readings
+    }
+}
+
+static STATIONS_STATE: CriticalSectionMutex<RefCell<Vec<Station, STATIONS>>> =
+    CriticalSectionMutex::new(RefCell::new(Vec::new()));
+
+/// Update the conditions of the station `data` came from, received just now,
+/// with the quantities it carries. Only sensors that send wind or rain count
+/// as stations, None for the rest.
+pub fn update(data: &SensorData) -> Option<Station> {
+    let now = time::monotonic();
+    STATIONS_STATE.lock(|stations| {
+        let mut stations = stations.borrow_mut();
+        let idx = match stations.iter().position(|station| station.matches(data)) {
+            Some(idx) => idx,
+            None => {
+                if data.wind().is_none() && data.rain().is_none() {
+                    return None;
+                }
+                if stations.is_full()
+                    && let Some(oldest) = stations
+                        .iter()
+                        .enumerate()
+                        .min_by_key(|(_, station)| station.heard)
+                        .map(|(idx, _)| idx)
+                {
+                    stations.swap_remove(oldest);
+                }
+                stations
+                    .push(Station {
+                        data: data.clone(),
+                        climate: None,
+                        wind: None,
+                        rain: None,
+                        heard: now,
+                    })
+                    .ok()?;
+                stations.len() - 1
+            }
+        };
+        let station = &mut stations[idx];
+        station.data = data.clone();
+        station.heard = now;
+        if let Some(climate) = data.climate() {
+            station.climate = Some(Field::new(climate, now));
+        }
+        if let Some(wind) = data.wind() {
+            station.wind = Some(Field::new(wind, now));
+        }
+        if let Some(rain) = data.rain() {
+            station.rain = Some(Field::new(rain, now));
+        }
+        Some(station.clone())
+    })
+}
@@ -0,0 +1,103 @@
+use heapless::Vec;
+
+use crate::decoder::SensorData;
+
+/// Min, max and sum of the values seen during the day
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Series {
+    pub min: i32,
+    pub max: i32,
+    sum: i32,
+    pub count: u32,
+}
+
+impl Series {
+    fn add(&mut self, value: i32) {
+        if self.count == 0 {
+            self.min = value;
+            self.max = value;
+        } else {
+            self.min = self.min.min(value);
+            self.max = self.max.max(value);
+        }
+        self.sum = self.sum.saturating_add(value);
+        self.count += 1;
+    }
+
+    pub fn avg(&self) -> Option<i32> {
+        self.sum.checked_div(self.count as i32)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct SensorStats {
+    pub model: &'static str,
+    pub id: u32,
+    pub channel: u8,
+    pub readings: u32,
+    /// Temperature * 10 in C
+    pub temperature: Series,
+    pub humidity: Series,
+}
+
+/// Daily statistics of up to N sensors, along with error counts. Sensors that
+/// don't fit are left out of the summary.
+#[derive(Clone)]
+pub struct Summary<const N: usize> {
+    pub sensors: Vec<SensorStats, N>,
+    pub decode_errors: u32,
+    pub publish_failures: u32,
+    /// Readings dropped by the publish rate limit
+    pub rate_limited: u32,
+}
+
+impl<const N: usize> Summary<N> {
+    pub const fn new() -> Self {
+        Summary {
+            sensors: Vec::new(),
+            decode_errors: 0,
+            publish_failures: 0,
+            rate_limited: 0,
+        }
+    }
+
+    pub fn record(&mut self, data: &SensorData) {
+        let sensor = match self.sensors.iter_mut().find(|sensor| {
+            sensor.model == data.model() && sensor.id == data.id && sensor.channel == data.channel
+        }) {
+            Some(sensor) => sensor,
+            None => {
+                let stats = SensorStats {
+                    model: data.model(),
+                    id: data.id,
+                    channel: data.channel,
+                    readings: 0,
+                    temperature: Series::default(),
+                    humidity: Series::default(),
+                };
+                if self.sensors.push(stats).is_err() {
+                    return;
+                }
+                self.sensors.last_mut().unwrap()
+            }
+        };
+        sensor.readings += 1;
+        if let Some(climate) = data.climate() {
+            sensor.temperature.add(climate.temp_decicelsius as i32);
+            if let Some(humidity) = climate.humidity {
+                sensor.humidity.add(humidity as i32);
+            }
+        }
+    }
+
+    /// Start over for the next day
+    pub fn clear(&mut self) {
+        *self = Self::new();
+    }
+}
+
+impl<const N: usize> Default for Summary<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,62 @@
+use core::cell::Cell;
+
+use critical_section::Mutex;
+use embassy_time::{Duration, Instant};
+
+// Intervals go by monotonic time, which only ever moves forward. Wall clock
+// time may be stepped back by NTP or the set time command, so it's only used to
+// timestamp what's published. Both are kept in us.
+
+// Monotonic time the current boot started at
+static RESUMED: Mutex<Cell<u64>> = Mutex::new(Cell::new(0));
+// Wall clock time minus monotonic time, as the main loop last saw it
+static WALL_OFFSET: Mutex<Cell<Option<i64>>> = Mutex::new(Cell::new(None));
+
+/// Monotonic time, in us. It's uptime, carried on across resets the state
+/// snapshot survives.
+pub fn monotonic() -> u64 {
+    let resumed = critical_section::with(|cs| RESUMED.borrow(cs).get());
+    resumed.saturating_add(Instant::now().as_micros())
+}
+
+/// Carry on monotonic time from `time`, saved before a reset. The reset
+/// itself takes no time.
+pub fn resume(time: u64) {
+    let uptime = Instant::now().as_micros();
+    critical_section::with(|cs| RESUMED.borrow(cs).set(time.saturating_sub(uptime)));
+}
+
+/// Note wall clock time `wall_now`, for tasks that don't own the clock
+pub fn track_wall(wall_now: u64) {
+    let offset = wall_now as i64 - monotonic() as i64;
+    critical_section::with(|cs| WALL_OFFSET.borrow(cs).set(Some(offset)));
+}
+
+/// Wall clock time as of the last `track_wall`, moved on by monotonic time
+/// since. None until the main loop first notes it.
+pub fn wall() -> Option<u64> {
+    let offset = critical_section::with(|cs| WALL_OFFSET.borrow(cs).get())?;
+    Some(monotonic().saturating_add_signed(offset))
+}
+
+/// Wall clock time of monotonic time `then`, given both at the same moment
+pub fn wall_time(then: u64, now: u64, wall_now: u64) -> u64 {
+    wall_now.saturating_sub(now.saturating_sub(then))
+}
+
+/// Time from `earlier` to `now`. None if `now` is before `earlier`, e.g. the
+/// wall clock has since been stepped back past it.
+pub fn since(now: u64, earlier: u64) -> Option<Duration> {
+    now.checked_sub(earlier).map(Duration::from_micros)
+}
+
+/// Whether `period` has passed from `earlier` by `now`. Stepping the clock back
+/// doesn't count as time passing.
+pub fn passed(now: u64, earlier: u64, period: Duration) -> bool {
+    since(now, earlier).is_some_and(|elapsed| elapsed >= period)
+}
+
+/// Whether `now` is less than `period` after `earlier`, or before it
+pub fn within(now: u64, earlier: u64, period: Duration) -> bool {
+    !passed(now, earlier, period)
+}
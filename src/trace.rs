@@ -0,0 +1,105 @@
+use core::cell::RefCell;
+
+use embassy_sync::blocking_mutex::CriticalSectionMutex;
+
+use crate::time;
+
+// Span timings of the `trace` feature, to find out which stage of the receive
+// and publish path holds things up. Tasks enter a span when they start on a
+// burst or reading and exit it when they're done, awaits included. Totals are
+// kept since boot, along with the span in progress, so the watchdog can tell
+// where the main loop got stuck.
+
+#[derive(Clone, Copy, Debug)]
+pub enum Phase {
+    /// Receiver task preparing a burst RMT has received
+    Capture,
+    /// Decoder task trying protocols on a burst
+    Decode,
+    /// Main loop handling a decoded burst, between watchdog feeds
+    Process,
+    /// Publisher task sending a reading to every backend
+    Publish,
+}
+
+impl Phase {
+    pub const ALL: [Phase; 4] = [
+        Phase::Capture,
+        Phase::Decode,
+        Phase::Process,
+        Phase::Publish,
+    ];
+
+    pub fn key(self) -> &'static str {
+        match self {
+            Phase::Capture => "capture",
+            Phase::Decode => "decode",
+            Phase::Process => "process",
+            Phase::Publish => "publish",
+        }
+    }
+}
+
+/// Timings of a phase, in us
+#[derive(Clone, Copy, Debug)]
+pub struct Timing {
+    /// Spans exited since boot
+    pub count: u32,
+    pub total_us: u64,
+    pub max_us: u32,
+    /// Monotonic time the span in progress was entered at
+    pub entered: Option<u64>,
+}
+
+impl Timing {
+    const fn new() -> Self {
+        Timing {
+            count: 0,
+            total_us: 0,
+            max_us: 0,
+            entered: None,
+        }
+    }
+
+    pub fn mean_us(&self) -> Option<u64> {
+        (self.count > 0).then(|| self.total_us / self.count as u64)
+    }
+
+    /// How long the span in progress has been open by monotonic `now`
+    pub fn open_us(&self, now: u64) -> Option<u64> {
+        self.entered.map(|entered| now.saturating_sub(entered))
+    }
+}
+
+static TIMINGS: CriticalSectionMutex<RefCell<[Timing; Phase::ALL.len()]>> =
+    CriticalSectionMutex::new(RefCell::new([Timing::new(); Phase::ALL.len()]));
+
+/// Span in progress, exited when dropped. Every phase runs on a single task,
+/// so spans of a phase never overlap.
+pub struct Span {
+    phase: Phase,
+    entered: u64,
+}
+
+pub fn enter(phase: Phase) -> Span {
+    let entered = time::monotonic();
+    TIMINGS.lock(|timings| timings.borrow_mut()[phase as usize].entered = Some(entered));
+    Span { phase, entered }
+}
+
+impl Drop for Span {
+    fn drop(&mut self) {
+        let us = time::monotonic().saturating_sub(self.entered);
+        TIMINGS.lock(|timings| {
+            let timing = &mut timings.borrow_mut()[self.phase as usize];
+            timing.count = timing.count.saturating_add(1);
+            timing.total_us = timing.total_us.saturating_add(us);
+            timing.max_us = timing.max_us.max(us.try_into().unwrap_or(u32::MAX));
+            timing.entered = None;
+        });
+    }
+}
+
+pub fn timing(phase: Phase) -> Timing {
+    TIMINGS.lock(|timings| timings.borrow()[phase as usize])
+}
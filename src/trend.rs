@@ -0,0 +1,103 @@
+use core::cell::RefCell;
+
+use embassy_sync::blocking_mutex::CriticalSectionMutex;
+use embassy_time::Duration;
+use heapless::{Deque, Vec};
+
+use crate::decoder::SensorData;
+use crate::snapshot::REGISTRY_LEN;
+use crate::time;
+
+// Recent values of every sensor kept in RAM for the dashboard's sparklines.
+// Each point is the last reading within its STEP, so LEN points cover 12 hours
+// however often a sensor transmits. Nothing survives a reset.
+
+/// Points kept per sensor
+pub const LEN: usize = 48;
+const STEP: Duration = Duration::from_secs(15 * 60);
+
+struct Trend {
+    model: &'static str,
+    id: u32,
+    channel: u8,
+    /// Monotonic time the newest point started at
+    start: u64,
+    /// Oldest first, in tenths of the sensor's main value
+    points: Deque<i32, LEN>,
+}
+
+static TRENDS: CriticalSectionMutex<RefCell<Vec<Trend, REGISTRY_LEN>>> =
+    CriticalSectionMutex::new(RefCell::new(Vec::new()));
+
+/// Value a sensor's trend follows, in tenths: temperature, average wind speed
+/// or rain total, whichever it reports first
+fn value(data: &SensorData) -> Option<i32> {
+    if let Some(climate) = data.climate() {
+        Some(climate.temp_decicelsius.into())
+    } else if let Some(wind) = data.wind() {
+        Some(wind.avg_speed.into())
+    } else {
+        data.rain().map(|rain| rain.total as i32)
+    }
+}
+
+/// Add a periodic reading accepted at monotonic `now`. The sensor seen least
+/// recently is dropped to make room for a new one.
+pub fn record(data: &SensorData, now: u64) {
+    let Some(value) = value(data) else {
+        return;
+    };
+    TRENDS.lock(|trends| {
+        let mut trends = trends.borrow_mut();
+        let found = trends.iter().position(|trend| {
+            trend.model == data.model() && trend.id == data.id && trend.channel == data.channel
+        });
+        let idx = match found {
+            Some(idx) => idx,
+            None => {
+                if trends.is_full()
+                    && let Some(oldest) = trends
+                        .iter()
+                        .enumerate()
+                        .min_by_key(|(_, trend)| trend.start)
+                        .map(|(idx, _)| idx)
+                {
+                    trends.swap_remove(oldest);
+                }
+                let trend = Trend {
+                    model: data.model(),
+                    id: data.id,
+                    channel: data.channel,
+                    start: now,
+                    points: Deque::new(),
+                };
+                if trends.push(trend).is_err() {
+                    return;
+                }
+                trends.len() - 1
+            }
+        };
+        let trend = &mut trends[idx];
+        if trend.points.is_empty() || time::passed(now, trend.start, STEP) {
+            if trend.points.is_full() {
+                trend.points.pop_front();
+            }
+            trend.points.push_back(value).ok();
+            trend.start = now;
+        } else if let Some(last) = trend.points.back_mut() {
+            *last = value;
+        }
+    });
+}
+
+/// Points of a sensor, oldest first, empty if it has none
+pub fn points(model: &str, id: u32, channel: u8) -> Vec<i32, LEN> {
+    TRENDS.lock(|trends| {
+        trends
+            .borrow()
+            .iter()
+            .find(|trend| trend.model == model && trend.id == id && trend.channel == channel)
+            .map(|trend| trend.points.iter().copied().collect())
+            .unwrap_or_default()
+    })
+}
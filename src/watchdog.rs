@@ -0,0 +1,172 @@
+use core::fmt::Write;
+use core::sync::atomic::{AtomicU8, Ordering};
+
+use esp_hal::handler;
+use esp_hal::interrupt::InterruptConfigurable;
+use esp_hal::peripherals::TIMG0;
+use esp_hal::time::Duration;
+use esp_hal::timer::timg::{MwdtStage, MwdtStageAction, Wdt};
+use log::warn;
+
+#[cfg(feature = "postmortem")]
+use crate::postmortem::{self, Kind, Report};
+#[cfg(feature = "trace")]
+use crate::time;
+#[cfg(feature = "trace")]
+use crate::trace::{self, Phase};
+use crate::{WATCHDOG_DUMP_SECS, WATCHDOG_RESET_SECS, WATCHDOG_TIMEOUT_SECS, logger, wifi};
+
+// Main loop watchdog that escalates instead of resetting right away. The
+// first stage restarts Wi-Fi, since the main loop is usually stuck waiting on
+// the network, the second writes out diagnostics for post-mortem analysis and
+// only the last one resets the unit. The first two run in the watchdog
+// interrupt, a hang with interrupts disabled goes straight to the reset.
+
+/// Stages expired since the last feed
+static EXPIRED: AtomicU8 = AtomicU8::new(0);
+
+pub struct Watchdog {
+    wdt: Wdt<TIMG0<'static>>,
+}
+
+impl Watchdog {
+    pub fn new(mut wdt: Wdt<TIMG0<'static>>) -> Self {
+        #[cfg(feature = "postmortem")]
+        postmortem::init();
+        // Enabling resets the stage actions. Setting timeouts makes the
+        // chips that need it take on the new configuration, so they go last.
+        wdt.enable();
+        wdt.set_stage_action(MwdtStage::Stage0, MwdtStageAction::Interrupt);
+        wdt.set_stage_action(MwdtStage::Stage1, MwdtStageAction::Interrupt);
+        wdt.set_stage_action(MwdtStage::Stage2, MwdtStageAction::ResetSystem);
+        set_timeouts(
+            &mut wdt,
+            [
+                (MwdtStage::Stage0, WATCHDOG_TIMEOUT_SECS),
+                (MwdtStage::Stage1, WATCHDOG_DUMP_SECS),
+                (MwdtStage::Stage2, WATCHDOG_RESET_SECS),
+            ],
+        );
+        wdt.set_interrupt_handler(expired);
+        TIMG0::regs().int_ena().modify(|_, w| w.wdt().set_bit());
+        wdt.feed();
+        Watchdog { wdt }
+    }
+
+    pub fn feed(&mut self) {
+        EXPIRED.store(0, Ordering::Relaxed);
+        self.wdt.feed();
+    }
+}
+
+/// Stages share the clock prescaler, which set_timeout() picks for the stage
+/// it sets. The longest stage is set first to get the prescaler all of them
+/// need, the others scaled down by it, and the longest one last again to put
+/// the prescaler back.
+fn set_timeouts(wdt: &mut Wdt<TIMG0<'static>>, stages: [(MwdtStage, u64); 3]) {
+    let Some(&(longest, longest_secs)) = stages.iter().max_by_key(|(_, secs)| *secs) else {
+        return;
+    };
+    wdt.set_timeout(longest, Duration::from_secs(longest_secs));
+    let prescale = TIMG0::regs()
+        .wdtconfig1()
+        .read()
+        .wdt_clk_prescale()
+        .bits()
+        .max(1) as u64;
+    for (stage, secs) in stages {
+        wdt.set_timeout(stage, Duration::from_micros(secs * 1_000_000 / prescale));
+    }
+    wdt.set_timeout(longest, Duration::from_secs(longest_secs));
+}
+
+#[handler]
+fn expired() {
+    TIMG0::regs()
+        .int_clr()
+        .write(|w| w.wdt().clear_bit_by_one());
+    // Nothing preempts the interrupt, a plain load and store will do
+    let stage = EXPIRED.load(Ordering::Relaxed);
+    EXPIRED.store(stage.saturating_add(1), Ordering::Relaxed);
+    if stage == 0 {
+        warn!(
+            "Main loop stuck for {} s, restarting Wi-Fi",
+            WATCHDOG_TIMEOUT_SECS
+        );
+        wifi::restart();
+    } else {
+        dump();
+    }
+}
+
+/// Diagnostics to the console, and to flash with the postmortem feature
+struct Dump {
+    #[cfg(feature = "postmortem")]
+    report: Option<Report>,
+}
+
+impl Write for Dump {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        esp_println::print!("{}", s);
+        #[cfg(feature = "postmortem")]
+        if let Some(report) = &mut self.report {
+            // Console still gets the rest
+            if report.write_str(s).is_err() {
+                self.report = None;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn dump() {
+    let mut dump = Dump {
+        #[cfg(feature = "postmortem")]
+        report: Report::begin(Kind::Watchdog),
+    };
+    let _ = writeln!(
+        dump,
+        "Main loop stuck for {} s, resetting in {} s",
+        WATCHDOG_TIMEOUT_SECS + WATCHDOG_DUMP_SECS,
+        WATCHDOG_RESET_SECS
+    );
+    let _ = writeln!(
+        dump,
+        "uptime {} ms, heap {} used {} free, {} log lines dropped",
+        esp_hal::time::Instant::now()
+            .duration_since_epoch()
+            .as_millis(),
+        esp_alloc::HEAP.used(),
+        esp_alloc::HEAP.free(),
+        logger::dropped()
+    );
+    // Whichever span is still open is where things got stuck
+    #[cfg(feature = "trace")]
+    {
+        let now = time::monotonic();
+        for phase in Phase::ALL {
+            let timing = trace::timing(phase);
+            let _ = write!(
+                dump,
+                "{}: {} spans, max {} us",
+                phase.key(),
+                timing.count,
+                timing.max_us
+            );
+            let _ = match timing.open_us(now) {
+                Some(open_us) => writeln!(dump, ", open for {} us", open_us),
+                None => writeln!(dump),
+            };
+        }
+    }
+    // Lines the console didn't get to are the last ones before the hang
+    logger::drain(|line| {
+        let _ = writeln!(dump, "{}", line);
+    });
+    #[cfg(feature = "postmortem")]
+    if let Some(report) = dump.report
+        && let Err(e) = report.finish()
+    {
+        esp_println::println!("Failed to write watchdog report: {:?}", e);
+    }
+}
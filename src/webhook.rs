@@ -0,0 +1,152 @@
+use core::fmt::Write;
+
+use embassy_net::{IpAddress, Stack, dns::DnsQueryType, tcp::TcpSocket};
+use embassy_sync::{blocking_mutex::raw::NoopRawMutex, mutex::Mutex};
+use embassy_time::{Duration, Timer};
+use heapless::String;
+use log::{debug, warn};
+
+use crate::MQTT_STATUS_TOPIC;
+use crate::mqtt::Class;
+use crate::output::Publisher;
+
+const DEFAULT_PORT: u16 = 80;
+const HEADER_LEN: usize = 256;
+
+#[derive(Debug)]
+pub enum Error {
+    DnsResolveFailed,
+    ConnectionFailed,
+    WriteFailed,
+    /// Server answered with something other than 2xx
+    Rejected,
+}
+
+/// Posts every message as JSON to `<url>/<topic>` over plain HTTP
+pub struct Webhook {
+    stack: &'static Mutex<NoopRawMutex, Stack<'static>>,
+    host: &'static str,
+    port: u16,
+    path: &'static str,
+    addr: Option<IpAddress>,
+}
+
+impl Webhook {
+    /// `url` is `http://host[:port][/path]`, HTTPS isn't supported
+    pub fn new(
+        stack: &'static Mutex<NoopRawMutex, Stack<'static>>,
+        url: &'static str,
+    ) -> Option<Self> {
+        let Some(rest) = url.strip_prefix("http://") else {
+            warn!("Webhook URL must start with http://, webhook disabled");
+            return None;
+        };
+        let (authority, path) = match rest.find('/') {
+            Some(idx) => rest.split_at(idx),
+            None => (rest, ""),
+        };
+        let (host, port) = match authority.rsplit_once(':') {
+            Some((host, port)) => match port.parse() {
+                Ok(port) => (host, port),
+                Err(_) => {
+                    warn!("Invalid webhook port {}, webhook disabled", port);
+                    return None;
+                }
+            },
+            None => (authority, DEFAULT_PORT),
+        };
+        Some(Webhook {
+            stack,
+            host,
+            port,
+            path: path.trim_end_matches('/'),
+            addr: None,
+        })
+    }
+
+    async fn post(&mut self, topic: &str, payload: &str) -> Result<(), Error> {
+        let stack = self.stack.lock().await;
+        let mut tx_buf = [0; 1024];
+        let mut rx_buf = [0; 256];
+
+        if self.addr.is_none() {
+            let addr = match stack.dns_query(self.host, DnsQueryType::A).await {
+                Ok(addrs) => addrs.first().copied(),
+                Err(e) => {
+                    warn!("DNS resolve failed: {:?}", e);
+                    None
+                }
+            };
+            self.addr = Some(addr.ok_or(Error::DnsResolveFailed)?);
+        }
+
+        let mut socket = TcpSocket::new(*stack, &mut rx_buf, &mut tx_buf);
+        socket.set_timeout(Some(Duration::from_secs(10)));
+        if let Err(e) = socket.connect((self.addr.unwrap(), self.port)).await {
+            self.addr = None; // Clear cached address on failure
+            warn!("Error: {:?}", e);
+            return Err(Error::ConnectionFailed);
+        }
+
+        let mut header: String<HEADER_LEN> = String::new();
+        write!(
+            header,
+            "POST {}/{} HTTP/1.0\r\nHost: {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n",
+            self.path,
+            topic,
+            self.host,
+            payload.len()
+        )
+        .map_err(|_| Error::WriteFailed)?;
+        for mut buf in [header.as_bytes(), payload.as_bytes()] {
+            while !buf.is_empty() {
+                match socket.write(buf).await {
+                    Ok(0) | Err(_) => return Err(Error::WriteFailed),
+                    Ok(len) => buf = &buf[len..],
+                }
+            }
+        }
+        socket.flush().await.map_err(|_| Error::WriteFailed)?;
+
+        // Status line is all that matters, e.g. `HTTP/1.1 204 No Content`
+        let mut status = [0; 12];
+        let mut len = 0;
+        while len < status.len() {
+            match socket.read(&mut status[len..]).await {
+                Ok(0) | Err(_) => break,
+                Ok(read) => len += read,
+            }
+        }
+        socket.close();
+        // Give stack some time to process the socket closure
+        Timer::after(Duration::from_millis(100)).await;
+        socket.abort();
+
+        if len < status.len() || !status.starts_with(b"HTTP/") || status[9] != b'2' {
+            return Err(Error::Rejected);
+        }
+        debug!("Posted {} to webhook", topic);
+        Ok(())
+    }
+}
+
+impl Publisher for Webhook {
+    type Error = Error;
+
+    async fn publish_reading(&mut self, topic: &str, payload: &str) -> Result<(), Error> {
+        self.post(topic, payload).await
+    }
+
+    async fn publish_diagnostics(
+        &mut self,
+        topic: &str,
+        payload: &str,
+        _class: Class,
+    ) -> Result<(), Error> {
+        self.post(topic, payload).await
+    }
+
+    async fn announce(&mut self, payload: &str) -> Result<(), Error> {
+        self.post(MQTT_STATUS_TOPIC, payload).await
+    }
+}
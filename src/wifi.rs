@@ -1,27 +1,124 @@
-use esp_hal::rng::Rng;
+use core::cell::RefCell;
+use core::fmt::{self, Write};
+
 use esp_radio::{
     Controller,
     wifi::{
         ClientConfig, ModeConfig, ScanConfig, WifiController, WifiDevice, WifiEvent, WifiStaState,
+        event::{EventExt, StaConnected},
     },
 };
 
 use embassy_executor::Spawner;
-use embassy_net::{DhcpConfig, Runner, Stack, StackResources};
+#[cfg(feature = "provisioning")]
+use embassy_futures::select::{Either, select};
+use embassy_futures::select::{Either3, select3};
+use embassy_net::{DhcpConfig, Ipv4Address, Ipv4Cidr, Runner, Stack, StackResources};
+use embassy_sync::blocking_mutex::CriticalSectionMutex;
 use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
 use embassy_sync::signal::Signal;
+#[cfg(feature = "provisioning")]
+use embassy_time::Instant;
 use embassy_time::{Duration, Timer};
-use heapless::String;
+use heapless::{String, Vec};
 use log::{info, warn};
 use static_cell::StaticCell;
 
-use crate::{PASSWORD, SSID};
+use crate::credentials::{Credentials, SSID_LEN};
+use crate::entropy;
+use crate::errors::{self, Code};
+use crate::events::{self, Event};
+#[cfg(feature = "provisioning")]
+use crate::{AP_FALLBACK_SECS, AP_STA, HTTP_PORT, http, softap};
+use crate::{ROAM_CHECK_SECS, ROAM_MARGIN_DB, ROAM_RSSI_DBM};
 
-static RESOURCES: StaticCell<StackResources<4>> = StaticCell::new();
+// Sockets at once, the HTTP listener keeps one to itself
+static RESOURCES: StaticCell<StackResources<5>> = StaticCell::new();
+// Access point kept up next to the network, for the DHCP server and the HTTP
+// listener
+#[cfg(feature = "provisioning")]
+static LOCAL_RESOURCES: StaticCell<StackResources<3>> = StaticCell::new();
 static LINK_STATE: Signal<CriticalSectionRawMutex, bool> = Signal::new();
+static RESTART: Signal<CriticalSectionRawMutex, ()> = Signal::new();
+// Gave up on the network and opened the setup access point instead
+#[cfg(feature = "provisioning")]
+static FALLBACK: Signal<CriticalSectionRawMutex, ()> = Signal::new();
+// Access point of the current connection, as the driver reported it
+static ACCESS_POINT: CriticalSectionMutex<RefCell<Option<AccessPoint>>> =
+    CriticalSectionMutex::new(RefCell::new(None));
+
+#[derive(Clone, Debug, PartialEq)]
+struct AccessPoint {
+    ssid: String<SSID_LEN>,
+    bssid: [u8; 6],
+    channel: u8,
+}
+
+/// Where the unit is on the network: the access point it's connected to and
+/// its IPv4 config, each None while there's none
+#[derive(Clone, Debug, PartialEq)]
+pub struct Link {
+    access_point: Option<AccessPoint>,
+    address: Option<Ipv4Cidr>,
+    gateway: Option<Ipv4Address>,
+    dns: Vec<Ipv4Address, 3>,
+}
+
+impl Link {
+    pub fn current(stack: &Stack<'_>) -> Self {
+        let config = stack.config_v4();
+        Link {
+            access_point: ACCESS_POINT.lock(|access_point| access_point.borrow().clone()),
+            address: config.as_ref().map(|config| config.address),
+            gateway: config.as_ref().and_then(|config| config.gateway),
+            dns: config
+                .map(|config| config.dns_servers.iter().copied().collect())
+                .unwrap_or_default(),
+        }
+    }
+
+    /// As a JSON object
+    pub fn write(&self, out: &mut impl Write) -> fmt::Result {
+        write!(out, "{{")?;
+        match &self.access_point {
+            Some(ap) => {
+                let [a, b, c, d, e, f] = ap.bssid;
+                write!(
+                    out,
+                    "\"ssid\" : \"{}\", \"bssid\" : \"{:02x}:{:02x}:{:02x}:{:02x}:{:02x}:{:02x}\", \"channel\" : {}, ",
+                    ap.ssid, a, b, c, d, e, f, ap.channel
+                )?;
+            }
+            None => write!(
+                out,
+                "\"ssid\" : null, \"bssid\" : null, \"channel\" : null, "
+            )?,
+        }
+        match self.address {
+            Some(address) => write!(out, "\"address\" : \"{}\", ", address)?,
+            None => write!(out, "\"address\" : null, ")?,
+        }
+        match self.gateway {
+            Some(gateway) => write!(out, "\"gateway\" : \"{}\", ", gateway)?,
+            None => write!(out, "\"gateway\" : null, ")?,
+        }
+        write!(out, "\"dns\" : [")?;
+        for (idx, server) in self.dns.iter().enumerate() {
+            write!(out, "{}\"{}\"", if idx > 0 { ", " } else { "" }, server)?;
+        }
+        write!(out, "] }}")
+    }
+}
+
+/// Tear down Wi-Fi connection and bring it up from scratch
+pub fn restart() {
+    RESTART.signal(());
+}
 
 pub struct Wifi {
     pub stack: Stack<'static>,
+    /// Stack is on the setup access point, the network couldn't be joined
+    pub fallback: bool,
 }
 
 #[derive(Debug)]
@@ -31,7 +128,7 @@ impl Wifi {
     pub async fn new(
         radio_init: &'static Controller<'static>,
         wifi: esp_hal::peripherals::WIFI<'static>,
-        rng: Rng,
+        credentials: &'static Credentials,
         spawner: Spawner,
     ) -> Result<Self, Error> {
         let config = esp_radio::wifi::Config::default().with_rx_queue_size(10);
@@ -45,25 +142,87 @@ impl Wifi {
         dhcp_config.hostname = Some(hostname);
         let config = embassy_net::Config::dhcpv4(dhcp_config);
 
-        let seed = (rng.random() as u64) << 32 | rng.random() as u64;
+        let seed = entropy::random();
 
         let resources = RESOURCES.init(StackResources::new());
 
-        spawner.spawn(connection(wifi_controller)).ok();
+        // Up before the network is joined, it may never be
+        #[cfg(feature = "provisioning")]
+        let (local, ap_interface) = if AP_STA {
+            info!("Starting stack on access point {}", softap::ssid());
+            let resources = LOCAL_RESOURCES.init(StackResources::new());
+            let (stack, runner) = embassy_net::new(
+                interfaces.ap,
+                softap::net_config(),
+                resources,
+                entropy::random(),
+            );
+            spawner.spawn(net_task(runner)).ok();
+            spawner.spawn(softap::dhcp(stack)).ok();
+            // Dashboard is reachable there whether or not the network comes up
+            if HTTP_PORT != 0 {
+                spawner.spawn(http::serve(stack)).ok();
+            }
+            (Some(stack), None)
+        } else {
+            (None, Some(interfaces.ap))
+        };
+
+        StaConnected::update_handler(|event| {
+            let ssid = event
+                .ssid()
+                .get(..event.ssid_len().into())
+                .and_then(|ssid| core::str::from_utf8(ssid).ok())
+                .and_then(|ssid| ssid.try_into().ok())
+                .unwrap_or_default();
+            let access_point = AccessPoint {
+                ssid,
+                bssid: event.bssid().try_into().unwrap_or_default(),
+                channel: event.channel(),
+            };
+            ACCESS_POINT.lock(|current| *current.borrow_mut() = Some(access_point));
+        });
+        spawner.spawn(connection(wifi_controller, credentials)).ok();
         info!("Waiting for link to come up...");
-        loop {
-            let link_is_up = LINK_STATE.wait().await;
-            Timer::after(Duration::from_millis(500)).await;
-            if link_is_up {
-                break;
+        let link_up = async {
+            loop {
+                let link_is_up = LINK_STATE.wait().await;
+                Timer::after(Duration::from_millis(500)).await;
+                if link_is_up {
+                    break;
+                }
             }
+        };
+        #[cfg(feature = "provisioning")]
+        if let Either::Second(_) = select(link_up, FALLBACK.wait()).await {
+            let stack = match (local, ap_interface) {
+                (Some(stack), _) => stack,
+                (None, Some(ap_interface)) => {
+                    info!("Starting stack on the setup access point");
+                    let (stack, runner) =
+                        embassy_net::new(ap_interface, softap::net_config(), resources, seed);
+                    spawner.spawn(net_task(runner)).ok();
+                    spawner.spawn(softap::dhcp(stack)).ok();
+                    stack
+                }
+                (None, None) => unreachable!(),
+            };
+            return Ok(Self {
+                stack,
+                fallback: true,
+            });
         }
+        #[cfg(not(feature = "provisioning"))]
+        link_up.await;
         info!("Link is up, starting stack");
 
         let (stack, runner) = embassy_net::new(wifi_interface, config, resources, seed);
         spawner.spawn(net_task(runner)).ok();
 
-        Ok(Self { stack })
+        Ok(Self {
+            stack,
+            fallback: false,
+        })
     }
 
     pub async fn wait_for_ip(&self) -> Result<(), Error> {
@@ -88,23 +247,125 @@ impl Wifi {
     }
 }
 
+/// Client config for the network, pinned to access point `bssid` if set. The
+/// unit's own access point comes along with AP_STA.
+fn client_config(credentials: &Credentials, bssid: Option<[u8; 6]>) -> ModeConfig {
+    let config = ClientConfig::default()
+        .with_ssid(credentials.ssid.as_str().into())
+        .with_password(credentials.password.as_str().into());
+    let config = match bssid {
+        Some(bssid) => config.with_bssid(bssid),
+        None => config,
+    };
+    #[cfg(feature = "provisioning")]
+    if AP_STA {
+        return ModeConfig::ApSta(config, softap::access_point_config());
+    }
+    ModeConfig::Client(config)
+}
+
+/// Access point of the same network to roam to. Only looked for once the
+/// signal is below ROAM_RSSI_DBM, since scanning interrupts traffic, and it
+/// has to be ROAM_MARGIN_DB stronger so the unit doesn't flap between two
+/// similar ones.
+async fn roam_target(controller: &mut WifiController<'static>, ssid: &str) -> Option<[u8; 6]> {
+    let rssi = controller.rssi().ok()?;
+    if rssi >= ROAM_RSSI_DBM as i32 {
+        return None;
+    }
+    let scan_config = ScanConfig::default().with_ssid(ssid);
+    let result = controller
+        .scan_with_config_async(scan_config)
+        .await
+        .inspect_err(|e| warn!("Failed to scan: {:?}", e))
+        .ok()?;
+    let best = result.iter().max_by_key(|ap| ap.signal_strength)?;
+    info!(
+        "Signal is {} dBm, strongest access point {:02x?} has {} dBm",
+        rssi, best.bssid, best.signal_strength
+    );
+    (best.signal_strength as i32 >= rssi + ROAM_MARGIN_DB as i32).then_some(best.bssid)
+}
+
+/// Switch from the network that can't be joined over to the setup access
+/// point
+#[cfg(feature = "provisioning")]
+async fn open_access_point(controller: &mut WifiController<'static>) {
+    warn!(
+        "No Wi-Fi connection in {} seconds, opening access point {}",
+        AP_FALLBACK_SECS,
+        softap::ssid()
+    );
+    if let Err(e) = controller.stop_async().await {
+        warn!("Failed to stop WiFi: {:?}", e);
+    }
+    if let Err(e) = controller.set_config(&softap::wifi_config()) {
+        warn!("Failed to configure access point: {:?}", e);
+    }
+    if let Err(e) = controller.start_async().await {
+        warn!("Failed to start access point: {:?}", e);
+    }
+    FALLBACK.signal(());
+}
+
 #[embassy_executor::task]
-async fn connection(mut controller: WifiController<'static>) {
+async fn connection(mut controller: WifiController<'static>, credentials: &'static Credentials) {
     info!("Start connection task");
     info!("Device capabilities: {:?}", controller.capabilities());
+    // Access point roamed to, any one of the network otherwise
+    let mut bssid = None;
+    #[cfg(feature = "provisioning")]
+    let started = Instant::now();
+    #[cfg(feature = "provisioning")]
+    let mut joined = false;
     loop {
         if esp_radio::wifi::sta_state() == WifiStaState::Connected {
-            // wait until we're no longer connected
-            controller.wait_for_event(WifiEvent::StaDisconnected).await;
-            Timer::after(Duration::from_millis(5000)).await
+            // wait until we're no longer connected or asked to start over,
+            // checking the signal in the meantime if roaming is enabled
+            let disconnected = controller.wait_for_event(WifiEvent::StaDisconnected);
+            let roam_check = async {
+                if ROAM_RSSI_DBM == 0 {
+                    core::future::pending().await
+                } else {
+                    Timer::after(Duration::from_secs(ROAM_CHECK_SECS)).await
+                }
+            };
+            let roaming = match select3(disconnected, RESTART.wait(), roam_check).await {
+                Either3::First(_) => false,
+                Either3::Second(_) => {
+                    info!("Restarting WiFi");
+                    if let Err(e) = controller.stop_async().await {
+                        warn!("Failed to stop WiFi: {:?}", e);
+                    }
+                    false
+                }
+                Either3::Third(_) => {
+                    let Some(target) = roam_target(&mut controller, &credentials.ssid).await else {
+                        continue;
+                    };
+                    info!("Roaming to {:02x?}", target);
+                    bssid = Some(target);
+                    if let Err(e) = controller.set_config(&client_config(credentials, bssid)) {
+                        warn!("Failed to configure WiFi: {:?}", e);
+                        continue;
+                    }
+                    if let Err(e) = controller.disconnect_async().await {
+                        warn!("Failed to disconnect: {:?}", e);
+                    }
+                    true
+                }
+            };
+            ACCESS_POINT.lock(|current| *current.borrow_mut() = None);
+            events::notify(Event::WifiDown);
+            if !roaming {
+                errors::report(Code::WifiDisconnected, "");
+                Timer::after(Duration::from_millis(5000)).await
+            }
         }
         if !matches!(controller.is_started(), Ok(true)) {
-            let client_config = ModeConfig::Client(
-                ClientConfig::default()
-                    .with_ssid(SSID.into())
-                    .with_password(PASSWORD.into()),
-            );
-            controller.set_config(&client_config).unwrap();
+            controller
+                .set_config(&client_config(credentials, bssid))
+                .unwrap();
             info!("Starting WiFi");
             controller.start_async().await.unwrap();
             info!("WiFi started!");
@@ -124,18 +385,43 @@ async fn connection(mut controller: WifiController<'static>) {
         match controller.connect_async().await {
             Ok(_) => {
                 info!("WiFi connected!");
+                #[cfg(feature = "provisioning")]
+                {
+                    joined = true;
+                }
+                // Fresh connection is as good as a restart
+                RESTART.reset();
                 LINK_STATE.signal(true);
+                events::notify(Event::WifiUp);
             }
             Err(e) => {
                 warn!("Failed to connect to WiFi: {:?}", e);
+                errors::report(Code::WifiConnectFailed, format_args!("{:?}", e));
                 LINK_STATE.signal(false);
+                // Access point roamed to may be gone, let the driver pick one
+                if bssid.take().is_some()
+                    && let Err(e) = controller.set_config(&client_config(credentials, None))
+                {
+                    warn!("Failed to configure WiFi: {:?}", e);
+                }
+                // Never joined since boot, it may be in a new place
+                #[cfg(feature = "provisioning")]
+                if !joined
+                    && AP_FALLBACK_SECS != 0
+                    && started.elapsed() >= Duration::from_secs(AP_FALLBACK_SECS)
+                {
+                    open_access_point(&mut controller).await;
+                    // The controller has to stay around for it
+                    core::future::pending::<()>().await;
+                }
                 Timer::after(Duration::from_millis(5000)).await
             }
         }
     }
 }
 
-#[embassy_executor::task]
+// One for the network, one for the unit's own access point
+#[embassy_executor::task(pool_size = 2)]
 async fn net_task(mut runner: Runner<'static, WifiDevice<'static>>) {
     runner.run().await
 }
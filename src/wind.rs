@@ -0,0 +1,239 @@
+use core::cell::RefCell;
+
+use embassy_sync::blocking_mutex::CriticalSectionMutex;
+use heapless::{Deque, Vec};
+
+use crate::WIND_AVERAGE_SECS;
+use crate::decoder::{SensorData, SensorReading, Wind};
+use crate::time;
+
+// Anemometers send what they measured over the last few seconds, which swings
+// a lot from one transmission to the next. Weather services report wind as
+// averaged over the last WIND_AVERAGE_SECS instead, 10 minutes by default:
+// speed is the mean of the samples' speeds, direction that of their vector sum
+// weighted by speed, so light breezes that turn around don't drag it off, and
+// gust is the strongest one the samples saw. Readings are rewritten with those
+// before they go anywhere.
+
+/// Anemometers tracked at once, the one heard least recently makes room
+pub const ANEMOMETERS: usize = 4;
+/// Samples kept per anemometer, older ones are dropped before they're out of
+/// the window if they come in more often
+const SAMPLES: usize = 64;
+/// Repeats of a transmission come in closer than this, they don't count twice
+const REPEAT_US: u64 = 2_000_000;
+const WINDOW_US: u64 = WIND_AVERAGE_SECS * 1_000_000;
+
+// sin() in 1 degree steps from 0 to 90 degrees, scaled by 10000
+const SIN: [i32; 91] = [
+    0, 175, 349, 523, 698, 872, 1045, 1219, 1392, 1564, 1736, 1908, 2079, 2250, 2419, 2588, 2756,
+    2924, 3090, 3256, 3420, 3584, 3746, 3907, 4067, 4226, 4384, 4540, 4695, 4848, 5000, 5150, 5299,
+    5446, 5592, 5736, 5878, 6018, 6157, 6293, 6428, 6561, 6691, 6820, 6947, 7071, 7193, 7314, 7431,
+    7547, 7660, 7771, 7880, 7986, 8090, 8192, 8290, 8387, 8480, 8572, 8660, 8746, 8829, 8910, 8988,
+    9063, 9135, 9205, 9272, 9336, 9397, 9455, 9511, 9563, 9613, 9659, 9703, 9744, 9781, 9816, 9848,
+    9877, 9903, 9925, 9945, 9962, 9976, 9986, 9994, 9998, 10000,
+];
+// tan() halfway between whole degrees from 0.5 to 44.5 degrees, scaled by
+// 10000, angles round to the nearest degree by how many of these they exceed
+const TAN_HALF: [u64; 45] = [
+    87, 262, 437, 612, 787, 963, 1139, 1317, 1495, 1673, 1853, 2035, 2217, 2401, 2586, 2773, 2962,
+    3153, 3346, 3541, 3739, 3939, 4142, 4348, 4557, 4770, 4986, 5206, 5430, 5658, 5890, 6128, 6371,
+    6619, 6873, 7133, 7400, 7673, 7954, 8243, 8541, 8847, 9163, 9490, 9827,
+];
+
+/// sin() of `degrees`, scaled by 10000
+fn sin(degrees: u16) -> i32 {
+    let degrees = degrees % 360;
+    let quadrant = degrees % 180;
+    let value = SIN[if quadrant > 90 {
+        180 - quadrant
+    } else {
+        quadrant
+    } as usize];
+    if degrees >= 180 { -value } else { value }
+}
+
+fn cos(degrees: u16) -> i32 {
+    sin(degrees % 360 + 90)
+}
+
+/// Direction in degrees, 0 is North, of a vector `north` and `east` long.
+/// None for one of no length.
+fn direction(north: i64, east: i64) -> Option<u16> {
+    let (along, across) = (north.unsigned_abs(), east.unsigned_abs());
+    let (short, long) = (along.min(across), along.max(across));
+    if long == 0 {
+        return None;
+    }
+    let angle = TAN_HALF
+        .iter()
+        .take_while(|&&tan| tan < short * 10000 / long)
+        .count() as u16;
+    // Angle from the nearest of North or South
+    let angle = if across > along { 90 - angle } else { angle };
+    Some(match (north >= 0, east >= 0) {
+        (true, true) => angle,
+        (false, true) => 180 - angle,
+        (false, false) => 180 + angle,
+        (true, false) => (360 - angle) % 360,
+    })
+}
+
+#[derive(Clone, Debug)]
+struct Sample {
+    /// Monotonic time it was received
+    at: u64,
+    wind: Wind,
+}
+
+struct Anemometer {
+    model: &'static str,
+    id: u32,
+    channel: u8,
+    samples: Deque<Sample, SAMPLES>,
+}
+
+impl Anemometer {
+    fn matches(&self, data: &SensorData) -> bool {
+        self.model == data.model() && self.id == data.id && self.channel == data.channel
+    }
+
+    fn heard(&self) -> u64 {
+        self.samples.back().map_or(0, |sample| sample.at)
+    }
+
+    fn add(&mut self, wind: &Wind, now: u64) {
+        if let Some(last) = self.samples.back()
+            && now.saturating_sub(last.at) < REPEAT_US
+            && last.wind == *wind
+        {
+            return;
+        }
+        while let Some(first) = self.samples.front()
+            && first.at.saturating_add(WINDOW_US) <= now
+        {
+            self.samples.pop_front();
+        }
+        if self.samples.is_full() {
+            self.samples.pop_front();
+        }
+        let _ = self.samples.push_back(Sample {
+            at: now,
+            wind: wind.clone(),
+        });
+    }
+
+    /// Wind over the window, None without samples
+    fn average(&self) -> Option<Wind> {
+        let last = self.samples.back()?;
+        let (mut speed, mut gust, mut north, mut east) = (0u32, 0, 0i64, 0i64);
+        for Sample { wind, .. } in &self.samples {
+            speed += wind.avg_speed as u32;
+            gust = gust.max(wind.gust_speed);
+            north += wind.avg_speed as i64 * cos(wind.direction) as i64;
+            east += wind.avg_speed as i64 * sin(wind.direction) as i64;
+        }
+        let count = self.samples.len() as u32;
+        Some(Wind {
+            avg_speed: ((speed + count / 2) / count) as u16,
+            gust_speed: gust,
+            // Calm has no direction, the last one given stands
+            direction: direction(north, east).unwrap_or(last.wind.direction),
+        })
+    }
+}
+
+static ANEMOMETERS_STATE: CriticalSectionMutex<RefCell<Vec<Anemometer, ANEMOMETERS>>> =
+    CriticalSectionMutex::new(RefCell::new(Vec::new()));
+
+/// Note the wind in `data`, received just now, and replace it with the wind
+/// over the window. Readings without wind are left alone, and all of them
+/// with WIND_AVERAGE_SECS of 0.
+pub fn average(data: &mut SensorData) {
+    if WIND_AVERAGE_SECS == 0 {
+        return;
+    }
+    let now = time::monotonic();
+    let averaged = ANEMOMETERS_STATE.lock(|anemometers| {
+        let mut anemometers = anemometers.borrow_mut();
+        let wind = data.wind()?;
+        let found = anemometers.iter().position(|found| found.matches(data));
+        let idx = match found {
+            Some(idx) => idx,
+            None => {
+                if anemometers.is_full()
+                    && let Some(oldest) = anemometers
+                        .iter()
+                        .enumerate()
+                        .min_by_key(|(_, anemometer)| anemometer.heard())
+                        .map(|(idx, _)| idx)
+                {
+                    anemometers.swap_remove(oldest);
+                }
+                let anemometer = Anemometer {
+                    model: data.model(),
+                    id: data.id,
+                    channel: data.channel,
+                    samples: Deque::new(),
+                };
+                anemometers.push(anemometer).ok()?;
+                anemometers.len() - 1
+            }
+        };
+        let anemometer = &mut anemometers[idx];
+        anemometer.add(wind, now);
+        anemometer.average()
+    });
+    if let Some(averaged) = averaged
+        && let SensorReading::Wind(wind) | SensorReading::Weather(_, wind, _) = &mut data.reading
+    {
+        *wind = averaged;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wind(avg_speed: u16, gust_speed: u16, direction: u16) -> Wind {
+        Wind {
+            avg_speed,
+            gust_speed,
+            direction,
+        }
+    }
+
+    #[test]
+    fn directions() {
+        assert_eq!(direction(1, 0), Some(0));
+        assert_eq!(direction(1, 1), Some(45));
+        assert_eq!(direction(0, 1), Some(90));
+        assert_eq!(direction(-1, 0), Some(180));
+        assert_eq!(direction(0, -1), Some(270));
+        assert_eq!(direction(0, 0), None);
+        for degrees in (0..360).step_by(7) {
+            let (north, east) = (cos(degrees) as i64, sin(degrees) as i64);
+            assert_eq!(direction(north, east), Some(degrees), "{degrees}");
+        }
+    }
+
+    #[test]
+    fn average_across_north() {
+        let mut anemometer = Anemometer {
+            model: "test",
+            id: 0,
+            channel: 0,
+            samples: Deque::new(),
+        };
+        anemometer.add(&wind(20, 30, 350), 0);
+        anemometer.add(&wind(40, 50, 10), REPEAT_US);
+        // Pulled towards the stronger of the two
+        assert_eq!(anemometer.average(), Some(wind(30, 50, 3)));
+        // A repeat of the last transmission doesn't count twice
+        anemometer.add(&wind(40, 50, 10), REPEAT_US + 1);
+        assert_eq!(anemometer.samples.len(), 2);
+        // Samples out of the window are dropped
+        anemometer.add(&wind(10, 10, 180), WINDOW_US + REPEAT_US);
+        assert_eq!(anemometer.average(), Some(wind(10, 10, 180)));
+    }
+}
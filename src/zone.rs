@@ -0,0 +1,51 @@
+use crate::config;
+use crate::latest::REGISTRY;
+
+// Sensors are put into zones by their settings, `upstairs` or `garden` say.
+// A zone's climate is the average over its sensors that are online, for
+// automations that care about a floor rather than a single room.
+
+/// Average climate of a zone
+#[derive(Clone, Copy, Debug)]
+pub struct ZoneClimate {
+    /// Sensors averaged
+    pub sensors: u8,
+    /// In 0.1 C
+    pub temp_decicelsius: i16,
+    /// In %, None if none of them measures it
+    pub humidity: Option<u8>,
+}
+
+/// Average over the online sensors of `zone` that measure temperature, None
+/// if there are none
+pub fn climate(zone: &str) -> Option<ZoneClimate> {
+    let (mut sensors, mut temp_sum) = (0i32, 0i32);
+    let (mut humid, mut humidity_sum) = (0i32, 0i32);
+    REGISTRY.lock(|registry| {
+        for sensor in registry.borrow().iter() {
+            let Some(climate) = sensor.reading.climate() else {
+                continue;
+            };
+            if !sensor.online || config::zone(&sensor.reading) != zone {
+                continue;
+            }
+            sensors += 1;
+            temp_sum += climate.temp_decicelsius as i32;
+            if let Some(humidity) = climate.humidity {
+                humid += 1;
+                humidity_sum += humidity as i32;
+            }
+        }
+    });
+    (sensors > 0).then(|| ZoneClimate {
+        sensors: sensors as u8,
+        temp_decicelsius: div_round(temp_sum, sensors) as i16,
+        humidity: (humid > 0).then(|| div_round(humidity_sum, humid) as u8),
+    })
+}
+
+/// Division rounding half away from zero
+fn div_round(sum: i32, count: i32) -> i32 {
+    let half = if sum < 0 { -count / 2 } else { count / 2 };
+    (sum + half) / count
+}